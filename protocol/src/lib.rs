@@ -0,0 +1,1025 @@
+//! The PicoSwitchController serial protocol: buttons, sticks, controller
+//! state and command formatting, with no I/O.
+//!
+//! `no_std` (with `alloc`), so the exact encoding can be shared with firmware.
+//! The `switchcontroller` crate layers the serial connection on top and
+//! re-exports its types.
+#![no_std]
+
+extern crate alloc;
+
+mod parse;
+
+pub use parse::{ParseError, ParseErrorKind, parse_line};
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+use core::time::Duration;
+
+/// How long the firmware holds buttons for a `PRESS` command.
+pub const PRESS_DURATION: Duration = Duration::from_millis(100);
+
+/// A Nintendo Switch controller button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Button {
+    A,
+    B,
+    X,
+    Y,
+    L,
+    R,
+    ZL,
+    ZR,
+    Plus,
+    Minus,
+    Home,
+    Capture,
+    LStick,
+    RStick,
+    DpadUp,
+    DpadDown,
+    DpadLeft,
+    DpadRight,
+}
+
+impl Button {
+    /// All buttons in STATE bit-order (index 0..17).
+    pub const ALL: [Button; 18] = [
+        Button::A,
+        Button::B,
+        Button::X,
+        Button::Y,
+        Button::L,
+        Button::R,
+        Button::ZL,
+        Button::ZR,
+        Button::Plus,
+        Button::Minus,
+        Button::Home,
+        Button::Capture,
+        Button::LStick,
+        Button::RStick,
+        Button::DpadUp,
+        Button::DpadDown,
+        Button::DpadLeft,
+        Button::DpadRight,
+    ];
+
+    /// The A, B, X and Y buttons.
+    pub const FACE: [Button; 4] = [Button::A, Button::B, Button::X, Button::Y];
+
+    /// The D-pad directions.
+    pub const DPAD: [Button; 4] = [
+        Button::DpadUp,
+        Button::DpadDown,
+        Button::DpadLeft,
+        Button::DpadRight,
+    ];
+
+    /// The shoulder buttons and triggers.
+    pub const SHOULDERS: [Button; 4] = [Button::L, Button::R, Button::ZL, Button::ZR];
+
+    /// Every button except Home, in [`Button::ALL`] order, for mashing or
+    /// releasing everything without leaving the game.
+    pub const ALL_NO_HOME: [Button; 17] = [
+        Button::A,
+        Button::B,
+        Button::X,
+        Button::Y,
+        Button::L,
+        Button::R,
+        Button::ZL,
+        Button::ZR,
+        Button::Plus,
+        Button::Minus,
+        Button::Capture,
+        Button::LStick,
+        Button::RStick,
+        Button::DpadUp,
+        Button::DpadDown,
+        Button::DpadLeft,
+        Button::DpadRight,
+    ];
+
+    /// Which group of the controller the button belongs to.
+    pub fn category(self) -> ButtonCategory {
+        match self {
+            Button::A | Button::B | Button::X | Button::Y => ButtonCategory::Face,
+            Button::DpadUp | Button::DpadDown | Button::DpadLeft | Button::DpadRight => {
+                ButtonCategory::Dpad
+            }
+            Button::L | Button::R | Button::ZL | Button::ZR => ButtonCategory::Shoulder,
+            Button::LStick | Button::RStick => ButtonCategory::StickClick,
+            Button::Plus | Button::Minus | Button::Home | Button::Capture => ButtonCategory::System,
+        }
+    }
+
+    /// Position of this button in [`Button::ALL`] and the `STATE` bit string.
+    pub fn index(self) -> usize {
+        Button::ALL.iter().position(|&b| b == self).unwrap()
+    }
+
+    /// Look up a button by its protocol name, as shown by `Display`
+    /// (`"a"`, `"zr"`, `"dpad_up"`, ...).
+    pub fn from_name(name: &str) -> Option<Button> {
+        Button::ALL.into_iter().find(|b| b.as_str() == name)
+    }
+
+    /// The button's protocol name, as used in `PRESS`/`HOLD`/`RELEASE`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Button::A => "a",
+            Button::B => "b",
+            Button::X => "x",
+            Button::Y => "y",
+            Button::L => "l",
+            Button::R => "r",
+            Button::ZL => "zl",
+            Button::ZR => "zr",
+            Button::Plus => "plus",
+            Button::Minus => "minus",
+            Button::Home => "home",
+            Button::Capture => "capture",
+            Button::LStick => "l_stick",
+            Button::RStick => "r_stick",
+            Button::DpadUp => "dpad_up",
+            Button::DpadDown => "dpad_down",
+            Button::DpadLeft => "dpad_left",
+            Button::DpadRight => "dpad_right",
+        }
+    }
+
+    /// The name to show people, as printed on the controller where there is
+    /// one (`"ZL"`, `"D-Pad Up"`), unlike the wire name of
+    /// [`Button::as_str`].
+    pub fn label(self) -> &'static str {
+        match self {
+            Button::A => "A",
+            Button::B => "B",
+            Button::X => "X",
+            Button::Y => "Y",
+            Button::L => "L",
+            Button::R => "R",
+            Button::ZL => "ZL",
+            Button::ZR => "ZR",
+            Button::Plus => "Plus",
+            Button::Minus => "Minus",
+            Button::Home => "Home",
+            Button::Capture => "Capture",
+            Button::LStick => "L Stick",
+            Button::RStick => "R Stick",
+            Button::DpadUp => "D-Pad Up",
+            Button::DpadDown => "D-Pad Down",
+            Button::DpadLeft => "D-Pad Left",
+            Button::DpadRight => "D-Pad Right",
+        }
+    }
+
+    /// A single Unicode character standing for the button in overlays and
+    /// TUIs, or `None` for buttons with no common one (the triggers and
+    /// stick clicks); fall back to [`Button::label`] for those.
+    pub fn glyph(self) -> Option<char> {
+        match self {
+            Button::A => Some('Ⓐ'),
+            Button::B => Some('Ⓑ'),
+            Button::X => Some('Ⓧ'),
+            Button::Y => Some('Ⓨ'),
+            Button::L => Some('Ⓛ'),
+            Button::R => Some('Ⓡ'),
+            Button::Plus => Some('⊕'),
+            Button::Minus => Some('⊖'),
+            Button::Home => Some('⌂'),
+            Button::Capture => Some('◉'),
+            Button::DpadUp => Some('↑'),
+            Button::DpadDown => Some('↓'),
+            Button::DpadLeft => Some('←'),
+            Button::DpadRight => Some('→'),
+            Button::ZL | Button::ZR | Button::LStick | Button::RStick => None,
+        }
+    }
+}
+
+impl fmt::Display for Button {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A group of buttons on the controller, as returned by [`Button::category`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ButtonCategory {
+    /// A, B, X and Y.
+    Face,
+    /// The four D-pad directions.
+    Dpad,
+    /// L, R, ZL and ZR.
+    Shoulder,
+    /// Clicking either stick in.
+    StickClick,
+    /// Plus, Minus, Home and Capture.
+    System,
+}
+
+impl ButtonCategory {
+    pub const ALL: [ButtonCategory; 5] = [
+        ButtonCategory::Face,
+        ButtonCategory::Dpad,
+        ButtonCategory::Shoulder,
+        ButtonCategory::StickClick,
+        ButtonCategory::System,
+    ];
+
+    /// The buttons in this category, in [`Button::ALL`] order.
+    pub fn buttons(self) -> &'static [Button] {
+        match self {
+            ButtonCategory::Face => &Button::FACE,
+            ButtonCategory::Dpad => &Button::DPAD,
+            ButtonCategory::Shoulder => &Button::SHOULDERS,
+            ButtonCategory::StickClick => &[Button::LStick, Button::RStick],
+            ButtonCategory::System => &[Button::Plus, Button::Minus, Button::Home, Button::Capture],
+        }
+    }
+}
+
+/// An analog stick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stick {
+    Left,
+    Right,
+}
+
+impl Stick {
+    /// Look up a stick by its protocol name, `"l_stick"` or `"r_stick"`.
+    pub fn from_name(name: &str) -> Option<Stick> {
+        [Stick::Left, Stick::Right]
+            .into_iter()
+            .find(|s| s.as_str() == name)
+    }
+
+    /// The stick's protocol name, as used in `STICK`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Stick::Left => "l_stick",
+            Stick::Right => "r_stick",
+        }
+    }
+}
+
+impl fmt::Display for Stick {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A D-pad direction: one of the eight directions, or none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Direction {
+    #[default]
+    Neutral,
+    Up,
+    UpRight,
+    Right,
+    DownRight,
+    Down,
+    DownLeft,
+    Left,
+    UpLeft,
+}
+
+impl Direction {
+    /// The D-pad buttons held for this direction.
+    pub fn buttons(self) -> &'static [Button] {
+        match self {
+            Direction::Neutral => &[],
+            Direction::Up => &[Button::DpadUp],
+            Direction::UpRight => &[Button::DpadUp, Button::DpadRight],
+            Direction::Right => &[Button::DpadRight],
+            Direction::DownRight => &[Button::DpadDown, Button::DpadRight],
+            Direction::Down => &[Button::DpadDown],
+            Direction::DownLeft => &[Button::DpadDown, Button::DpadLeft],
+            Direction::Left => &[Button::DpadLeft],
+            Direction::UpLeft => &[Button::DpadUp, Button::DpadLeft],
+        }
+    }
+
+    /// The unit stick vector for this direction, as `(horizontal, vertical)`
+    /// in the firmware's convention of up being negative. Diagonals have the
+    /// same length as the other directions; `Neutral` is `(0, 0)`.
+    pub fn vector(self) -> (f32, f32) {
+        use core::f32::consts::FRAC_1_SQRT_2 as D;
+        match self {
+            Direction::Neutral => (0.0, 0.0),
+            Direction::Up => (0.0, -1.0),
+            Direction::UpRight => (D, -D),
+            Direction::Right => (1.0, 0.0),
+            Direction::DownRight => (D, D),
+            Direction::Down => (0.0, 1.0),
+            Direction::DownLeft => (-D, D),
+            Direction::Left => (-1.0, 0.0),
+            Direction::UpLeft => (-D, -D),
+        }
+    }
+
+    /// The direction of a set of held D-pad buttons. Opposite directions held
+    /// together cancel out.
+    pub fn from_buttons(up: bool, down: bool, left: bool, right: bool) -> Direction {
+        match (up as i8 - down as i8, right as i8 - left as i8) {
+            (1, 0) => Direction::Up,
+            (1, 1) => Direction::UpRight,
+            (0, 1) => Direction::Right,
+            (-1, 1) => Direction::DownRight,
+            (-1, 0) => Direction::Down,
+            (-1, -1) => Direction::DownLeft,
+            (0, -1) => Direction::Left,
+            (1, -1) => Direction::UpLeft,
+            _ => Direction::Neutral,
+        }
+    }
+}
+
+/// How a [`SocdCleaner`] resolves opposite D-pad directions held together
+/// (simultaneous opposing cardinal directions, "SOCD").
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Socd {
+    /// Neither direction is sent.
+    #[default]
+    Neutral,
+    /// The direction pressed most recently wins. If both were pressed in the
+    /// same state, neither is sent.
+    LastWins,
+}
+
+/// Opposite D-pad directions.
+const DPAD_AXES: [[Button; 2]; 2] = [
+    [Button::DpadUp, Button::DpadDown],
+    [Button::DpadLeft, Button::DpadRight],
+];
+
+/// Removes opposite D-pad directions from a stream of controller states, so
+/// a script or a hitbox-style source can never send Left+Right or Up+Down.
+#[derive(Debug, Clone, Default)]
+pub struct SocdCleaner {
+    socd: Socd,
+    /// Per axis of [`DPAD_AXES`], the buttons held in the previous input.
+    previous: [(bool, bool); 2],
+    /// Per axis, the direction sent for the previous input.
+    winner: [Option<Button>; 2],
+}
+
+impl SocdCleaner {
+    pub fn new(socd: Socd) -> Self {
+        Self {
+            socd,
+            ..Self::default()
+        }
+    }
+
+    pub fn socd(&self) -> Socd {
+        self.socd
+    }
+
+    /// Clean the next state in the stream.
+    pub fn apply(&mut self, state: &mut ControllerState) {
+        for (axis, [a, b]) in DPAD_AXES.into_iter().enumerate() {
+            let held = (state.button(a), state.button(b));
+            let winner = match held {
+                (true, true) => {
+                    let winner = match (self.socd, self.previous[axis]) {
+                        (Socd::Neutral, _) => None,
+                        (Socd::LastWins, (true, false)) => Some(b),
+                        (Socd::LastWins, (false, true)) => Some(a),
+                        (Socd::LastWins, (true, true)) => self.winner[axis],
+                        (Socd::LastWins, (false, false)) => None,
+                    };
+                    state.set_button(a, winner == Some(a));
+                    state.set_button(b, winner == Some(b));
+                    winner
+                }
+                (true, false) => Some(a),
+                (false, true) => Some(b),
+                (false, false) => None,
+            };
+            self.previous[axis] = held;
+            self.winner[axis] = winner;
+        }
+    }
+}
+
+/// A stick axis value in [-1.0, 1.0] quantized to the 256 steps of a HID
+/// report, with 0 at -1.0 and 255 at 1.0. Values outside the range clamp.
+pub fn quantize_axis(value: f32) -> u8 {
+    // No `round` without std; the value is non-negative, so add a half.
+    ((value.clamp(-1.0, 1.0) + 1.0) / 2.0 * 255.0 + 0.5) as u8
+}
+
+fn quantize_stick(stick: Option<(f32, f32)>) -> Option<(u8, u8)> {
+    stick.map(|(h, v)| (quantize_axis(h), quantize_axis(v)))
+}
+
+/// Full controller state for the `STATE` command.
+///
+/// States compare and hash by their buttons and their sticks quantized with
+/// [`quantize_axis`], so positions too close to tell apart on the device are
+/// equal. A stick that isn't set differs from one set to center.
+#[derive(Debug, Clone, Default)]
+pub struct ControllerState {
+    /// Button state as a bitmask in the order defined by [`Button::ALL`].
+    buttons: [bool; 18],
+    /// Optional left stick position (horizontal, vertical), each in [-1.0, 1.0].
+    pub left_stick: Option<(f32, f32)>,
+    /// Optional right stick position (horizontal, vertical), each in [-1.0, 1.0].
+    pub right_stick: Option<(f32, f32)>,
+}
+
+impl ControllerState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All buttons released and both sticks explicitly centered.
+    pub fn neutral() -> Self {
+        let mut state = Self::new();
+        state.set_left_stick(0.0, 0.0).set_right_stick(0.0, 0.0);
+        state
+    }
+
+    /// Whether a button is set as pressed.
+    pub fn button(&self, button: Button) -> bool {
+        self.buttons[button.index()]
+    }
+
+    /// Set a button's pressed state.
+    pub fn set_button(&mut self, button: Button, pressed: bool) -> &mut Self {
+        self.buttons[button.index()] = pressed;
+        self
+    }
+
+    /// The D-pad direction held, with opposite directions cancelling out.
+    pub fn dpad(&self) -> Direction {
+        Direction::from_buttons(
+            self.button(Button::DpadUp),
+            self.button(Button::DpadDown),
+            self.button(Button::DpadLeft),
+            self.button(Button::DpadRight),
+        )
+    }
+
+    /// Set the D-pad to a direction, releasing the other D-pad buttons.
+    pub fn set_dpad(&mut self, direction: Direction) -> &mut Self {
+        for button in DPAD_AXES.into_iter().flatten() {
+            self.set_button(button, false);
+        }
+        for &button in direction.buttons() {
+            self.set_button(button, true);
+        }
+        self
+    }
+
+    /// Set the left stick position.
+    pub fn set_left_stick(&mut self, horizontal: f32, vertical: f32) -> &mut Self {
+        self.left_stick = Some((horizontal, vertical));
+        self
+    }
+
+    /// Set the right stick position. `STATE` can only carry the right stick
+    /// after the left, so without a left stick the left is sent centered;
+    /// [`ControllerState::builder`] and [`StickPair`] rule that case out.
+    pub fn set_right_stick(&mut self, horizontal: f32, vertical: f32) -> &mut Self {
+        self.right_stick = Some((horizontal, vertical));
+        self
+    }
+
+    /// Start building a state whose right stick can only be set after its
+    /// left stick.
+    pub fn builder() -> StateBuilder<NoSticks> {
+        StateBuilder {
+            state: Self::new(),
+            sticks: PhantomData,
+        }
+    }
+
+    /// The stick positions as the `STATE` command sends them, with a centered
+    /// left stick standing in when only the right stick is set.
+    pub fn sticks(&self) -> Option<StickPair> {
+        match (self.left_stick, self.right_stick) {
+            (None, None) => None,
+            (left, right) => Some(StickPair {
+                left: left.unwrap_or((0.0, 0.0)),
+                right,
+            }),
+        }
+    }
+
+    /// Set both sticks from a pair.
+    pub fn set_sticks(&mut self, sticks: StickPair) -> &mut Self {
+        self.left_stick = Some(sticks.left);
+        self.right_stick = sticks.right;
+        self
+    }
+
+    /// Buttons set as pressed, as a bitmask with bit `i` standing for
+    /// [`Button::ALL`]`[i]`.
+    pub fn button_mask(&self) -> u32 {
+        self.buttons
+            .iter()
+            .enumerate()
+            .filter(|&(_, &pressed)| pressed)
+            .fold(0, |mask, (i, _)| mask | (1 << i))
+    }
+
+    /// Set every button from a bitmask in the order of [`Button::ALL`].
+    pub fn set_button_mask(&mut self, mask: u32) -> &mut Self {
+        for (i, pressed) in self.buttons.iter_mut().enumerate() {
+            *pressed = mask & (1 << i) != 0;
+        }
+        self
+    }
+
+    /// Release every button, leaving the sticks as they are.
+    pub fn clear_buttons(&mut self) -> &mut Self {
+        self.buttons = [false; 18];
+        self
+    }
+
+    /// Layer `other` on top of this state: buttons pressed in either are
+    /// pressed, and each stick `other` sets replaces this state's.
+    pub fn merge(&mut self, other: &ControllerState) -> &mut Self {
+        for (pressed, &theirs) in self.buttons.iter_mut().zip(&other.buttons) {
+            *pressed |= theirs;
+        }
+        self.left_stick = other.left_stick.or(self.left_stick);
+        self.right_stick = other.right_stick.or(self.right_stick);
+        self
+    }
+
+    /// Both states combined with neither one winning: buttons pressed in
+    /// either are pressed, and stick positions set in both are added together
+    /// and clamped to [-1.0, 1.0].
+    pub fn union(&self, other: &ControllerState) -> ControllerState {
+        fn add(a: Option<(f32, f32)>, b: Option<(f32, f32)>) -> Option<(f32, f32)> {
+            match (a, b) {
+                (Some((ah, av)), Some((bh, bv))) => {
+                    Some(((ah + bh).clamp(-1.0, 1.0), (av + bv).clamp(-1.0, 1.0)))
+                }
+                (a, b) => a.or(b),
+            }
+        }
+        let mut state = self.clone();
+        state.merge(other);
+        state.left_stick = add(self.left_stick, other.left_stick);
+        state.right_stick = add(self.right_stick, other.right_stick);
+        state
+    }
+
+    /// Update the state as the device does when it receives `command`.
+    /// Presses, sleeps and raw commands leave it unchanged, and a `STATE`
+    /// without sticks keeps the sticks as they were.
+    pub fn apply(&mut self, command: &Command) -> &mut Self {
+        match command {
+            Command::Hold(buttons) | Command::Release(buttons) => {
+                let pressed = matches!(command, Command::Hold(_));
+                for &button in buttons {
+                    self.set_button(button, pressed);
+                }
+            }
+            Command::Stick(Stick::Left, h, v) => {
+                self.set_left_stick(*h, *v);
+            }
+            Command::Stick(Stick::Right, h, v) => {
+                self.set_right_stick(*h, *v);
+            }
+            Command::State(state) => {
+                self.buttons = state.buttons;
+                self.left_stick = state.left_stick.or(self.left_stick);
+                self.right_stick = state.right_stick.or(self.right_stick);
+            }
+            Command::Press(_) | Command::Sleep(_) | Command::Raw(_) => {}
+        }
+        self
+    }
+
+    /// Whether both states press the same buttons and set the same sticks,
+    /// with every stick axis within `tolerance` of the other's.
+    pub fn approx_eq(&self, other: &ControllerState, tolerance: f32) -> bool {
+        let close = |a: Option<(f32, f32)>, b: Option<(f32, f32)>| match (a, b) {
+            (Some((ah, av)), Some((bh, bv))) => {
+                (ah - bh).abs() <= tolerance && (av - bv).abs() <= tolerance
+            }
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        self.buttons == other.buttons
+            && close(self.left_stick, other.left_stick)
+            && close(self.right_stick, other.right_stick)
+    }
+
+    /// The `STATE` command line for this state, without the newline.
+    pub fn to_command(&self) -> String {
+        let bits: String = self
+            .buttons
+            .iter()
+            .map(|&b| if b { '1' } else { '0' })
+            .collect();
+        let mut cmd = format!("STATE {bits}");
+        if let Some((lh, lv)) = self.left_stick {
+            cmd.push_str(&format!(" {lh} {lv}"));
+            if let Some((rh, rv)) = self.right_stick {
+                cmd.push_str(&format!(" {rh} {rv}"));
+            }
+        } else if let Some((rh, rv)) = self.right_stick {
+            // Must provide left stick values to include right stick.
+            cmd.push_str(&format!(" 0.0 0.0 {rh} {rv}"));
+        }
+        cmd
+    }
+}
+
+impl PartialEq for ControllerState {
+    fn eq(&self, other: &Self) -> bool {
+        self.buttons == other.buttons
+            && quantize_stick(self.left_stick) == quantize_stick(other.left_stick)
+            && quantize_stick(self.right_stick) == quantize_stick(other.right_stick)
+    }
+}
+
+impl Eq for ControllerState {}
+
+impl Hash for ControllerState {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.buttons.hash(state);
+        quantize_stick(self.left_stick).hash(state);
+        quantize_stick(self.right_stick).hash(state);
+    }
+}
+
+/// Stick positions in the form the `STATE` command carries them: a left
+/// stick, optionally followed by a right stick.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct StickPair {
+    /// Left stick position (horizontal, vertical), each in [-1.0, 1.0].
+    pub left: (f32, f32),
+    /// Optional right stick position (horizontal, vertical).
+    pub right: Option<(f32, f32)>,
+}
+
+impl StickPair {
+    /// Just the left stick.
+    pub fn left(horizontal: f32, vertical: f32) -> Self {
+        Self {
+            left: (horizontal, vertical),
+            right: None,
+        }
+    }
+
+    /// Add the right stick.
+    pub fn with_right(mut self, horizontal: f32, vertical: f32) -> Self {
+        self.right = Some((horizontal, vertical));
+        self
+    }
+}
+
+/// [`StateBuilder`] stage before any stick is set.
+#[derive(Debug, Clone, Copy)]
+pub struct NoSticks;
+
+/// [`StateBuilder`] stage with the left stick set; the right may follow.
+#[derive(Debug, Clone, Copy)]
+pub struct LeftStick;
+
+/// [`StateBuilder`] stage with both sticks set.
+#[derive(Debug, Clone, Copy)]
+pub struct BothSticks;
+
+/// Builds a [`ControllerState`], tracking in its type which sticks are set
+/// so that `right_stick` is only available once `left_stick` has been
+/// called. Start one with [`ControllerState::builder`].
+#[derive(Debug, Clone)]
+pub struct StateBuilder<S> {
+    state: ControllerState,
+    sticks: PhantomData<S>,
+}
+
+impl<S> StateBuilder<S> {
+    /// Press a button.
+    pub fn button(mut self, button: Button) -> Self {
+        self.state.set_button(button, true);
+        self
+    }
+
+    /// Press several buttons.
+    pub fn buttons(mut self, buttons: &[Button]) -> Self {
+        for &button in buttons {
+            self.state.set_button(button, true);
+        }
+        self
+    }
+
+    /// Set the D-pad to a direction, releasing the other D-pad buttons.
+    pub fn dpad(mut self, direction: Direction) -> Self {
+        self.state.set_dpad(direction);
+        self
+    }
+
+    pub fn build(self) -> ControllerState {
+        self.state
+    }
+
+    fn stage<T>(self) -> StateBuilder<T> {
+        StateBuilder {
+            state: self.state,
+            sticks: PhantomData,
+        }
+    }
+}
+
+impl StateBuilder<NoSticks> {
+    pub fn left_stick(mut self, horizontal: f32, vertical: f32) -> StateBuilder<LeftStick> {
+        self.state.set_left_stick(horizontal, vertical);
+        self.stage()
+    }
+
+    /// Set both sticks at once from a pair.
+    pub fn sticks(mut self, sticks: StickPair) -> StateBuilder<BothSticks> {
+        self.state.set_sticks(sticks);
+        self.stage()
+    }
+}
+
+impl StateBuilder<LeftStick> {
+    pub fn right_stick(mut self, horizontal: f32, vertical: f32) -> StateBuilder<BothSticks> {
+        self.state.set_right_stick(horizontal, vertical);
+        self.stage()
+    }
+}
+
+/// Format a button command such as `PRESS a b`.
+pub fn button_command(verb: &str, buttons: &[Button]) -> String {
+    let names: Vec<&str> = buttons.iter().map(|b| b.as_str()).collect();
+    format!("{verb} {}", names.join(" "))
+}
+
+/// Format a `STICK` command.
+pub fn stick_command(stick: Stick, horizontal: f32, vertical: f32) -> String {
+    format!("STICK {stick} {horizontal} {vertical}")
+}
+
+/// Format a `SLEEP` command.
+pub fn sleep_command(seconds: f32) -> String {
+    format!("SLEEP {seconds}")
+}
+
+/// A command to the device, as a value that can be stored, queued or logged.
+/// Its `Display` is the command line, without the newline.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Press and immediately release buttons (`PRESS`).
+    Press(Vec<Button>),
+    /// Hold buttons down until released (`HOLD`).
+    Hold(Vec<Button>),
+    /// Release held buttons (`RELEASE`).
+    Release(Vec<Button>),
+    /// Set a stick position (`STICK`).
+    Stick(Stick, f32, f32),
+    /// Set the entire controller state (`STATE`).
+    State(ControllerState),
+    /// Pause command processing on the device for this many seconds (`SLEEP`).
+    Sleep(f32),
+    /// A line sent as given, for commands this crate doesn't model.
+    Raw(String),
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Command::Press(buttons) => f.write_str(&button_command("PRESS", buttons)),
+            Command::Hold(buttons) => f.write_str(&button_command("HOLD", buttons)),
+            Command::Release(buttons) => f.write_str(&button_command("RELEASE", buttons)),
+            Command::Stick(stick, h, v) => f.write_str(&stick_command(*stick, *h, *v)),
+            Command::State(state) => f.write_str(&state.to_command()),
+            Command::Sleep(seconds) => f.write_str(&sleep_command(*seconds)),
+            Command::Raw(line) => f.write_str(line),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn button_display() {
+        assert_eq!(Button::A.to_string(), "a");
+        assert_eq!(Button::ZL.to_string(), "zl");
+        assert_eq!(Button::DpadUp.to_string(), "dpad_up");
+        assert_eq!(Button::ZL.label(), "ZL");
+        assert_eq!(Button::DpadUp.label(), "D-Pad Up");
+        assert_eq!(Button::A.glyph(), Some('Ⓐ'));
+        assert_eq!(Button::ZR.glyph(), None);
+    }
+
+    #[test]
+    fn button_groups() {
+        for category in ButtonCategory::ALL {
+            assert!(category.buttons().iter().all(|b| b.category() == category));
+        }
+        let total: usize = ButtonCategory::ALL.iter().map(|c| c.buttons().len()).sum();
+        assert_eq!(total, Button::ALL.len());
+        assert!(!Button::ALL_NO_HOME.contains(&Button::Home));
+        assert_eq!(Button::ALL_NO_HOME.len(), Button::ALL.len() - 1);
+    }
+
+    #[test]
+    fn state_command_no_sticks() {
+        let state = ControllerState::new();
+        assert_eq!(state.to_command(), "STATE 000000000000000000");
+    }
+
+    #[test]
+    fn state_command_with_buttons() {
+        let mut state = ControllerState::new();
+        state
+            .set_button(Button::A, true)
+            .set_button(Button::X, true)
+            .set_button(Button::Y, true);
+        assert_eq!(state.to_command(), "STATE 101100000000000000");
+    }
+
+    #[test]
+    fn state_command_with_left_stick() {
+        let mut state = ControllerState::new();
+        state.set_button(Button::A, true);
+        state.set_left_stick(0.5, -1.0);
+        assert_eq!(state.to_command(), "STATE 100000000000000000 0.5 -1");
+    }
+
+    #[test]
+    fn state_command_with_both_sticks() {
+        let mut state = ControllerState::new();
+        state.set_button(Button::A, true);
+        state.set_left_stick(0.0, 0.0);
+        state.set_right_stick(-1.0, 0.0);
+        assert_eq!(state.to_command(), "STATE 100000000000000000 0 0 -1 0");
+    }
+
+    #[test]
+    fn state_command_with_right_stick_only() {
+        let mut state = ControllerState::new();
+        state.set_right_stick(-1.0, 0.0);
+        assert_eq!(state.to_command(), "STATE 000000000000000000 0.0 0.0 -1 0");
+    }
+
+    #[test]
+    fn state_builder() {
+        let state = ControllerState::builder()
+            .button(Button::A)
+            .left_stick(0.5, -1.0)
+            .right_stick(-1.0, 0.0)
+            .build();
+        assert_eq!(state.to_command(), "STATE 100000000000000000 0.5 -1 -1 0");
+        assert_eq!(
+            state.sticks(),
+            Some(StickPair::left(0.5, -1.0).with_right(-1.0, 0.0))
+        );
+
+        let mut right_only = ControllerState::new();
+        right_only.set_right_stick(-1.0, 0.0);
+        assert_eq!(right_only.sticks().unwrap().left, (0.0, 0.0));
+        let sticks = ControllerState::builder()
+            .sticks(StickPair::left(0.0, 1.0))
+            .build();
+        assert_eq!(sticks.right_stick, None);
+    }
+
+    #[test]
+    fn merge_states() {
+        let mut movement = ControllerState::new();
+        movement
+            .set_button(Button::B, true)
+            .set_left_stick(1.0, 0.0);
+        let mut combat = ControllerState::new();
+        combat
+            .set_button(Button::A, true)
+            .set_left_stick(0.5, -1.0)
+            .set_right_stick(0.0, 1.0);
+
+        let union = movement.union(&combat);
+        assert_eq!(union.to_command(), "STATE 110000000000000000 1 -1 0 1");
+        movement.merge(&combat);
+        assert_eq!(movement.to_command(), "STATE 110000000000000000 0.5 -1 0 1");
+        movement.clear_buttons();
+        assert_eq!(movement.to_command(), "STATE 000000000000000000 0.5 -1 0 1");
+    }
+
+    #[test]
+    fn state_equality() {
+        let mut a = ControllerState::new();
+        a.set_button(Button::A, true).set_left_stick(0.5, -1.0);
+        let mut b = a.clone();
+        b.set_left_stick(0.501, -1.0);
+        assert_eq!(a, b);
+        assert!(a.approx_eq(&b, 0.01) && !a.approx_eq(&b, 0.0001));
+        b.set_left_stick(0.6, -1.0);
+        assert_ne!(a, b);
+        assert_ne!(ControllerState::new(), ControllerState::neutral());
+        assert_eq!(quantize_axis(-1.0), 0);
+        assert_eq!(quantize_axis(2.0), 255);
+
+        let mut screens = alloc::collections::BTreeMap::new();
+        screens.insert("menu", a.clone());
+        assert_eq!(screens["menu"], a);
+
+        // `b` is back to a hair off `a`, below what the wire can tell apart.
+        b.set_left_stick(0.501, -1.0);
+        let seen: std::collections::HashSet<ControllerState> = [a, b].into_iter().collect();
+        assert_eq!(seen.len(), 1);
+    }
+
+    #[test]
+    fn command_lines() {
+        assert_eq!(
+            Command::Press(alloc::vec![Button::A, Button::B]).to_string(),
+            "PRESS a b"
+        );
+        assert_eq!(
+            Command::Stick(Stick::Right, 0.5, -1.0).to_string(),
+            "STICK r_stick 0.5 -1"
+        );
+        assert_eq!(Command::Sleep(0.25).to_string(), "SLEEP 0.25");
+        assert_eq!(
+            Command::State(ControllerState::neutral()).to_string(),
+            ControllerState::neutral().to_command()
+        );
+    }
+
+    #[test]
+    fn dpad_directions() {
+        let mut state = ControllerState::new();
+        state.set_button(Button::DpadLeft, true);
+        state.set_dpad(Direction::UpRight);
+        assert!(!state.button(Button::DpadLeft));
+        assert_eq!(state.dpad(), Direction::UpRight);
+        state.set_button(Button::DpadLeft, true);
+        assert_eq!(state.dpad(), Direction::Up);
+
+        assert_eq!(Direction::Left.vector(), (-1.0, 0.0));
+        let (x, y) = Direction::DownRight.vector();
+        assert!((x * x + y * y - 1.0).abs() < 1e-6 && x > 0.0 && y > 0.0);
+    }
+
+    #[test]
+    fn socd_cleaning() {
+        let dpad = |up, left, right| {
+            let mut state = ControllerState::new();
+            state
+                .set_button(Button::DpadUp, up)
+                .set_button(Button::DpadLeft, left)
+                .set_button(Button::DpadRight, right);
+            state
+        };
+        let run = |socd, inputs: &[ControllerState]| {
+            let mut cleaner = SocdCleaner::new(socd);
+            inputs
+                .iter()
+                .map(|input| {
+                    let mut state = input.clone();
+                    cleaner.apply(&mut state);
+                    state.dpad()
+                })
+                .collect::<Vec<_>>()
+        };
+        let inputs = [
+            dpad(false, true, false),
+            dpad(true, true, true),
+            dpad(false, true, true),
+            dpad(false, false, true),
+        ];
+        assert_eq!(
+            run(Socd::LastWins, &inputs),
+            [
+                Direction::Left,
+                Direction::UpRight,
+                Direction::Right,
+                Direction::Right
+            ]
+        );
+        assert_eq!(
+            run(Socd::Neutral, &inputs),
+            [
+                Direction::Left,
+                Direction::Up,
+                Direction::Neutral,
+                Direction::Right
+            ]
+        );
+        assert_eq!(
+            run(Socd::LastWins, &[dpad(false, true, true)]),
+            [Direction::Neutral]
+        );
+    }
+}
@@ -0,0 +1,316 @@
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt;
+use core::str::FromStr;
+
+use crate::{Button, Command, ControllerState, Stick};
+
+/// What is wrong with a command line, as part of a [`ParseError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParseErrorKind {
+    /// The line is blank or a comment, where a command was required.
+    Empty,
+    /// The first word is not a command.
+    UnknownCommand,
+    /// `PRESS`, `HOLD` or `RELEASE` without a button.
+    MissingButton,
+    UnknownButton,
+    /// `STICK` without a stick name.
+    MissingStick,
+    UnknownStick,
+    /// The line ends before a required number.
+    MissingValue,
+    /// A word that should be a number isn't one, or isn't finite.
+    InvalidNumber,
+    /// A stick value outside [-1.0, 1.0], or a negative `SLEEP`.
+    OutOfRange,
+    /// `STATE` button bits that aren't 18 digits of `0` or `1`.
+    InvalidButtonBits,
+    /// More words than the command takes.
+    TrailingInput,
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ParseErrorKind::Empty => "no command",
+            ParseErrorKind::UnknownCommand => "unknown command",
+            ParseErrorKind::MissingButton => "missing button",
+            ParseErrorKind::UnknownButton => "unknown button",
+            ParseErrorKind::MissingStick => "missing stick",
+            ParseErrorKind::UnknownStick => "unknown stick",
+            ParseErrorKind::MissingValue => "missing value",
+            ParseErrorKind::InvalidNumber => "invalid number",
+            ParseErrorKind::OutOfRange => "value out of range",
+            ParseErrorKind::InvalidButtonBits => "invalid button bits",
+            ParseErrorKind::TrailingInput => "unexpected text",
+        })
+    }
+}
+
+/// An error decoding a command line, at a byte offset into the line: the
+/// start of the offending word, the offending digit of `STATE` button bits,
+/// or the end of the line for something missing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParseError {
+    pub offset: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.kind, self.offset)
+    }
+}
+
+impl Error for ParseError {}
+
+/// The words of a line with their byte offsets.
+struct Words<'a> {
+    line: &'a str,
+    rest: core::str::SplitAsciiWhitespace<'a>,
+}
+
+impl<'a> Words<'a> {
+    fn new(line: &'a str) -> Self {
+        Self {
+            line,
+            rest: line.split_ascii_whitespace(),
+        }
+    }
+
+    fn offset(&self, word: &str) -> usize {
+        word.as_ptr() as usize - self.line.as_ptr() as usize
+    }
+
+    fn error(&self, word: &str, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            offset: self.offset(word),
+            kind,
+        }
+    }
+
+    fn end(&self, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            offset: self.line.len(),
+            kind,
+        }
+    }
+
+    /// Fail unless every word has been read.
+    fn finish(&mut self) -> Result<(), ParseError> {
+        match self.rest.next() {
+            Some(word) => Err(self.error(word, ParseErrorKind::TrailingInput)),
+            None => Ok(()),
+        }
+    }
+
+    fn number(&mut self, min: f32, max: f32) -> Result<f32, ParseError> {
+        let word = self
+            .rest
+            .next()
+            .ok_or_else(|| self.end(ParseErrorKind::MissingValue))?;
+        self.value(word, min, max)
+    }
+
+    fn value(&self, word: &str, min: f32, max: f32) -> Result<f32, ParseError> {
+        let value: f32 = word
+            .parse()
+            .ok()
+            .filter(|v: &f32| v.is_finite())
+            .ok_or_else(|| self.error(word, ParseErrorKind::InvalidNumber))?;
+        if !(min..=max).contains(&value) {
+            return Err(self.error(word, ParseErrorKind::OutOfRange));
+        }
+        Ok(value)
+    }
+
+    fn buttons(&mut self) -> Result<Vec<Button>, ParseError> {
+        let mut buttons = Vec::new();
+        while let Some(word) = self.rest.next() {
+            let button = Button::ALL
+                .into_iter()
+                .find(|b| b.as_str().eq_ignore_ascii_case(word))
+                .ok_or_else(|| self.error(word, ParseErrorKind::UnknownButton))?;
+            buttons.push(button);
+        }
+        if buttons.is_empty() {
+            return Err(self.end(ParseErrorKind::MissingButton));
+        }
+        Ok(buttons)
+    }
+
+    fn state(&mut self) -> Result<ControllerState, ParseError> {
+        let bits = self
+            .rest
+            .next()
+            .ok_or_else(|| self.end(ParseErrorKind::InvalidButtonBits))?;
+        let mut state = ControllerState::new();
+        let mut digits = bits.bytes();
+        for (i, button) in Button::ALL.into_iter().enumerate() {
+            let pressed = match digits.next() {
+                Some(b'0') => false,
+                Some(b'1') => true,
+                _ => {
+                    return Err(ParseError {
+                        offset: self.offset(bits) + i.min(bits.len()),
+                        kind: ParseErrorKind::InvalidButtonBits,
+                    });
+                }
+            };
+            state.set_button(button, pressed);
+        }
+        if digits.next().is_some() {
+            return Err(ParseError {
+                offset: self.offset(bits) + Button::ALL.len(),
+                kind: ParseErrorKind::InvalidButtonBits,
+            });
+        }
+        let Some(first) = self.rest.next() else {
+            return Ok(state);
+        };
+        let lh = self.value(first, -1.0, 1.0)?;
+        let lv = self.number(-1.0, 1.0)?;
+        state.set_left_stick(lh, lv);
+        if let Some(word) = self.rest.next() {
+            let rh = self.value(word, -1.0, 1.0)?;
+            let rv = self.number(-1.0, 1.0)?;
+            state.set_right_stick(rh, rv);
+        }
+        Ok(state)
+    }
+}
+
+/// Decode one line of the wire format (see `COMMANDS.md`) into a
+/// [`Command`], or `None` for a blank or comment line.
+///
+/// Commands and names are case-insensitive and surrounding whitespace,
+/// including a trailing `\r\n`, is ignored. Decoding is strict: stick
+/// values must be within [-1.0, 1.0] and nothing may follow a command's
+/// arguments. It never panics, whatever the input.
+pub fn parse_line(line: &str) -> Result<Option<Command>, ParseError> {
+    let mut words = Words::new(line);
+    let Some(verb) = words.rest.next() else {
+        return Ok(None);
+    };
+    if verb.starts_with('#') {
+        return Ok(None);
+    }
+    let is = |name: &str| verb.eq_ignore_ascii_case(name);
+    let command = if is("press") {
+        Command::Press(words.buttons()?)
+    } else if is("hold") {
+        Command::Hold(words.buttons()?)
+    } else if is("release") {
+        Command::Release(words.buttons()?)
+    } else if is("stick") {
+        let name = words
+            .rest
+            .next()
+            .ok_or_else(|| words.end(ParseErrorKind::MissingStick))?;
+        let stick = [Stick::Left, Stick::Right]
+            .into_iter()
+            .find(|s| s.as_str().eq_ignore_ascii_case(name))
+            .ok_or_else(|| words.error(name, ParseErrorKind::UnknownStick))?;
+        let h = words.number(-1.0, 1.0)?;
+        let v = words.number(-1.0, 1.0)?;
+        Command::Stick(stick, h, v)
+    } else if is("state") {
+        Command::State(words.state()?)
+    } else if is("sleep") {
+        Command::Sleep(words.number(0.0, f32::MAX)?)
+    } else {
+        return Err(words.error(verb, ParseErrorKind::UnknownCommand));
+    };
+    words.finish()?;
+    Ok(Some(command))
+}
+
+impl FromStr for Command {
+    type Err = ParseError;
+
+    /// Decode a command line with [`parse_line`], failing with
+    /// [`ParseErrorKind::Empty`] for a blank or comment line.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_line(s)?.ok_or(ParseError {
+            offset: 0,
+            kind: ParseErrorKind::Empty,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn parses_wire_lines() {
+        let lines = [
+            "PRESS a b",
+            "hold ZR",
+            "RELEASE dpad_up",
+            "STICK r_stick 0.5 -1",
+            "STATE 100000000000000000 0 0 -1 0",
+            "SLEEP 0.25",
+        ];
+        for line in lines {
+            let command: Command = line.parse().unwrap();
+            assert!(command.to_string().eq_ignore_ascii_case(line));
+        }
+        assert_eq!(parse_line("  # comment\r\n"), Ok(None));
+        assert_eq!(
+            parse_line("PRESS a\r\n").unwrap().unwrap().to_string(),
+            "PRESS a"
+        );
+
+        let error = |line: &str| parse_line(line).unwrap_err();
+        let at = |offset, kind| ParseError { offset, kind };
+        assert_eq!(error("JUMP"), at(0, ParseErrorKind::UnknownCommand));
+        assert_eq!(error("PRESS"), at(5, ParseErrorKind::MissingButton));
+        assert_eq!(error("PRESS a q"), at(8, ParseErrorKind::UnknownButton));
+        assert_eq!(error("STICK c 0 0"), at(6, ParseErrorKind::UnknownStick));
+        assert_eq!(
+            error("STICK l_stick 0"),
+            at(15, ParseErrorKind::MissingValue)
+        );
+        assert_eq!(
+            error("STICK l_stick x 0"),
+            at(14, ParseErrorKind::InvalidNumber)
+        );
+        assert_eq!(
+            error("STICK l_stick 2 0"),
+            at(14, ParseErrorKind::OutOfRange)
+        );
+        assert_eq!(
+            error("STATE 0001"),
+            at(10, ParseErrorKind::InvalidButtonBits)
+        );
+        assert_eq!(
+            error("STATE 000200000000000000"),
+            at(9, ParseErrorKind::InvalidButtonBits)
+        );
+        assert_eq!(
+            error("STATE 000000000000000000 0"),
+            at(26, ParseErrorKind::MissingValue)
+        );
+        assert_eq!(error("SLEEP 1 2"), at(8, ParseErrorKind::TrailingInput));
+        assert_eq!(error("SLEEP nan"), at(6, ParseErrorKind::InvalidNumber));
+        assert_eq!("".parse::<Command>(), Err(at(0, ParseErrorKind::Empty)));
+
+        // Every truncation and single-byte change of valid lines decodes or
+        // fails cleanly.
+        for line in lines {
+            for end in 0..=line.len() {
+                let _ = parse_line(&line[..end]);
+            }
+            for i in 0..line.len() {
+                for byte in [b' ', b'1', b'-', b'#', b'\xff'] {
+                    let mut bytes = line.as_bytes().to_vec();
+                    bytes[i] = byte;
+                    let _ = parse_line(&alloc::string::String::from_utf8_lossy(&bytes));
+                }
+            }
+        }
+    }
+}
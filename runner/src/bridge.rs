@@ -0,0 +1,133 @@
+use std::io::{self, BufReader};
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use switchcontroller::{
+    Command, ControllerState, FrameReader, JitterBuffer, Passthrough, PassthroughConfig, Profile,
+    SwitchController, parse_line,
+};
+
+/// How often the bridge checks for an interrupt while waiting.
+const POLL: Duration = Duration::from_millis(50);
+
+/// `runner bridge <address> <serial-port> [--jitter <ms>] [--passthrough]`:
+/// forward commands from one network client at a time to the device,
+/// expanding compressed batches and replaying timestamped commands through a
+/// jitter buffer `jitter` long, until `interrupted` is set.
+///
+/// Lines that aren't valid commands are logged and dropped rather than
+/// passed to the device. With `passthrough`, the client is a live gamepad:
+/// its `STATE` lines go through a [`Passthrough`], calibrated by the
+/// profile, which coalesces them and neutralizes the device when the client
+/// goes silent.
+pub fn bridge(
+    address: &str,
+    port: &str,
+    jitter: Duration,
+    passthrough: bool,
+    profile: Option<&Profile>,
+    interrupted: &AtomicBool,
+) -> io::Result<()> {
+    let mut ctrl = SwitchController::open(port, 115200)?;
+    crate::setup(&mut ctrl, profile);
+    let listener = TcpListener::bind(address)?;
+    eprintln!(
+        "bridging {} to {port} with a {} ms jitter buffer",
+        listener.local_addr()?,
+        jitter.as_millis()
+    );
+    listener.set_nonblocking(true)?;
+    while !interrupted.load(Ordering::Relaxed) {
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted
+                ) =>
+            {
+                thread::sleep(POLL);
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        stream.set_nonblocking(false)?;
+        let peer = stream.peer_addr()?;
+        eprintln!("{peer} connected");
+        let (tx, rx) = mpsc::channel();
+        let mut reader = FrameReader::new(BufReader::new(stream.try_clone()?), stream);
+        thread::spawn(move || {
+            loop {
+                match reader.next_line() {
+                    Ok(Some(line)) => {
+                        if tx.send(line).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        eprintln!("{peer}: {e}");
+                        break;
+                    }
+                }
+            }
+        });
+
+        let mut buffer = JitterBuffer::new(jitter);
+        let config = PassthroughConfig::default();
+        let mut live = passthrough.then(|| {
+            let mut live = Passthrough::new(config);
+            if let Some(profile) = profile {
+                live.set_profile(profile);
+            }
+            live
+        });
+        let mut connected = true;
+        while connected || !buffer.is_empty() {
+            if interrupted.load(Ordering::Relaxed) {
+                // Dropping the controller releases everything.
+                return Ok(());
+            }
+            while let Some(line) = buffer.pop_due(Instant::now()) {
+                match (parse_line(&line), &mut live) {
+                    (Ok(Some(Command::State(state))), Some(live)) => {
+                        live.feed(&mut ctrl, &state)?
+                    }
+                    (Ok(Some(command)), _) => ctrl.execute(&command)?,
+                    (Ok(None), _) => {}
+                    (Err(e), _) => eprintln!("{peer}: rejected `{line}`: {e}"),
+                }
+            }
+            // Passthrough sends coalesced states and idle neutrals on ticks,
+            // so it sets how long to wait at most.
+            let mut poll = POLL;
+            if let Some(live) = &mut live {
+                live.tick(&mut ctrl)?;
+                poll = poll.min(config.send_interval);
+            }
+            let timeout = buffer
+                .next_due()
+                .map_or(poll, |due| due.saturating_duration_since(Instant::now()))
+                .min(poll);
+            if !connected {
+                thread::sleep(timeout);
+                continue;
+            }
+            match rx.recv_timeout(timeout) {
+                Ok(line) => {
+                    if let Err(e) = buffer.push(&line) {
+                        eprintln!("{peer}: {e}");
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => connected = false,
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+        }
+        eprintln!("{peer} disconnected, releasing all inputs");
+        ctrl.state(&ControllerState::neutral())?;
+    }
+    Ok(())
+}
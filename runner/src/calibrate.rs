@@ -0,0 +1,86 @@
+use std::io::{self, BufRead, BufReader};
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use switchcontroller::{
+    Calibration, CalibrationStep, CalibrationWizard, Command, ControllerState, Profile, Stick,
+    parse_line,
+};
+
+/// `runner calibrate <address> <profile>`: take raw readings from one
+/// network client sending its gamepad as `STATE` or `STICK` lines, as for
+/// `runner bridge --passthrough`, walk through the calibration steps on the
+/// terminal, and save the result to the named profile, creating it if
+/// needed. Returns early, saving nothing, once `interrupted` is set.
+pub fn calibrate(address: &str, name: &str, interrupted: &AtomicBool) -> io::Result<()> {
+    let dir = Profile::default_dir();
+    let mut profile = match Profile::load(&dir, name) {
+        Ok(profile) => profile,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Profile::new(name),
+        Err(e) => return Err(e),
+    };
+    let listener = TcpListener::bind(address)?;
+    eprintln!("waiting for the gamepad on {}", listener.local_addr()?);
+    let stream = loop {
+        match listener.accept() {
+            Ok((stream, _)) => break stream,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {
+                if interrupted.load(Ordering::Relaxed) {
+                    return Ok(());
+                }
+            }
+            Err(e) => return Err(e),
+        }
+    };
+    eprintln!("{} connected", stream.peer_addr()?);
+
+    let wizard = Arc::new(Mutex::new(CalibrationWizard::new()));
+    let samples = Arc::clone(&wizard);
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else { break };
+            let raw = match parse_line(&line) {
+                Ok(Some(Command::State(state))) => state,
+                Ok(Some(Command::Stick(stick, h, v))) => {
+                    let mut state = ControllerState::new();
+                    match stick {
+                        Stick::Left => state.set_left_stick(h, v),
+                        Stick::Right => state.set_right_stick(h, v),
+                    };
+                    state
+                }
+                _ => continue,
+            };
+            samples.lock().unwrap().sample(&raw);
+        }
+    });
+
+    let mut input = io::stdin().lock();
+    let mut step = wizard.lock().unwrap().step();
+    while step != CalibrationStep::Done {
+        println!("{step} Press Enter when done.");
+        if crate::read_line(&mut input, interrupted)?.is_none() {
+            if interrupted.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "input ended before calibration was complete",
+            ));
+        }
+        step = wizard.lock().unwrap().advance();
+    }
+    let calibration = wizard.lock().unwrap().calibration();
+    if calibration == Calibration::default() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "no stick readings received from the gamepad",
+        ));
+    }
+    profile.calibration = Some(calibration);
+    profile.save(&dir)?;
+    println!("{step} Saved to profile {}.", profile.name);
+    Ok(())
+}
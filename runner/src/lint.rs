@@ -0,0 +1,40 @@
+use std::io;
+use std::path::Path;
+
+use switchcontroller::{InputSequence, Program, ScriptLoader, SequenceFile, Validation};
+
+/// Read `path` by its extension: `.json` sequence files, `.toml` sequences,
+/// `.txt` nx-TAS scripts, and macro scripts otherwise.
+fn load(path: &Path) -> io::Result<Program> {
+    let sequence = match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => SequenceFile::load(path)?.sequence,
+        Some("toml") => InputSequence::from_toml(&std::fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+        Some("txt") => InputSequence::load_nx_tas(path)?,
+        _ => return ScriptLoader::from_env().load_program(path),
+    };
+    Ok(Program::from(sequence))
+}
+
+/// Parse and validate `path` without touching hardware, printing what was
+/// found. Returns whether the file is free of issues.
+pub fn lint(path: &str) -> bool {
+    let program = match load(Path::new(path)) {
+        Ok(program) => program,
+        Err(e) => {
+            println!("{path}: {e}");
+            return false;
+        }
+    };
+    let Validation { issues, duration } = program.validate();
+    for issue in &issues {
+        println!("{path}: {issue}");
+    }
+    let looping = if program.has_jumps() { " per pass" } else { "" };
+    println!(
+        "{path}: {} steps, {duration:?}{looping}, {} issues",
+        program.instructions().len(),
+        issues.len()
+    );
+    issues.is_empty()
+}
@@ -1,23 +1,352 @@
-use switchcontroller::{Button, ControllerState, Stick, SwitchController};
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+mod bridge;
+mod calibrate;
+mod lint;
+mod packs;
+mod repl;
+mod run;
+mod serve;
+
+use switchcontroller::{
+    Button, ControllerState, InputSequence, Profile, Scheduler, ScriptLoader, Stick,
+    SwitchController, available_ports,
+};
+
+fn usage() -> ! {
+    eprintln!("Usage: runner <serial-port> [baud-rate] [--report] [--repl]");
+    eprintln!("       runner run <script> <serial-port> [baud-rate] [--step | --frame-advance]");
+    eprintln!("       runner lint <file>...");
+    eprintln!("       runner packs install <dir> | runner packs list");
+    eprintln!("       runner serve <address> <serial-port>... [--schedule <file>]");
+    eprintln!("       runner bridge <address> <serial-port> [--jitter <ms>] [--passthrough]");
+    eprintln!("       runner calibrate <address> <profile>");
+    eprintln!("  --profile <name> applies a profile to the devices of any mode");
+    eprintln!("  e.g. runner /dev/ttyACM0 115200 --profile splatoon-aim");
+    if let Ok(ports) = available_ports() {
+        if !ports.is_empty() {
+            eprintln!("Available ports:");
+        }
+        for port in ports {
+            let pico = if port.is_pico() { " [Pico]" } else { "" };
+            match port.name {
+                Some(name) => eprintln!("  {} ({name}){pico}", port.path),
+                None => eprintln!("  {}{pico}", port.path),
+            }
+        }
+    }
+    std::process::exit(1);
+}
+
+/// Set by SIGINT/SIGTERM; cancels playback. A second signal exits at once.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+fn install_signal_handlers() {
+    extern "C" fn on_signal(_: libc::c_int) {
+        if INTERRUPTED.swap(true, Ordering::Relaxed) {
+            // SAFETY: _exit is async-signal-safe.
+            unsafe { libc::_exit(130) };
+        }
+    }
+    // Without SA_RESTART, so that a signal also interrupts a blocked read of
+    // stdin (see `read_line`) rather than waiting for the next line.
+    // SAFETY: the handler only touches an atomic and calls _exit, both
+    // async-signal-safe, and `action` is fully initialized before use.
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(libc::SIGINT, &action, std::ptr::null_mut());
+        libc::sigaction(libc::SIGTERM, &action, std::ptr::null_mut());
+    }
+}
+
+#[cfg(not(unix))]
+fn install_signal_handlers() {}
+
+/// Read a line from `input` without its line ending, or `None` at the end
+/// of input or once `interrupted` is set, which a signal arriving during
+/// the read also does. `BufRead::lines` would retry the read instead.
+pub(crate) fn read_line(
+    input: &mut impl BufRead,
+    interrupted: &AtomicBool,
+) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    loop {
+        if interrupted.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+        let buf = match input.fill_buf() {
+            Ok(buf) => buf,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if buf.is_empty() {
+            if line.is_empty() {
+                return Ok(None);
+            }
+            break;
+        }
+        match buf.iter().position(|&b| b == b'\n') {
+            Some(end) => {
+                line.extend_from_slice(&buf[..end]);
+                input.consume(end + 1);
+                break;
+            }
+            None => {
+                line.extend_from_slice(buf);
+                let len = buf.len();
+                input.consume(len);
+            }
+        }
+    }
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    String::from_utf8(line)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Release everything when the runner exits, however it exits, and apply
+/// the `--profile`: its timings and confirm button, and its mapping to every
+/// command sent.
+pub(crate) fn setup(ctrl: &mut SwitchController, profile: Option<&Profile>) {
+    let mut neutral = InputSequence::new();
+    neutral.state(&ControllerState::neutral());
+    ctrl.set_shutdown_sequence(neutral);
+    if let Some(profile) = profile {
+        ctrl.set_timings(profile.timings())
+            .set_confirm_button(profile.confirm)
+            .add_layer(profile.remap.clone());
+    }
+}
+
+/// Stop after playback is cancelled: the shutdown sequence neutralizes the
+/// controller as it is dropped.
+fn exit_interrupted(ctrl: SwitchController) -> ! {
+    eprintln!("interrupted, releasing all inputs");
+    drop(ctrl);
+    let _ = std::io::stdout().flush();
+    std::process::exit(130);
+}
 
 fn main() {
-    let port = std::env::args().nth(1).unwrap_or_else(|| {
-        eprintln!("Usage: runner <serial-port> [baud-rate]");
-        eprintln!("  e.g. runner /dev/ttyACM0 115200");
-        std::process::exit(1);
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    // Applies to every mode that opens a device
+    let profile = match args.iter().position(|a| a == "--profile") {
+        Some(i) if i + 1 < args.len() => {
+            let name = args.remove(i + 1);
+            args.remove(i);
+            let dir = Profile::default_dir();
+            Some(Profile::load(&dir, &name).unwrap_or_else(|e| {
+                eprintln!("failed to load profile {name} from {}: {e}", dir.display());
+                std::process::exit(1);
+            }))
+        }
+        Some(_) => usage(),
+        None => None,
+    };
+    if args.first().is_some_and(|a| a == "lint") {
+        if args.len() < 2 {
+            usage();
+        }
+        // Lint every file, rather than stopping at the first bad one
+        let results: Vec<bool> = args[1..].iter().map(|path| lint::lint(path)).collect();
+        std::process::exit(if results.iter().all(|&ok| ok) { 0 } else { 1 });
+    }
+    if args.first().is_some_and(|a| a == "packs") {
+        if let Err(e) = packs::packs(&args[1..]) {
+            eprintln!("packs: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.first().is_some_and(|a| a == "serve") {
+        let schedule = match args.iter().position(|a| a == "--schedule") {
+            Some(i) if i + 1 < args.len() => {
+                let path = args.remove(i + 1);
+                args.remove(i);
+                Some(Scheduler::load(&path).unwrap_or_else(|e| {
+                    eprintln!("failed to load schedule {path}: {e}");
+                    std::process::exit(1);
+                }))
+            }
+            Some(_) => usage(),
+            None => None,
+        };
+        if args.len() < 3 {
+            usage();
+        }
+        install_signal_handlers();
+        if let Err(e) = serve::serve(
+            &args[1],
+            &args[2..],
+            profile.as_ref(),
+            schedule,
+            &INTERRUPTED,
+        ) {
+            eprintln!("serve: {e}");
+            std::process::exit(1);
+        }
+        if INTERRUPTED.load(Ordering::Relaxed) {
+            eprintln!("interrupted, released all inputs");
+            std::process::exit(130);
+        }
+        return;
+    }
+    if args.first().is_some_and(|a| a == "bridge") {
+        let jitter = match args.iter().position(|a| a == "--jitter") {
+            Some(i) if i + 1 < args.len() => {
+                let ms = args.remove(i + 1).parse().unwrap_or_else(|_| usage());
+                args.remove(i);
+                Duration::from_millis(ms)
+            }
+            Some(_) => usage(),
+            None => Duration::from_millis(50),
+        };
+        let passthrough = match args.iter().position(|a| a == "--passthrough") {
+            Some(i) => {
+                args.remove(i);
+                true
+            }
+            None => false,
+        };
+        if args.len() != 3 {
+            usage();
+        }
+        install_signal_handlers();
+        if let Err(e) = bridge::bridge(
+            &args[1],
+            &args[2],
+            jitter,
+            passthrough,
+            profile.as_ref(),
+            &INTERRUPTED,
+        ) {
+            eprintln!("bridge: {e}");
+            std::process::exit(1);
+        }
+        if INTERRUPTED.load(Ordering::Relaxed) {
+            eprintln!("interrupted, released all inputs");
+            std::process::exit(130);
+        }
+        return;
+    }
+    if args.first().is_some_and(|a| a == "calibrate") {
+        if args.len() != 3 {
+            usage();
+        }
+        install_signal_handlers();
+        if let Err(e) = calibrate::calibrate(&args[1], &args[2], &INTERRUPTED) {
+            eprintln!("calibrate: {e}");
+            std::process::exit(1);
+        }
+        if INTERRUPTED.load(Ordering::Relaxed) {
+            std::process::exit(130);
+        }
+        return;
+    }
+    let script = match args.first().map(String::as_str) {
+        Some("run") if args.len() > 1 => {
+            args.remove(0);
+            Some(args.remove(0))
+        }
+        Some("run") => usage(),
+        _ => None,
+    };
+    let step = match args.iter().position(|a| a == "--step") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+    let frame_advance = match args.iter().position(|a| a == "--frame-advance") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+    let json_report = match args.iter().position(|a| a == "--report") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+    let interactive = match args.iter().position(|a| a == "--repl") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+    let program = script.map(|path| {
+        ScriptLoader::from_env()
+            .load_program(&path)
+            .unwrap_or_else(|e| {
+                eprintln!("failed to load {path}: {e}");
+                std::process::exit(1);
+            })
     });
-    let baud: u32 = std::env::args()
-        .nth(2)
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(115200);
+    let port = args.first().cloned().unwrap_or_else(|| usage());
+    let baud: u32 = args.get(1).and_then(|s| s.parse().ok()).unwrap_or(115200);
 
     let mut ctrl = SwitchController::open(&port, baud).expect("failed to open serial port");
+    install_signal_handlers();
+    setup(&mut ctrl, profile.as_ref());
 
-    // Press A
-    ctrl.press(&[Button::A]).unwrap();
-    ctrl.sleep(1.0).unwrap();
-    ctrl.press(&[Button::Y]).unwrap();
+    if let Some(program) = program {
+        let result = if frame_advance {
+            run::frame_advance(&mut ctrl, &program, &INTERRUPTED)
+        } else {
+            run::run(&mut ctrl, &program, step, &INTERRUPTED)
+        };
+        if let Err(e) = result {
+            eprintln!("script failed: {e}");
+            drop(ctrl);
+            std::process::exit(1);
+        }
+        if INTERRUPTED.load(Ordering::Relaxed) {
+            exit_interrupted(ctrl);
+        }
+        return;
+    }
 
+    if interactive {
+        if let Err(e) = repl::run(&mut ctrl, &INTERRUPTED) {
+            eprintln!("repl failed: {e}");
+            drop(ctrl);
+            std::process::exit(1);
+        }
+        if INTERRUPTED.load(Ordering::Relaxed) {
+            exit_interrupted(ctrl);
+        }
+        return;
+    }
+
+    // Press A, then Y, through the profile's mapping if one was given
+    let mut seq = InputSequence::new();
+    seq.press(&[Button::A])
+        .wait(Duration::from_secs(1))
+        .press(&[Button::Y]);
+    let report = ctrl.play_looped(&seq, 1, &INTERRUPTED);
+    if json_report {
+        println!("{}", report.to_json());
+    }
+    if INTERRUPTED.load(Ordering::Relaxed) {
+        exit_interrupted(ctrl);
+    }
+    if let Some(error) = report.errors.first() {
+        eprintln!("playback failed: {error}");
+        drop(ctrl);
+        std::process::exit(1);
+    }
 
     // // Hold ZR, press A, then release ZR
     // ctrl.hold(&[Button::ZR]).unwrap();
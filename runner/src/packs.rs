@@ -0,0 +1,40 @@
+use std::io;
+
+use switchcontroller::Pack;
+
+/// `runner packs install <dir>` and `runner packs list`, against
+/// [`Pack::default_dir`].
+pub fn packs(args: &[String]) -> io::Result<()> {
+    let dir = Pack::default_dir();
+    match args {
+        [cmd, source] if cmd == "install" => {
+            let pack = Pack::install(source, &dir)?;
+            println!(
+                "installed {} to {}",
+                pack.manifest().name,
+                pack.root().display()
+            );
+        }
+        [cmd] if cmd == "list" => {
+            if !dir.is_dir() {
+                return Ok(());
+            }
+            for pack in Pack::list(&dir)? {
+                let manifest = pack.manifest();
+                let version = manifest.version.as_deref().unwrap_or("-");
+                println!("{} {version}", manifest.name);
+                if let Some(description) = &manifest.description {
+                    println!("  {description}");
+                }
+                for name in pack.sequences()? {
+                    println!("  sequence {name}");
+                }
+                for name in pack.profiles()? {
+                    println!("  profile {name}");
+                }
+            }
+        }
+        _ => crate::usage(),
+    }
+    Ok(())
+}
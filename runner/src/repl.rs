@@ -0,0 +1,105 @@
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use switchcontroller::{InputSequence, PlaybackPosition, Recorder, SwitchController};
+
+const HELP: &str = "\
+Commands:
+  <command>            send a command, e.g. `press a` or `stick l_stick 1 0`
+  wait <duration>      wait on the host (750ms, 2.5s, 12f)
+  play <file>          play a macro script
+  play <file> <from> [<to>]
+                       play it from one marker to another (or the end)
+  record start <name>  start recording the session to <name>.macro
+  mark <name>          add a marker to the recording
+  record stop          stop recording and save the macro
+  help                 show this help
+  quit                 leave the REPL";
+
+/// Read commands from stdin and send them until `quit`, end of input, or
+/// `interrupted` is set.
+pub fn run(ctrl: &mut SwitchController, interrupted: &AtomicBool) -> io::Result<()> {
+    let mut recording: Option<(String, Recorder)> = None;
+    let mut input = io::stdin().lock();
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
+        let Some(line) = crate::read_line(&mut input, interrupted)? else {
+            break;
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let result = match words[..] {
+            [] => Ok(()),
+            ["quit" | "exit"] => break,
+            ["help"] => {
+                println!("{HELP}");
+                Ok(())
+            }
+            ["record", "start", name] => {
+                if recording.is_some() {
+                    println!("already recording; `record stop` first");
+                } else {
+                    recording = Some((name.to_string(), Recorder::start(ctrl)));
+                    println!("recording to {name}.macro");
+                }
+                Ok(())
+            }
+            ["mark", name] => {
+                match &mut recording {
+                    Some((_, recorder)) => recorder.mark(name),
+                    None => println!("not recording"),
+                }
+                Ok(())
+            }
+            ["record", "stop"] => match recording.take() {
+                Some((name, recorder)) => {
+                    let path = format!("{name}.macro");
+                    let seq = recorder.stop();
+                    seq.save_script(&path)
+                        .map(|()| println!("saved {} steps to {path}", seq.len()))
+                }
+                None => {
+                    println!("not recording");
+                    Ok(())
+                }
+            },
+            ["play", path] => {
+                InputSequence::load_script(path).and_then(|seq| play(ctrl, &seq, interrupted))
+            }
+            ["play", path, from] | ["play", path, from, _] => {
+                let to = words.get(3).copied();
+                InputSequence::load_script(path)
+                    .and_then(|seq| ctrl.play_between(&seq, from, to))
+                    .and_then(|()| ctrl.flush())
+            }
+            _ => match line.parse::<InputSequence>() {
+                Ok(seq) => play(ctrl, &seq, interrupted),
+                Err(e) => {
+                    println!("{}; type `help` for commands", e.message);
+                    Ok(())
+                }
+            },
+        };
+        if let Err(e) = result {
+            println!("error: {e}");
+        }
+        if interrupted.load(Ordering::Relaxed) {
+            break;
+        }
+    }
+    if let Some((name, recorder)) = recording {
+        let path = format!("{name}.macro");
+        recorder.stop().save_script(&path)?;
+        println!("saved unfinished recording to {path}");
+    }
+    Ok(())
+}
+
+fn play(
+    ctrl: &mut SwitchController,
+    seq: &InputSequence,
+    interrupted: &AtomicBool,
+) -> io::Result<()> {
+    ctrl.play_cancellable(seq, PlaybackPosition::default(), interrupted)?;
+    ctrl.flush()
+}
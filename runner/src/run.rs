@@ -0,0 +1,78 @@
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use switchcontroller::{Instruction, Program, SwitchController};
+
+/// Run `program`, asking on the terminal whenever it checks `detected(..)`,
+/// since the runner has no capture support of its own.
+///
+/// With `step`, print each command before it is sent and wait for Enter;
+/// `q` stops the script. So does the end of input, which would otherwise
+/// leave a detection loop spinning.
+pub fn run(
+    ctrl: &mut SwitchController,
+    program: &Program,
+    step: bool,
+    interrupted: &AtomicBool,
+) -> io::Result<()> {
+    let mut input = io::stdin().lock();
+    let mut ask = |prompt: &str| -> io::Result<Option<String>> {
+        print!("{prompt}");
+        io::stdout().flush()?;
+        crate::read_line(&mut input, interrupted)
+    };
+    let mut pc = Some(0);
+    while let Some(index) = pc {
+        if interrupted.load(Ordering::Relaxed) {
+            break;
+        }
+        let Some(instruction) = program.instructions().get(index) else {
+            break;
+        };
+        if let Instruction::Step(s) = instruction
+            && let Some(cmd) = step.then(|| s.to_command()).flatten()
+        {
+            let answer = ask(&format!("[{index}] {cmd} (Enter to send, q to stop) "))?;
+            if answer.is_none_or(|a| a.trim() == "q") {
+                break;
+            }
+        }
+        let mut detected = false;
+        if let Instruction::IfDetected { template, .. } = instruction {
+            let Some(answer) = ask(&format!("[{index}] is {template} on screen? [y/N] "))? else {
+                break;
+            };
+            detected = answer.trim().eq_ignore_ascii_case("y");
+        }
+        pc = ctrl.step_program(program, index, |_| detected)?;
+        ctrl.flush()?;
+    }
+    Ok(())
+}
+
+/// Play `program` a frame at a time, printing each frame's `STATE` and
+/// waiting for Enter before the next; `q` or the end of input stops.
+/// Scripts that check the screen cannot be played this way.
+pub fn frame_advance(
+    ctrl: &mut SwitchController,
+    program: &Program,
+    interrupted: &AtomicBool,
+) -> io::Result<()> {
+    let sequence = program.to_sequence().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "frame advance needs a script without detection checks or jumps",
+        )
+    })?;
+    let mut input = io::stdin().lock();
+    ctrl.play_frame_advance(&sequence, |frame, state| {
+        print!(
+            "[frame {frame}] {} (Enter for next frame, q to stop) ",
+            state.to_command()
+        );
+        let _ = io::stdout().flush();
+        let answer = crate::read_line(&mut input, interrupted).ok().flatten();
+        answer.is_some_and(|a| a.trim() != "q")
+    })?;
+    Ok(())
+}
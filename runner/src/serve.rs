@@ -0,0 +1,68 @@
+use std::io;
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use switchcontroller::{
+    JobManager, Profile, RpcServer, Scheduler, SchedulerEvent, SwitchController,
+};
+
+/// `runner serve <address> <serial-port>... [--schedule <file>]`: open every
+/// port, naming each device after its path, and answer JSON-RPC requests on
+/// `address` until `interrupted` is set. Every device is released as the
+/// jobs are dropped.
+///
+/// With a `schedule`, its runs are queued as jobs on their devices too,
+/// where RPC clients can see and cancel them.
+pub fn serve(
+    address: &str,
+    ports: &[String],
+    profile: Option<&Profile>,
+    schedule: Option<Scheduler>,
+    interrupted: &AtomicBool,
+) -> io::Result<()> {
+    let mut jobs = JobManager::new();
+    for port in ports {
+        let mut ctrl = SwitchController::open(port, 115200)?;
+        crate::setup(&mut ctrl, profile);
+        jobs.add_device(port, ctrl)?;
+    }
+    let listener = TcpListener::bind(address)?;
+    eprintln!(
+        "serving {} device(s) on {}",
+        ports.len(),
+        listener.local_addr()?
+    );
+    let server = RpcServer::new(jobs);
+    // Also set when serving fails, so the scheduler doesn't outlive it.
+    let stopped = AtomicBool::new(false);
+    thread::scope(|scope| {
+        if let Some(mut scheduler) = schedule {
+            for job in scheduler.jobs() {
+                eprintln!("scheduled {}", job.name());
+            }
+            let (server, stopped) = (&server, &stopped);
+            scope.spawn(move || {
+                if let Err(e) = scheduler.run_jobs(server.jobs(), stopped, log) {
+                    eprintln!("schedule stopped: {e}");
+                }
+            });
+        }
+        let result = server.serve_until(listener, interrupted);
+        stopped.store(true, Ordering::Relaxed);
+        result
+    })
+}
+
+fn log(event: SchedulerEvent<'_>) {
+    match event {
+        SchedulerEvent::Started { job } => eprintln!("{job}: started"),
+        SchedulerEvent::Finished { job, elapsed } => {
+            eprintln!("{job}: finished in {:.1}s", elapsed.as_secs_f64())
+        }
+        SchedulerEvent::Skipped { job, .. } => {
+            eprintln!("{job}: skipped a run, the device was busy")
+        }
+        SchedulerEvent::Failed { job, error } => eprintln!("{job}: failed: {error}"),
+    }
+}
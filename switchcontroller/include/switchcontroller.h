@@ -0,0 +1,67 @@
+/* C interface to the switchcontroller crate (built with the `ffi` feature).
+ *
+ * Keep in sync with src/ffi.rs. Functions returning int return 0 on success
+ * and -1 on failure; switch_last_error() then describes the failure.
+ */
+#ifndef SWITCHCONTROLLER_H
+#define SWITCHCONTROLLER_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct SwitchController SwitchController;
+typedef struct InputSequence InputSequence;
+
+/* Button bits for the `mask` parameters. */
+#define SWITCH_BUTTON_A          (1u << 0)
+#define SWITCH_BUTTON_B          (1u << 1)
+#define SWITCH_BUTTON_X          (1u << 2)
+#define SWITCH_BUTTON_Y          (1u << 3)
+#define SWITCH_BUTTON_L          (1u << 4)
+#define SWITCH_BUTTON_R          (1u << 5)
+#define SWITCH_BUTTON_ZL         (1u << 6)
+#define SWITCH_BUTTON_ZR         (1u << 7)
+#define SWITCH_BUTTON_PLUS       (1u << 8)
+#define SWITCH_BUTTON_MINUS      (1u << 9)
+#define SWITCH_BUTTON_HOME       (1u << 10)
+#define SWITCH_BUTTON_CAPTURE    (1u << 11)
+#define SWITCH_BUTTON_LSTICK     (1u << 12)
+#define SWITCH_BUTTON_RSTICK     (1u << 13)
+#define SWITCH_BUTTON_DPAD_UP    (1u << 14)
+#define SWITCH_BUTTON_DPAD_DOWN  (1u << 15)
+#define SWITCH_BUTTON_DPAD_LEFT  (1u << 16)
+#define SWITCH_BUTTON_DPAD_RIGHT (1u << 17)
+
+/* Sticks for the `which` parameters. */
+#define SWITCH_STICK_LEFT  0
+#define SWITCH_STICK_RIGHT 1
+
+const char *switch_last_error(void);
+
+SwitchController *switch_open(const char *path, uint32_t baud_rate);
+void switch_close(SwitchController *ctrl);
+
+int switch_press(SwitchController *ctrl, uint32_t mask);
+int switch_hold(SwitchController *ctrl, uint32_t mask);
+int switch_release(SwitchController *ctrl, uint32_t mask);
+int switch_stick(SwitchController *ctrl, int which, float horizontal, float vertical);
+int switch_state(SwitchController *ctrl, uint32_t mask, float left_h, float left_v,
+                 float right_h, float right_v);
+
+InputSequence *switch_sequence_new(void);
+void switch_sequence_free(InputSequence *seq);
+void switch_sequence_press(InputSequence *seq, uint32_t mask);
+void switch_sequence_hold(InputSequence *seq, uint32_t mask);
+void switch_sequence_release(InputSequence *seq, uint32_t mask);
+void switch_sequence_stick(InputSequence *seq, int which, float horizontal, float vertical);
+void switch_sequence_wait(InputSequence *seq, uint64_t millis);
+int switch_run_sequence(SwitchController *ctrl, const InputSequence *seq);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SWITCHCONTROLLER_H */
@@ -0,0 +1,71 @@
+use std::io::{self, Write};
+
+use crate::SwitchController;
+
+impl SwitchController {
+    /// Start buffering commands instead of writing each one immediately.
+    ///
+    /// Commands sent until [`SwitchController::end_batch`] or
+    /// [`SwitchController::flush`] go out together in a single write, saving a
+    /// write and flush per command in bursts. Sequence playback flushes before
+    /// every wait, so batching a playback only groups the commands between
+    /// waits. Write errors while batching are reported by the flush.
+    pub fn begin_batch(&mut self) -> &mut Self {
+        self.batching = true;
+        self
+    }
+
+    /// Stop buffering and write everything buffered so far.
+    pub fn end_batch(&mut self) -> io::Result<()> {
+        self.batching = false;
+        self.flush()
+    }
+
+    pub fn is_batching(&self) -> bool {
+        self.batching
+    }
+
+    /// Write any buffered commands to the device now.
+    ///
+    /// Also sends every command queued by the rate limit, waiting for each
+    /// one's turn.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.drain_queue()?;
+        if self.out.is_empty() {
+            return Ok(());
+        }
+        let pending = String::from_utf8_lossy(&self.out).trim_end().to_string();
+        match self.write_out() {
+            Ok(()) => Ok(()),
+            Err(e) => self.recover(&pending, e),
+        }
+    }
+
+    /// Queue a command, writing it right away unless batching. Does not apply
+    /// the error policy.
+    pub(crate) fn write_line(&mut self, cmd: &str) -> io::Result<()> {
+        self.out.extend_from_slice(cmd.as_bytes());
+        self.out.push(b'\n');
+        if self.batching {
+            return Ok(());
+        }
+        self.write_out()
+    }
+
+    /// Write a command right away, even while batching. Does not apply the
+    /// error policy.
+    pub(crate) fn write_now(&mut self, cmd: &str) -> io::Result<()> {
+        self.out.extend_from_slice(cmd.as_bytes());
+        self.out.push(b'\n');
+        self.write_out()
+    }
+
+    fn write_out(&mut self) -> io::Result<()> {
+        let result = self
+            .port
+            .write_all(&self.out)
+            .and_then(|()| self.port.flush());
+        self.out.clear();
+        result
+    }
+}
@@ -0,0 +1,267 @@
+use std::fmt;
+
+use crate::{ControllerState, Stick};
+
+/// Calibration of one raw stick axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AxisCalibration {
+    /// Raw value reported at rest.
+    pub center: f32,
+    /// Raw value at full tilt in the negative direction.
+    pub min: f32,
+    /// Raw value at full tilt in the positive direction.
+    pub max: f32,
+    /// Raw distance from `center` treated as noise and mapped to zero.
+    pub deadzone: f32,
+}
+
+impl Default for AxisCalibration {
+    fn default() -> Self {
+        Self {
+            center: 0.0,
+            min: -1.0,
+            max: 1.0,
+            deadzone: 0.0,
+        }
+    }
+}
+
+impl AxisCalibration {
+    /// Map a raw value to [-1.0, 1.0], with the deadzone mapped to zero and
+    /// the remaining range rescaled so full tilt still reaches ±1.0.
+    pub fn apply(&self, raw: f32) -> f32 {
+        let offset = raw - self.center;
+        let range = if offset >= 0.0 {
+            self.max - self.center
+        } else {
+            self.center - self.min
+        };
+        let live = range - self.deadzone;
+        if offset.abs() <= self.deadzone || live <= 0.0 {
+            return 0.0;
+        }
+        ((offset.abs() - self.deadzone) / live * offset.signum()).clamp(-1.0, 1.0)
+    }
+}
+
+/// Calibration of both axes of a stick.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct StickCalibration {
+    pub horizontal: AxisCalibration,
+    pub vertical: AxisCalibration,
+}
+
+impl StickCalibration {
+    pub fn apply(&self, (h, v): (f32, f32)) -> (f32, f32) {
+        (self.horizontal.apply(h), self.vertical.apply(v))
+    }
+}
+
+/// Calibration of a physical source gamepad's sticks, mapping its raw
+/// readings to the [-1.0, 1.0] range sent to the device.
+///
+/// Build one with a [`CalibrationWizard`], or fill it in by hand.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Calibration {
+    pub left: StickCalibration,
+    pub right: StickCalibration,
+}
+
+impl Calibration {
+    pub fn stick(&self, stick: Stick) -> &StickCalibration {
+        match stick {
+            Stick::Left => &self.left,
+            Stick::Right => &self.right,
+        }
+    }
+
+    /// Calibrate the stick positions of a raw state in place. Sticks the state
+    /// does not set are left alone.
+    pub fn apply(&self, state: &mut ControllerState) {
+        if let Some(position) = &mut state.left_stick {
+            *position = self.left.apply(*position);
+        }
+        if let Some(position) = &mut state.right_stick {
+            *position = self.right.apply(*position);
+        }
+    }
+}
+
+/// A phase of the [`CalibrationWizard`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalibrationStep {
+    /// Both sticks are left at rest while their center and noise are captured.
+    Center,
+    /// Both sticks are rotated around their full range while their extents are
+    /// captured.
+    Extents,
+    /// Capture is complete.
+    Done,
+}
+
+impl fmt::Display for CalibrationStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CalibrationStep::Center => "Leave both sticks at rest.",
+            CalibrationStep::Extents => "Rotate both sticks slowly around their full range.",
+            CalibrationStep::Done => "Calibration complete.",
+        })
+    }
+}
+
+/// Raw samples seen for one axis.
+#[derive(Debug, Clone, Copy, Default)]
+struct AxisSamples {
+    sum: f64,
+    count: u32,
+    low: f32,
+    high: f32,
+}
+
+impl AxisSamples {
+    fn add(&mut self, value: f32) {
+        if self.count == 0 {
+            self.low = value;
+            self.high = value;
+        }
+        self.sum += value as f64;
+        self.count += 1;
+        self.low = self.low.min(value);
+        self.high = self.high.max(value);
+    }
+}
+
+/// Walks through capturing a [`Calibration`] from a physical gamepad.
+///
+/// Show the user the current [`step`](CalibrationWizard::step), pass every raw
+/// reading to [`sample`](CalibrationWizard::sample), and call
+/// [`advance`](CalibrationWizard::advance) when they are done with a step.
+/// Axes that received no samples keep the default calibration.
+#[derive(Debug, Clone)]
+pub struct CalibrationWizard {
+    step: CalibrationStep,
+    /// Left horizontal, left vertical, right horizontal, right vertical.
+    center: [AxisSamples; 4],
+    extents: [AxisSamples; 4],
+}
+
+impl Default for CalibrationWizard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CalibrationWizard {
+    pub fn new() -> Self {
+        Self {
+            step: CalibrationStep::Center,
+            center: [AxisSamples::default(); 4],
+            extents: [AxisSamples::default(); 4],
+        }
+    }
+
+    pub fn step(&self) -> CalibrationStep {
+        self.step
+    }
+
+    /// Record a raw reading for the current step.
+    pub fn sample(&mut self, raw: &ControllerState) {
+        let samples = match self.step {
+            CalibrationStep::Center => &mut self.center,
+            CalibrationStep::Extents => &mut self.extents,
+            CalibrationStep::Done => return,
+        };
+        for (i, position) in [raw.left_stick, raw.right_stick].into_iter().enumerate() {
+            if let Some((h, v)) = position {
+                samples[i * 2].add(h);
+                samples[i * 2 + 1].add(v);
+            }
+        }
+    }
+
+    /// Move on to the next step.
+    pub fn advance(&mut self) -> CalibrationStep {
+        self.step = match self.step {
+            CalibrationStep::Center => CalibrationStep::Extents,
+            CalibrationStep::Extents | CalibrationStep::Done => CalibrationStep::Done,
+        };
+        self.step
+    }
+
+    /// The calibration captured so far.
+    pub fn calibration(&self) -> Calibration {
+        let axis = |i: usize| {
+            let mut axis = AxisCalibration::default();
+            let rest = self.center[i];
+            if rest.count > 0 {
+                axis.center = (rest.sum / rest.count as f64) as f32;
+                axis.deadzone = (rest.high - axis.center).max(axis.center - rest.low);
+            }
+            let extents = self.extents[i];
+            if extents.count > 0 {
+                axis.min = extents.low.min(axis.center);
+                axis.max = extents.high.max(axis.center);
+            }
+            axis
+        };
+        Calibration {
+            left: StickCalibration {
+                horizontal: axis(0),
+                vertical: axis(1),
+            },
+            right: StickCalibration {
+                horizontal: axis(2),
+                vertical: axis(3),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(lh: f32, lv: f32) -> ControllerState {
+        let mut state = ControllerState::new();
+        state.set_left_stick(lh, lv);
+        state
+    }
+
+    #[test]
+    fn axis_rescales_around_deadzone() {
+        let axis = AxisCalibration {
+            center: 0.1,
+            min: -0.7,
+            max: 0.9,
+            deadzone: 0.1,
+        };
+        assert_eq!(axis.apply(0.15), 0.0);
+        assert!((axis.apply(0.9) - 1.0).abs() < 1e-6);
+        assert!((axis.apply(0.5) - 3.0 / 7.0).abs() < 1e-6);
+        assert!((axis.apply(-0.7) + 1.0).abs() < 1e-6);
+        assert_eq!(axis.apply(-2.0), -1.0);
+    }
+
+    #[test]
+    fn wizard_captures_center_and_extents() {
+        let mut wizard = CalibrationWizard::new();
+        wizard.sample(&raw(0.08, -0.02));
+        wizard.sample(&raw(0.12, 0.02));
+        assert_eq!(wizard.advance(), CalibrationStep::Extents);
+        for (h, v) in [(0.9, 0.0), (0.0, -0.8), (-0.6, 0.0), (0.0, 0.85)] {
+            wizard.sample(&raw(h, v));
+        }
+        assert_eq!(wizard.advance(), CalibrationStep::Done);
+
+        let calibration = wizard.calibration();
+        let h = calibration.left.horizontal;
+        assert!((h.center - 0.1).abs() < 1e-6 && (h.deadzone - 0.02).abs() < 1e-6);
+        assert_eq!((h.min, h.max), (-0.6, 0.9));
+        assert_eq!(calibration.right, StickCalibration::default());
+
+        let mut state = raw(0.9, -0.8);
+        calibration.apply(&mut state);
+        let (lh, lv) = state.left_stick.unwrap();
+        assert!((lh - 1.0).abs() < 1e-6 && (lv + 1.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,207 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+
+use crate::toml::{Document, Value};
+use crate::{InputSequence, PlaybackPosition, SwitchController};
+
+/// Saved progress of a [`ResumableJob`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// Number of iterations already completed.
+    pub iteration: u64,
+    /// Where to resume within the current iteration.
+    pub position: PlaybackPosition,
+}
+
+impl Checkpoint {
+    /// Read a checkpoint file, or `None` if it does not exist.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Option<Checkpoint>> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let doc = Document::parse(&text).map_err(|e| invalid(e.to_string()))?;
+        let field = |key: &str| {
+            doc.table("")
+                .and_then(|t| t.iter().find(|(k, _)| k == key))
+                .and_then(|(_, v)| v.as_f64())
+                .filter(|n| *n >= 0.0 && n.fract() == 0.0)
+                .map(|n| n as u64)
+                .ok_or_else(|| invalid(format!("checkpoint is missing `{key}`")))
+        };
+        Ok(Some(Checkpoint {
+            iteration: field("iteration")?,
+            position: PlaybackPosition {
+                step: field("step")? as usize,
+                offset: Duration::from_micros(field("offset_us")?),
+            },
+        }))
+    }
+
+    /// Write the checkpoint to `path`, replacing any previous one atomically
+    /// so a crash mid-write never leaves a truncated file.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut doc = Document::new();
+        doc.set("", "iteration", Value::Number(self.iteration as f64));
+        doc.set("", "step", Value::Number(self.position.step as f64));
+        doc.set(
+            "",
+            "offset_us",
+            Value::Number(self.position.offset.as_micros() as f64),
+        );
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        fs::write(&tmp, doc.to_string())?;
+        fs::rename(&tmp, path)
+    }
+
+    /// Delete a checkpoint file, if there is one.
+    pub fn remove(path: impl AsRef<Path>) -> io::Result<()> {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A long-running sequence loop that persists its progress to a checkpoint
+/// file and picks up where it left off after a crash or reboot.
+///
+/// ```no_run
+/// # use switchcontroller::{InputSequence, ResumableJob, SwitchController};
+/// # use std::sync::atomic::AtomicBool;
+/// # let mut ctrl = SwitchController::from_writer(Vec::new());
+/// # let (hatch, back_to_route) = (InputSequence::new(), InputSequence::new());
+/// # let stop = AtomicBool::new(false);
+/// let job = ResumableJob::new(hatch, "hatch.checkpoint")
+///     .iterations(1000)
+///     .recovery(back_to_route);
+/// job.run(&mut ctrl, &stop)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct ResumableJob {
+    sequence: InputSequence,
+    path: PathBuf,
+    iterations: Option<u64>,
+    recovery: Option<InputSequence>,
+    save_every: Duration,
+}
+
+impl ResumableJob {
+    /// A job repeating `sequence` forever, checkpointing to `path`.
+    pub fn new(sequence: InputSequence, path: impl Into<PathBuf>) -> Self {
+        Self {
+            sequence,
+            path: path.into(),
+            iterations: None,
+            recovery: None,
+            save_every: Duration::from_secs(5),
+        }
+    }
+
+    /// Stop after `count` iterations in total, across restarts.
+    pub fn iterations(mut self, count: u64) -> Self {
+        self.iterations = Some(count);
+        self
+    }
+
+    /// Play `sequence` before resuming from a checkpoint, to bring the console
+    /// back to the state the job expects (for example, back to the overworld
+    /// after a reboot).
+    pub fn recovery(mut self, sequence: InputSequence) -> Self {
+        self.recovery = Some(sequence);
+        self
+    }
+
+    /// How often progress is written to disk. Defaults to 5 seconds.
+    pub fn save_every(mut self, interval: Duration) -> Self {
+        self.save_every = interval;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Run the job, resuming from the checkpoint file if there is one.
+    ///
+    /// Returns `None` once every iteration has completed, at which point the
+    /// checkpoint file is removed. If `stop` is set first, the current
+    /// progress is saved and returned.
+    pub fn run(
+        &self,
+        ctrl: &mut SwitchController,
+        stop: &AtomicBool,
+    ) -> io::Result<Option<Checkpoint>> {
+        let mut checkpoint = match Checkpoint::load(&self.path)? {
+            Some(checkpoint) => {
+                if let Some(recovery) = &self.recovery {
+                    ctrl.play(recovery)?;
+                }
+                checkpoint
+            }
+            None => Checkpoint::default(),
+        };
+        let mut last_save = Instant::now();
+
+        while self.iterations.is_none_or(|n| checkpoint.iteration < n) {
+            let iteration = checkpoint.iteration;
+            let mut progress = |position| {
+                if last_save.elapsed() >= self.save_every {
+                    last_save = Instant::now();
+                    Checkpoint {
+                        iteration,
+                        position,
+                    }
+                    .save(&self.path)?;
+                }
+                Ok(())
+            };
+            let stopped_at =
+                ctrl.play_observed(&self.sequence, checkpoint.position, stop, &mut progress)?;
+            if let Some(position) = stopped_at {
+                checkpoint.position = position;
+                checkpoint.save(&self.path)?;
+                return Ok(Some(checkpoint));
+            }
+            checkpoint = Checkpoint {
+                iteration: iteration + 1,
+                position: PlaybackPosition::default(),
+            };
+        }
+        Checkpoint::remove(&self.path)?;
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let path = std::env::temp_dir().join(format!("checkpoint-{}.test", std::process::id()));
+        assert_eq!(Checkpoint::load(&path).unwrap(), None);
+
+        let checkpoint = Checkpoint {
+            iteration: 412,
+            position: PlaybackPosition {
+                step: 7,
+                offset: Duration::from_micros(1_250_500),
+            },
+        };
+        checkpoint.save(&path).unwrap();
+        assert_eq!(Checkpoint::load(&path).unwrap(), Some(checkpoint));
+
+        Checkpoint::remove(&path).unwrap();
+        Checkpoint::remove(&path).unwrap();
+        assert_eq!(Checkpoint::load(&path).unwrap(), None);
+    }
+}
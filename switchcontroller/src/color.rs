@@ -0,0 +1,105 @@
+use crate::{Frame, Region};
+
+/// The mean color of `region` in `frame`.
+pub fn average_color(frame: &Frame, region: &Region) -> [u8; 3] {
+    let mut sums = [0u64; 3];
+    let mut count = 0u64;
+    for pixel in frame.region_pixels(region) {
+        for (sum, channel) in sums.iter_mut().zip(pixel) {
+            *sum += u64::from(channel);
+        }
+        count += 1;
+    }
+    sums.map(|sum| ((sum + count / 2) / count) as u8)
+}
+
+/// How far apart two colors are, from 0 (equal) to 1 (black and white).
+pub fn color_distance(a: [u8; 3], b: [u8; 3]) -> f64 {
+    let squares: u32 = a
+        .iter()
+        .zip(b)
+        .map(|(&x, y)| u32::from(x.abs_diff(y)).pow(2))
+        .sum();
+    (squares as f64 / (3.0 * 255.0 * 255.0)).sqrt()
+}
+
+/// A color histogram of a screen region, for matching a region against a
+/// reference by its colors rather than pixel by pixel.
+///
+/// Pixels are binned by chromaticity (their share of red and green, not
+/// their brightness), so the same scene compares as similar under dimmer or
+/// brighter lighting. Near-black pixels, which have no meaningful color,
+/// get a bin of their own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    bins: Vec<f64>,
+}
+
+impl Histogram {
+    const BINS: usize = 8;
+    /// Pixels at or below this channel sum count as black.
+    const BLACK: u32 = 24;
+
+    /// The histogram of `region` in `frame`.
+    pub fn of(frame: &Frame, region: &Region) -> Self {
+        let mut bins = vec![0.0; Self::BINS * Self::BINS + 1];
+        let mut count = 0.0;
+        for [r, g, b] in frame.region_pixels(region) {
+            let sum = u32::from(r) + u32::from(g) + u32::from(b);
+            let bin = if sum <= Self::BLACK {
+                bins.len() - 1
+            } else {
+                let share = |c: u8| (u32::from(c) * Self::BINS as u32 / (sum + 1)) as usize;
+                share(r) * Self::BINS + share(g)
+            };
+            bins[bin] += 1.0;
+            count += 1.0;
+        }
+        for bin in &mut bins {
+            *bin /= count;
+        }
+        Self { bins }
+    }
+
+    /// How alike two histograms are, from 0 (no colors in common) to 1
+    /// (identical distributions).
+    pub fn similarity(&self, other: &Histogram) -> f64 {
+        self.bins
+            .iter()
+            .zip(&other.bins)
+            .map(|(a, b)| a.min(*b))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_colors_under_lighting_changes() {
+        let mut frame = Frame::filled(4, 4, [200, 40, 40]);
+        for x in 0..4 {
+            frame.set_pixel(x, 0, [40, 40, 200]);
+        }
+        assert_eq!(average_color(&frame, &Region::FULL), [160, 40, 80]);
+        let bottom = Region::new(0.0, 25.0, 100.0, 75.0).unwrap();
+        assert_eq!(average_color(&frame, &bottom), [200, 40, 40]);
+        assert_eq!(color_distance([0; 3], [255; 3]), 1.0);
+        assert_eq!(color_distance([9; 3], [9; 3]), 0.0);
+
+        let reference = Histogram::of(&frame, &Region::FULL);
+        let mut dim = frame.clone();
+        for y in 0..4 {
+            for x in 0..4 {
+                dim.set_pixel(x, y, frame.pixel(x, y).map(|c| c / 2));
+            }
+        }
+        let similarity = reference.similarity(&Histogram::of(&dim, &Region::FULL));
+        assert!((similarity - 1.0).abs() < 1e-9);
+        let other = Histogram::of(&Frame::filled(4, 4, [40, 200, 40]), &Region::FULL);
+        assert_eq!(reference.similarity(&other), 0.0);
+        let black = Histogram::of(&Frame::filled(4, 4, [0; 3]), &Region::FULL);
+        assert_eq!(black.similarity(&black), 1.0);
+    }
+}
@@ -0,0 +1,106 @@
+use std::time::Duration;
+
+use crate::{Button, ControllerState, InputSequence, PRESS_DURATION, Timeline};
+
+/// A timeline sampled into full `STATE` frames at a fixed interval.
+///
+/// Produced by [`Timeline::compile_states`]. Play it with
+/// `ctrl.play(&frames.to_sequence())`.
+#[derive(Debug, Clone)]
+pub struct StateFrames {
+    /// Time between consecutive frames.
+    pub interval: Duration,
+    /// One state per interval, starting at time zero.
+    pub frames: Vec<ControllerState>,
+}
+
+impl StateFrames {
+    /// A sequence sending each frame as a `STATE` command followed by a wait of
+    /// one interval.
+    pub fn to_sequence(&self) -> InputSequence {
+        let mut seq = InputSequence::new();
+        for frame in &self.frames {
+            seq.state(frame).wait(self.interval);
+        }
+        seq
+    }
+}
+
+impl Timeline {
+    /// Sample the timeline into `STATE` frames every `interval`.
+    ///
+    /// Every frame carries both stick positions. A momentary press is held for
+    /// [`PRESS_DURATION`], and is always included in at least one frame even
+    /// if that is shorter than `interval`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    pub fn compile_states(&self, interval: Duration) -> StateFrames {
+        assert!(!interval.is_zero(), "frame interval must be non-zero");
+        let count = self.duration().div_duration_f64(interval).ceil().max(1.0) as usize;
+        let frames = (0..count)
+            .map(|i| {
+                let start = interval * i as u32;
+                let end = start + interval;
+                let mut state = self.state_at(start);
+                for b in Button::ALL {
+                    let pressed = self
+                        .button(b)
+                        .presses
+                        .iter()
+                        .any(|&p| p < end && start < p + PRESS_DURATION);
+                    if pressed {
+                        state.set_button(b, true);
+                    }
+                }
+                state
+            })
+            .collect();
+        StateFrames { interval, frames }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Stick;
+
+    #[test]
+    fn samples_holds_presses_and_sticks() {
+        let mut seq = InputSequence::new();
+        seq.hold(&[Button::ZR])
+            .wait(Duration::from_millis(100))
+            .release(&[Button::ZR])
+            .press(&[Button::A])
+            .stick(Stick::Right, 0.0, -1.0)
+            .wait(Duration::from_millis(200));
+
+        let frames = Timeline::from(&seq).compile_states(Duration::from_millis(50));
+        let commands: Vec<_> = frames.frames.iter().map(|s| s.to_command()).collect();
+        assert_eq!(
+            commands,
+            [
+                "STATE 000000010000000000 0 0 0 0",
+                "STATE 000000010000000000 0 0 0 0",
+                "STATE 100000000000000000 0 0 0 -1",
+                "STATE 100000000000000000 0 0 0 -1",
+                "STATE 000000000000000000 0 0 0 -1",
+                "STATE 000000000000000000 0 0 0 -1",
+            ]
+        );
+    }
+
+    #[test]
+    fn short_press_is_not_lost() {
+        let mut seq = InputSequence::new();
+        seq.wait(Duration::from_millis(10))
+            .press(&[Button::B])
+            .wait(Duration::from_millis(990));
+
+        let frames = Timeline::from(&seq).compile_states(Duration::from_millis(500));
+        assert_eq!(frames.frames.len(), 2);
+        assert!(frames.frames[0].button(Button::B));
+        assert!(!frames.frames[1].button(Button::B));
+    }
+}
@@ -0,0 +1,190 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use crate::{Button, InputSequence, Step, Stick, SwitchController};
+
+/// The buttons and sticks a sequence drives.
+///
+/// A `STATE` step sets every button, so it counts as using all of them, and
+/// the sticks it gives a position for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Footprint {
+    /// Bitmask in the order of [`Button::ALL`].
+    buttons: u32,
+    left_stick: bool,
+    right_stick: bool,
+}
+
+impl Footprint {
+    /// The buttons used, in [`Button::ALL`] order.
+    pub fn buttons(&self) -> impl Iterator<Item = Button> + '_ {
+        Button::ALL
+            .into_iter()
+            .filter(|b| self.buttons & (1 << b.index()) != 0)
+    }
+
+    pub fn uses_button(&self, button: Button) -> bool {
+        self.buttons & (1 << button.index()) != 0
+    }
+
+    pub fn uses_stick(&self, stick: Stick) -> bool {
+        match stick {
+            Stick::Left => self.left_stick,
+            Stick::Right => self.right_stick,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Footprint::default()
+    }
+
+    /// What both footprints use.
+    pub fn overlap(&self, other: &Footprint) -> Footprint {
+        Footprint {
+            buttons: self.buttons & other.buttons,
+            left_stick: self.left_stick && other.left_stick,
+            right_stick: self.right_stick && other.right_stick,
+        }
+    }
+
+    fn add_buttons(&mut self, buttons: &[Button]) {
+        for b in buttons {
+            self.buttons |= 1 << b.index();
+        }
+    }
+
+    fn add_stick(&mut self, stick: Stick) {
+        match stick {
+            Stick::Left => self.left_stick = true,
+            Stick::Right => self.right_stick = true,
+        }
+    }
+}
+
+impl fmt::Display for Footprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sticks = [
+            (self.left_stick, "left stick"),
+            (self.right_stick, "right stick"),
+        ];
+        let names: Vec<&str> = self
+            .buttons()
+            .map(Button::as_str)
+            .chain(
+                sticks
+                    .into_iter()
+                    .filter(|(used, _)| *used)
+                    .map(|(_, name)| name),
+            )
+            .collect();
+        match names.is_empty() {
+            true => f.write_str("nothing"),
+            false => f.write_str(&names.join(", ")),
+        }
+    }
+}
+
+/// Why [`InputSequence::compose`] refused two sequences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComposeConflict {
+    /// The inputs both sequences drive.
+    pub overlap: Footprint,
+}
+
+impl fmt::Display for ComposeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "both sequences drive {}", self.overlap)
+    }
+}
+
+impl Error for ComposeConflict {}
+
+impl InputSequence {
+    /// The buttons and sticks this sequence drives.
+    pub fn footprint(&self) -> Footprint {
+        let mut footprint = Footprint::default();
+        for step in self.steps() {
+            match step {
+                Step::Press(buttons) | Step::Hold(buttons) | Step::Release(buttons) => {
+                    footprint.add_buttons(buttons);
+                }
+                Step::Stick(stick, _, _) => footprint.add_stick(*stick),
+                Step::State(state) => {
+                    footprint.add_buttons(&Button::ALL);
+                    if state.left_stick.is_some() {
+                        footprint.add_stick(Stick::Left);
+                    }
+                    if state.right_stick.is_some() {
+                        footprint.add_stick(Stick::Right);
+                    }
+                }
+                Step::Wait(_) | Step::Marker(_) => {}
+            }
+        }
+        footprint
+    }
+
+    /// This sequence and `other` played in parallel, as with
+    /// [`InputSequence::overlay`], if they drive different inputs, e.g. a
+    /// turbo-A track over a stick-movement track.
+    ///
+    /// Sequences that share a button or stick would fight over it, so they
+    /// are refused with the shared inputs. Tracks meant for composing should
+    /// use `PRESS`/`HOLD`/`RELEASE` and `STICK` rather than `STATE`.
+    pub fn compose(&self, other: &InputSequence) -> Result<InputSequence, ComposeConflict> {
+        let overlap = self.footprint().overlap(&other.footprint());
+        if !overlap.is_empty() {
+            return Err(ComposeConflict { overlap });
+        }
+        Ok(self.overlay(other))
+    }
+}
+
+impl SwitchController {
+    /// Play two sequences at once with [`InputSequence::compose`]. A
+    /// conflict is an [`io::ErrorKind::InvalidInput`] error wrapping
+    /// [`ComposeConflict`], and nothing is sent.
+    pub fn play_parallel(&mut self, a: &InputSequence, b: &InputSequence) -> io::Result<()> {
+        let composed = a
+            .compose(b)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        self.play(&composed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ControllerState;
+    use std::time::Duration;
+
+    #[test]
+    fn composes_disjoint_tracks() {
+        let tick = Duration::from_millis(50);
+        let mut turbo = InputSequence::new();
+        turbo.press(&[Button::A]).wait(tick).press(&[Button::A]);
+        let mut walk = InputSequence::new();
+        walk.stick(Stick::Left, 1.0, 0.0)
+            .wait(tick * 2)
+            .stick(Stick::Left, 0.0, 0.0);
+
+        let both = turbo.compose(&walk).unwrap();
+        assert_eq!(
+            both.to_script(),
+            "PRESS a\nSTICK l_stick 1 0\nWAIT 50ms\nPRESS a\nWAIT 50ms\nSTICK l_stick 0 0\n"
+        );
+
+        let mut mash = InputSequence::new();
+        mash.hold(&[Button::A, Button::B])
+            .stick(Stick::Left, 0.0, 1.0);
+        let err = both.compose(&mash).unwrap_err();
+        assert_eq!(err.to_string(), "both sequences drive a, left stick");
+
+        let mut state = InputSequence::new();
+        state.state(&ControllerState::new());
+        let overlap = state.compose(&turbo).unwrap_err().overlap;
+        assert!(overlap.uses_button(Button::A));
+        assert!(!overlap.uses_stick(Stick::Left));
+    }
+}
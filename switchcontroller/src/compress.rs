@@ -0,0 +1,135 @@
+//! The LZ4 block format, for compressing batches sent to a bridge.
+
+/// Matches are found through a table of this many (as a power of two) recent
+/// positions, indexed by a hash of the four bytes there.
+const HASH_BITS: u32 = 12;
+/// The format's furthest match distance.
+const MAX_OFFSET: usize = 65535;
+/// Shortest match the format can express.
+const MIN_MATCH: usize = 4;
+/// The last match must start at least this far from the end...
+const MATCH_START_LIMIT: usize = 12;
+/// ...and the last this many bytes must be literals.
+const LAST_LITERALS: usize = 5;
+
+/// Compress `input` as one LZ4 block.
+pub(crate) fn compress(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len() / 2 + 16);
+    let mut table = vec![usize::MAX; 1 << HASH_BITS];
+    let mut anchor = 0;
+    let mut i = 0;
+    let end = input.len();
+    let word = |at: usize| u32::from_le_bytes(input[at..at + 4].try_into().unwrap());
+    while i + MATCH_START_LIMIT < end {
+        let hash = (word(i).wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize;
+        let candidate = std::mem::replace(&mut table[hash], i);
+        if candidate == usize::MAX || i - candidate > MAX_OFFSET || word(candidate) != word(i) {
+            i += 1;
+            continue;
+        }
+        let mut len = MIN_MATCH;
+        while i + len < end - LAST_LITERALS && input[candidate + len] == input[i + len] {
+            len += 1;
+        }
+        write_sequence(&mut out, &input[anchor..i], Some((i - candidate, len)));
+        i += len;
+        anchor = i;
+    }
+    write_sequence(&mut out, &input[anchor..], None);
+    out
+}
+
+/// Write literals followed by an `(offset, length)` match, or by nothing
+/// for the final sequence.
+fn write_sequence(out: &mut Vec<u8>, literals: &[u8], found: Option<(usize, usize)>) {
+    let match_len = found.map_or(0, |(_, len)| len - MIN_MATCH);
+    let token = (literals.len().min(15) << 4 | match_len.min(15)) as u8;
+    out.push(token);
+    write_length(out, literals.len());
+    out.extend_from_slice(literals);
+    if let Some((offset, _)) = found {
+        out.extend_from_slice(&(offset as u16).to_le_bytes());
+        write_length(out, match_len);
+    }
+}
+
+/// The bytes past a token's nibble for a length of 15 or more.
+fn write_length(out: &mut Vec<u8>, len: usize) {
+    if len < 15 {
+        return;
+    }
+    let mut rest = len - 15;
+    while rest >= 255 {
+        out.push(255);
+        rest -= 255;
+    }
+    out.push(rest as u8);
+}
+
+/// Decompress one LZ4 block expected to hold exactly `len` bytes, or `None`
+/// if it is malformed or of a different length.
+pub(crate) fn decompress(input: &[u8], len: usize) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(len);
+    let mut i = 0;
+    loop {
+        let token = *input.get(i)?;
+        i += 1;
+        let literals = read_length(input, &mut i, (token >> 4) as usize)?;
+        if literals > len - out.len() {
+            return None;
+        }
+        out.extend_from_slice(input.get(i..i + literals)?);
+        i += literals;
+        if i == input.len() {
+            break;
+        }
+        let offset = u16::from_le_bytes([*input.get(i)?, *input.get(i + 1)?]) as usize;
+        i += 2;
+        let match_len = read_length(input, &mut i, (token & 15) as usize)? + MIN_MATCH;
+        if offset == 0 || offset > out.len() || match_len > len - out.len() {
+            return None;
+        }
+        // Copied a byte at a time, as a match may overlap its own output.
+        let start = out.len() - offset;
+        for k in 0..match_len {
+            out.push(out[start + k]);
+        }
+    }
+    (out.len() == len).then_some(out)
+}
+
+/// A length from a token's `nibble` and any bytes following at `i`.
+fn read_length(input: &[u8], i: &mut usize, nibble: usize) -> Option<usize> {
+    let mut len = nibble;
+    if nibble == 15 {
+        loop {
+            let byte = *input.get(*i)?;
+            *i += 1;
+            len = len.checked_add(byte as usize)?;
+            if byte != 255 {
+                break;
+            }
+        }
+    }
+    Some(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        let mut script = String::new();
+        for i in 0..2000 {
+            script.push_str(&format!("STICK l_stick {} 0\nPRESS a\n", i % 7));
+        }
+        for input in [&b""[..], b"PRESS a\n", script.as_bytes()] {
+            let compressed = compress(input);
+            assert_eq!(decompress(&compressed, input.len()).unwrap(), input);
+        }
+        assert!(compress(script.as_bytes()).len() < script.len() / 10);
+        assert_eq!(decompress(&[0x1f, b'a', 1, 0], 5), None);
+        assert_eq!(decompress(&[0xf0], 100), None);
+    }
+}
@@ -0,0 +1,103 @@
+use std::io;
+use std::time::Duration;
+
+use crate::{Button, InputSequence, Profile, SwitchController, Timings};
+
+/// How long Home is held to open the quick settings.
+const QUICK_SETTINGS_HOLD: Duration = Duration::from_secs(1);
+
+impl SwitchController {
+    /// Close the running game and start it again: Home, X (close software),
+    /// A to confirm, A to relaunch, then A once the title screen is up. The
+    /// waits come from `profile`'s timings ([`Timings::MENU_TRANSITION`],
+    /// [`Timings::CLOSE_SOFTWARE`] and [`Timings::GAME_LAUNCH`]), so each
+    /// game can be tuned in its profile.
+    pub fn soft_reset(&mut self, profile: &Profile) -> io::Result<()> {
+        self.play(&soft_reset_sequence(&profile.timings()))
+    }
+
+    /// Put the console to sleep: hold Home for the quick settings and pick
+    /// Sleep Mode, the first entry. For idling between scheduled jobs.
+    pub fn sleep_console(&mut self) -> io::Result<()> {
+        let sequence = sleep_sequence(&self.timings);
+        self.play(&sequence)
+    }
+
+    /// Wake a sleeping console with Home, then press A three times to get
+    /// past the lock screen, after [`Timings::WAKE_UP`]. The console must be
+    /// allowed to wake from controllers, as docked consoles are by default.
+    pub fn wake_console(&mut self) -> io::Result<()> {
+        let sequence = wake_sequence(&self.timings);
+        self.play(&sequence)
+    }
+}
+
+fn sleep_sequence(timings: &Timings) -> InputSequence {
+    let mut sequence = InputSequence::new();
+    sequence
+        .hold(&[Button::Home])
+        .wait(QUICK_SETTINGS_HOLD)
+        .release(&[Button::Home])
+        .wait(timings[Timings::MENU_TRANSITION])
+        .press(&[Button::A]);
+    sequence
+}
+
+fn wake_sequence(timings: &Timings) -> InputSequence {
+    let mut sequence = InputSequence::new();
+    sequence
+        .press(&[Button::Home])
+        .wait(timings[Timings::WAKE_UP]);
+    for _ in 0..3 {
+        sequence
+            .press(&[Button::A])
+            .wait(timings[Timings::MENU_TRANSITION]);
+    }
+    sequence
+}
+
+fn soft_reset_sequence(timings: &Timings) -> InputSequence {
+    let menu = timings[Timings::MENU_TRANSITION];
+    let mut sequence = InputSequence::new();
+    sequence
+        .press(&[Button::Home])
+        .wait(menu)
+        .press(&[Button::X])
+        .wait(menu)
+        .press(&[Button::A])
+        .wait(timings[Timings::CLOSE_SOFTWARE])
+        .press(&[Button::A])
+        .wait(timings[Timings::GAME_LAUNCH])
+        .press(&[Button::A]);
+    sequence
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn soft_reset_uses_profile_timings() {
+        let profile: Profile = "[timings]\ngame_launch = 12000\nmenu_transition = 800"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            soft_reset_sequence(&profile.timings()).to_script(),
+            "PRESS home\nWAIT 800ms\nPRESS x\nWAIT 800ms\nPRESS a\nWAIT 3s\n\
+             PRESS a\nWAIT 12s\nPRESS a\n"
+        );
+    }
+
+    #[test]
+    fn sleeps_and_wakes() {
+        let timings = Timings::new();
+        assert_eq!(
+            sleep_sequence(&timings).to_script(),
+            "HOLD home\nWAIT 1s\nRELEASE home\nWAIT 500ms\nPRESS a\n"
+        );
+        assert_eq!(
+            wake_sequence(&timings).to_script(),
+            "PRESS home\nWAIT 3s\n".to_string() + &"PRESS a\nWAIT 500ms\n".repeat(3)
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::toml::{Document, Value};
+use crate::{InputSequence, SwitchController};
+
+/// One named counter in a [`CounterStore`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Counter {
+    pub count: u64,
+    /// When the counter was created or last reset.
+    pub started: SystemTime,
+}
+
+/// Rates and odds derived from a [`Counter`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CounterStats {
+    pub count: u64,
+    /// Average increments per hour since the counter started.
+    pub per_hour: f64,
+    /// With odds of 1 in `odds`, the chance of at least one success by now.
+    pub chance: Option<f64>,
+    /// Estimated time until `count` reaches `odds`, at the current rate. `None`
+    /// if already past or no rate is known yet.
+    pub eta: Option<Duration>,
+}
+
+/// Persistent named counters (encounters, resets, eggs hatched), saved as a
+/// small TOML file so a hunt survives restarts.
+///
+/// ```no_run
+/// # use switchcontroller::{CounterStore, InputSequence, SwitchController};
+/// # let mut ctrl = SwitchController::from_writer(Vec::new());
+/// # let soft_reset = InputSequence::new();
+/// let mut counters = CounterStore::open("counters.toml")?;
+/// loop {
+///     let resets = counters.play_counted(&mut ctrl, "resets", &soft_reset)?;
+///     let stats = counters.stats("resets", Some(4096)).unwrap();
+///     println!("{resets} resets, {:.0}/h, {:.1}% so far", stats.per_hour, stats.chance.unwrap() * 100.0);
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CounterStore {
+    path: Option<PathBuf>,
+    counters: BTreeMap<String, Counter>,
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+impl CounterStore {
+    /// A store that is never written to disk.
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Open the store at `path`, loading its counters if the file exists.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let mut store = Self {
+            path: Some(path.clone()),
+            counters: BTreeMap::new(),
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(store),
+            Err(e) => return Err(e),
+        };
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let doc = Document::parse(&text).map_err(|e| invalid(e.to_string()))?;
+        let number = |value: &Value, name: &str| {
+            value
+                .as_f64()
+                .filter(|n| *n >= 0.0 && n.fract() == 0.0)
+                .map(|n| n as u64)
+                .ok_or_else(|| invalid(format!("invalid value for counter `{name}`")))
+        };
+        let started = doc.table("started");
+        for (name, value) in doc.table("counts").into_iter().flatten() {
+            let since = started
+                .and_then(|t| t.iter().find(|(k, _)| k == name))
+                .map(|(_, v)| number(v, name))
+                .transpose()?
+                .unwrap_or(0);
+            store.counters.insert(
+                name.clone(),
+                Counter {
+                    count: number(value, name)?,
+                    started: UNIX_EPOCH + Duration::from_secs(since),
+                },
+            );
+        }
+        Ok(store)
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Write every counter back to the store's file. Does nothing for an
+    /// in-memory store.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut doc = Document::new();
+        for (name, counter) in &self.counters {
+            doc.set("counts", name, Value::Number(counter.count as f64));
+            doc.set(
+                "started",
+                name,
+                Value::Number(unix_secs(counter.started) as f64),
+            );
+        }
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        fs::write(&tmp, doc.to_string())?;
+        fs::rename(&tmp, path)
+    }
+
+    pub fn counter(&self, name: &str) -> Option<&Counter> {
+        self.counters.get(name)
+    }
+
+    /// Current value of a counter, zero if it does not exist.
+    pub fn get(&self, name: &str) -> u64 {
+        self.counters.get(name).map_or(0, |c| c.count)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.counters.keys().map(String::as_str)
+    }
+
+    /// Add `amount` to a counter, creating it if needed, and return the new
+    /// value. Call [`CounterStore::save`] to persist it.
+    pub fn add(&mut self, name: &str, amount: u64) -> u64 {
+        let counter = self
+            .counters
+            .entry(name.to_string())
+            .or_insert_with(|| Counter {
+                count: 0,
+                started: SystemTime::now(),
+            });
+        counter.count += amount;
+        counter.count
+    }
+
+    pub fn increment(&mut self, name: &str) -> u64 {
+        self.add(name, 1)
+    }
+
+    /// Set a counter back to zero and restart its clock.
+    pub fn reset(&mut self, name: &str) {
+        self.counters.insert(
+            name.to_string(),
+            Counter {
+                count: 0,
+                started: SystemTime::now(),
+            },
+        );
+    }
+
+    /// Play one iteration of `sequence`, then increment and save the counter.
+    /// The counter is left unchanged if playback fails.
+    pub fn play_counted(
+        &mut self,
+        ctrl: &mut SwitchController,
+        name: &str,
+        sequence: &InputSequence,
+    ) -> io::Result<u64> {
+        ctrl.play(sequence)?;
+        let count = self.increment(name);
+        self.save()?;
+        Ok(count)
+    }
+
+    /// Rate and odds for a counter, with success odds of 1 in `odds` if given.
+    pub fn stats(&self, name: &str, odds: Option<u64>) -> Option<CounterStats> {
+        self.stats_at(name, odds, SystemTime::now())
+    }
+
+    fn stats_at(&self, name: &str, odds: Option<u64>, now: SystemTime) -> Option<CounterStats> {
+        let counter = self.counters.get(name)?;
+        let hours = now
+            .duration_since(counter.started)
+            .unwrap_or_default()
+            .as_secs_f64()
+            / 3600.0;
+        let per_hour = if hours > 0.0 {
+            counter.count as f64 / hours
+        } else {
+            0.0
+        };
+        let odds = odds.filter(|&n| n > 0);
+        Some(CounterStats {
+            count: counter.count,
+            per_hour,
+            chance: odds.map(|n| 1.0 - (1.0 - 1.0 / n as f64).powf(counter.count as f64)),
+            eta: odds
+                .filter(|&n| n > counter.count && per_hour > 0.0)
+                .map(|n| Duration::from_secs_f64((n - counter.count) as f64 / per_hour * 3600.0)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn persists_counts_and_start_times() {
+        let path = std::env::temp_dir().join(format!("counters-{}.test", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut store = CounterStore::open(&path).unwrap();
+        store.add("resets", 41);
+        assert_eq!(store.increment("resets"), 42);
+        store.increment("eggs hatched");
+        store.save().unwrap();
+
+        let loaded = CounterStore::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.get("resets"), 42);
+        assert_eq!(loaded.get("eggs hatched"), 1);
+        assert_eq!(
+            unix_secs(loaded.counter("resets").unwrap().started),
+            unix_secs(store.counter("resets").unwrap().started)
+        );
+    }
+
+    #[test]
+    fn stats_from_rate_and_odds() {
+        let mut store = CounterStore::in_memory();
+        store.add("resets", 100);
+        let started = store.counter("resets").unwrap().started;
+        let stats = store
+            .stats_at("resets", Some(400), started + Duration::from_secs(3600))
+            .unwrap();
+        assert_eq!(stats.per_hour, 100.0);
+        assert_eq!(stats.eta, Some(Duration::from_secs(3 * 3600)));
+        assert!((stats.chance.unwrap() - (1.0 - (399.0f64 / 400.0).powi(100))).abs() < 1e-12);
+        assert!(store.stats("missing", None).is_none());
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::BTreeSet;
+use std::io;
+use std::time::Duration;
+
+use crate::{ControllerState, InputSequence, Step, SwitchController};
+
+/// Why [`Debugger::resume`] returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// Stopped before executing the step at this index.
+    Breakpoint(usize),
+    /// Every step has been executed.
+    Finished,
+}
+
+/// Step-by-step playback of an [`InputSequence`] with breakpoints.
+///
+/// The debugger keeps its own copy of the sequence so upcoming wait times can
+/// be edited between steps, and tracks the held controller state so it can be
+/// inspected while paused.
+#[derive(Debug, Clone)]
+pub struct Debugger {
+    sequence: InputSequence,
+    position: usize,
+    breakpoints: BTreeSet<usize>,
+    state: ControllerState,
+}
+
+impl Debugger {
+    pub fn new(sequence: InputSequence) -> Self {
+        Self {
+            sequence,
+            position: 0,
+            breakpoints: BTreeSet::new(),
+            state: ControllerState::new(),
+        }
+    }
+
+    /// Break before executing the step at `index`.
+    pub fn set_breakpoint(&mut self, index: usize) -> &mut Self {
+        self.breakpoints.insert(index);
+        self
+    }
+
+    /// Remove a breakpoint previously set with [`Debugger::set_breakpoint`].
+    pub fn clear_breakpoint(&mut self, index: usize) -> &mut Self {
+        self.breakpoints.remove(&index);
+        self
+    }
+
+    /// Indices of all breakpoints, in ascending order.
+    pub fn breakpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    /// Index of the next step to execute.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The next step to execute, or `None` once playback has finished.
+    pub fn next_step(&self) -> Option<&Step> {
+        self.sequence.steps().get(self.position)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.sequence.len()
+    }
+
+    /// The sequence being debugged, including any timing edits.
+    pub fn sequence(&self) -> &InputSequence {
+        &self.sequence
+    }
+
+    /// The controller state held after the steps executed so far.
+    pub fn state(&self) -> &ControllerState {
+        &self.state
+    }
+
+    /// Change the duration of an upcoming [`Step::Wait`].
+    ///
+    /// Returns `false` if `index` is not a wait step or has already been executed.
+    pub fn set_wait(&mut self, index: usize, duration: Duration) -> bool {
+        if index < self.position {
+            return false;
+        }
+        match self.sequence.steps_mut().get_mut(index) {
+            Some(Step::Wait(d)) => {
+                *d = duration;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Execute the next step, returning its index, or `None` if playback has finished.
+    pub fn step(&mut self, ctrl: &mut SwitchController) -> io::Result<Option<usize>> {
+        let index = self.position;
+        let Some(step) = self.sequence.steps().get(index) else {
+            return Ok(None);
+        };
+        ctrl.run_step(step)?;
+        step.apply(&mut self.state);
+        self.position += 1;
+        Ok(Some(index))
+    }
+
+    /// Execute steps until the next breakpoint or the end of the sequence.
+    ///
+    /// A breakpoint on the current position does not stop playback, so calling
+    /// `resume` again after a break continues past it.
+    pub fn resume(&mut self, ctrl: &mut SwitchController) -> io::Result<StopReason> {
+        self.step(ctrl)?;
+        while !self.is_finished() {
+            if self.breakpoints.contains(&self.position) {
+                return Ok(StopReason::Breakpoint(self.position));
+            }
+            self.step(ctrl)?;
+        }
+        Ok(StopReason::Finished)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Button;
+
+    #[test]
+    fn set_wait_only_edits_wait_steps() {
+        let mut seq = InputSequence::new();
+        seq.press(&[Button::A]).wait(Duration::from_millis(100));
+        let mut dbg = Debugger::new(seq);
+
+        assert!(!dbg.set_wait(0, Duration::from_millis(50)));
+        assert!(dbg.set_wait(1, Duration::from_millis(50)));
+        assert!(!dbg.set_wait(2, Duration::from_millis(50)));
+        assert!(matches!(
+            dbg.sequence().steps()[1],
+            Step::Wait(d) if d == Duration::from_millis(50)
+        ));
+    }
+
+    #[test]
+    fn breakpoints_are_sorted() {
+        let mut dbg = Debugger::new(InputSequence::new());
+        dbg.set_breakpoint(4)
+            .set_breakpoint(1)
+            .set_breakpoint(2)
+            .clear_breakpoint(2);
+        assert_eq!(dbg.breakpoints().collect::<Vec<_>>(), vec![1, 4]);
+        assert!(dbg.is_finished());
+        assert!(dbg.next_step().is_none());
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{Frame, Histogram, MatchBackend, Region, average_color, color_distance, find_template};
+
+/// How to check whether something is on screen, chosen per detection in a
+/// profile's `[detections.<name>]` tables:
+///
+/// ```toml
+/// [detections.shiny]
+/// method = "template"
+/// template = "pokemon/shiny_sparkle"
+/// region = "sparkle"      # a name from [regions], or [x, y, width, height]
+///
+/// [detections.battle]
+/// method = "color"
+/// region = [0, 85, 100, 15]
+/// color = [230, 60, 40]
+/// tolerance = 0.1
+///
+/// [detections.overworld]
+/// method = "histogram"
+/// template = "pokemon/grass"
+/// similarity = 0.8
+/// ```
+///
+/// Color and histogram checks are much cheaper than template matching and
+/// tolerate lighting changes better, at the cost of precision.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Detection {
+    /// Find `template` in the region with [`find_template`], or an external
+    /// matcher.
+    Template {
+        template: String,
+        region: Region,
+        /// The match score required, for matchers that score from 0 to 1.
+        threshold: f64,
+    },
+    /// The region's average color is within `tolerance` of `color`, as
+    /// measured by [`color_distance`].
+    Color {
+        region: Region,
+        color: [u8; 3],
+        tolerance: f64,
+    },
+    /// The region's [`Histogram`] is at least `similarity` alike to that of
+    /// the image `template`.
+    Histogram {
+        template: String,
+        region: Region,
+        similarity: f64,
+    },
+}
+
+impl Detection {
+    pub const DEFAULT_THRESHOLD: f64 = 0.9;
+    pub const DEFAULT_TOLERANCE: f64 = 0.1;
+    pub const DEFAULT_SIMILARITY: f64 = 0.8;
+
+    pub fn region(&self) -> &Region {
+        match self {
+            Detection::Template { region, .. }
+            | Detection::Color { region, .. }
+            | Detection::Histogram { region, .. } => region,
+        }
+    }
+
+    /// The template image the detection needs, if any.
+    pub fn template(&self) -> Option<&str> {
+        match self {
+            Detection::Template { template, .. } | Detection::Histogram { template, .. } => {
+                Some(template)
+            }
+            Detection::Color { .. } => None,
+        }
+    }
+
+    /// Check `frame`, with the decoded [`template`](Self::template) image
+    /// as `reference`. Returns `None` if the detection needs a reference and
+    /// none was given.
+    pub fn check(&self, frame: &Frame, reference: Option<&Frame>) -> Option<bool> {
+        match self {
+            Detection::Template {
+                region, threshold, ..
+            } => reference.map(|reference| {
+                find_template(frame, reference, region, MatchBackend::best())
+                    .is_some_and(|found| found.score >= *threshold)
+            }),
+            Detection::Color {
+                region,
+                color,
+                tolerance,
+            } => Some(color_distance(average_color(frame, region), *color) <= *tolerance),
+            Detection::Histogram {
+                region, similarity, ..
+            } => reference.map(|reference| {
+                Histogram::of(frame, region).similarity(&Histogram::of(reference, &Region::FULL))
+                    >= *similarity
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_frames() {
+        let frame = Frame::filled(8, 8, [230, 60, 40]);
+        let color = |tolerance| Detection::Color {
+            region: Region::FULL,
+            color: [220, 70, 40],
+            tolerance,
+        };
+        assert_eq!(color(0.1).check(&frame, None), Some(true));
+        assert_eq!(color(0.01).check(&frame, None), Some(false));
+
+        let histogram = Detection::Histogram {
+            template: "pokemon/grass".into(),
+            region: Region::FULL,
+            similarity: Detection::DEFAULT_SIMILARITY,
+        };
+        assert_eq!(histogram.template(), Some("pokemon/grass"));
+        assert_eq!(histogram.check(&frame, None), None);
+        let reference = Frame::filled(2, 2, [115, 30, 20]);
+        assert_eq!(histogram.check(&frame, Some(&reference)), Some(true));
+        let reference = Frame::filled(2, 2, [30, 115, 20]);
+        assert_eq!(histogram.check(&frame, Some(&reference)), Some(false));
+
+        let template = Detection::Template {
+            template: "pokemon/grass".into(),
+            region: Region::FULL,
+            threshold: Detection::DEFAULT_THRESHOLD,
+        };
+        assert_eq!(template.check(&frame, None), None);
+        let patch = Frame::filled(2, 2, [230, 60, 40]);
+        assert_eq!(template.check(&frame, Some(&patch)), Some(true));
+        let patch = Frame::filled(2, 2, [255, 255, 255]);
+        assert_eq!(template.check(&frame, Some(&patch)), Some(false));
+    }
+}
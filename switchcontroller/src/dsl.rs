@@ -0,0 +1,722 @@
+//! The macro language layered over script lines: variables, arithmetic,
+//! `repeat` blocks and parameterized macros, all expanded into plain script
+//! lines before parsing. Values are numbers, or words such as button names.
+//! `include "file"` expands another script in place, so shared macros can
+//! live in their own files.
+//!
+//! Scripts loaded as a [`Program`](crate::Program) can also jump: `label name` marks a
+//! point (like `mark`), `goto name` continues there, and
+//! `if detected("shiny.png") goto name` jumps when a detector reports the
+//! template on screen.
+//!
+//! ```text
+//! include "common/menus.macro"
+//! let delay = 250
+//! macro hatch(steps, button) {
+//!     repeat $steps * 2 {
+//!         press $button
+//!         wait ${delay}ms
+//!     }
+//! }
+//! hatch(5, a)
+//! ```
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::script::parse_line;
+use crate::{ParseScriptError, PluginCommand, Step};
+
+/// Most steps an expansion may produce, so a runaway `repeat` fails instead
+/// of exhausting memory.
+const MAX_STEPS: usize = 1_000_000;
+/// Most `repeat` iterations an expansion may run, counting nested loops
+/// separately, so loops over lines that produce no steps end too.
+const MAX_REPEATS: usize = 1_000_000;
+/// Deepest macro call nesting, which also stops unbounded recursion.
+const MAX_DEPTH: usize = 64;
+
+type Vars = HashMap<String, String>;
+
+struct Macro {
+    params: Vec<String>,
+    body: Vec<(usize, String)>,
+    /// The included file the macro was defined in, for error messages.
+    source: Option<String>,
+}
+
+fn numbered(source: &str) -> Vec<(usize, String)> {
+    source
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.to_string()))
+        .collect()
+}
+
+/// An expanded script line. Jump targets are resolved by [`Program`](crate::Program).
+#[derive(Debug, Clone)]
+pub(crate) enum Op {
+    Step(Step),
+    Goto {
+        label: String,
+        line: usize,
+    },
+    IfDetected {
+        template: String,
+        label: String,
+        line: usize,
+    },
+}
+
+/// Expand `source` and parse the resulting lines into steps.
+///
+/// Includes are looked up next to `file` (the working directory if `None`),
+/// then in each of `search_path` in order. Calls that are not macros are
+/// looked up in `commands`. `confined` limits includes to `search_path`, as
+/// for [`ScriptLoader::confine`](crate::ScriptLoader::confine).
+pub(crate) fn expand(
+    source: &str,
+    file: Option<&Path>,
+    search_path: &[PathBuf],
+    commands: &[PluginCommand],
+    confined: bool,
+) -> Result<Vec<Step>, ParseScriptError> {
+    let ops = expand_ops(source, file, search_path, commands, false, confined)?;
+    Ok(ops
+        .into_iter()
+        .filter_map(|op| match op {
+            Op::Step(step) => Some(step),
+            _ => None,
+        })
+        .collect())
+}
+
+/// [`expand`], also allowing `goto` and `if detected(..) goto`.
+pub(crate) fn expand_ops(
+    source: &str,
+    file: Option<&Path>,
+    search_path: &[PathBuf],
+    commands: &[PluginCommand],
+    jumps: bool,
+    confined: bool,
+) -> Result<Vec<Op>, ParseScriptError> {
+    let mut expander = Expander {
+        macros: HashMap::new(),
+        out: Vec::new(),
+        jumps,
+        search_path,
+        confined,
+        commands,
+        root: file.and_then(|f| fs::canonicalize(f).ok()),
+        includes: Vec::new(),
+        repeats: 0,
+    };
+    expander.run(&numbered(source), &mut Vars::new(), 0)?;
+    Ok(expander.out)
+}
+
+/// Parse `detected("template") goto label`, after an `if`.
+fn if_detected(rest: &str) -> Result<(String, String), String> {
+    let malformed = || "expected `if detected(\"template\") goto label`".to_string();
+    let rest = rest.strip_prefix("detected(").ok_or_else(malformed)?;
+    let (template, rest) = rest
+        .strip_prefix('"')
+        .and_then(|r| r.split_once('"'))
+        .ok_or_else(malformed)?;
+    let rest = rest.trim_start().strip_prefix(')').ok_or_else(malformed)?;
+    match rest.split_whitespace().collect::<Vec<_>>()[..] {
+        [goto, label] if goto.eq_ignore_ascii_case("goto") => {
+            Ok((template.to_string(), label.to_string()))
+        }
+        _ => Err(malformed()),
+    }
+}
+
+struct Expander<'a> {
+    macros: HashMap<String, Macro>,
+    out: Vec<Op>,
+    /// Whether jumps are allowed; only [`Program`](crate::Program)s can run them.
+    jumps: bool,
+    search_path: &'a [PathBuf],
+    /// Whether includes must stay inside `search_path`.
+    confined: bool,
+    /// Plugin commands, called like macros.
+    commands: &'a [PluginCommand],
+    root: Option<PathBuf>,
+    /// Files being included, innermost last, with their names as written.
+    includes: Vec<(PathBuf, String)>,
+    /// `repeat` iterations run so far.
+    repeats: usize,
+}
+
+impl Expander<'_> {
+    fn push(&mut self, op: Op) -> Result<(), String> {
+        if self.out.len() >= MAX_STEPS {
+            return Err("script expands to too many steps".into());
+        }
+        self.out.push(op);
+        Ok(())
+    }
+
+    /// The file `include "name"` refers to, from the current file.
+    fn resolve(&self, name: &str) -> Option<PathBuf> {
+        let current = self.includes.last().map(|(p, _)| p).or(self.root.as_ref());
+        let here = current
+            .and_then(|p| p.parent())
+            .map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+        // A confined script's own directory is only searched from inside
+        // an include, which is already known to be in the search path.
+        let here = (!self.confined || !self.includes.is_empty()).then_some(here);
+        let path = here
+            .into_iter()
+            .chain(self.search_path.iter().cloned())
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+            .and_then(|path| fs::canonicalize(path).ok())?;
+        // Canonical paths rule out symlinks leading back out, too.
+        let inside = || {
+            self.search_path
+                .iter()
+                .filter_map(|dir| fs::canonicalize(dir).ok())
+                .any(|dir| path.starts_with(dir))
+        };
+        (!self.confined || inside()).then_some(path)
+    }
+
+    /// The message for an error on a line of the included `file`. Confined,
+    /// it leaves out what the line says, as whoever sent the script may not
+    /// be allowed to read the file.
+    fn in_file(&self, file: &str, e: &ParseScriptError) -> String {
+        match self.confined {
+            true => format!("{file}: line {}: invalid script", e.line),
+            false => format!("{file}: line {}: {}", e.line, e.message),
+        }
+    }
+
+    fn include(&mut self, name: &str, vars: &mut Vars, depth: usize) -> Result<(), String> {
+        if self.confined
+            && !Path::new(name)
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)))
+        {
+            return Err(format!("include `{name}` must be inside the macro path"));
+        }
+        let path = self
+            .resolve(name)
+            .ok_or_else(|| format!("cannot find include `{name}`"))?;
+        if self.root.as_ref() == Some(&path) || self.includes.iter().any(|(p, _)| *p == path) {
+            return Err(format!("`{name}` includes itself"));
+        }
+        if depth >= MAX_DEPTH {
+            return Err("includes nested too deeply".into());
+        }
+        let source = fs::read_to_string(&path).map_err(|e| format!("cannot read `{name}`: {e}"))?;
+        self.includes.push((path, name.to_string()));
+        let result = self.run(&numbered(&source), vars, depth + 1);
+        self.includes.pop();
+        result.map_err(|e| self.in_file(name, &e))
+    }
+
+    fn run(
+        &mut self,
+        lines: &[(usize, String)],
+        vars: &mut Vars,
+        depth: usize,
+    ) -> Result<(), ParseScriptError> {
+        let mut i = 0;
+        while i < lines.len() {
+            let (number, line) = &lines[i];
+            let err = |message: String| ParseScriptError {
+                line: *number,
+                message,
+            };
+            let line = line.trim();
+            i += 1;
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let rest = rest.trim();
+            match keyword.to_ascii_lowercase().as_str() {
+                "let" => {
+                    let (name, expr) = rest
+                        .split_once('=')
+                        .ok_or_else(|| err("expected `=`".into()))?;
+                    let name = name.trim();
+                    if !is_name(name) {
+                        return Err(err(format!("invalid variable name `{name}`")));
+                    }
+                    let value = value(expr, vars).map_err(err)?;
+                    vars.insert(name.to_string(), value);
+                }
+                "macro" => {
+                    let header = rest
+                        .strip_suffix('{')
+                        .ok_or_else(|| err("expected `{` after macro header".into()))?;
+                    let (name, args) = call(header.trim())
+                        .ok_or_else(|| err("expected `macro name(params) {`".into()))?;
+                    let params: Vec<String> = args.iter().map(|p| p.to_string()).collect();
+                    if let Some(p) = params.iter().find(|p| !is_name(p)) {
+                        return Err(err(format!("invalid parameter name `{p}`")));
+                    }
+                    let end = block_end(lines, i).ok_or_else(|| err("unclosed `{`".into()))?;
+                    let body = lines[i..end].to_vec();
+                    let source = self.includes.last().map(|(_, n)| n.clone());
+                    self.macros.insert(
+                        name.to_string(),
+                        Macro {
+                            params,
+                            body,
+                            source,
+                        },
+                    );
+                    i = end + 1;
+                }
+                "repeat" => {
+                    let expr = rest
+                        .strip_suffix('{')
+                        .ok_or_else(|| err("expected `{` after repeat count".into()))?;
+                    let count = eval(expr, vars).map_err(err)?;
+                    if count < 0.0 || count.fract() != 0.0 {
+                        return Err(err(format!("invalid repeat count {count}")));
+                    }
+                    let end = block_end(lines, i).ok_or_else(|| err("unclosed `{`".into()))?;
+                    let count = count as usize;
+                    if count > MAX_REPEATS - self.repeats {
+                        return Err(err("script repeats too many times".into()));
+                    }
+                    self.repeats += count;
+                    for _ in 0..count {
+                        self.run(&lines[i..end], vars, depth)?;
+                    }
+                    i = end + 1;
+                }
+                "include" => {
+                    let name = rest
+                        .strip_prefix('"')
+                        .and_then(|r| r.strip_suffix('"'))
+                        .ok_or_else(|| err("expected `include \"path\"`".into()))?;
+                    self.include(name, vars, depth).map_err(err)?;
+                }
+                "label" => {
+                    let name = substitute(rest, vars).map_err(err)?;
+                    if name.is_empty() || name.contains(char::is_whitespace) {
+                        return Err(err("expected `label name`".into()));
+                    }
+                    self.push(Op::Step(Step::Marker(name))).map_err(err)?;
+                }
+                "goto" | "if" => {
+                    if !self.jumps {
+                        return Err(err(format!(
+                            "`{keyword}` needs a program; load the script with \
+                             `ScriptLoader::load_program`"
+                        )));
+                    }
+                    let rest = substitute(rest, vars).map_err(err)?;
+                    let op = if keyword.eq_ignore_ascii_case("goto") {
+                        if rest.is_empty() || rest.contains(char::is_whitespace) {
+                            return Err(err("expected `goto label`".into()));
+                        }
+                        Op::Goto {
+                            label: rest,
+                            line: *number,
+                        }
+                    } else {
+                        let (template, label) = if_detected(&rest).map_err(err)?;
+                        Op::IfDetected {
+                            template,
+                            label,
+                            line: *number,
+                        }
+                    };
+                    self.push(op).map_err(err)?;
+                }
+                "}" => return Err(err("unmatched `}`".into())),
+                _ => {
+                    let line = substitute(line, vars).map_err(err)?;
+                    if let Some((name, args)) = call(&line)
+                        && self.macros.contains_key(name)
+                    {
+                        self.call(name, &args, vars, depth)
+                            .map_err(|e| match e.line {
+                                0 => err(e.message),
+                                _ => e,
+                            })?;
+                    } else if let Some((name, args)) = call(&line)
+                        && let Some(command) = self.commands.iter().find(|c| c.name() == name)
+                    {
+                        let args = args
+                            .iter()
+                            .map(|arg| value(arg, vars))
+                            .collect::<Result<Vec<_>, _>>()
+                            .map_err(err)?;
+                        let seq = command
+                            .expand(&args)
+                            .map_err(|e| err(format!("{name}: {e}")))?;
+                        for step in seq.steps() {
+                            self.push(Op::Step(step.clone())).map_err(err)?;
+                        }
+                    } else {
+                        if let Some(step) = parse_line(&line).map_err(err)? {
+                            self.push(Op::Step(step)).map_err(err)?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Expand the macro `name` with `args` evaluated in the caller's `vars`.
+    /// Errors not tied to a body line have line 0, for the caller to fill in.
+    fn call(
+        &mut self,
+        name: &str,
+        args: &[&str],
+        vars: &Vars,
+        depth: usize,
+    ) -> Result<(), ParseScriptError> {
+        let err = |message: String| ParseScriptError { line: 0, message };
+        if depth >= MAX_DEPTH {
+            return Err(err("macros nested too deeply".into()));
+        }
+        let Macro {
+            params,
+            body,
+            source,
+        } = &self.macros[name];
+        if params.len() != args.len() {
+            return Err(err(format!(
+                "`{name}` takes {} arguments, got {}",
+                params.len(),
+                args.len()
+            )));
+        }
+        let mut scope = vars.clone();
+        for (param, arg) in params.iter().zip(args) {
+            scope.insert(param.clone(), value(arg, vars).map_err(err)?);
+        }
+        let (body, source) = (body.clone(), source.clone());
+        let result = self.run(&body, &mut scope, depth + 1);
+        match source {
+            Some(file) => result.map_err(|e| err(self.in_file(&file, &e))),
+            None => result,
+        }
+    }
+}
+
+fn is_name(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Split `name(a, b)` into its name and argument texts.
+fn call(text: &str) -> Option<(&str, Vec<&str>)> {
+    let (name, rest) = text.split_once('(')?;
+    let args = rest.trim_end().strip_suffix(')')?;
+    let name = name.trim();
+    if !is_name(name) {
+        return None;
+    }
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in args.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(args[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if !args.trim().is_empty() {
+        parts.push(args[start..].trim());
+    }
+    Some((name, parts))
+}
+
+/// The index of the `}` closing the block whose body starts at `start`.
+fn block_end(lines: &[(usize, String)], start: usize) -> Option<usize> {
+    let mut depth = 1;
+    for (i, (_, line)) in lines.iter().enumerate().skip(start) {
+        let line = line.trim();
+        if line == "}" {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
+            }
+        } else if line.ends_with('{') && !line.starts_with('#') {
+            depth += 1;
+        }
+    }
+    None
+}
+
+/// Replace `$name` and `${name}` with variable values.
+fn substitute(line: &str, vars: &Vars) -> Result<String, String> {
+    let mut out = String::new();
+    let mut rest = line;
+    while let Some(i) = rest.find('$') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+        let name = match rest.strip_prefix('{') {
+            Some(braced) => {
+                let end = braced.find('}').ok_or("unclosed `${`")?;
+                rest = &braced[end + 1..];
+                &braced[..end]
+            }
+            None => {
+                let end = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len());
+                let name = &rest[..end];
+                rest = &rest[end..];
+                name
+            }
+        };
+        let value = vars
+            .get(name)
+            .ok_or_else(|| format!("undefined variable `{name}`"))?;
+        out.push_str(value);
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// A bare word as itself, anything else as an arithmetic expression.
+fn value(expr: &str, vars: &Vars) -> Result<String, String> {
+    let expr = expr.trim();
+    if is_name(expr) {
+        return Ok(expr.to_string());
+    }
+    eval(expr, vars).map(|n| n.to_string())
+}
+
+/// Evaluate `+ - * /` and parentheses over numbers and `$variables`.
+fn eval(expr: &str, vars: &Vars) -> Result<f64, String> {
+    let expr = substitute(expr, vars)?;
+    let mut parser = Expr {
+        text: expr.trim(),
+        depth: 0,
+    };
+    let value = parser.sum()?;
+    if !parser.text.trim().is_empty() {
+        return Err(format!("unexpected `{}` in expression", parser.text.trim()));
+    }
+    if !value.is_finite() {
+        return Err(format!(
+            "expression `{}` is not a finite number",
+            expr.trim()
+        ));
+    }
+    Ok(value)
+}
+
+struct Expr<'a> {
+    text: &'a str,
+    depth: usize,
+}
+
+impl Expr<'_> {
+    fn eat(&mut self, c: char) -> bool {
+        self.text = self.text.trim_start();
+        match self.text.strip_prefix(c) {
+            Some(rest) => {
+                self.text = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn sum(&mut self) -> Result<f64, String> {
+        let mut value = self.product()?;
+        loop {
+            if self.eat('+') {
+                value += self.product()?;
+            } else if self.eat('-') {
+                value -= self.product()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<f64, String> {
+        let mut value = self.atom()?;
+        loop {
+            if self.eat('*') {
+                value *= self.atom()?;
+            } else if self.eat('/') {
+                value /= self.atom()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn atom(&mut self) -> Result<f64, String> {
+        let negate = self.eat('-');
+        if negate || self.eat('(') {
+            self.depth += 1;
+            if self.depth > MAX_DEPTH {
+                return Err("expression nested too deeply".into());
+            }
+            let value = if negate {
+                self.atom().map(|v| -v)?
+            } else {
+                let value = self.sum()?;
+                if !self.eat(')') {
+                    return Err("expected `)`".into());
+                }
+                value
+            };
+            self.depth -= 1;
+            return Ok(value);
+        }
+        self.text = self.text.trim_start();
+        let end = self
+            .text
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(self.text.len());
+        let (number, rest) = self.text.split_at(end);
+        self.text = rest;
+        number
+            .parse()
+            .map_err(|_| format!("expected a number, found `{}`", rest.trim()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::script_line;
+
+    fn lines(source: &str, file: Option<&Path>, search_path: &[PathBuf]) -> Vec<String> {
+        expand(source, file, search_path, &[], false)
+            .unwrap()
+            .iter()
+            .map(script_line)
+            .collect()
+    }
+
+    #[test]
+    fn expands_variables_and_macros() {
+        let source = "\
+let delay = 100 * 2 + 50
+let n = 2
+macro hatch(steps, button) {
+    repeat $steps * ($n - 1) {
+        press $button
+    }
+    wait ${delay}ms
+}
+hatch(1 + 1, b)
+# $undefined in a comment
+mark end";
+        assert_eq!(
+            lines(source, None, &[]),
+            ["PRESS b", "PRESS b", "WAIT 250ms", "MARK end"]
+        );
+        assert_eq!(eval("-(1 + 2) * 3 / 2", &Vars::new()), Ok(-4.5));
+    }
+
+    #[test]
+    fn reports_errors_on_source_lines() {
+        let err = |s: &str| expand(s, None, &[], &[], false).unwrap_err();
+        assert_eq!(err("press a\nwait $t").line, 2);
+        assert_eq!(err("repeat 2 {\npress a").message, "unclosed `{`");
+        assert_eq!(err("}").message, "unmatched `}`");
+        let e = err("macro m(x) {\npress $y\n}\n\nm(1)");
+        assert_eq!((e.line, e.message.as_str()), (2, "undefined variable `y`"));
+        let e = err("macro m(x) {\n}\nm()");
+        assert_eq!(
+            (e.line, e.message.as_str()),
+            (3, "`m` takes 1 arguments, got 0")
+        );
+        assert_eq!(
+            err("macro r() {\nr()\n}\nr()").message,
+            "macros nested too deeply"
+        );
+        assert!(err("let x = 1 / 0").message.contains("not a finite number"));
+        assert_eq!(err("repeat 0.5 {\n}").message, "invalid repeat count 0.5");
+        assert!(err("let x = a + 1").message.contains("expected a number"));
+        assert!(
+            err("repeat 600000 {\npress a\npress b\n}")
+                .message
+                .contains("too many steps")
+        );
+        let many = "repeat 1000 {\nrepeat 1000 {\n}\n}";
+        assert_eq!(err(many).message, "script repeats too many times");
+        assert!(err("repeat 100000000000 {\n}").message.contains("too many"));
+        let minus = format!("let x = {}1", "-".repeat(100_000));
+        assert!(err(&minus).message.contains("nested too deeply"));
+    }
+
+    #[test]
+    fn includes() {
+        let dir = std::env::temp_dir().join(format!("switchcontroller-dsl-{}", std::process::id()));
+        let lib = dir.join("lib");
+        fs::create_dir_all(lib.join("common")).unwrap();
+        fs::write(
+            lib.join("common/menus.macro"),
+            "include \"buttons.macro\"\nmacro go_home() {\n    press home\n    wait $menu\n}\n",
+        )
+        .unwrap();
+        fs::write(lib.join("common/buttons.macro"), "let menu = 1\n").unwrap();
+        fs::write(lib.join("loop.macro"), "include \"loop.macro\"\n").unwrap();
+        fs::write(lib.join("bad.macro"), "macro broken() {\n    press q\n}\n").unwrap();
+
+        let search = [lib.clone()];
+        let main = dir.join("main.macro");
+        assert_eq!(
+            lines(
+                "include \"common/menus.macro\"\ngo_home()",
+                Some(&main),
+                &search
+            ),
+            ["PRESS home", "WAIT 1s"]
+        );
+
+        let err = |s: &str| expand(s, Some(&main), &search, &[], false).unwrap_err();
+        assert_eq!(err("\ninclude \"missing.macro\"").line, 2);
+        assert_eq!(
+            err("include \"loop.macro\"").message,
+            "loop.macro: line 1: `loop.macro` includes itself"
+        );
+        let e = err("include \"bad.macro\"\nbroken()");
+        assert_eq!(
+            (e.line, e.message.as_str()),
+            (2, "bad.macro: line 2: unknown button `q`")
+        );
+
+        // Confined, only the search path can be read, and included lines
+        // are not quoted back.
+        fs::write(dir.join("secret.macro"), "let secret = 1\n").unwrap();
+        let confined = |s: &str| expand(s, None, &search, &[], true).unwrap_err().message;
+        assert!(
+            expand(
+                "include \"common/menus.macro\"\ngo_home()",
+                None,
+                &search,
+                &[],
+                true
+            )
+            .is_ok()
+        );
+        assert_eq!(
+            confined("include \"../secret.macro\""),
+            "include `../secret.macro` must be inside the macro path"
+        );
+        let secret = dir.join("secret.macro").display().to_string();
+        assert!(confined(&format!("include \"{secret}\"")).contains("must be inside"));
+        assert_eq!(
+            confined("include \"bad.macro\"\nbroken()"),
+            "bad.macro: line 2: invalid script"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
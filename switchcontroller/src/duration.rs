@@ -0,0 +1,78 @@
+//! Human-friendly durations for hand-edited files: `750ms`, `2.5s`, `12f`
+//! (frames at 60 fps), or a bare number of seconds.
+
+use std::time::Duration;
+
+/// Frames per second assumed by the `f` suffix, the rate most Switch games
+/// run their input loop at.
+pub(crate) const FRAME_RATE: u64 = 60;
+
+/// The length of one frame, [`frames`]`(1)`.
+pub(crate) const FRAME: Duration =
+    Duration::from_nanos((1_000_000_000 + FRAME_RATE / 2) / FRAME_RATE);
+
+/// The length of `count` frames, rounded to the nearest nanosecond, or
+/// `None` if that many nanoseconds don't fit in a `u64`.
+pub(crate) fn frames(count: u64) -> Option<Duration> {
+    let nanos = count
+        .checked_mul(1_000_000_000)?
+        .checked_add(FRAME_RATE / 2)?;
+    Some(Duration::from_nanos(nanos / FRAME_RATE))
+}
+
+pub(crate) fn parse_duration(text: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration `{text}`");
+    if let Some(count) = text.strip_suffix('f') {
+        return count.parse().ok().and_then(frames).ok_or_else(invalid);
+    }
+    let (number, scale) = match text.strip_suffix("ms") {
+        Some(ms) => (ms, 1e-3),
+        None => (text.strip_suffix('s').unwrap_or(text), 1.0),
+    };
+    let value: f64 = number.parse().map_err(|_| invalid())?;
+    Duration::try_from_secs_f64(value * scale).map_err(|_| invalid())
+}
+
+/// An exact spelling of `duration` that [`parse_duration`] reads back:
+/// milliseconds below a second, seconds above, and frames for frame counts
+/// that are not whole milliseconds.
+pub(crate) fn format_duration(duration: Duration) -> String {
+    if !duration.subsec_nanos().is_multiple_of(1_000_000) {
+        let count = (duration.as_nanos() * FRAME_RATE as u128 + 500_000_000) / 1_000_000_000;
+        if frames(count as u64) == Some(duration) {
+            return format!("{count}f");
+        }
+        return format!("{}s", duration.as_secs_f64());
+    }
+    let millis = duration.as_millis();
+    if millis < 1000 {
+        format!("{millis}ms")
+    } else {
+        format!("{}s", duration.as_secs_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_formats() {
+        for (text, millis, formatted) in [
+            ("750ms", 750, "750ms"),
+            ("2.5s", 2500, "2.5s"),
+            ("12f", 200, "200ms"),
+            ("0.25", 250, "250ms"),
+            ("1s", 1000, "1s"),
+        ] {
+            let duration = parse_duration(text).unwrap();
+            assert_eq!(duration, Duration::from_millis(millis), "{text}");
+            assert_eq!(format_duration(duration), formatted);
+        }
+        assert_eq!(format_duration(FRAME), "1f");
+        assert_eq!(format_duration(Duration::from_nanos(1)), "0.000000001s");
+        for bad in ["", "ms", "-1s", "1.5f", "fast", "20000000000f"] {
+            assert!(parse_duration(bad).is_err(), "{bad}");
+        }
+    }
+}
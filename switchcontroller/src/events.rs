@@ -0,0 +1,134 @@
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::SchedulerEvent;
+
+/// Something that happened during automation, published on an [`EventBus`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum AutomationEvent {
+    /// A named sequence started playing.
+    SequenceStarted { name: String },
+    /// A named sequence finished playing.
+    SequenceFinished { name: String, elapsed: Duration },
+    /// A run of a named sequence was skipped.
+    SequenceSkipped { name: String },
+    /// A detection (a shiny, a screen, ...) fired.
+    Detection { name: String },
+    /// A [`DetectionRecorder`](crate::DetectionRecorder) saved the frame a
+    /// detection matched or timed out on.
+    DetectionFrameSaved {
+        name: String,
+        matched: bool,
+        score: Option<f64>,
+        path: PathBuf,
+    },
+    /// A video clip is being saved on the console; see
+    /// [`SwitchController::save_video_clip_with`](crate::SwitchController::save_video_clip_with).
+    VideoClip,
+    /// An operation failed.
+    Error { message: String },
+    /// The connection to the device was re-established.
+    Reconnected,
+}
+
+impl From<SchedulerEvent<'_>> for AutomationEvent {
+    fn from(event: SchedulerEvent<'_>) -> Self {
+        match event {
+            SchedulerEvent::Started { job } => AutomationEvent::SequenceStarted {
+                name: job.to_string(),
+            },
+            SchedulerEvent::Finished { job, elapsed } => AutomationEvent::SequenceFinished {
+                name: job.to_string(),
+                elapsed,
+            },
+            SchedulerEvent::Skipped { job, .. } => AutomationEvent::SequenceSkipped {
+                name: job.to_string(),
+            },
+            SchedulerEvent::Failed { job, error } => AutomationEvent::Error {
+                message: format!("{job}: {error}"),
+            },
+        }
+    }
+}
+
+/// Fans [`AutomationEvent`]s out to every subscriber.
+///
+/// Notification, recording and dashboard code each call
+/// [`EventBus::subscribe`] and read events from their own channel, typically
+/// on their own thread. Clones share the same subscribers, so the bus can be
+/// handed to every producer:
+///
+/// ```no_run
+/// # use switchcontroller::{EventBus, Scheduler, SwitchController};
+/// # use std::sync::atomic::AtomicBool;
+/// # let mut ctrl = SwitchController::from_writer(Vec::new());
+/// # let mut scheduler = Scheduler::new();
+/// # let stop = AtomicBool::new(false);
+/// let bus = EventBus::new();
+/// let events = bus.subscribe();
+/// std::thread::spawn(move || {
+///     for event in events {
+///         println!("{event:?}");
+///     }
+/// });
+/// scheduler.run(&mut ctrl, &stop, |event| bus.publish(event.into()))?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Vec<Sender<AutomationEvent>>>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A channel receiving every event published from now on. Dropping the
+    /// receiver unsubscribes.
+    pub fn subscribe(&self) -> Receiver<AutomationEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    /// Send `event` to every subscriber.
+    pub fn publish(&self, event: AutomationEvent) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|tx| tx.send(event.clone()).is_ok());
+    }
+
+    /// Number of live subscribers, as of the last publish.
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers.lock().unwrap().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fans_out_and_drops_closed_subscribers() {
+        let bus = EventBus::new();
+        let a = bus.subscribe();
+        let b = bus.clone().subscribe();
+        bus.publish(AutomationEvent::Reconnected);
+        assert_eq!(a.try_recv(), Ok(AutomationEvent::Reconnected));
+        assert_eq!(b.try_recv(), Ok(AutomationEvent::Reconnected));
+
+        drop(b);
+        bus.publish(AutomationEvent::Detection {
+            name: "shiny".into(),
+        });
+        assert_eq!(bus.subscriber_count(), 1);
+        assert!(matches!(
+            a.try_recv(),
+            Ok(AutomationEvent::Detection { name }) if name == "shiny"
+        ));
+    }
+}
@@ -0,0 +1,269 @@
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::{Button, ControllerState, InputSequence, StateFrames, Step};
+
+/// Convert a stick axis in [-1.0, 1.0] to a signed byte.
+fn axis_to_i8(value: f32) -> i8 {
+    (value.clamp(-1.0, 1.0) * 127.0).round() as i8
+}
+
+/// Turn `name` into a valid C identifier.
+fn c_identifier(name: &str) -> String {
+    let mut ident: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !ident.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        ident.insert(0, '_');
+    }
+    ident
+}
+
+/// A Rust expression constructing `duration`.
+fn duration_expr(duration: Duration) -> String {
+    let nanos = duration.as_nanos();
+    if nanos.is_multiple_of(1_000_000) {
+        format!("Duration::from_millis({})", nanos / 1_000_000)
+    } else if nanos.is_multiple_of(1_000) {
+        format!("Duration::from_micros({})", nanos / 1_000)
+    } else {
+        format!("Duration::from_nanos({nanos})")
+    }
+}
+
+/// A Rust expression for `value`, spelling non-finite values as their
+/// `f32` constants since `{:?}` would write `NaN` or `inf`.
+fn f32_expr(value: f32) -> String {
+    if value.is_nan() {
+        "f32::NAN".into()
+    } else if value == f32::INFINITY {
+        "f32::INFINITY".into()
+    } else if value == f32::NEG_INFINITY {
+        "f32::NEG_INFINITY".into()
+    } else {
+        format!("{value:?}")
+    }
+}
+
+/// A Rust slice expression such as `&[Button::A, Button::ZR]`.
+fn buttons_expr(buttons: &[Button]) -> String {
+    let names: Vec<String> = buttons.iter().map(|b| format!("Button::{b:?}")).collect();
+    format!("&[{}]", names.join(", "))
+}
+
+impl InputSequence {
+    /// Emit a standalone Rust program that builds this sequence step by step
+    /// and plays it on the serial port given as its first argument.
+    ///
+    /// The output is meant as a starting point for hand-editing, for example
+    /// to turn a recorded session into maintainable code.
+    pub fn to_rust_source(&self) -> String {
+        let steps = self.steps();
+        let uses = |f: fn(&Step) -> bool| steps.iter().any(f);
+        let mut imports = Vec::new();
+        // Only when a button is named, so the output has no unused imports.
+        if uses(|s| match s {
+            Step::Press(b) | Step::Hold(b) | Step::Release(b) => !b.is_empty(),
+            Step::State(state) => state.button_mask() != 0,
+            _ => false,
+        }) {
+            imports.push("Button");
+        }
+        if uses(|s| matches!(s, Step::State(_))) {
+            imports.push("ControllerState");
+        }
+        imports.push("InputSequence");
+        if uses(|s| matches!(s, Step::Stick(..))) {
+            imports.push("Stick");
+        }
+        imports.push("SwitchController");
+
+        let mut out = String::new();
+        if uses(|s| matches!(s, Step::Wait(_))) {
+            out.push_str("use std::time::Duration;\n\n");
+        }
+        let _ = writeln!(out, "use switchcontroller::{{{}}};\n", imports.join(", "));
+        out.push_str("fn main() {\n");
+        out.push_str(
+            "    let port = std::env::args().nth(1).expect(\"usage: <program> <serial-port>\");\n",
+        );
+        out.push_str(
+            "    let mut ctrl = SwitchController::open(&port, 115200).expect(\"failed to open serial port\");\n\n",
+        );
+        out.push_str("    let mut seq = InputSequence::new();\n");
+        for step in steps {
+            let _ = match step {
+                Step::Press(b) => writeln!(out, "    seq.press({});", buttons_expr(b)),
+                Step::Hold(b) => writeln!(out, "    seq.hold({});", buttons_expr(b)),
+                Step::Release(b) => writeln!(out, "    seq.release({});", buttons_expr(b)),
+                Step::Stick(stick, h, v) => {
+                    let (h, v) = (f32_expr(*h), f32_expr(*v));
+                    writeln!(out, "    seq.stick(Stick::{stick:?}, {h}, {v});")
+                }
+                Step::Wait(d) => writeln!(out, "    seq.wait({});", duration_expr(*d)),
+                Step::Marker(name) => writeln!(out, "    seq.marker({name:?});"),
+                Step::State(state) => {
+                    out.push_str("    {\n        let mut state = ControllerState::new();\n");
+                    for b in Button::ALL.into_iter().filter(|&b| state.button(b)) {
+                        let _ = writeln!(out, "        state.set_button(Button::{b:?}, true);");
+                    }
+                    for (side, position) in
+                        [("left", state.left_stick), ("right", state.right_stick)]
+                    {
+                        if let Some((h, v)) = position {
+                            let (h, v) = (f32_expr(h), f32_expr(v));
+                            let _ = writeln!(out, "        state.set_{side}_stick({h}, {v});");
+                        }
+                    }
+                    writeln!(out, "        seq.state(&state);\n    }}")
+                }
+            };
+        }
+        out.push_str("\n    ctrl.play(&seq).unwrap();\n}\n");
+        out
+    }
+}
+
+impl StateFrames {
+    /// Emit a self-contained C header holding these frames as a constant array,
+    /// for firmware (Pico, Arduino Leonardo HID, ...) that plays them back with
+    /// no host attached.
+    ///
+    /// Consecutive identical frames are merged into one entry with a longer
+    /// duration. Each entry is a `<name>_frame_t`:
+    ///
+    /// - `buttons`: bit `i` set when [`Button::ALL`]`[i]` is pressed (the
+    ///   `STATE` bit order).
+    /// - `lx`, `ly`, `rx`, `ry`: stick axes scaled to -127..=127, with the
+    ///   same directions as the `STICK` command.
+    /// - `duration_ms`: how long to hold the entry before moving on.
+    ///
+    /// [`Button::ALL`]: crate::Button::ALL
+    pub fn to_c_source(&self, name: &str) -> String {
+        let name = c_identifier(name);
+        let upper = name.to_ascii_uppercase();
+        let interval_ms = self.interval.as_secs_f64() * 1000.0;
+
+        // (frame, number of intervals it lasts)
+        let mut runs: Vec<(&ControllerState, u32)> = Vec::new();
+        for frame in &self.frames {
+            match runs.last_mut() {
+                Some((last, count)) if last.to_command() == frame.to_command() => *count += 1,
+                _ => runs.push((frame, 1)),
+            }
+        }
+
+        let mut out = String::new();
+        let _ = writeln!(out, "// Generated by switchcontroller. Do not edit.");
+        let _ = writeln!(
+            out,
+            "// {} frames at {interval_ms} ms, {} entries.",
+            self.frames.len(),
+            runs.len()
+        );
+        let _ = writeln!(out, "#ifndef {upper}_H");
+        let _ = writeln!(out, "#define {upper}_H\n");
+        let _ = writeln!(out, "#include <stdint.h>\n");
+        let _ = writeln!(out, "typedef struct {{");
+        let _ = writeln!(out, "    uint32_t buttons;");
+        let _ = writeln!(out, "    int8_t lx, ly, rx, ry;");
+        let _ = writeln!(out, "    uint32_t duration_ms;");
+        let _ = writeln!(out, "}} {name}_frame_t;\n");
+        let _ = writeln!(out, "#define {upper}_LEN {}\n", runs.len());
+        let _ = writeln!(out, "static const {name}_frame_t {name}[{upper}_LEN] = {{");
+        for (frame, count) in runs {
+            let (lh, lv) = frame.left_stick.unwrap_or((0.0, 0.0));
+            let (rh, rv) = frame.right_stick.unwrap_or((0.0, 0.0));
+            let _ = writeln!(
+                out,
+                "    {{0x{:05x}, {}, {}, {}, {}, {}}},",
+                frame.button_mask(),
+                axis_to_i8(lh),
+                axis_to_i8(lv),
+                axis_to_i8(rh),
+                axis_to_i8(rv),
+                (interval_ms * count as f64).round() as u32
+            );
+        }
+        let _ = writeln!(out, "}};\n");
+        let _ = writeln!(out, "#endif // {upper}_H");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Stick, Timeline};
+
+    #[test]
+    fn c_source_merges_identical_frames() {
+        let mut seq = InputSequence::new();
+        seq.hold(&[Button::A, Button::DpadRight])
+            .stick(Stick::Left, 1.0, -1.0)
+            .wait(Duration::from_millis(100))
+            .release(&[Button::A, Button::DpadRight])
+            .wait(Duration::from_millis(50));
+
+        let frames = Timeline::from(&seq).compile_states(Duration::from_millis(50));
+        let source = frames.to_c_source("farm loop");
+        assert!(source.contains("} farm_loop_frame_t;"));
+        assert!(source.contains("#define FARM_LOOP_LEN 2"));
+        assert!(source.contains(
+            "    {0x20001, 127, -127, 0, 0, 100},\n    {0x00000, 127, -127, 0, 0, 50},\n"
+        ));
+    }
+
+    #[test]
+    fn rust_source_mirrors_steps() {
+        let mut state = ControllerState::new();
+        state
+            .set_button(Button::ZR, true)
+            .set_right_stick(-1.0, 0.0);
+        let mut seq = InputSequence::new();
+        seq.press(&[Button::A, Button::DpadUp])
+            .wait(Duration::from_millis(250))
+            .stick(Stick::Left, 0.5, 0.0)
+            .wait(Duration::from_micros(1500))
+            .state(&state);
+
+        let source = seq.to_rust_source();
+        assert!(source.starts_with(
+            "use std::time::Duration;\n\nuse switchcontroller::{Button, ControllerState, InputSequence, Stick, SwitchController};\n"
+        ));
+        assert!(source.contains(
+            "    seq.press(&[Button::A, Button::DpadUp]);\n    seq.wait(Duration::from_millis(250));\n"
+        ));
+        assert!(source.contains("    seq.stick(Stick::Left, 0.5, 0.0);\n"));
+        assert!(source.contains("    seq.wait(Duration::from_micros(1500));\n"));
+        assert!(source.contains(
+            "        state.set_button(Button::ZR, true);\n        state.set_right_stick(-1.0, 0.0);\n"
+        ));
+        assert!(source.ends_with("    ctrl.play(&seq).unwrap();\n}\n"));
+    }
+
+    #[test]
+    fn rust_source_compiles_edge_values() {
+        let mut state = ControllerState::new();
+        state.set_left_stick(f32::NAN, 0.0);
+        let mut seq = InputSequence::new();
+        seq.stick(Stick::Right, f32::INFINITY, f32::NEG_INFINITY)
+            .state(&state);
+
+        let source = seq.to_rust_source();
+        assert!(source.contains(
+            "use switchcontroller::{ControllerState, InputSequence, Stick, SwitchController};\n"
+        ));
+        assert!(
+            source.contains("    seq.stick(Stick::Right, f32::INFINITY, f32::NEG_INFINITY);\n")
+        );
+        assert!(source.contains("        state.set_left_stick(f32::NAN, 0.0);\n"));
+    }
+
+    #[test]
+    fn identifiers_are_sanitized() {
+        assert_eq!(c_identifier("shiny-reset"), "shiny_reset");
+        assert_eq!(c_identifier("3x"), "_3x");
+    }
+}
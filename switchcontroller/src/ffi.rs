@@ -0,0 +1,233 @@
+//! A C interface to the controller, for C, C++ and C# frontends. Enabled by
+//! the `ffi` feature; the matching header is `include/switchcontroller.h`.
+//!
+//! Controllers and sequences are opaque pointers owned by the caller and
+//! released with [`switch_close`] and [`switch_sequence_free`]. Buttons are
+//! passed as bitmasks, bit `i` standing for `Button::ALL[i]`. Functions that
+//! can fail return `0` on success and `-1` on failure, with the reason
+//! available from [`switch_last_error`].
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char, c_int};
+use std::io;
+use std::ptr;
+use std::time::Duration;
+
+use crate::{Button, ControllerState, InputSequence, Stick, SwitchController};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: impl ToString) {
+    let message = CString::new(message.to_string().replace('\0', " ")).unwrap();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+fn status(result: io::Result<()>) -> c_int {
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            set_last_error(e);
+            -1
+        }
+    }
+}
+
+fn with_ctrl(
+    ctrl: Option<&mut SwitchController>,
+    f: impl FnOnce(&mut SwitchController) -> io::Result<()>,
+) -> c_int {
+    match ctrl {
+        Some(ctrl) => status(f(ctrl)),
+        None => {
+            set_last_error("null controller");
+            -1
+        }
+    }
+}
+
+fn buttons(mask: u32) -> Vec<Button> {
+    Button::ALL
+        .into_iter()
+        .filter(|b| mask & (1 << b.index()) != 0)
+        .collect()
+}
+
+fn stick(stick: c_int) -> Option<Stick> {
+    match stick {
+        0 => Some(Stick::Left),
+        1 => Some(Stick::Right),
+        _ => None,
+    }
+}
+
+/// The message of the last error on this thread, or null if there was none.
+/// Valid until the next failing call on the same thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn switch_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |s| s.as_ptr()))
+}
+
+/// Open a controller on the serial port at `path`. Returns null on failure.
+///
+/// # Safety
+///
+/// `path` must be null or point to a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn switch_open(path: *const c_char, baud_rate: u32) -> *mut SwitchController {
+    if path.is_null() {
+        set_last_error("null path");
+        return ptr::null_mut();
+    }
+    let Ok(path) = unsafe { CStr::from_ptr(path) }.to_str() else {
+        set_last_error("path is not valid UTF-8");
+        return ptr::null_mut();
+    };
+    match SwitchController::open(path, baud_rate) {
+        Ok(ctrl) => Box::into_raw(Box::new(ctrl)),
+        Err(e) => {
+            set_last_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Close a controller, playing its shutdown sequence. Null is ignored.
+#[unsafe(no_mangle)]
+pub extern "C" fn switch_close(ctrl: Option<Box<SwitchController>>) {
+    drop(ctrl);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn switch_press(ctrl: Option<&mut SwitchController>, mask: u32) -> c_int {
+    with_ctrl(ctrl, |c| c.press(&buttons(mask)))
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn switch_hold(ctrl: Option<&mut SwitchController>, mask: u32) -> c_int {
+    with_ctrl(ctrl, |c| c.hold(&buttons(mask)))
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn switch_release(ctrl: Option<&mut SwitchController>, mask: u32) -> c_int {
+    with_ctrl(ctrl, |c| c.release(&buttons(mask)))
+}
+
+/// Set a stick position; `which` is `0` for the left stick, `1` for the right.
+#[unsafe(no_mangle)]
+pub extern "C" fn switch_stick(
+    ctrl: Option<&mut SwitchController>,
+    which: c_int,
+    horizontal: f32,
+    vertical: f32,
+) -> c_int {
+    with_ctrl(ctrl, |c| match stick(which) {
+        Some(s) => c.stick(s, horizontal, vertical),
+        None => Err(io::Error::new(io::ErrorKind::InvalidInput, "unknown stick")),
+    })
+}
+
+/// Set the whole controller state: the held buttons and both sticks.
+#[unsafe(no_mangle)]
+pub extern "C" fn switch_state(
+    ctrl: Option<&mut SwitchController>,
+    mask: u32,
+    left_h: f32,
+    left_v: f32,
+    right_h: f32,
+    right_v: f32,
+) -> c_int {
+    let mut state = ControllerState::new();
+    state
+        .set_button_mask(mask)
+        .set_left_stick(left_h, left_v)
+        .set_right_stick(right_h, right_v);
+    with_ctrl(ctrl, |c| c.state(&state))
+}
+
+/// Create an empty sequence, to be filled with the `switch_sequence_*`
+/// functions and played with [`switch_run_sequence`].
+#[unsafe(no_mangle)]
+pub extern "C" fn switch_sequence_new() -> *mut InputSequence {
+    Box::into_raw(Box::new(InputSequence::new()))
+}
+
+/// Free a sequence. Null is ignored.
+#[unsafe(no_mangle)]
+pub extern "C" fn switch_sequence_free(seq: Option<Box<InputSequence>>) {
+    drop(seq);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn switch_sequence_press(seq: Option<&mut InputSequence>, mask: u32) {
+    if let Some(seq) = seq {
+        seq.press(&buttons(mask));
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn switch_sequence_hold(seq: Option<&mut InputSequence>, mask: u32) {
+    if let Some(seq) = seq {
+        seq.hold(&buttons(mask));
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn switch_sequence_release(seq: Option<&mut InputSequence>, mask: u32) {
+    if let Some(seq) = seq {
+        seq.release(&buttons(mask));
+    }
+}
+
+/// Append a stick movement; an unknown `which` is ignored.
+#[unsafe(no_mangle)]
+pub extern "C" fn switch_sequence_stick(
+    seq: Option<&mut InputSequence>,
+    which: c_int,
+    horizontal: f32,
+    vertical: f32,
+) {
+    if let (Some(seq), Some(s)) = (seq, stick(which)) {
+        seq.stick(s, horizontal, vertical);
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn switch_sequence_wait(seq: Option<&mut InputSequence>, millis: u64) {
+    if let Some(seq) = seq {
+        seq.wait(Duration::from_millis(millis));
+    }
+}
+
+/// Play a sequence, blocking until it finishes.
+#[unsafe(no_mangle)]
+pub extern "C" fn switch_run_sequence(
+    ctrl: Option<&mut SwitchController>,
+    seq: Option<&InputSequence>,
+) -> c_int {
+    with_ctrl(ctrl, |c| match seq {
+        Some(seq) => c.play(seq),
+        None => Err(io::Error::new(io::ErrorKind::InvalidInput, "null sequence")),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn button_masks() {
+        assert_eq!(buttons(0), []);
+        assert_eq!(buttons(0b11), [Button::A, Button::B]);
+        assert_eq!(buttons(1 << 17), [Button::DpadRight]);
+        assert_eq!(buttons(u32::MAX).len(), Button::ALL.len());
+    }
+
+    #[test]
+    fn errors_are_reported() {
+        assert_eq!(switch_press(None, 1), -1);
+        let message = unsafe { CStr::from_ptr(switch_last_error()) };
+        assert_eq!(message.to_str(), Ok("null controller"));
+    }
+}
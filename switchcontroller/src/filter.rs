@@ -0,0 +1,205 @@
+use std::time::{Duration, Instant};
+
+use crate::{ControllerState, Stick};
+
+/// Filtering settings for a single stick axis.
+///
+/// The default passes values through unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct AxisFilter {
+    /// Time constant of a first-order low-pass filter. Larger values smooth
+    /// out more noise at the cost of responsiveness. Zero disables it.
+    pub time_constant: Duration,
+    /// Largest change per second, in full-scale units (2.0 crosses from one
+    /// edge to the other in one second). `None` disables slew limiting.
+    pub max_slew: Option<f32>,
+}
+
+impl AxisFilter {
+    fn step(&self, previous: f32, target: f32, dt: Duration) -> f32 {
+        let dt_secs = dt.as_secs_f32();
+        let tau = self.time_constant.as_secs_f32();
+        let mut value = if tau > 0.0 {
+            previous + (target - previous) * dt_secs / (tau + dt_secs)
+        } else {
+            target
+        };
+        if let Some(slew) = self.max_slew {
+            let limit = slew * dt_secs;
+            value = previous + (value - previous).clamp(-limit, limit);
+        }
+        value
+    }
+}
+
+/// A response curve reshaping how far a stick is tilted, keeping its
+/// direction.
+///
+/// The tilt magnitude `m` becomes `(m ^ exponent * scale)`, capped at full
+/// tilt. Exponents above 1.0 give finer control near the center, as for
+/// aiming; below 1.0 make small movements count more.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StickCurve {
+    pub exponent: f32,
+    pub scale: f32,
+}
+
+impl Default for StickCurve {
+    fn default() -> Self {
+        Self {
+            exponent: 1.0,
+            scale: 1.0,
+        }
+    }
+}
+
+impl StickCurve {
+    pub fn apply(&self, (h, v): (f32, f32)) -> (f32, f32) {
+        let magnitude = (h * h + v * v).sqrt();
+        if magnitude == 0.0 {
+            return (0.0, 0.0);
+        }
+        let shaped = (magnitude.min(1.0).powf(self.exponent) * self.scale).min(1.0);
+        let k = shaped / magnitude;
+        ((h * k).clamp(-1.0, 1.0), (v * k).clamp(-1.0, 1.0))
+    }
+}
+
+/// Low-pass filtering and slew-rate limiting for stick data from a noisy
+/// source, configured per axis.
+///
+/// Feed it every incoming state with [`StickFilter::apply`], or attach it to a
+/// [`Passthrough`](crate::Passthrough) with
+/// [`set_filter`](crate::Passthrough::set_filter).
+#[derive(Debug, Clone, Default)]
+pub struct StickFilter {
+    /// Settings for left horizontal, left vertical, right horizontal, right
+    /// vertical.
+    axes: [AxisFilter; 4],
+    values: [Option<f32>; 4],
+    last: Option<Instant>,
+}
+
+impl StickFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A filter using the same settings on every axis.
+    pub fn uniform(filter: AxisFilter) -> Self {
+        Self {
+            axes: [filter; 4],
+            ..Self::default()
+        }
+    }
+
+    /// Set the filter for the horizontal axis of a stick.
+    pub fn horizontal(&mut self, stick: Stick, filter: AxisFilter) -> &mut Self {
+        self.axes[Self::axis(stick)] = filter;
+        self
+    }
+
+    /// Set the filter for the vertical axis of a stick.
+    pub fn vertical(&mut self, stick: Stick, filter: AxisFilter) -> &mut Self {
+        self.axes[Self::axis(stick) + 1] = filter;
+        self
+    }
+
+    /// Forget the filter history, so the next state passes through unchanged.
+    pub fn reset(&mut self) {
+        self.values = [None; 4];
+        self.last = None;
+    }
+
+    /// Filter the stick positions of a state received at `now`, in place.
+    ///
+    /// The first state after creation or [`StickFilter::reset`] is passed
+    /// through unchanged. Sticks the state does not set are left alone.
+    pub fn apply(&mut self, state: &mut ControllerState, now: Instant) {
+        let dt = self
+            .last
+            .map_or(Duration::ZERO, |t| now.saturating_duration_since(t));
+        self.last = Some(now);
+        for (stick, position) in [
+            (Stick::Left, &mut state.left_stick),
+            (Stick::Right, &mut state.right_stick),
+        ] {
+            let Some((h, v)) = position else { continue };
+            let axis = Self::axis(stick);
+            for (i, value) in [(axis, h), (axis + 1, v)] {
+                let filtered = match self.values[i] {
+                    Some(previous) => self.axes[i].step(previous, *value, dt),
+                    None => *value,
+                };
+                self.values[i] = Some(filtered);
+                *value = filtered;
+            }
+        }
+    }
+
+    fn axis(stick: Stick) -> usize {
+        match stick {
+            Stick::Left => 0,
+            Stick::Right => 2,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn left(h: f32, v: f32) -> ControllerState {
+        let mut state = ControllerState::new();
+        state.set_left_stick(h, v);
+        state
+    }
+
+    #[test]
+    fn curve_keeps_direction() {
+        let curve = StickCurve {
+            exponent: 2.0,
+            scale: 1.0,
+        };
+        let (h, v) = curve.apply((0.3, 0.4));
+        assert!((h - 0.15).abs() < 1e-6 && (v - 0.2).abs() < 1e-6);
+        assert_eq!(StickCurve::default().apply((0.3, -0.4)), (0.3, -0.4));
+    }
+
+    #[test]
+    fn low_pass_converges() {
+        let mut filter = StickFilter::uniform(AxisFilter {
+            time_constant: Duration::from_millis(100),
+            max_slew: None,
+        });
+        let start = Instant::now();
+        let mut state = left(0.0, 0.0);
+        filter.apply(&mut state, start);
+
+        let mut state = left(1.0, -1.0);
+        filter.apply(&mut state, start + Duration::from_millis(100));
+        let (h, v) = state.left_stick.unwrap();
+        assert!((h - 0.5).abs() < 1e-6 && (v + 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn slew_limit_is_per_axis() {
+        let mut filter = StickFilter::new();
+        filter.horizontal(
+            Stick::Left,
+            AxisFilter {
+                max_slew: Some(2.0),
+                ..AxisFilter::default()
+            },
+        );
+        let start = Instant::now();
+        let mut state = left(-1.0, -1.0);
+        filter.apply(&mut state, start);
+
+        let mut state = left(1.0, 1.0);
+        filter.apply(&mut state, start + Duration::from_millis(250));
+        let (h, v) = state.left_stick.unwrap();
+        assert!((h + 0.5).abs() < 1e-6);
+        assert_eq!(v, 1.0);
+    }
+}
@@ -0,0 +1,147 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::Region;
+
+/// A captured video frame as 8-bit RGB, row by row.
+///
+/// The crate does no capturing itself; frames come from whatever capture
+/// library the caller uses, converted to this layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Frame {
+    /// A frame from `width * height * 3` bytes of RGB, or `None` if the
+    /// length does not match or the frame is empty.
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Option<Self> {
+        (width > 0 && height > 0 && pixels.len() == width as usize * height as usize * 3).then_some(
+            Self {
+                width,
+                height,
+                pixels,
+            },
+        )
+    }
+
+    /// A frame of a single color.
+    pub fn filled(width: u32, height: u32, rgb: [u8; 3]) -> Self {
+        Self::new(width, height, rgb.repeat(width as usize * height as usize))
+            .expect("frame must not be empty")
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 3] {
+        let i = (y as usize * self.width as usize + x as usize) * 3;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2]]
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, rgb: [u8; 3]) {
+        let i = (y as usize * self.width as usize + x as usize) * 3;
+        self.pixels[i..i + 3].copy_from_slice(&rgb);
+    }
+
+    /// Draw the outline of `region`, one pixel wide, in `rgb`.
+    pub fn outline(&mut self, region: &Region, rgb: [u8; 3]) {
+        let (left, top, width, height) = region.to_pixels((self.width, self.height));
+        let (right, bottom) = (left + width - 1, top + height - 1);
+        for x in left..=right {
+            self.set_pixel(x, top, rgb);
+            self.set_pixel(x, bottom, rgb);
+        }
+        for y in top..=bottom {
+            self.set_pixel(left, y, rgb);
+            self.set_pixel(right, y, rgb);
+        }
+    }
+
+    /// Write the frame as a binary PPM image, a format virtually every image
+    /// viewer opens. Each line of `comment` becomes a comment in the header.
+    pub fn write_ppm(&self, mut out: impl Write, comment: &str) -> io::Result<()> {
+        writeln!(out, "P6")?;
+        for line in comment.lines() {
+            writeln!(out, "# {line}")?;
+        }
+        writeln!(out, "{} {}\n255", self.width, self.height)?;
+        out.write_all(&self.pixels)
+    }
+
+    pub fn save_ppm(&self, path: impl AsRef<Path>, comment: &str) -> io::Result<()> {
+        let mut out = io::BufWriter::new(fs::File::create(path)?);
+        self.write_ppm(&mut out, comment)?;
+        out.flush()
+    }
+
+    /// The pixels inside `region`, row by row.
+    pub(crate) fn region_pixels(&self, region: &Region) -> impl Iterator<Item = [u8; 3]> + '_ {
+        let (left, top, width, height) = region.to_pixels((self.width, self.height));
+        (top..top + height).flat_map(move |y| (left..left + width).map(move |x| self.pixel(x, y)))
+    }
+
+    /// How much `region` changed between this frame and `other`: the mean
+    /// absolute difference in brightness, from 0 (identical) to 1 (black to
+    /// white everywhere). `None` if the frames differ in size.
+    pub fn difference(&self, other: &Frame, region: &Region) -> Option<f64> {
+        if (self.width, self.height) != (other.width, other.height) {
+            return None;
+        }
+        let (mut total, mut count) = (0u64, 0u64);
+        for (a, b) in self.region_pixels(region).zip(other.region_pixels(region)) {
+            total += u64::from(luma(a).abs_diff(luma(b)));
+            count += 1;
+        }
+        Some(total as f64 / (count * 255) as f64)
+    }
+}
+
+/// Perceived brightness of `rgb`, 0 to 255.
+pub(crate) fn luma([r, g, b]: [u8; 3]) -> u8 {
+    ((299 * u32::from(r) + 587 * u32::from(g) + 114 * u32::from(b) + 500) / 1000) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn differences_within_regions() {
+        let black = Frame::filled(10, 10, [0, 0, 0]);
+        let mut dot = black.clone();
+        dot.set_pixel(9, 9, [255, 255, 255]);
+        assert_eq!(black.difference(&black, &Region::FULL), Some(0.0));
+        assert_eq!(black.difference(&dot, &Region::FULL), Some(0.01));
+        let corner = Region::new(90.0, 90.0, 10.0, 10.0).unwrap();
+        assert_eq!(black.difference(&dot, &corner), Some(1.0));
+        let elsewhere = Region::new(0.0, 0.0, 50.0, 50.0).unwrap();
+        assert_eq!(black.difference(&dot, &elsewhere), Some(0.0));
+        assert_eq!(
+            black.difference(&Frame::filled(5, 5, [0; 3]), &corner),
+            None
+        );
+        assert!(Frame::new(2, 2, vec![0; 11]).is_none());
+
+        let mut image = Frame::filled(3, 3, [0; 3]);
+        image.outline(&Region::FULL, [255, 0, 0]);
+        assert_eq!(image.pixel(0, 0), [255, 0, 0]);
+        assert_eq!(image.pixel(1, 1), [0, 0, 0]);
+        let mut ppm = Vec::new();
+        image.write_ppm(&mut ppm, "score 0.5").unwrap();
+        assert!(ppm.starts_with(b"P6\n# score 0.5\n3 3\n255\n\xff\0\0"));
+        assert_eq!(ppm.len(), 23 + 27);
+    }
+}
@@ -0,0 +1,199 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{AutomationEvent, EventBus, Frame, Region};
+
+/// Saves the frames detections matched or gave up on, for finding out
+/// afterwards why a bot stalled.
+///
+/// Each frame is written to the debug directory as a PPM image named
+/// `<unix-ms>-<detection>-<matched|timeout>.ppm`, with the region outlined
+/// in red and the score in the image header, and an
+/// [`AutomationEvent::DetectionFrameSaved`] is published if an [`EventBus`]
+/// is attached.
+///
+/// ```no_run
+/// # use switchcontroller::{DetectionRecorder, EventBus, Frame, Region};
+/// # let frame = Frame::filled(1280, 720, [0; 3]);
+/// let mut recorder = DetectionRecorder::new("debug").events(EventBus::new());
+/// recorder.record("shiny", false, &frame, &Region::FULL, Some(0.42))?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct DetectionRecorder {
+    dir: PathBuf,
+    matches: bool,
+    timeouts: bool,
+    bus: Option<EventBus>,
+    saved: u64,
+}
+
+impl DetectionRecorder {
+    /// A recorder saving to `dir`, created on first use. Records timeouts
+    /// only until [`matches`](Self::matches) is enabled.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            matches: false,
+            timeouts: true,
+            bus: None,
+            saved: 0,
+        }
+    }
+
+    /// Whether to save frames that matched.
+    pub fn matches(mut self, enabled: bool) -> Self {
+        self.matches = enabled;
+        self
+    }
+
+    /// Whether to save frames of detections that timed out.
+    pub fn timeouts(mut self, enabled: bool) -> Self {
+        self.timeouts = enabled;
+        self
+    }
+
+    /// Publish a [`AutomationEvent::DetectionFrameSaved`] for every saved
+    /// frame.
+    pub fn events(mut self, bus: EventBus) -> Self {
+        self.bus = Some(bus);
+        self
+    }
+
+    /// Number of frames saved so far.
+    pub fn saved(&self) -> u64 {
+        self.saved
+    }
+
+    /// Save `frame` from detection `name`, which looked at `region` and
+    /// either `matched` or timed out, if that outcome is being recorded.
+    /// Returns the path written.
+    pub fn record(
+        &mut self,
+        name: &str,
+        matched: bool,
+        frame: &Frame,
+        region: &Region,
+        score: Option<f64>,
+    ) -> io::Result<Option<PathBuf>> {
+        if !(if matched { self.matches } else { self.timeouts }) {
+            return Ok(None);
+        }
+        fs::create_dir_all(&self.dir)?;
+        let outcome = if matched { "matched" } else { "timeout" };
+        let safe_name: String = name
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let path = timestamped_path(&self.dir, &format!("{safe_name}-{outcome}"));
+
+        let mut annotated = frame.clone();
+        annotated.outline(region, [255, 0, 0]);
+        let mut comment = format!("detection {name}: {outcome}");
+        if let Some(score) = score {
+            comment += &format!("\nscore {score}");
+        }
+        annotated.save_ppm(&path, &comment)?;
+        self.saved += 1;
+        if let Some(bus) = &self.bus {
+            bus.publish(AutomationEvent::DetectionFrameSaved {
+                name: name.to_string(),
+                matched,
+                score,
+                path: path.clone(),
+            });
+        }
+        Ok(Some(path))
+    }
+}
+
+/// `<dir>/<unix-ms>-<name>.ppm`, or `<unix-ms>-<name>-<n>.ppm` numbered
+/// after the frames already saved in the same millisecond, so names sort in
+/// the order frames were saved even after older ones are deleted.
+pub(crate) fn timestamped_path(dir: &Path, name: &str) -> PathBuf {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis());
+    let stem = format!("{millis}-{name}");
+    let taken = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let file = entry.ok()?.file_name();
+            let rest = file.to_str()?.strip_prefix(&stem)?.strip_suffix(".ppm")?;
+            match rest.strip_prefix('-') {
+                None if rest.is_empty() => Some(1),
+                Some(n) => n.parse::<u64>().ok(),
+                None => None,
+            }
+        })
+        .max();
+    match taken {
+        None => dir.join(format!("{stem}.ppm")),
+        Some(n) => dir.join(format!("{stem}-{}.ppm", n + 1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saves_selected_outcomes() {
+        let dir =
+            std::env::temp_dir().join(format!("switchcontroller-debug-{}", std::process::id()));
+        let bus = EventBus::new();
+        let events = bus.subscribe();
+        let mut recorder = DetectionRecorder::new(&dir).events(bus);
+        let frame = Frame::filled(4, 4, [0; 3]);
+        let corner = Region::new(50.0, 50.0, 50.0, 50.0).unwrap();
+        assert_eq!(
+            recorder
+                .record("shiny", true, &frame, &corner, None)
+                .unwrap(),
+            None
+        );
+
+        let first = recorder
+            .record("pokemon/shiny", false, &frame, &corner, Some(0.4))
+            .unwrap()
+            .unwrap();
+        let second = recorder
+            .record("pokemon/shiny", false, &frame, &corner, Some(0.4))
+            .unwrap()
+            .unwrap();
+        assert_ne!(first, second);
+        assert!(
+            first
+                .to_str()
+                .unwrap()
+                .ends_with("-pokemon_shiny-timeout.ppm")
+        );
+        assert_eq!(recorder.saved(), 2);
+
+        let image = fs::read(&first).unwrap();
+        let header = b"P6\n# detection pokemon/shiny: timeout\n# score 0.4\n4 4\n255\n";
+        assert!(image.starts_with(header));
+        // The region's top-left corner is outlined
+        let pixel = header.len() + (2 * 4 + 2) * 3;
+        assert_eq!(image[pixel..pixel + 3], [255, 0, 0]);
+        assert_eq!(
+            events.try_recv(),
+            Ok(AutomationEvent::DetectionFrameSaved {
+                name: "pokemon/shiny".into(),
+                matched: false,
+                score: Some(0.4),
+                path: first,
+            })
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,100 @@
+//! Smoke tests against a real Pico, for checking that the firmware and this
+//! crate still agree on a bench.
+//!
+//! Opt in with the `hardware-tests` feature and name the port in
+//! `SWITCHCONTROLLER_TEST_PORT`:
+//!
+//! ```text
+//! SWITCHCONTROLLER_TEST_PORT=/dev/ttyACM0 cargo test -p switchcontroller --features hardware-tests hardware
+//! ```
+//!
+//! The serial link is write-only, so the tests check that every command is
+//! accepted; watch the console (e.g. the controller test screen under System
+//! Settings > Controllers and Sensors) to confirm the inputs arrive. Home and
+//! Capture are never pressed.
+
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use crate::{Button, ControllerState, Direction, InputSequence, Stick, SwitchController};
+
+/// The port can only be opened by one test at a time.
+static PORT: Mutex<()> = Mutex::new(());
+
+const PORT_VAR: &str = "SWITCHCONTROLLER_TEST_PORT";
+
+/// Open the test port and run `test` on it, releasing everything after.
+fn with_controller(test: impl FnOnce(&mut SwitchController)) {
+    let _port = PORT.lock().unwrap_or_else(|e| e.into_inner());
+    let path = std::env::var(PORT_VAR)
+        .unwrap_or_else(|_| panic!("set {PORT_VAR} to the Pico's serial port"));
+    let mut ctrl = SwitchController::open(&path, 115200)
+        .unwrap_or_else(|e| panic!("failed to open {path}: {e}"));
+    let mut neutral = InputSequence::new();
+    neutral.state(&ControllerState::neutral());
+    ctrl.set_shutdown_sequence(neutral);
+    test(&mut ctrl);
+    ctrl.shutdown().unwrap();
+}
+
+#[test]
+fn opens_and_neutralizes() {
+    with_controller(|ctrl| {
+        ctrl.state(&ControllerState::neutral()).unwrap();
+        ctrl.flush().unwrap();
+    });
+}
+
+#[test]
+fn presses_each_button() {
+    with_controller(|ctrl| {
+        for button in Button::ALL_NO_HOME {
+            if button == Button::Capture {
+                continue;
+            }
+            ctrl.press(&[button]).unwrap();
+            thread::sleep(Duration::from_millis(250));
+        }
+    });
+}
+
+#[test]
+fn sweeps_sticks() {
+    with_controller(|ctrl| {
+        for stick in [Stick::Left, Stick::Right] {
+            for direction in [
+                Direction::Up,
+                Direction::UpRight,
+                Direction::Right,
+                Direction::DownRight,
+                Direction::Down,
+                Direction::DownLeft,
+                Direction::Left,
+                Direction::UpLeft,
+            ] {
+                let (h, v) = direction.vector();
+                ctrl.stick(stick, h, v).unwrap();
+                thread::sleep(Duration::from_millis(150));
+            }
+            ctrl.stick(stick, 0.0, 0.0).unwrap();
+        }
+    });
+}
+
+#[test]
+fn state_round_trips() {
+    with_controller(|ctrl| {
+        let state = ControllerState::builder()
+            .buttons(&[Button::A, Button::ZR, Button::DpadLeft])
+            .left_stick(0.5, -1.0)
+            .right_stick(-0.25, 0.75)
+            .build();
+        ctrl.state(&state).unwrap();
+        thread::sleep(Duration::from_millis(250));
+        assert_eq!(ctrl.snapshot(), state);
+        let parsed: InputSequence = state.to_command().parse().unwrap();
+        assert_eq!(parsed.state_at(parsed.len()), state);
+        ctrl.state(&ControllerState::neutral()).unwrap();
+    });
+}
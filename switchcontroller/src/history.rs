@@ -0,0 +1,131 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::time::Instant;
+
+use crate::{SentCommand, SwitchController};
+
+/// How many commands a controller remembers unless changed with
+/// [`SwitchController::set_history_capacity`].
+pub const DEFAULT_HISTORY_CAPACITY: usize = 64;
+
+/// How many of the most recent commands are attached to errors and reports.
+const ERROR_TAIL: usize = 8;
+
+/// A failed write, with the commands sent just before it for post-mortems.
+///
+/// Write errors returned by the controller carry one inside the
+/// [`io::Error`], which keeps the original error's kind; get it back with
+/// `error.get_ref().and_then(|e| e.downcast_ref::<WriteError>())`.
+#[derive(Debug)]
+pub struct WriteError {
+    pub error: io::Error,
+    /// The most recent command lines, oldest first, ending with the one that
+    /// failed.
+    pub recent: Vec<String>,
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)?;
+        if !self.recent.is_empty() {
+            write!(f, " (last commands: {})", self.recent.join(" | "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for WriteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl SwitchController {
+    /// Remember the last `capacity` commands sent, for
+    /// [`SwitchController::recent_commands`]. `0` turns the history off.
+    pub fn set_history_capacity(&mut self, capacity: usize) -> &mut Self {
+        self.history_capacity = capacity;
+        while self.history.len() > capacity {
+            self.history.pop_front();
+        }
+        self
+    }
+
+    pub fn history_capacity(&self) -> usize {
+        self.history_capacity
+    }
+
+    /// The last commands sent, oldest first, with when each was sent. Unlike
+    /// [`SwitchController::subscribe_commands`], this costs nothing until
+    /// read, so it can stay on to explain desyncs after the fact.
+    pub fn recent_commands(&self) -> impl ExactSizeIterator<Item = &SentCommand> {
+        self.history.iter()
+    }
+
+    /// Add a command line being written to the history.
+    pub(crate) fn record_history(&mut self, cmd: &str, at: Instant) {
+        if self.history_capacity == 0 {
+            return;
+        }
+        if self.history.len() == self.history_capacity {
+            self.history.pop_front();
+        }
+        self.history.push_back(SentCommand {
+            command: cmd.to_string(),
+            at,
+        });
+    }
+
+    /// The command lines attached to errors and reports.
+    pub(crate) fn history_tail(&self) -> Vec<String> {
+        let skip = self.history.len().saturating_sub(ERROR_TAIL);
+        self.history
+            .iter()
+            .skip(skip)
+            .map(|sent| sent.command.clone())
+            .collect()
+    }
+
+    /// `error` with the history tail attached, as a [`WriteError`].
+    pub(crate) fn with_history(&self, error: io::Error) -> io::Error {
+        let recent = self.history_tail();
+        io::Error::new(error.kind(), WriteError { error, recent })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Button;
+
+    struct Broken;
+
+    impl io::Write for Broken {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn keeps_recent_commands() {
+        let mut ctrl = SwitchController::from_writer(io::sink());
+        ctrl.set_history_capacity(2);
+        ctrl.press(&[Button::A]).unwrap();
+        ctrl.press(&[Button::B]).unwrap();
+        ctrl.hold(&[Button::X]).unwrap();
+        let recent: Vec<_> = ctrl.recent_commands().map(|c| c.command.as_str()).collect();
+        assert_eq!(recent, ["PRESS b", "HOLD x"]);
+
+        let mut ctrl = SwitchController::from_writer(Broken);
+        let err = ctrl.press(&[Button::A]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        let write_error = err.get_ref().unwrap().downcast_ref::<WriteError>().unwrap();
+        assert_eq!(write_error.recent, ["PRESS a"]);
+        assert!(err.to_string().ends_with("(last commands: PRESS a)"));
+    }
+}
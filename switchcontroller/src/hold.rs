@@ -0,0 +1,99 @@
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::{Button, Command, SwitchController};
+
+impl SwitchController {
+    /// Hold buttons for at most `max_duration`.
+    ///
+    /// The controller releases them once the time is up even if
+    /// [`SwitchController::release`] is never called: expiry is checked
+    /// before every command, during playback waits, in
+    /// [`SwitchController::pump`] and on drop. Call `pump` to release on
+    /// time while sending nothing else. Releasing the buttons earlier cancels
+    /// the timer.
+    pub fn hold_timed(&mut self, buttons: &[Button], max_duration: Duration) -> io::Result<()> {
+        self.hold(buttons)?;
+        self.timed_holds
+            .push((Instant::now() + max_duration, buttons.to_vec()));
+        Ok(())
+    }
+
+    /// When the next button held with [`SwitchController::hold_timed`] is due
+    /// to be released.
+    pub fn next_timed_release(&self) -> Option<Instant> {
+        self.timed_holds.iter().map(|&(at, _)| at).min()
+    }
+
+    /// Send `RELEASE` for timed holds due by `now`, or for all of them with
+    /// `None`.
+    pub(crate) fn release_timed(&mut self, now: Option<Instant>) -> io::Result<()> {
+        if self.timed_holds.is_empty() {
+            return Ok(());
+        }
+        let (due, pending) = std::mem::take(&mut self.timed_holds)
+            .into_iter()
+            .partition(|&(at, _)| now.is_none_or(|now| at <= now));
+        self.timed_holds = pending;
+        for (_, buttons) in due {
+            self.send_tracked(&Command::Release(buttons))?;
+        }
+        Ok(())
+    }
+
+    /// Stop the timers of buttons the caller released.
+    pub(crate) fn forget_timed(&mut self, released: &[Button]) {
+        for (_, buttons) in &mut self.timed_holds {
+            buttons.retain(|b| !released.contains(b));
+        }
+        self.timed_holds.retain(|(_, buttons)| !buttons.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Shared;
+    use crate::{ControllerState, InputSequence};
+
+    #[test]
+    fn timed_holds_expire() {
+        let out = Shared::default();
+        let mut ctrl = SwitchController::from_writer(out.clone());
+        ctrl.hold_timed(&[Button::A, Button::B], Duration::ZERO)
+            .unwrap();
+        ctrl.hold_timed(&[Button::X, Button::Y], Duration::from_secs(60))
+            .unwrap();
+        ctrl.release(&[Button::X]).unwrap();
+        assert_eq!(
+            ctrl.timed_holds,
+            [(ctrl.next_timed_release().unwrap(), vec![Button::Y])]
+        );
+        drop(ctrl);
+        assert_eq!(
+            out.text(),
+            "HOLD a b\nRELEASE a b\nHOLD x y\nRELEASE x\nRELEASE y\n"
+        );
+    }
+
+    #[test]
+    fn played_releases_cancel_timers() {
+        let out = Shared::default();
+        let mut ctrl = SwitchController::from_writer(out.clone());
+        ctrl.hold_timed(&[Button::A], Duration::from_millis(20))
+            .unwrap();
+        ctrl.hold_timed(&[Button::B], Duration::from_millis(20))
+            .unwrap();
+        let mut seq = InputSequence::new();
+        seq.release(&[Button::A])
+            .hold(&[Button::A])
+            .state(ControllerState::new().set_button(Button::A, true))
+            .wait(Duration::from_millis(50));
+        ctrl.play(&seq).unwrap();
+        assert_eq!(ctrl.next_timed_release(), None);
+        assert_eq!(
+            out.text(),
+            "HOLD a\nHOLD b\nRELEASE a\nHOLD a\nSTATE 100000000000000000\n"
+        );
+    }
+}
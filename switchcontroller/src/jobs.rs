@@ -0,0 +1,518 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+
+use crate::{Button, ControllerState, InputSequence, PlaybackPosition, SwitchController};
+
+/// How many finished, cancelled or failed jobs a [`JobManager`] keeps by
+/// default.
+pub const DEFAULT_RETAINED_JOBS: usize = 100;
+
+/// Identifies a job submitted to a [`JobManager`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct JobId(pub u64);
+
+/// Where a job is in its life.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobStatus {
+    /// Waiting for earlier jobs on its device to finish.
+    Queued,
+    Running,
+    /// Stopped mid-sequence with all inputs released, until resumed.
+    Paused,
+    Finished,
+    Cancelled,
+    Failed(String),
+}
+
+impl JobStatus {
+    /// Whether the job is finished, cancelled or failed.
+    pub fn is_done(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Finished | JobStatus::Cancelled | JobStatus::Failed(_)
+        )
+    }
+}
+
+/// A sequence to play some number of times on one device.
+#[derive(Debug, Clone)]
+pub struct JobSpec {
+    pub name: String,
+    pub sequence: InputSequence,
+    pub iterations: u64,
+}
+
+impl JobSpec {
+    /// A job playing `sequence` once.
+    pub fn new(name: impl Into<String>, sequence: InputSequence) -> Self {
+        Self {
+            name: name.into(),
+            sequence,
+            iterations: 1,
+        }
+    }
+
+    pub fn iterations(mut self, count: u64) -> Self {
+        self.iterations = count;
+        self
+    }
+}
+
+/// A snapshot of a job, from [`JobManager::job`] or [`JobManager::jobs`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobInfo {
+    pub id: JobId,
+    pub name: String,
+    pub device: String,
+    pub status: JobStatus,
+    /// Iterations completed so far.
+    pub completed: u64,
+    pub iterations: u64,
+    /// What happened to the job, oldest first.
+    pub log: Vec<String>,
+}
+
+/// What the manager has asked a job to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Request {
+    Run,
+    Pause,
+    Cancel,
+}
+
+#[derive(Debug)]
+struct JobState {
+    info: JobInfo,
+    request: Request,
+}
+
+/// A job shared between the manager and its device's worker thread.
+#[derive(Debug)]
+struct SharedJob {
+    state: Mutex<JobState>,
+    changed: Condvar,
+    /// Set to stop the current playback, so a pause or cancel takes effect
+    /// mid-sequence.
+    interrupt: AtomicBool,
+    sequence: InputSequence,
+}
+
+impl SharedJob {
+    fn lock(&self) -> MutexGuard<'_, JobState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn set_status(&self, status: JobStatus, message: String) {
+        let mut state = self.lock();
+        state.info.status = status;
+        state.info.log.push(message);
+        self.changed.notify_all();
+    }
+
+    fn request(&self, request: Request) {
+        let mut state = self.lock();
+        if state.info.status.is_done() {
+            return;
+        }
+        state.request = request;
+        self.interrupt
+            .store(request != Request::Run, Ordering::Relaxed);
+        self.changed.notify_all();
+    }
+}
+
+/// A device's worker thread, which owns its controller.
+#[derive(Debug)]
+struct Device {
+    queue: Sender<Arc<SharedJob>>,
+    thread: JoinHandle<SwitchController>,
+}
+
+/// Runs automation jobs across several controllers at once.
+///
+/// Each device added with [`JobManager::add_device`] is owned by its own
+/// worker thread, which plays that device's jobs one at a time in the order
+/// they were submitted. Jobs on different devices run concurrently. Jobs can
+/// be paused, resumed and cancelled from any thread holding the manager, and
+/// keep a log of what happened to them. Only the most recent jobs that are
+/// done are kept, [`DEFAULT_RETAINED_JOBS`] unless changed with
+/// [`JobManager::set_retained_jobs`], so a long-running manager doesn't
+/// grow without bound.
+///
+#[cfg_attr(feature = "serial", doc = "```no_run")]
+#[cfg_attr(not(feature = "serial"), doc = "```ignore")]
+/// # use switchcontroller::{InputSequence, JobSpec, JobManager, SwitchController};
+/// # let hatch = InputSequence::new();
+/// let mut jobs = JobManager::new();
+/// jobs.add_device("left", SwitchController::open("/dev/ttyACM0", 115200)?)?;
+/// jobs.add_device("right", SwitchController::open("/dev/ttyACM1", 115200)?)?;
+/// let a = jobs.submit("left", JobSpec::new("hatch", hatch.clone()).iterations(500))?;
+/// let b = jobs.submit("right", JobSpec::new("hatch", hatch).iterations(500))?;
+/// jobs.pause(a);
+/// println!("{:?}", jobs.job(b).unwrap().status);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct JobManager {
+    devices: BTreeMap<String, Device>,
+    jobs: Mutex<BTreeMap<JobId, Arc<SharedJob>>>,
+    next_id: Mutex<u64>,
+    /// How many jobs that are done to keep.
+    retained: usize,
+}
+
+impl Default for JobManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JobManager {
+    pub fn new() -> Self {
+        Self {
+            devices: BTreeMap::new(),
+            jobs: Mutex::new(BTreeMap::new()),
+            next_id: Mutex::new(0),
+            retained: DEFAULT_RETAINED_JOBS,
+        }
+    }
+
+    /// Keep only the `count` most recent jobs that are done, forgetting
+    /// older ones as new jobs are submitted. Jobs still queued, running or
+    /// paused are always kept.
+    pub fn set_retained_jobs(&mut self, count: usize) -> &mut Self {
+        self.retained = count;
+        self
+    }
+
+    /// Hand `ctrl` to the manager under `name`. Fails with
+    /// [`io::ErrorKind::AlreadyExists`] if the name is taken.
+    pub fn add_device(&mut self, name: &str, mut ctrl: SwitchController) -> io::Result<()> {
+        if self.devices.contains_key(name) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("device `{name}` is already managed"),
+            ));
+        }
+        let (queue, jobs) = mpsc::channel::<Arc<SharedJob>>();
+        let thread = thread::spawn(move || {
+            for job in jobs {
+                run_job(&mut ctrl, &job);
+            }
+            ctrl
+        });
+        self.devices
+            .insert(name.to_string(), Device { queue, thread });
+        Ok(())
+    }
+
+    /// Names of the managed devices, sorted.
+    pub fn devices(&self) -> impl Iterator<Item = &str> {
+        self.devices.keys().map(String::as_str)
+    }
+
+    /// Cancel the device's remaining jobs and take its controller back, or
+    /// `None` if there is no such device.
+    pub fn remove_device(&mut self, name: &str) -> Option<SwitchController> {
+        let device = self.devices.remove(name)?;
+        for job in self.lock_jobs().values() {
+            if job.lock().info.device == name {
+                job.request(Request::Cancel);
+            }
+        }
+        drop(device.queue);
+        device.thread.join().ok()
+    }
+
+    /// Queue `job` on `device`. Fails with [`io::ErrorKind::NotFound`] if
+    /// there is no such device.
+    pub fn submit(&self, device: &str, job: JobSpec) -> io::Result<JobId> {
+        let Some(worker) = self.devices.get(device) else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no device `{device}`"),
+            ));
+        };
+        let id = {
+            let mut next = self.next_id.lock().unwrap_or_else(PoisonError::into_inner);
+            *next += 1;
+            JobId(*next)
+        };
+        let shared = Arc::new(SharedJob {
+            state: Mutex::new(JobState {
+                info: JobInfo {
+                    id,
+                    name: job.name,
+                    device: device.to_string(),
+                    status: JobStatus::Queued,
+                    completed: 0,
+                    iterations: job.iterations,
+                    log: vec!["queued".to_string()],
+                },
+                request: Request::Run,
+            }),
+            changed: Condvar::new(),
+            interrupt: AtomicBool::new(false),
+            sequence: job.sequence,
+        });
+        self.prune();
+        self.lock_jobs().insert(id, shared.clone());
+        worker
+            .queue
+            .send(shared)
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "device worker has stopped"))?;
+        Ok(id)
+    }
+
+    /// Pause a queued or running job. Returns `false` if there is no such
+    /// job or it is already done.
+    pub fn pause(&self, id: JobId) -> bool {
+        self.request(id, Request::Pause)
+    }
+
+    /// Resume a paused job.
+    pub fn resume(&self, id: JobId) -> bool {
+        self.request(id, Request::Run)
+    }
+
+    /// Cancel a job, stopping it mid-sequence with all inputs released if it
+    /// is running. A job that fails releases them too.
+    pub fn cancel(&self, id: JobId) -> bool {
+        self.request(id, Request::Cancel)
+    }
+
+    /// A snapshot of the job, or `None` if there is no such job or it was
+    /// done and has been forgotten.
+    pub fn job(&self, id: JobId) -> Option<JobInfo> {
+        let job = self.lock_jobs().get(&id)?.clone();
+        Some(job.lock().info.clone())
+    }
+
+    /// Snapshots of every job still kept, oldest first.
+    pub fn jobs(&self) -> Vec<JobInfo> {
+        let jobs: Vec<_> = self.lock_jobs().values().cloned().collect();
+        jobs.iter().map(|job| job.lock().info.clone()).collect()
+    }
+
+    /// Block until the job is done and return its final status, or `None`
+    /// if there is no such job.
+    pub fn wait(&self, id: JobId) -> Option<JobStatus> {
+        let job = self.lock_jobs().get(&id)?.clone();
+        let mut state = job.lock();
+        while !state.info.status.is_done() {
+            state = job
+                .changed
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        Some(state.info.status.clone())
+    }
+
+    /// Forget the oldest jobs that are done, beyond the retained count.
+    fn prune(&self) {
+        let mut jobs = self.lock_jobs();
+        let done: Vec<JobId> = jobs
+            .iter()
+            .filter(|(_, job)| job.lock().info.status.is_done())
+            .map(|(&id, _)| id)
+            .collect();
+        for id in &done[..done.len().saturating_sub(self.retained)] {
+            jobs.remove(id);
+        }
+    }
+
+    fn request(&self, id: JobId, request: Request) -> bool {
+        let Some(job) = self.lock_jobs().get(&id).cloned() else {
+            return false;
+        };
+        if job.lock().info.status.is_done() {
+            return false;
+        }
+        job.request(request);
+        true
+    }
+
+    fn lock_jobs(&self) -> MutexGuard<'_, BTreeMap<JobId, Arc<SharedJob>>> {
+        self.jobs.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Drop for JobManager {
+    /// Cancel every job and wait for the workers to stop.
+    fn drop(&mut self) {
+        let names: Vec<String> = self.devices.keys().cloned().collect();
+        for name in names {
+            self.remove_device(&name);
+        }
+    }
+}
+
+/// Play `job` on a worker thread until it finishes, fails or is cancelled.
+fn run_job(ctrl: &mut SwitchController, job: &SharedJob) {
+    let iterations = job.lock().info.iterations;
+    for iteration in 0..iterations {
+        let mut position = PlaybackPosition::default();
+        loop {
+            job.interrupt.store(false, Ordering::Relaxed);
+            if !wait_while_paused(ctrl, job) {
+                release_all(ctrl);
+                job.set_status(JobStatus::Cancelled, "cancelled".to_string());
+                return;
+            }
+            match ctrl.play_cancellable(&job.sequence, position, &job.interrupt) {
+                Ok(None) => break,
+                Ok(Some(stopped)) => position = stopped,
+                Err(e) => {
+                    release_all(ctrl);
+                    job.set_status(JobStatus::Failed(e.to_string()), format!("failed: {e}"));
+                    return;
+                }
+            }
+        }
+        let mut state = job.lock();
+        state.info.completed = iteration + 1;
+        state
+            .info
+            .log
+            .push(format!("iteration {} of {iterations} done", iteration + 1));
+    }
+    job.set_status(JobStatus::Finished, "finished".to_string());
+}
+
+/// Release every input and drop pending timed holds, so a paused or
+/// stopped job leaves nothing held for the next one. Errors are ignored: the
+/// job is stopping either way.
+fn release_all(ctrl: &mut SwitchController) {
+    ctrl.forget_timed(&Button::ALL);
+    let _ = ctrl.state(&ControllerState::neutral());
+    let _ = ctrl.flush();
+}
+
+/// Block while the job is paused, with every input released. Returns
+/// `false` if the job was cancelled.
+fn wait_while_paused(ctrl: &mut SwitchController, job: &SharedJob) -> bool {
+    let mut state = job.lock();
+    if state.request == Request::Pause {
+        state.info.status = JobStatus::Paused;
+        state.info.log.push("paused".to_string());
+        job.changed.notify_all();
+        // Released with the lock dropped, so status queries don't wait on
+        // the port.
+        drop(state);
+        release_all(ctrl);
+        state = job.lock();
+        while state.request == Request::Pause {
+            state = job
+                .changed
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+    if state.request == Request::Cancel {
+        return false;
+    }
+    if state.info.status != JobStatus::Running {
+        let message = match state.info.status {
+            JobStatus::Paused => "resumed",
+            _ => "started",
+        };
+        state.info.status = JobStatus::Running;
+        state.info.log.push(message.to_string());
+        job.changed.notify_all();
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Shared;
+    use std::time::Duration;
+
+    #[test]
+    fn runs_pauses_and_cancels_jobs() {
+        let out = Shared::default();
+        let mut jobs = JobManager::new();
+        jobs.add_device("left", SwitchController::from_writer(out.clone()))
+            .unwrap();
+        assert!(
+            jobs.add_device("left", SwitchController::from_writer(Shared::default()))
+                .is_err()
+        );
+        jobs.add_device("right", SwitchController::from_writer(Shared::default()))
+            .unwrap();
+        assert_eq!(jobs.devices().collect::<Vec<_>>(), ["left", "right"]);
+
+        let mut seq = InputSequence::new();
+        seq.press(&[Button::A]);
+        let quick = jobs
+            .submit("left", JobSpec::new("mash", seq.clone()).iterations(3))
+            .unwrap();
+        assert_eq!(jobs.wait(quick), Some(JobStatus::Finished));
+        let info = jobs.job(quick).unwrap();
+        assert_eq!(info.completed, 3);
+        assert_eq!(info.log.first().map(String::as_str), Some("queued"));
+        assert_eq!(out.text(), "PRESS a\nPRESS a\nPRESS a\n");
+
+        let mut slow = InputSequence::new();
+        slow.hold(&[Button::B]).wait(Duration::from_secs(60));
+        let long = jobs.submit("right", JobSpec::new("hold", slow)).unwrap();
+        assert!(jobs.pause(long));
+        let paused = |jobs: &JobManager| jobs.job(long).unwrap().status == JobStatus::Paused;
+        while !paused(&jobs) {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(jobs.cancel(long));
+        assert_eq!(jobs.wait(long), Some(JobStatus::Cancelled));
+        assert!(!jobs.resume(long));
+        assert!(jobs.submit("nowhere", JobSpec::new("x", seq)).is_err());
+
+        let ctrl = jobs.remove_device("left").unwrap();
+        drop(ctrl);
+        assert_eq!(jobs.jobs().len(), 2);
+    }
+
+    #[test]
+    fn cancelled_jobs_release_inputs() {
+        let out = Shared::default();
+        let mut jobs = JobManager::new();
+        jobs.add_device("left", SwitchController::from_writer(out.clone()))
+            .unwrap();
+        let mut seq = InputSequence::new();
+        seq.hold(&[Button::B]).wait(Duration::from_secs(60));
+        let id = jobs.submit("left", JobSpec::new("hold", seq)).unwrap();
+        while !out.text().contains("HOLD b") {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(jobs.cancel(id));
+        assert_eq!(jobs.wait(id), Some(JobStatus::Cancelled));
+        assert_eq!(out.text(), "HOLD b\nSTATE 000000000000000000 0 0 0 0\n");
+    }
+
+    #[test]
+    fn forgets_old_finished_jobs() {
+        let mut jobs = JobManager::new();
+        jobs.add_device("left", SwitchController::from_writer(io::sink()))
+            .unwrap();
+        jobs.set_retained_jobs(2);
+        let mut seq = InputSequence::new();
+        seq.press(&[Button::A]);
+        let ids: Vec<JobId> = (0..4)
+            .map(|_| {
+                let id = jobs
+                    .submit("left", JobSpec::new("tap", seq.clone()))
+                    .unwrap();
+                jobs.wait(id);
+                id
+            })
+            .collect();
+        // The latest job is kept on top of the two retained before it.
+        let kept: Vec<JobId> = jobs.jobs().iter().map(|info| info.id).collect();
+        assert_eq!(kept, ids[1..]);
+        assert!(jobs.job(ids[0]).is_none());
+    }
+}
@@ -0,0 +1,113 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::{Button, SwitchController};
+
+/// How often the keep-awake thread checks whether the controller has gone idle.
+const IDLE_POLL: Duration = Duration::from_millis(100);
+
+/// Settings for [`KeepAwake`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeepAwakeConfig {
+    /// Button pressed to keep the console awake.
+    pub button: Button,
+    /// How long the controller must be idle before the button is pressed.
+    pub interval: Duration,
+}
+
+impl Default for KeepAwakeConfig {
+    /// Click the left stick after 30 seconds of inactivity, which few games
+    /// react to outside of gameplay.
+    fn default() -> Self {
+        Self {
+            button: Button::LStick,
+            interval: Duration::from_secs(30),
+        }
+    }
+}
+
+/// A background thread that stops the console from sleeping during long idle
+/// periods.
+///
+/// Whenever no command has been sent for [`KeepAwakeConfig::interval`], the
+/// configured button is pressed. Any other command resets the timer, so the
+/// thread stays quiet while a sequence is playing. The thread stops when the
+/// handle is dropped.
+#[derive(Debug)]
+pub struct KeepAwake {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<io::Result<()>>>,
+}
+
+impl KeepAwake {
+    /// Start keeping the console awake through a shared controller.
+    pub fn spawn(ctrl: Arc<Mutex<SwitchController>>, config: KeepAwakeConfig) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = stop.clone();
+        let thread = thread::spawn(move || {
+            while !flag.load(Ordering::Relaxed) {
+                thread::sleep(IDLE_POLL);
+                let mut ctrl = ctrl.lock().unwrap_or_else(PoisonError::into_inner);
+                if ctrl.idle_for() >= config.interval {
+                    ctrl.press(&[config.button])?;
+                }
+            }
+            Ok(())
+        });
+        Self {
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    /// Stop the thread, returning the write error that ended it early, if any.
+    pub fn stop(mut self) -> io::Result<()> {
+        self.shutdown()
+    }
+
+    fn shutdown(&mut self) -> io::Result<()> {
+        self.stop.store(true, Ordering::Relaxed);
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(io::Error::other("keep-awake thread panicked")),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for KeepAwake {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Shared;
+
+    #[test]
+    fn nudges_while_idle_until_stopped() {
+        let out = Shared::default();
+        let ctrl = Arc::new(Mutex::new(SwitchController::from_writer(out.clone())));
+        let awake = KeepAwake::spawn(
+            ctrl,
+            KeepAwakeConfig {
+                button: Button::X,
+                interval: Duration::from_millis(150),
+            },
+        );
+        thread::sleep(Duration::from_millis(700));
+        awake.stop().unwrap();
+        let text = out.text();
+        assert!(text.lines().count() >= 2);
+        assert!(text.lines().all(|line| line == "PRESS x"));
+
+        // Nothing more once the thread is joined.
+        thread::sleep(Duration::from_millis(300));
+        assert_eq!(out.text(), text);
+    }
+}
@@ -0,0 +1,189 @@
+use std::io;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::{Command, SwitchController};
+
+/// The rest of a layer chain, ending at the port.
+pub type Next<'a> = dyn FnMut(Command) -> io::Result<()> + 'a;
+
+/// Middleware that every command passes through on its way to the device,
+/// added with [`SwitchController::add_layer`].
+///
+/// A layer gets each command and passes it on by calling `next`, which may
+/// be done after a delay, with a changed command, more than once, or not at
+/// all to drop the command.
+///
+/// Rate limiting ([`SwitchController::set_rate_limit`]) and duplicate `STATE`
+/// suppression stay built into the controller rather than being layers:
+/// they also cover what the controller sends on its own without passing
+/// through the layers, such as timed releases, and the rate limit's queue is
+/// drained by [`SwitchController::flush`] and [`SwitchController::pump`].
+pub trait Layer: Send {
+    fn handle(&mut self, command: Command, next: &mut Next<'_>) -> io::Result<()>;
+}
+
+/// Writes every command to a log, one line each, then passes it on.
+pub struct Logging<W> {
+    out: W,
+    start: Instant,
+}
+
+impl<W: io::Write + Send> Logging<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl<W: io::Write + Send> Layer for Logging<W> {
+    /// Lines are `<seconds since the layer was created> <command>`.
+    fn handle(&mut self, command: Command, next: &mut Next<'_>) -> io::Result<()> {
+        writeln!(
+            self.out,
+            "{:.3} {command}",
+            self.start.elapsed().as_secs_f64()
+        )?;
+        next(command)
+    }
+}
+
+/// Delays each command by a random time up to `max_delay`, so input timing
+/// varies the way a person's does.
+#[derive(Debug, Clone)]
+pub struct Humanize {
+    max_delay: Duration,
+    rng: u64,
+}
+
+impl Humanize {
+    /// Seeded from the clock; use [`Humanize::seed`] for repeatable delays.
+    pub fn new(max_delay: Duration) -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        Self::seed(max_delay, nanos)
+    }
+
+    pub fn seed(max_delay: Duration, seed: u64) -> Self {
+        Self {
+            max_delay,
+            // xorshift gets stuck at zero.
+            rng: seed | 1,
+        }
+    }
+
+    /// The next delay, from a xorshift64 generator.
+    fn delay(&mut self) -> Duration {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.max_delay
+            .mul_f64((self.rng >> 11) as f64 / (1u64 << 53) as f64)
+    }
+}
+
+impl Layer for Humanize {
+    fn handle(&mut self, command: Command, next: &mut Next<'_>) -> io::Result<()> {
+        thread::sleep(self.delay());
+        next(command)
+    }
+}
+
+/// Pass `command` through `layers` in order, then to `sink`.
+fn run_layers(
+    layers: &mut [Box<dyn Layer>],
+    ctrl: &mut SwitchController,
+    command: Command,
+    sink: fn(&mut SwitchController, &Command) -> io::Result<()>,
+) -> io::Result<()> {
+    match layers.split_first_mut() {
+        None => sink(ctrl, &command),
+        Some((layer, rest)) => layer.handle(command, &mut |command| {
+            run_layers(rest, ctrl, command, sink)
+        }),
+    }
+}
+
+impl SwitchController {
+    /// Add a layer that every command, including those of played sequences,
+    /// passes through before the controller's own settings apply. Layers run
+    /// in the order added.
+    pub fn add_layer(&mut self, layer: impl Layer + 'static) -> &mut Self {
+        self.layers.push(Box::new(layer));
+        self
+    }
+
+    /// Remove every layer.
+    pub fn clear_layers(&mut self) -> &mut Self {
+        self.layers.clear();
+        self
+    }
+
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Pass `command` through the layers, then to `sink`.
+    pub(crate) fn through_layers(
+        &mut self,
+        command: Command,
+        sink: fn(&mut SwitchController, &Command) -> io::Result<()>,
+    ) -> io::Result<()> {
+        if self.layers.is_empty() {
+            return sink(self, &command);
+        }
+        let mut layers = std::mem::take(&mut self.layers);
+        let result = run_layers(&mut layers, self, command, sink);
+        self.layers = layers;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Shared;
+    use crate::{Button, ControllerState, InputSequence, Stick};
+
+    /// Sends every `PRESS` twice.
+    struct Double;
+
+    impl Layer for Double {
+        fn handle(&mut self, command: Command, next: &mut Next<'_>) -> io::Result<()> {
+            if let Command::Press(_) = command {
+                next(command.clone())?;
+            }
+            next(command)
+        }
+    }
+
+    #[test]
+    fn layers_run_in_order() {
+        let out = Shared::default();
+        let log = Shared::default();
+        let mut ctrl = SwitchController::from_writer(out.clone());
+        ctrl.add_layer(Double).add_layer(Logging::new(log.clone()));
+        ctrl.press(&[Button::A]).unwrap();
+        ctrl.stick(Stick::Left, 1.0, 0.0).unwrap();
+        let mut seq = InputSequence::new();
+        seq.stick(Stick::Left, 1.0, 0.0)
+            .state(&ControllerState::neutral())
+            .state(&ControllerState::neutral());
+        ctrl.play(&seq).unwrap();
+        assert_eq!(
+            out.text(),
+            "PRESS a\nPRESS a\nSTICK l_stick 1 0\nSTICK l_stick 1 0\nSTATE 000000000000000000 0 0 0 0\n"
+        );
+        // The repeated STATE reaches the layers, then the controller drops it.
+        assert_eq!(log.text().lines().count(), 6);
+        assert!(log.text().ends_with(" STATE 000000000000000000 0 0 0 0\n"));
+
+        let mut humanize = Humanize::seed(Duration::from_millis(10), 7);
+        assert!((0..100).all(|_| humanize.delay() < Duration::from_millis(10)));
+        ctrl.clear_layers();
+        assert_eq!(ctrl.layer_count(), 0);
+    }
+}
@@ -1,260 +1,411 @@
-use std::fmt;
-use std::io::{self, Write};
-use std::time::Duration;
+use std::collections::VecDeque;
+use std::io;
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
 
-/// A Nintendo Switch controller button.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub enum Button {
-    A,
-    B,
-    X,
-    Y,
-    L,
-    R,
-    ZL,
-    ZR,
-    Plus,
-    Minus,
-    Home,
-    Capture,
-    LStick,
-    RStick,
-    DpadUp,
-    DpadDown,
-    DpadLeft,
-    DpadRight,
-}
-
-impl Button {
-    /// All buttons in STATE bit-order (index 0..17).
-    pub const ALL: [Button; 18] = [
-        Button::A,
-        Button::B,
-        Button::X,
-        Button::Y,
-        Button::L,
-        Button::R,
-        Button::ZL,
-        Button::ZR,
-        Button::Plus,
-        Button::Minus,
-        Button::Home,
-        Button::Capture,
-        Button::LStick,
-        Button::RStick,
-        Button::DpadUp,
-        Button::DpadDown,
-        Button::DpadLeft,
-        Button::DpadRight,
-    ];
-
-    fn as_str(self) -> &'static str {
-        match self {
-            Button::A => "a",
-            Button::B => "b",
-            Button::X => "x",
-            Button::Y => "y",
-            Button::L => "l",
-            Button::R => "r",
-            Button::ZL => "zl",
-            Button::ZR => "zr",
-            Button::Plus => "plus",
-            Button::Minus => "minus",
-            Button::Home => "home",
-            Button::Capture => "capture",
-            Button::LStick => "l_stick",
-            Button::RStick => "r_stick",
-            Button::DpadUp => "dpad_up",
-            Button::DpadDown => "dpad_down",
-            Button::DpadLeft => "dpad_left",
-            Button::DpadRight => "dpad_right",
-        }
-    }
-}
+mod batch;
+mod calibrate;
+mod checkpoint;
+mod color;
+mod compile;
+mod compose;
+mod compress;
+mod console;
+mod counter;
+mod debugger;
+mod detection;
+mod dsl;
+mod duration;
+mod events;
+mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod filter;
+mod frame;
+mod framelog;
+#[cfg(all(test, feature = "hardware-tests"))]
+mod hardware;
+mod history;
+mod hold;
+mod jobs;
+mod json;
+mod keepawake;
+mod layer;
+mod matcher;
+mod menu;
+mod monitor;
+mod motion;
+mod nxtas;
+mod pack;
+mod passthrough;
+mod pipeline;
+mod playback;
+mod plugin;
+#[cfg(feature = "serial")]
+mod port;
+mod profile;
+mod program;
+mod ratelimit;
+mod record;
+mod recovery;
+mod region;
+mod remap;
+mod remote;
+mod render;
+mod report;
+mod retry;
+mod route;
+mod rpc;
+mod schedule;
+mod screenshot;
+mod script;
+mod seqfile;
+mod sequence;
+mod shutdown;
+mod snapshot;
+mod tas;
+mod template;
+mod timeline;
+mod timing;
+mod toml;
+mod transform;
+mod validate;
+mod walk;
 
-impl fmt::Display for Button {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str(self.as_str())
-    }
-}
+pub use calibrate::{
+    AxisCalibration, Calibration, CalibrationStep, CalibrationWizard, StickCalibration,
+};
+pub use checkpoint::{Checkpoint, ResumableJob};
+pub use color::{Histogram, average_color, color_distance};
+pub use compile::StateFrames;
+pub use compose::{ComposeConflict, Footprint};
+pub use counter::{Counter, CounterStats, CounterStore};
+pub use debugger::{Debugger, StopReason};
+pub use detection::Detection;
+pub use events::{AutomationEvent, EventBus};
+pub use filter::{AxisFilter, StickCurve, StickFilter};
+pub use frame::Frame;
+pub use framelog::DetectionRecorder;
+pub use history::{DEFAULT_HISTORY_CAPACITY, WriteError};
+pub use jobs::{DEFAULT_RETAINED_JOBS, JobId, JobInfo, JobManager, JobSpec, JobStatus};
+pub use keepawake::{KeepAwake, KeepAwakeConfig};
+pub use layer::{Humanize, Layer, Logging, Next};
+pub use matcher::{Match, MatchBackend, find_template};
+pub use menu::{ConfirmButton, LogicalButton};
+pub use monitor::SentCommand;
+pub use motion::{MotionDetector, wait_for_motion, wait_for_still};
+pub use pack::{Pack, PackManifest};
+pub use passthrough::{Passthrough, PassthroughConfig};
+pub use pipeline::{FramePipeline, FrameQueue};
+pub use playback::PlaybackPosition;
+pub use plugin::{Plugin, PluginCommand, PluginRegistry};
+#[cfg(feature = "serial")]
+pub use port::{PortInfo, available_ports};
+pub use profile::{ParseProfileError, Profile};
+pub use program::{Instruction, Program};
+pub use ratelimit::{Backpressure, QueueFull, RateLimit, RateLimitPolicy};
+pub use record::Recorder;
+pub use recovery::{ErrorAction, OnWriteError};
+pub use region::Region;
+pub use remap::Remap;
+pub use remote::{
+    CompressedWriter, Compression, FrameReader, JitterBuffer, TimestampedWriter, handshake,
+};
+pub use report::RunReport;
+pub use retry::{Backoff, Retry, RetryOutcome};
+pub use route::{Route, Waypoint};
+pub use rpc::{RPC_SCHEMA_VERSION, RpcServer};
+pub use schedule::{Cron, Job, Overlap, ParseCronError, Schedule, Scheduler, SchedulerEvent};
+pub use screenshot::{FrameStore, VIDEO_CLIP_HOLD};
+pub use script::{ParseScriptError, ScriptLoader};
+pub use seqfile::{ParseSequenceFileError, SequenceFile, SequenceMetadata};
+pub use sequence::{InputSequence, Step};
+pub use tas::{SyncPoint, TasSync};
+pub use template::{Template, TemplateStore};
+pub use timeline::{ButtonTrack, Span, StickTrack, Timeline};
+pub use timing::Timings;
+pub use validate::{Issue, IssueKind, Validation};
+pub use walk::Movement;
 
-/// An analog stick.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub enum Stick {
-    Left,
-    Right,
-}
-
-impl Stick {
-    fn as_str(self) -> &'static str {
-        match self {
-            Stick::Left => "l_stick",
-            Stick::Right => "r_stick",
-        }
-    }
-}
-
-impl fmt::Display for Stick {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str(self.as_str())
-    }
-}
+pub use switchcontroller_protocol::{
+    BothSticks, Button, ButtonCategory, Command, ControllerState, Direction, LeftStick, NoSticks,
+    PRESS_DURATION, ParseError, ParseErrorKind, Socd, SocdCleaner, StateBuilder, Stick, StickPair,
+    parse_line, quantize_axis,
+};
 
-/// Full controller state for the `STATE` command.
-#[derive(Debug, Clone, Default)]
-pub struct ControllerState {
-    /// Button state as a bitmask in the order defined by [`Button::ALL`].
-    buttons: [bool; 18],
-    /// Optional left stick position (horizontal, vertical), each in [-1.0, 1.0].
-    pub left_stick: Option<(f32, f32)>,
-    /// Optional right stick position (horizontal, vertical), each in [-1.0, 1.0].
-    pub right_stick: Option<(f32, f32)>,
+/// A connection to a Switch controller Pico device over serial.
+pub struct SwitchController {
+    port: Box<dyn io::Write + Send>,
+    /// Multiplier applied to host-side waits during sequence playback.
+    speed: f32,
+    /// Estimated time for a command to reach the console once written.
+    latency: Duration,
+    /// When the last command was written.
+    last_sent: Instant,
+    /// Path and baud rate to reopen the port with, if opened by path.
+    #[cfg(feature = "serial")]
+    reopen: Option<(String, u32)>,
+    on_write_error: OnWriteError,
+    /// Played once when the controller shuts down.
+    shutdown: Option<InputSequence>,
+    /// Skip `STATE` commands identical to the last one sent.
+    suppress_duplicate_states: bool,
+    /// The last command sent, if it was a `STATE` sent by [`SwitchController::state`].
+    last_state: Option<String>,
+    /// Buffer commands until flushed.
+    batching: bool,
+    /// Commands not yet written to the port.
+    out: Vec<u8>,
+    rate_limit: Option<RateLimit>,
+    /// Commands held back by [`RateLimitPolicy::Queue`].
+    queue: VecDeque<String>,
+    /// Resolves opposite D-pad directions in [`SwitchController::state`].
+    socd: Option<SocdCleaner>,
+    /// Buttons held with [`SwitchController::hold_timed`] and when to release them.
+    timed_holds: Vec<(Instant, Vec<Button>)>,
+    /// Receivers of [`SwitchController::subscribe_commands`].
+    subscribers: Vec<Sender<SentCommand>>,
+    /// The last commands sent, for [`SwitchController::recent_commands`].
+    history: VecDeque<SentCommand>,
+    history_capacity: usize,
+    /// Middleware added with [`SwitchController::add_layer`].
+    layers: Vec<Box<dyn Layer>>,
+    /// What the commands sent so far hold, for [`SwitchController::snapshot`].
+    held: ControllerState,
+    /// Waits used by helpers such as [`SwitchController::confirm`].
+    timings: Timings,
+    /// Which button [`SwitchController::confirm`] and its relatives press.
+    confirm: ConfirmButton,
+    /// How [`SwitchController::walk`] and [`SwitchController::run`] move.
+    movement: Movement,
 }
 
-impl ControllerState {
-    pub fn new() -> Self {
-        Self::default()
+impl SwitchController {
+    /// Open a serial connection to the Pico at the given path (e.g. `/dev/ttyACM0`).
+    #[cfg(feature = "serial")]
+    pub fn open(path: &str, baud_rate: u32) -> Result<Self, serialport::Error> {
+        let mut ctrl = Self::from_port(port::open_port(path, baud_rate)?);
+        ctrl.reopen = Some((path.to_string(), baud_rate));
+        Ok(ctrl)
     }
 
-    /// Set a button's pressed state.
-    pub fn set_button(&mut self, button: Button, pressed: bool) -> &mut Self {
-        let idx = Button::ALL.iter().position(|&b| b == button).unwrap();
-        self.buttons[idx] = pressed;
-        self
+    /// Create a `SwitchController` from an already-opened serial port.
+    #[cfg(feature = "serial")]
+    pub fn from_port(port: Box<dyn serialport::SerialPort>) -> Self {
+        Self::from_writer(port)
     }
 
-    /// Set the left stick position.
-    pub fn set_left_stick(&mut self, horizontal: f32, vertical: f32) -> &mut Self {
-        self.left_stick = Some((horizontal, vertical));
-        self
+    /// Create a `SwitchController` that writes commands to any byte sink: a
+    /// TCP stream to a network bridge, a file, or a buffer in tests.
+    pub fn from_writer(writer: impl io::Write + Send + 'static) -> Self {
+        Self {
+            port: Box::new(writer),
+            speed: 1.0,
+            latency: Duration::ZERO,
+            last_sent: Instant::now(),
+            #[cfg(feature = "serial")]
+            reopen: None,
+            on_write_error: OnWriteError::default(),
+            shutdown: None,
+            suppress_duplicate_states: true,
+            last_state: None,
+            batching: false,
+            out: Vec::new(),
+            rate_limit: None,
+            queue: VecDeque::new(),
+            socd: None,
+            timed_holds: Vec::new(),
+            subscribers: Vec::new(),
+            history: VecDeque::new(),
+            history_capacity: DEFAULT_HISTORY_CAPACITY,
+            layers: Vec::new(),
+            held: ControllerState::new(),
+            timings: Timings::new(),
+            confirm: ConfirmButton::default(),
+            movement: Movement::default(),
+        }
     }
 
-    /// Set the right stick position.
-    pub fn set_right_stick(&mut self, horizontal: f32, vertical: f32) -> &mut Self {
-        self.right_stick = Some((horizontal, vertical));
-        self
+    /// Send a command, releasing any timed holds that are due first.
+    fn send(&mut self, command: &Command) -> io::Result<()> {
+        self.release_timed(Some(Instant::now()))?;
+        self.send_tracked(command)
     }
 
-    fn to_command(&self) -> String {
-        let bits: String = self.buttons.iter().map(|&b| if b { '1' } else { '0' }).collect();
-        let mut cmd = format!("STATE {bits}");
-        if let Some((lh, lv)) = self.left_stick {
-            cmd.push_str(&format!(" {lh} {lv}"));
-            if let Some((rh, rv)) = self.right_stick {
-                cmd.push_str(&format!(" {rh} {rv}"));
-            }
-        } else if let Some((rh, rv)) = self.right_stick {
-            // Must provide left stick values to include right stick.
-            cmd.push_str(&format!(" 0.0 0.0 {rh} {rv}"));
+    /// Send a command now, bypassing the rate limit.
+    pub(crate) fn send_now(&mut self, cmd: &str) -> io::Result<()> {
+        self.last_sent = Instant::now();
+        self.last_state = None;
+        self.record_history(cmd, self.last_sent);
+        self.notify_sent(cmd);
+        match self.write_line(cmd) {
+            Ok(()) => Ok(()),
+            Err(e) => self.recover(cmd, e),
         }
-        cmd
     }
-}
-
-/// A connection to a Switch controller Pico device over serial.
-pub struct SwitchController {
-    port: Box<dyn serialport::SerialPort>,
-}
 
-impl SwitchController {
-    /// Open a serial connection to the Pico at the given path (e.g. `/dev/ttyACM0`).
-    pub fn open(path: &str, baud_rate: u32) -> Result<Self, serialport::Error> {
-        let port = serialport::new(path, baud_rate)
-            .timeout(Duration::from_secs(1))
-            .open()?;
-        Ok(Self { port })
+    /// Time since the last command was sent (or since the connection was opened).
+    pub fn idle_for(&self) -> Duration {
+        self.last_sent.elapsed()
     }
 
-    /// Create a `SwitchController` from an already-opened serial port.
-    pub fn from_port(port: Box<dyn serialport::SerialPort>) -> Self {
-        Self { port }
+    /// Send a command, as the other command methods do.
+    ///
+    /// The command passes through any [`Layer`]s first. `STATE` commands
+    /// then go through the D-pad cleaning and duplicate suppression of
+    /// [`SwitchController::state`]; releasing buttons, with `RELEASE` or a
+    /// `STATE` without them, cancels their [`SwitchController::hold_timed`]
+    /// release.
+    pub fn execute(&mut self, command: &Command) -> io::Result<()> {
+        self.through_layers(command.clone(), Self::execute_direct)
     }
 
-    /// Send a raw newline-terminated command string.
-    fn send(&mut self, cmd: &str) -> io::Result<()> {
-        write!(self.port, "{cmd}\n")?;
-        self.port.flush()
+    /// [`SwitchController::execute`] after the layers.
+    fn execute_direct(&mut self, command: &Command) -> io::Result<()> {
+        match command {
+            Command::State(state) => {
+                let mut cleaned = state.clone();
+                if let Some(cleaner) = &mut self.socd {
+                    cleaner.apply(&mut cleaned);
+                }
+                let cmd = cleaned.to_command();
+                let released: Vec<Button> = Button::ALL
+                    .into_iter()
+                    .filter(|&b| !state.button(b))
+                    .collect();
+                self.forget_timed(&released);
+                if self.suppress_duplicate_states
+                    && self.last_state.as_deref() == Some(cmd.as_str())
+                {
+                    return Ok(());
+                }
+                self.send(&Command::State(cleaned))?;
+                self.last_state = Some(cmd);
+                Ok(())
+            }
+            Command::Release(buttons) => {
+                self.forget_timed(buttons);
+                self.send(command)
+            }
+            command => self.send(command),
+        }
     }
 
     /// Press and immediately release one or more buttons.
     pub fn press(&mut self, buttons: &[Button]) -> io::Result<()> {
-        let names: Vec<&str> = buttons.iter().map(|b| b.as_str()).collect();
-        self.send(&format!("PRESS {}", names.join(" ")))
+        self.execute(&Command::Press(buttons.to_vec()))
+    }
+
+    /// Press buttons at the given offsets from now, releasing them together
+    /// once the last has been down for a press. See
+    /// [`InputSequence::press_staggered`].
+    pub fn press_staggered(&mut self, buttons: &[(Button, Duration)]) -> io::Result<()> {
+        self.play(InputSequence::new().press_staggered(buttons))
     }
 
     /// Hold one or more buttons down until explicitly released.
     pub fn hold(&mut self, buttons: &[Button]) -> io::Result<()> {
-        let names: Vec<&str> = buttons.iter().map(|b| b.as_str()).collect();
-        self.send(&format!("HOLD {}", names.join(" ")))
+        self.execute(&Command::Hold(buttons.to_vec()))
     }
 
     /// Release one or more currently held buttons.
     pub fn release(&mut self, buttons: &[Button]) -> io::Result<()> {
-        let names: Vec<&str> = buttons.iter().map(|b| b.as_str()).collect();
-        self.send(&format!("RELEASE {}", names.join(" ")))
+        self.execute(&Command::Release(buttons.to_vec()))
     }
 
     /// Set an analog stick position. Values range from -1.0 to 1.0.
     pub fn stick(&mut self, stick: Stick, horizontal: f32, vertical: f32) -> io::Result<()> {
-        self.send(&format!("STICK {stick} {horizontal} {vertical}"))
+        self.execute(&Command::Stick(stick, horizontal, vertical))
     }
 
     /// Set the entire controller state in a single command.
+    ///
+    /// Opposite D-pad directions are resolved first if enabled with
+    /// [`SwitchController::set_socd`]. A state identical to the one sent just
+    /// before is skipped, unless disabled with
+    /// [`SwitchController::set_suppress_duplicate_states`].
     pub fn state(&mut self, state: &ControllerState) -> io::Result<()> {
-        self.send(&state.to_command())
+        self.execute(&Command::State(state.clone()))
+    }
+
+    /// Resolve opposite D-pad directions in every state passed to
+    /// [`SwitchController::state`] or played in a sequence, or send states as
+    /// given with `None` (the default). `PRESS`/`HOLD` commands are not
+    /// affected.
+    pub fn set_socd(&mut self, socd: Option<Socd>) -> &mut Self {
+        self.socd = socd.map(SocdCleaner::new);
+        self
+    }
+
+    /// Whether [`SwitchController::state`] and played sequences skip a state
+    /// identical to the previously sent one. On by default, to save bandwidth when a source
+    /// publishes states unconditionally.
+    pub fn set_suppress_duplicate_states(&mut self, suppress: bool) -> &mut Self {
+        self.suppress_duplicate_states = suppress;
+        self
     }
 
     /// Pause command processing on the device for the given duration.
     pub fn sleep(&mut self, seconds: f32) -> io::Result<()> {
-        self.send(&format!("SLEEP {seconds}"))
+        self.execute(&Command::Sleep(seconds))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::{Arc, Mutex};
+
     use super::*;
 
-    #[test]
-    fn button_display() {
-        assert_eq!(Button::A.to_string(), "a");
-        assert_eq!(Button::ZL.to_string(), "zl");
-        assert_eq!(Button::DpadUp.to_string(), "dpad_up");
-    }
+    /// A writer whose output stays readable after the controller is dropped.
+    #[derive(Clone, Default)]
+    pub(crate) struct Shared(Arc<Mutex<Vec<u8>>>);
 
-    #[test]
-    fn state_command_no_sticks() {
-        let state = ControllerState::new();
-        assert_eq!(state.to_command(), "STATE 000000000000000000");
+    impl Shared {
+        pub(crate) fn text(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
     }
 
-    #[test]
-    fn state_command_with_buttons() {
-        let mut state = ControllerState::new();
-        state.set_button(Button::A, true).set_button(Button::X, true).set_button(Button::Y, true);
-        assert_eq!(state.to_command(), "STATE 101100000000000000");
-    }
+    impl io::Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
 
-    #[test]
-    fn state_command_with_left_stick() {
-        let mut state = ControllerState::new();
-        state.set_button(Button::A, true);
-        state.set_left_stick(0.5, -1.0);
-        assert_eq!(state.to_command(), "STATE 100000000000000000 0.5 -1");
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
     }
 
     #[test]
-    fn state_command_with_both_sticks() {
-        let mut state = ControllerState::new();
-        state.set_button(Button::A, true);
-        state.set_left_stick(0.0, 0.0);
-        state.set_right_stick(-1.0, 0.0);
-        assert_eq!(state.to_command(), "STATE 100000000000000000 0 0 -1 0");
+    fn commands_go_to_writer() {
+        let out = Shared::default();
+        let mut ctrl = SwitchController::from_writer(out.clone());
+        ctrl.press(&[Button::A, Button::ZR]).unwrap();
+        ctrl.state(&ControllerState::neutral()).unwrap();
+        ctrl.state(&ControllerState::neutral()).unwrap();
+        ctrl.stick(Stick::Left, 1.0, 0.0).unwrap();
+        assert!(ctrl.reconnect().is_err());
+        drop(ctrl);
+        assert_eq!(
+            out.text(),
+            "PRESS a zr\nSTATE 000000000000000000 0 0 0 0\nSTICK l_stick 1 0\n"
+        );
     }
 
     #[test]
-    fn state_command_with_right_stick_only() {
-        let mut state = ControllerState::new();
-        state.set_right_stick(-1.0, 0.0);
-        assert_eq!(state.to_command(), "STATE 000000000000000000 0.0 0.0 -1 0");
+    fn executes_command_values() {
+        let out = Shared::default();
+        let mut ctrl = SwitchController::from_writer(out.clone());
+        let queued = [
+            Command::Hold(vec![Button::B]),
+            Command::Sleep(0.5),
+            Command::Release(vec![Button::B]),
+            Command::Raw("# comment".into()),
+        ];
+        for command in &queued {
+            ctrl.execute(command).unwrap();
+        }
+        assert_eq!(out.text(), "HOLD b\nSLEEP 0.5\nRELEASE b\n# comment\n");
     }
 }
@@ -0,0 +1,204 @@
+use crate::frame::luma;
+use crate::{Frame, Region};
+
+/// How [`find_template`] compares pixels.
+///
+/// Every backend finds the same match; they differ only in speed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchBackend {
+    /// Plain Rust, available everywhere.
+    Portable,
+    /// AVX2 vector instructions, on x86-64 CPUs that have them, comparing 32
+    /// pixels per instruction.
+    Avx2,
+}
+
+impl MatchBackend {
+    /// The fastest backend this CPU supports.
+    pub fn best() -> Self {
+        if MatchBackend::Avx2.is_available() {
+            MatchBackend::Avx2
+        } else {
+            MatchBackend::Portable
+        }
+    }
+
+    /// Whether this CPU supports the backend. An unavailable backend falls
+    /// back to [`MatchBackend::Portable`].
+    pub fn is_available(self) -> bool {
+        match self {
+            MatchBackend::Portable => true,
+            #[cfg(target_arch = "x86_64")]
+            MatchBackend::Avx2 => std::arch::is_x86_feature_detected!("avx2"),
+            #[cfg(not(target_arch = "x86_64"))]
+            MatchBackend::Avx2 => false,
+        }
+    }
+
+    /// The sum of absolute differences of two equally long rows.
+    fn row_difference(self) -> fn(&[u8], &[u8]) -> u64 {
+        #[cfg(target_arch = "x86_64")]
+        if self == MatchBackend::Avx2 && self.is_available() {
+            // SAFETY: AVX2 support was just checked.
+            return |a, b| unsafe { avx2::row_difference(a, b) };
+        }
+        portable_row_difference
+    }
+}
+
+/// Where a template best matched.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Match {
+    /// The template's top-left corner in the frame, in pixels.
+    pub x: u32,
+    pub y: u32,
+    /// How well it matched, from 0 (opposite brightness everywhere) to 1
+    /// (identical).
+    pub score: f64,
+}
+
+/// Find the position in `region` of `frame` where `template` matches best,
+/// comparing brightness. `None` if the template does not fit in the region.
+///
+/// This is an exhaustive search; keep regions tight around where the
+/// template can appear.
+pub fn find_template(
+    frame: &Frame,
+    template: &Frame,
+    region: &Region,
+    backend: MatchBackend,
+) -> Option<Match> {
+    let (left, top, width, height) = region.to_pixels((frame.width(), frame.height()));
+    let (tw, th) = (template.width(), template.height());
+    if tw > width || th > height {
+        return None;
+    }
+    let plane = brightness(frame);
+    let pattern = brightness(template);
+    let row_difference = backend.row_difference();
+    let (fw, tw_us) = (frame.width() as usize, tw as usize);
+
+    let mut best = (u64::MAX, 0, 0);
+    for y in top..=top + height - th {
+        for x in left..=left + width - tw {
+            let mut total = 0;
+            for row in 0..th as usize {
+                let start = (y as usize + row) * fw + x as usize;
+                total += row_difference(
+                    &plane[start..start + tw_us],
+                    &pattern[row * tw_us..(row + 1) * tw_us],
+                );
+                // Already worse than the best so far
+                if total >= best.0 {
+                    break;
+                }
+            }
+            if total < best.0 {
+                best = (total, x, y);
+            }
+        }
+    }
+    let pixels = u64::from(tw) * u64::from(th);
+    Some(Match {
+        x: best.1,
+        y: best.2,
+        score: 1.0 - best.0 as f64 / (pixels * 255) as f64,
+    })
+}
+
+fn brightness(frame: &Frame) -> Vec<u8> {
+    frame
+        .pixels()
+        .chunks_exact(3)
+        .map(|p| luma([p[0], p[1], p[2]]))
+        .collect()
+}
+
+fn portable_row_difference(a: &[u8], b: &[u8]) -> u64 {
+    a.iter()
+        .zip(b)
+        .map(|(&x, &y)| u64::from(x.abs_diff(y)))
+        .sum()
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use std::arch::x86_64::*;
+
+    /// # Safety
+    ///
+    /// The CPU must support AVX2.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn row_difference(a: &[u8], b: &[u8]) -> u64 {
+        let chunks = a.len().min(b.len()) / 32;
+        let mut sums = _mm256_setzero_si256();
+        for i in 0..chunks {
+            // SAFETY: both slices hold at least 32 bytes from `i * 32`, and
+            // unaligned loads are allowed.
+            let (x, y) = unsafe {
+                (
+                    _mm256_loadu_si256(a.as_ptr().add(i * 32).cast()),
+                    _mm256_loadu_si256(b.as_ptr().add(i * 32).cast()),
+                )
+            };
+            // Four 64-bit lanes, each summing the differences of 8 bytes
+            sums = _mm256_add_epi64(sums, _mm256_sad_epu8(x, y));
+        }
+        let mut lanes = [0u64; 4];
+        // SAFETY: `lanes` is 32 bytes.
+        unsafe { _mm256_storeu_si256(lanes.as_mut_ptr().cast(), sums) };
+        lanes.iter().sum::<u64>()
+            + super::portable_row_difference(&a[chunks * 32..], &b[chunks * 32..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A frame with a noisy gradient, so every position looks different.
+    fn scene(width: u32, height: u32) -> Frame {
+        let mut frame = Frame::filled(width, height, [0; 3]);
+        for y in 0..height {
+            for x in 0..width {
+                let v = ((x * 7 + y * 13) ^ (x * y)) as u8;
+                frame.set_pixel(x, y, [v, v / 2, 255 - v]);
+            }
+        }
+        frame
+    }
+
+    fn crop(frame: &Frame, x0: u32, y0: u32, width: u32, height: u32) -> Frame {
+        let mut out = Frame::filled(width, height, [0; 3]);
+        for y in 0..height {
+            for x in 0..width {
+                out.set_pixel(x, y, frame.pixel(x0 + x, y0 + y));
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn backends_agree() {
+        let frame = scene(120, 60);
+        let template = crop(&frame, 70, 25, 40, 9);
+        for backend in [MatchBackend::Portable, MatchBackend::Avx2] {
+            let found = find_template(&frame, &template, &Region::FULL, backend).unwrap();
+            assert_eq!((found.x, found.y, found.score), (70, 25, 1.0));
+        }
+        let left = Region::new(0.0, 0.0, 50.0, 100.0).unwrap();
+        let elsewhere = find_template(&frame, &template, &left, MatchBackend::best()).unwrap();
+        assert!(elsewhere.x <= 20 && elsewhere.score < 1.0);
+        assert_eq!(
+            find_template(&template, &frame, &Region::FULL, MatchBackend::best()),
+            None
+        );
+
+        let a: Vec<u8> = (0..100).collect();
+        let b: Vec<u8> = (0..100).rev().collect();
+        assert_eq!(
+            MatchBackend::Avx2.row_difference()(&a, &b),
+            portable_row_difference(&a, &b)
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use std::io;
+use std::time::Duration;
+
+use crate::{Button, InputSequence, SwitchController, Timings};
+
+/// A button named by what it does in menus rather than where it is, resolved
+/// to A or B by the [`ConfirmButton`] convention in use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LogicalButton {
+    Confirm,
+    Back,
+}
+
+/// Which face button confirms in menus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ConfirmButton {
+    /// A confirms and B backs out, as in most titles.
+    #[default]
+    A,
+    /// B confirms and A backs out, as in some Japanese releases.
+    B,
+}
+
+impl ConfirmButton {
+    /// The physical button for `logical` under this convention.
+    pub fn resolve(self, logical: LogicalButton) -> Button {
+        match (self, logical) {
+            (ConfirmButton::A, LogicalButton::Confirm)
+            | (ConfirmButton::B, LogicalButton::Back) => Button::A,
+            (ConfirmButton::A, LogicalButton::Back)
+            | (ConfirmButton::B, LogicalButton::Confirm) => Button::B,
+        }
+    }
+
+    /// Look up a convention by its confirm button's name, `"a"` or `"b"`.
+    pub fn from_name(name: &str) -> Option<ConfirmButton> {
+        match name {
+            "a" => Some(ConfirmButton::A),
+            "b" => Some(ConfirmButton::B),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ConfirmButton::A => "a",
+            ConfirmButton::B => "b",
+        }
+    }
+}
+
+impl SwitchController {
+    /// Use `timings` for the waits of helpers like
+    /// [`SwitchController::confirm`], typically a profile's
+    /// [`Profile::timings`](crate::Profile::timings). The built-in defaults
+    /// are used until this is called.
+    pub fn set_timings(&mut self, timings: Timings) -> &mut Self {
+        self.timings = timings;
+        self
+    }
+
+    /// The timing constants used by helpers.
+    pub fn timings(&self) -> &Timings {
+        &self.timings
+    }
+
+    /// Use `confirm` to resolve [`LogicalButton`]s in helpers like
+    /// [`SwitchController::confirm`], typically a profile's
+    /// [`Profile::confirm`](crate::Profile::confirm). A confirms until this
+    /// is called.
+    pub fn set_confirm_button(&mut self, confirm: ConfirmButton) -> &mut Self {
+        self.confirm = confirm;
+        self
+    }
+
+    pub fn confirm_button(&self) -> ConfirmButton {
+        self.confirm
+    }
+
+    /// The physical button for `logical` under the current convention.
+    pub fn resolve(&self, logical: LogicalButton) -> Button {
+        self.confirm.resolve(logical)
+    }
+
+    /// Press the confirm button and wait for [`Timings::MENU_TRANSITION`].
+    pub fn confirm(&mut self) -> io::Result<()> {
+        self.menu_press(self.resolve(LogicalButton::Confirm))
+    }
+
+    /// Press the back button and wait for [`Timings::MENU_TRANSITION`].
+    pub fn cancel(&mut self) -> io::Result<()> {
+        self.menu_press(self.resolve(LogicalButton::Back))
+    }
+
+    /// Press the confirm button `times` times, waiting `interval` after each
+    /// press for the next text box to print. [`Timings::TEXT_SPEED`] is the
+    /// usual interval.
+    pub fn advance_dialog(&mut self, times: usize, interval: Duration) -> io::Result<()> {
+        let confirm = self.resolve(LogicalButton::Confirm);
+        let mut sequence = InputSequence::new();
+        for _ in 0..times {
+            sequence.press(&[confirm]).wait(interval);
+        }
+        self.play(&sequence)
+    }
+
+    fn menu_press(&mut self, button: Button) -> io::Result<()> {
+        let wait = self.timings[Timings::MENU_TRANSITION];
+        self.play(InputSequence::new().press(&[button]).wait(wait))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::tests::Shared;
+
+    #[test]
+    fn waits_come_from_the_timings() {
+        let out = Shared::default();
+        let mut ctrl = SwitchController::from_writer(out.clone());
+        let mut timings = Timings::new();
+        timings.set(Timings::MENU_TRANSITION, Duration::from_millis(30));
+        ctrl.set_timings(timings);
+
+        let start = Instant::now();
+        ctrl.confirm().unwrap();
+        ctrl.cancel().unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(60));
+        ctrl.advance_dialog(3, Duration::ZERO).unwrap();
+        assert_eq!(out.text(), "PRESS a\nPRESS b\nPRESS a\nPRESS a\nPRESS a\n");
+
+        ctrl.set_confirm_button(ConfirmButton::B);
+        ctrl.confirm().unwrap();
+        ctrl.advance_dialog(1, Duration::ZERO).unwrap();
+        assert!(out.text().ends_with("PRESS a\nPRESS b\nPRESS b\n"));
+        assert_eq!(ctrl.resolve(LogicalButton::Back), Button::A);
+    }
+}
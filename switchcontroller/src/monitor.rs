@@ -0,0 +1,38 @@
+use std::sync::mpsc::{self, Receiver};
+use std::time::Instant;
+
+use crate::SwitchController;
+
+/// A command the controller sent, as seen by [`SwitchController::subscribe_commands`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SentCommand {
+    /// The command line, without the trailing newline.
+    pub command: String,
+    /// When the command was handed to the port (or to the batch buffer).
+    pub at: Instant,
+}
+
+impl SwitchController {
+    /// Receive a copy of every command sent from now on, for recorders,
+    /// overlays or metrics running on other threads.
+    ///
+    /// Commands skipped as duplicate states or still waiting in the rate-limit
+    /// queue are not reported. Dropping the receiver unsubscribes it.
+    pub fn subscribe_commands(&mut self) -> Receiver<SentCommand> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.push(tx);
+        rx
+    }
+
+    /// Report `cmd` to subscribers, forgetting any that have gone away.
+    pub(crate) fn notify_sent(&mut self, cmd: &str) {
+        if self.subscribers.is_empty() {
+            return;
+        }
+        let sent = SentCommand {
+            command: cmd.to_string(),
+            at: self.last_sent,
+        };
+        self.subscribers.retain(|tx| tx.send(sent.clone()).is_ok());
+    }
+}
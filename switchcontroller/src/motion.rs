@@ -0,0 +1,162 @@
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::{Frame, Region};
+
+/// Watches a screen region for movement by comparing consecutive frames.
+///
+/// For triggers like "wait until the loading spinner stops": sleeping a
+/// fixed time is either too short on a slow load or wastes time on a fast
+/// one. A frame counts as moving when its [`Frame::difference`] from the
+/// previous one exceeds the threshold.
+#[derive(Debug, Clone)]
+pub struct MotionDetector {
+    region: Region,
+    threshold: f64,
+    previous: Option<Frame>,
+    still_since: Option<Instant>,
+}
+
+impl MotionDetector {
+    /// A threshold that ignores capture noise but catches a small spinner.
+    pub const DEFAULT_THRESHOLD: f64 = 0.01;
+
+    pub fn new(region: Region, threshold: f64) -> Self {
+        Self {
+            region,
+            threshold,
+            previous: None,
+            still_since: None,
+        }
+    }
+
+    /// Compare `frame`, captured at `now`, with the previous one and return
+    /// the difference, or `None` for the first frame (or one of a different
+    /// size, which starts over).
+    pub fn feed(&mut self, frame: Frame, now: Instant) -> Option<f64> {
+        let difference = self
+            .previous
+            .as_ref()
+            .and_then(|previous| previous.difference(&frame, &self.region));
+        match difference {
+            Some(d) if d <= self.threshold => {
+                self.still_since.get_or_insert(now);
+            }
+            _ => self.still_since = None,
+        }
+        self.previous = Some(frame);
+        difference
+    }
+
+    /// How long the region has been still as of `now`, counted from the
+    /// first frame that matched its predecessor.
+    pub fn still_for(&self, now: Instant) -> Duration {
+        self.still_since
+            .map_or(Duration::ZERO, |since| now.saturating_duration_since(since))
+    }
+
+    /// Read frames from `next_frame` until the region moves. Returns `false`
+    /// if nothing moved within `timeout`.
+    pub fn wait_for_motion(
+        &mut self,
+        mut next_frame: impl FnMut() -> io::Result<Frame>,
+        timeout: Duration,
+    ) -> io::Result<bool> {
+        let start = Instant::now();
+        while start.elapsed() < timeout {
+            if self
+                .feed(next_frame()?, Instant::now())
+                .is_some_and(|d| d > self.threshold)
+            {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Read frames from `next_frame` until the region has been still for
+    /// `duration`. Returns `false` if it kept moving past `timeout`.
+    pub fn wait_for_still(
+        &mut self,
+        mut next_frame: impl FnMut() -> io::Result<Frame>,
+        duration: Duration,
+        timeout: Duration,
+    ) -> io::Result<bool> {
+        let start = Instant::now();
+        while start.elapsed() < timeout {
+            let now = Instant::now();
+            self.feed(next_frame()?, now);
+            if self.still_since.is_some() && self.still_for(now) >= duration {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Wait until `region` moves by more than `threshold`; see
+/// [`MotionDetector`].
+pub fn wait_for_motion(
+    next_frame: impl FnMut() -> io::Result<Frame>,
+    region: Region,
+    threshold: f64,
+    timeout: Duration,
+) -> io::Result<bool> {
+    MotionDetector::new(region, threshold).wait_for_motion(next_frame, timeout)
+}
+
+/// Wait until `region` has stayed still for `duration`; see
+/// [`MotionDetector`].
+pub fn wait_for_still(
+    next_frame: impl FnMut() -> io::Result<Frame>,
+    region: Region,
+    duration: Duration,
+    timeout: Duration,
+) -> io::Result<bool> {
+    MotionDetector::new(region, MotionDetector::DEFAULT_THRESHOLD)
+        .wait_for_still(next_frame, duration, timeout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 10x10 frame with a white spinner pixel at `x` in the top row.
+    fn spinner(x: u32) -> Frame {
+        let mut frame = Frame::filled(10, 10, [0, 0, 0]);
+        frame.set_pixel(x, 0, [255, 255, 255]);
+        frame
+    }
+
+    #[test]
+    fn tracks_stillness() {
+        let top = Region::new(0.0, 0.0, 100.0, 10.0).unwrap();
+        let mut detector = MotionDetector::new(top, MotionDetector::DEFAULT_THRESHOLD);
+        let t = Instant::now();
+        let at = |ms| t + Duration::from_millis(ms);
+        assert_eq!(detector.feed(spinner(0), at(0)), None);
+        assert_eq!(detector.feed(spinner(1), at(100)), Some(0.2));
+        assert_eq!(detector.still_for(at(100)), Duration::ZERO);
+        assert_eq!(detector.feed(spinner(1), at(200)), Some(0.0));
+        assert_eq!(detector.feed(spinner(1), at(300)), Some(0.0));
+        assert_eq!(detector.still_for(at(300)), Duration::from_millis(100));
+
+        // Motion outside the region is ignored
+        let bottom = Region::new(0.0, 50.0, 100.0, 50.0).unwrap();
+        let mut frames = (0..).map(|i| spinner(i % 10));
+        let next = || Ok(frames.next().unwrap());
+        assert!(!wait_for_motion(next, bottom, 0.0, Duration::from_millis(20)).unwrap());
+        let mut frames = (0..).map(|i| spinner(i % 10));
+        let next = || Ok(frames.next().unwrap());
+        assert!(wait_for_motion(next, top, 0.0, Duration::from_secs(1)).unwrap());
+        assert!(
+            wait_for_still(
+                || Ok(spinner(3)),
+                top,
+                Duration::ZERO,
+                Duration::from_secs(1)
+            )
+            .unwrap()
+        );
+    }
+}
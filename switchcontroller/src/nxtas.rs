@@ -0,0 +1,167 @@
+//! Import of nx-TAS scripts, the frame-by-frame format used by TAS-nx and
+//! compatible tools:
+//!
+//! ```text
+//! 1 KEY_A 0;0 0;0
+//! 30 KEY_ZR;KEY_DLEFT 0;32767 -32767;0
+//! ```
+//!
+//! Each line is a frame number, `;`-separated buttons (or `NONE`), and the
+//! left and right stick positions. Frames without a line have no input.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::duration::{FRAME, frames};
+use crate::{Button, ControllerState, InputSequence, ParseScriptError};
+
+fn button(name: &str) -> Option<Button> {
+    Some(match name {
+        "KEY_A" => Button::A,
+        "KEY_B" => Button::B,
+        "KEY_X" => Button::X,
+        "KEY_Y" => Button::Y,
+        "KEY_L" => Button::L,
+        "KEY_R" => Button::R,
+        "KEY_ZL" => Button::ZL,
+        "KEY_ZR" => Button::ZR,
+        "KEY_PLUS" => Button::Plus,
+        "KEY_MINUS" => Button::Minus,
+        "KEY_HOME" => Button::Home,
+        "KEY_CAPTURE" => Button::Capture,
+        "KEY_LSTICK" => Button::LStick,
+        "KEY_RSTICK" => Button::RStick,
+        "KEY_DUP" => Button::DpadUp,
+        "KEY_DDOWN" => Button::DpadDown,
+        "KEY_DLEFT" => Button::DpadLeft,
+        "KEY_DRIGHT" => Button::DpadRight,
+        _ => return None,
+    })
+}
+
+fn stick(text: &str) -> Result<(f32, f32), String> {
+    let axis = |s: &str| {
+        s.parse::<i32>()
+            .map(|v| (v as f32 / 32767.0).clamp(-1.0, 1.0))
+            .map_err(|_| format!("invalid stick position `{text}`"))
+    };
+    let (h, v) = text
+        .split_once(';')
+        .ok_or_else(|| format!("invalid stick position `{text}`"))?;
+    // nx-TAS has up as positive; the firmware has it negative. Subtracting
+    // keeps a centered stick at 0 rather than -0.
+    Ok((axis(h)?, 0.0 - axis(v)?))
+}
+
+fn parse_frame(line: &str) -> Result<(u64, ControllerState), String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let [frame, buttons, left, right] = words[..] else {
+        return Err("expected `<frame> <buttons> <lx;ly> <rx;ry>`".into());
+    };
+    let frame = frame
+        .parse()
+        .map_err(|_| format!("invalid frame number `{frame}`"))?;
+    let mut state = ControllerState::new();
+    if buttons != "NONE" {
+        for name in buttons.split(';') {
+            let b = button(name).ok_or_else(|| format!("unknown button `{name}`"))?;
+            state.set_button(b, true);
+        }
+    }
+    let (lh, lv) = stick(left)?;
+    let (rh, rv) = stick(right)?;
+    state.set_left_stick(lh, lv).set_right_stick(rh, rv);
+    Ok((frame, state))
+}
+
+fn is_neutral(state: &ControllerState) -> bool {
+    state.to_command() == ControllerState::neutral().to_command()
+}
+
+impl InputSequence {
+    /// Convert an nx-TAS script into `STATE` steps and waits, at 60 frames
+    /// per second. Frame 1 plays immediately.
+    pub fn from_nx_tas(s: &str) -> Result<Self, ParseScriptError> {
+        let mut seq = InputSequence::new();
+        // The frame the device is at, and whether its state is neutral.
+        let mut now = 1;
+        let mut neutral = true;
+        let mut last = None;
+        for (i, line) in s.lines().enumerate() {
+            let err = |message: String| ParseScriptError {
+                line: i + 1,
+                message,
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (frame, state) = parse_frame(line).map_err(err)?;
+            if last.is_some_and(|last| frame <= last) {
+                return Err(err(format!("frame {frame} is out of order")));
+            }
+            last = Some(frame);
+            // Scripts numbered from 0 start at frame 0.
+            now = now.min(frame);
+            if frame > now && !neutral {
+                // Only the listed frame has input.
+                seq.wait(FRAME).state(&ControllerState::neutral());
+                now += 1;
+                neutral = true;
+            }
+            if neutral && is_neutral(&state) {
+                continue;
+            }
+            if frame > now {
+                let gap = frames(frame - now)
+                    .ok_or_else(|| err(format!("frame {frame} is too far from frame {now}")))?;
+                seq.wait(gap);
+            }
+            seq.state(&state);
+            neutral = is_neutral(&state);
+            now = frame;
+        }
+        if !neutral {
+            seq.wait(FRAME).state(&ControllerState::neutral());
+        }
+        Ok(seq)
+    }
+
+    /// Read an nx-TAS script from a file.
+    pub fn load_nx_tas(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_nx_tas(&fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_frames() {
+        let seq = InputSequence::from_nx_tas(
+            "1 KEY_A;KEY_DLEFT 0;32767 0;0\n2 NONE 0;0 0;0\n\n31 KEY_ZR 0;0 -32767;0\n",
+        )
+        .unwrap();
+        assert_eq!(
+            seq.to_script(),
+            "STATE 100000000000000010 0 -1 0 0\nWAIT 1f\nSTATE 000000000000000000 0 0 0 0\n\
+             WAIT 29f\nSTATE 000000010000000000 0 0 -1 0\nWAIT 1f\n\
+             STATE 000000000000000000 0 0 0 0\n"
+        );
+        assert_eq!(seq.duration(), frames(31).unwrap());
+
+        let err = InputSequence::from_nx_tas("5 NONE 0;0 0;0\n3 KEY_Q 0;0 0;0").unwrap_err();
+        assert_eq!(
+            (err.line, err.message.as_str()),
+            (2, "unknown button `KEY_Q`")
+        );
+        assert!(InputSequence::from_nx_tas("5 NONE 0;0").is_err());
+        let from_zero = InputSequence::from_nx_tas("0 KEY_A 0;0 0;0").unwrap();
+        assert_eq!(from_zero.duration(), FRAME);
+        assert!(InputSequence::from_nx_tas("5 NONE 0;0 0;0\n3 NONE 0;0 0;0").is_err());
+        let far = InputSequence::from_nx_tas("0 KEY_A 0;0 0;0\n20000000000 KEY_B 0;0 0;0");
+        assert_eq!(far.unwrap_err().line, 2);
+    }
+}
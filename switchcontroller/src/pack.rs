@@ -0,0 +1,269 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::toml::Document;
+use crate::{Profile, Program, ScriptLoader, TemplateStore};
+
+/// The manifest file at the root of every pack.
+const MANIFEST: &str = "pack.toml";
+
+/// The `pack.toml` of a [`Pack`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackManifest {
+    pub name: String,
+    pub version: Option<String>,
+    pub author: Option<String>,
+    pub description: Option<String>,
+}
+
+impl PackManifest {
+    fn parse(text: &str) -> io::Result<Self> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let doc = Document::parse(text).map_err(|e| invalid(e.to_string()))?;
+        let field = |key: &str| {
+            doc.table("")
+                .and_then(|t| t.iter().find(|(k, _)| k == key))
+                .and_then(|(_, v)| v.as_str())
+                .map(str::to_string)
+        };
+        let name =
+            field("name").ok_or_else(|| invalid("pack manifest is missing `name`".into()))?;
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            return Err(invalid(format!("invalid pack name `{name}`")));
+        }
+        Ok(Self {
+            name,
+            version: field("version"),
+            author: field("author"),
+            description: field("description"),
+        })
+    }
+}
+
+/// A shareable bundle of automations: a directory with a `pack.toml`
+/// manifest, macro scripts in `sequences/`, profiles in `profiles/` and
+/// detection templates (any files) in `templates/`.
+///
+/// ```text
+/// pokemon-pack/
+///   pack.toml          name = "pokemon-pack", plus optional version,
+///                      author and description
+///   sequences/hatch.macro
+///   profiles/violet.toml
+///   templates/shiny.png
+/// ```
+///
+/// Scripts in a pack can include each other by their path within
+/// `sequences/`.
+#[derive(Debug, Clone)]
+pub struct Pack {
+    root: PathBuf,
+    manifest: PackManifest,
+}
+
+impl Pack {
+    /// Open the pack in directory `dir`.
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let root = dir.as_ref().to_path_buf();
+        if root.extension().is_some_and(|e| e == "zip") {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "zipped packs are not supported; unpack the archive first",
+            ));
+        }
+        let manifest = PackManifest::parse(&fs::read_to_string(root.join(MANIFEST))?)?;
+        Ok(Self { root, manifest })
+    }
+
+    /// The packs directory: `$SWITCHCONTROLLER_PACKS` if set, otherwise
+    /// `packs` in the working directory.
+    pub fn default_dir() -> PathBuf {
+        std::env::var_os("SWITCHCONTROLLER_PACKS")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("packs"))
+    }
+
+    /// Copy the pack in `source` into `<packs_dir>/<name>`, replacing any
+    /// installed version, and open the copy. Installing a pack from where
+    /// it is already installed leaves it as it is.
+    pub fn install(source: impl AsRef<Path>, packs_dir: impl AsRef<Path>) -> io::Result<Pack> {
+        let pack = Pack::open(source)?;
+        let target = packs_dir.as_ref().join(&pack.manifest.name);
+        if target.exists() {
+            if fs::canonicalize(&target)? == fs::canonicalize(&pack.root)? {
+                return Pack::open(target);
+            }
+            fs::remove_dir_all(&target)?;
+        }
+        copy_dir(&pack.root, &target)?;
+        Pack::open(target)
+    }
+
+    /// The packs installed in `packs_dir`, sorted by name. Directories
+    /// without a manifest are skipped.
+    pub fn list(packs_dir: impl AsRef<Path>) -> io::Result<Vec<Pack>> {
+        let mut packs = Vec::new();
+        for entry in fs::read_dir(packs_dir)? {
+            let path = entry?.path();
+            if path.join(MANIFEST).is_file() {
+                packs.push(Pack::open(path)?);
+            }
+        }
+        packs.sort_by(|a, b| a.manifest.name.cmp(&b.manifest.name));
+        Ok(packs)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn manifest(&self) -> &PackManifest {
+        &self.manifest
+    }
+
+    /// Names of the macro scripts in `sequences/`, sorted.
+    pub fn sequences(&self) -> io::Result<Vec<String>> {
+        stems(&self.root.join("sequences"), "macro")
+    }
+
+    /// Load the script `sequences/<name>.macro`.
+    pub fn load_sequence(&self, name: &str) -> io::Result<Program> {
+        let dir = self.root.join("sequences");
+        ScriptLoader::from_env()
+            .search_dir(&dir)
+            .load_program(dir.join(format!("{name}.macro")))
+    }
+
+    /// Names of the profiles in `profiles/`, sorted.
+    pub fn profiles(&self) -> io::Result<Vec<String>> {
+        stems(&self.root.join("profiles"), "toml")
+    }
+
+    pub fn load_profile(&self, name: &str) -> io::Result<Profile> {
+        Profile::load(self.root.join("profiles"), name)
+    }
+
+    /// The pack's `templates/` as a store, for packs laid out by game and
+    /// resolution.
+    pub fn template_store(&self) -> TemplateStore {
+        TemplateStore::new(self.root.join("templates"))
+    }
+
+    /// Paths of the files directly in `templates/`, sorted.
+    pub fn templates(&self) -> io::Result<Vec<PathBuf>> {
+        let dir = self.root.join("templates");
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_file() {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        Ok(paths)
+    }
+}
+
+/// Stems of the files in `dir` with `extension`, sorted; empty if `dir`
+/// does not exist.
+fn stems(dir: &Path, extension: &str) -> io::Result<Vec<String>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut names = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|e| e == extension)
+            && let Some(stem) = path.file_stem().and_then(|s| s.to_str())
+        {
+            names.push(stem.to_string());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn install_and_load() {
+        let dir =
+            std::env::temp_dir().join(format!("switchcontroller-pack-{}", std::process::id()));
+        let source = dir.join("download");
+        fs::create_dir_all(source.join("sequences/common")).unwrap();
+        fs::create_dir_all(source.join("profiles")).unwrap();
+        fs::create_dir_all(source.join("templates")).unwrap();
+        fs::write(
+            source.join("pack.toml"),
+            "name = \"pokemon\"\nversion = \"1.0\"\n",
+        )
+        .unwrap();
+        fs::write(
+            source.join("sequences/common/menu.macro"),
+            "macro home() {\npress home\n}\n",
+        )
+        .unwrap();
+        fs::write(
+            source.join("sequences/hatch.macro"),
+            "include \"common/menu.macro\"\nhome()\n",
+        )
+        .unwrap();
+        fs::write(
+            source.join("profiles/violet.toml"),
+            "[remap.buttons]\na = \"b\"\n",
+        )
+        .unwrap();
+        fs::write(source.join("templates/shiny.png"), [0u8; 4]).unwrap();
+
+        let packs = dir.join("packs");
+        let pack = Pack::install(&source, &packs).unwrap();
+        assert_eq!(pack.root(), packs.join("pokemon"));
+        assert_eq!(pack.manifest().version.as_deref(), Some("1.0"));
+        assert_eq!(pack.sequences().unwrap(), ["hatch"]);
+        assert_eq!(pack.profiles().unwrap(), ["violet"]);
+        assert_eq!(
+            pack.templates().unwrap(),
+            [packs.join("pokemon/templates/shiny.png")]
+        );
+        let hatch = pack.load_sequence("hatch").unwrap().to_sequence().unwrap();
+        assert_eq!(hatch.to_script(), "PRESS home\n");
+        assert_eq!(pack.load_profile("violet").unwrap().name, "violet");
+
+        let names: Vec<_> = Pack::list(&packs)
+            .unwrap()
+            .into_iter()
+            .map(|p| p.manifest().name.clone())
+            .collect();
+        assert_eq!(names, ["pokemon"]);
+
+        // Reinstalling from the installed copy must not delete it first.
+        let again = Pack::install(packs.join("pokemon"), &packs).unwrap();
+        assert_eq!(again.sequences().unwrap(), ["hatch"]);
+        assert!(packs.join("pokemon/templates/shiny.png").is_file());
+
+        fs::write(source.join("pack.toml"), "name = \"../escape\"\n").unwrap();
+        assert!(Pack::install(&source, &packs).is_err());
+        assert!(Pack::open(dir.join("pack.zip")).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,255 @@
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::{Calibration, ControllerState, Profile, StickFilter, SwitchController};
+
+/// Settings for [`Passthrough`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PassthroughConfig {
+    /// Minimum time between states sent to the device. Updates arriving faster
+    /// are coalesced and only the freshest is sent.
+    pub send_interval: Duration,
+    /// How long the upstream source may stay silent before the controller is
+    /// neutralized.
+    pub idle_timeout: Duration,
+    /// How often the neutral state is re-sent while idle.
+    pub idle_interval: Duration,
+}
+
+impl Default for PassthroughConfig {
+    fn default() -> Self {
+        Self {
+            send_interval: Duration::from_micros(16_667),
+            idle_timeout: Duration::from_millis(500),
+            idle_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Forwards controller states from a live upstream source (a gamepad, a
+/// network client, ...) to the device.
+///
+/// Call [`Passthrough::feed`] for every upstream update and
+/// [`Passthrough::tick`] regularly from the same loop, at least once per
+/// [`PassthroughConfig::send_interval`]. States are sent no faster than that
+/// interval: when updates arrive faster, intermediate ones are dropped and the
+/// freshest is sent on the next tick, so a fast source never builds up a
+/// backlog that plays back late.
+///
+/// When the source goes
+/// silent for [`PassthroughConfig::idle_timeout`], a neutral state is sent so
+/// no input stays stuck, and it is then only repeated every
+/// [`PassthroughConfig::idle_interval`]. The next upstream update resumes
+/// forwarding immediately.
+///
+/// An optional [`Calibration`] first maps the source's raw stick readings to
+/// the full range, then an optional [`StickFilter`] smooths out noise before
+/// the state is forwarded.
+#[derive(Debug, Clone)]
+pub struct Passthrough {
+    config: PassthroughConfig,
+    calibration: Option<Calibration>,
+    filter: Option<StickFilter>,
+    /// The freshest upstream state not yet sent.
+    pending: Option<ControllerState>,
+    last_input: Instant,
+    last_sent: Option<Instant>,
+    idle: bool,
+}
+
+impl Passthrough {
+    pub fn new(config: PassthroughConfig) -> Self {
+        Self {
+            config,
+            calibration: None,
+            filter: None,
+            pending: None,
+            last_input: Instant::now(),
+            last_sent: None,
+            idle: false,
+        }
+    }
+
+    /// Calibrate raw stick data from the upstream source before filtering and
+    /// forwarding it.
+    pub fn set_calibration(&mut self, calibration: Calibration) -> &mut Self {
+        self.calibration = Some(calibration);
+        self
+    }
+
+    /// Calibrate with a [`Profile`]'s `[calibration]` tables, if it has them,
+    /// as `runner calibrate` writes. The profile's remap is left to the
+    /// controller, as a [`Layer`](crate::Layer) it is given.
+    pub fn set_profile(&mut self, profile: &Profile) -> &mut Self {
+        if let Some(calibration) = profile.calibration {
+            self.set_calibration(calibration);
+        }
+        self
+    }
+
+    /// Filter stick data from the upstream source before forwarding it.
+    pub fn set_filter(&mut self, filter: StickFilter) -> &mut Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Whether the upstream source is currently considered silent.
+    pub fn is_idle(&self) -> bool {
+        self.idle
+    }
+
+    /// Forward an upstream state to the device, or queue it as the freshest
+    /// state if one was sent less than a send interval ago.
+    pub fn feed(&mut self, ctrl: &mut SwitchController, state: &ControllerState) -> io::Result<()> {
+        let now = Instant::now();
+        let mut state = state.clone();
+        if let Some(calibration) = &self.calibration {
+            calibration.apply(&mut state);
+        }
+        if let Some(filter) = &mut self.filter {
+            if self.idle {
+                filter.reset();
+            }
+            filter.apply(&mut state, now);
+        }
+        self.last_input = now;
+        self.idle = false;
+        self.pending = Some(state);
+        self.tick(ctrl)
+    }
+
+    /// Send the freshest queued state once the send interval allows, or
+    /// neutralize the controller if the upstream source has gone silent.
+    pub fn tick(&mut self, ctrl: &mut SwitchController) -> io::Result<()> {
+        // Repeats while idle are deliberate, so they bypass duplicate
+        // suppression, but still go through the layers like every frame.
+        let repeat = self.idle;
+        match self.next_frame(Instant::now()) {
+            Some(state) => {
+                if repeat {
+                    ctrl.last_state = None;
+                }
+                ctrl.state(&state)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// The state to send at `now`, if any.
+    fn next_frame(&mut self, now: Instant) -> Option<ControllerState> {
+        let since_sent = |interval| {
+            self.last_sent
+                .is_none_or(|t| now.duration_since(t) >= interval)
+        };
+        let frame = if self.pending.is_some() {
+            if !since_sent(self.config.send_interval) {
+                return None;
+            }
+            self.pending.take()
+        } else if self.idle {
+            since_sent(self.config.idle_interval).then(ControllerState::neutral)
+        } else if now.duration_since(self.last_input) >= self.config.idle_timeout {
+            self.idle = true;
+            Some(ControllerState::neutral())
+        } else {
+            None
+        };
+        if frame.is_some() {
+            self.last_sent = Some(now);
+        }
+        frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Shared;
+
+    fn test_config() -> PassthroughConfig {
+        PassthroughConfig {
+            send_interval: Duration::from_millis(10),
+            idle_timeout: Duration::from_millis(100),
+            idle_interval: Duration::from_millis(1000),
+        }
+    }
+
+    #[test]
+    fn neutralizes_then_slows_down() {
+        let mut pt = Passthrough::new(test_config());
+        let start = pt.last_input;
+        let at = |ms| start + Duration::from_millis(ms);
+        let neutral = Some(ControllerState::neutral().to_command());
+        let frame = |pt: &mut Passthrough, ms| pt.next_frame(at(ms)).map(|s| s.to_command());
+
+        assert_eq!(frame(&mut pt, 50), None);
+        assert_eq!(frame(&mut pt, 100), neutral);
+        assert!(pt.is_idle());
+        assert_eq!(frame(&mut pt, 600), None);
+        assert_eq!(frame(&mut pt, 1100), neutral);
+
+        // New input resumes forwarding and restarts the timeout.
+        pt.last_input = at(1200);
+        pt.idle = false;
+        assert_eq!(frame(&mut pt, 1250), None);
+        assert_eq!(frame(&mut pt, 1300), neutral);
+    }
+
+    #[test]
+    fn coalesces_to_freshest_state() {
+        let mut pt = Passthrough::new(test_config());
+        let start = pt.last_input;
+        let at = |ms| start + Duration::from_millis(ms);
+        let with_a = |h| {
+            let mut s = ControllerState::new();
+            s.set_left_stick(h, 0.0);
+            s
+        };
+
+        pt.pending = Some(with_a(0.1));
+        assert_eq!(pt.next_frame(at(0)).unwrap().left_stick, Some((0.1, 0.0)));
+        pt.pending = Some(with_a(0.2));
+        assert!(pt.next_frame(at(4)).is_none());
+        pt.pending = Some(with_a(0.3));
+        assert!(pt.next_frame(at(8)).is_none());
+        assert_eq!(pt.next_frame(at(10)).unwrap().left_stick, Some((0.3, 0.0)));
+        assert!(pt.next_frame(at(11)).is_none());
+    }
+
+    #[test]
+    fn calibrates_from_profile() {
+        let profile: Profile = "[calibration.l_stick.horizontal]\ncenter = 0.2\n"
+            .parse()
+            .unwrap();
+        let out = Shared::default();
+        let mut ctrl = SwitchController::from_writer(out.clone());
+        let mut pt = Passthrough::new(test_config());
+        pt.set_profile(&profile);
+        let mut raw = ControllerState::new();
+        raw.set_left_stick(0.2, 0.0);
+        pt.feed(&mut ctrl, &raw).unwrap();
+        assert_eq!(out.text(), "STATE 000000000000000000 0 0\n");
+    }
+
+    #[test]
+    fn idle_repeats_go_through_layers() {
+        let (out, log) = (Shared::default(), Shared::default());
+        let mut ctrl = SwitchController::from_writer(out.clone());
+        ctrl.add_layer(crate::Logging::new(log.clone()));
+        let mut pt = Passthrough::new(PassthroughConfig {
+            send_interval: Duration::from_millis(1),
+            idle_timeout: Duration::from_millis(20),
+            idle_interval: Duration::from_millis(20),
+        });
+        let mut state = ControllerState::new();
+        state.set_button(crate::Button::A, true);
+        pt.feed(&mut ctrl, &state).unwrap();
+        for _ in 0..2 {
+            std::thread::sleep(Duration::from_millis(30));
+            pt.tick(&mut ctrl).unwrap();
+        }
+        let neutral = ControllerState::neutral().to_command();
+        assert!(out.text().ends_with(&format!("{neutral}\n{neutral}\n")));
+        assert_eq!(log.text().lines().count(), 3);
+    }
+}
@@ -0,0 +1,254 @@
+use std::collections::VecDeque;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+
+use crate::Frame;
+
+/// A bounded queue of frames where the newest wins: pushing onto a full
+/// queue drops the oldest frame, so a slow consumer always sees recent
+/// frames rather than a growing backlog.
+#[derive(Debug)]
+pub struct FrameQueue {
+    capacity: usize,
+    state: Mutex<QueueState>,
+    ready: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    frames: VecDeque<Frame>,
+    closed: bool,
+    dropped: u64,
+}
+
+impl FrameQueue {
+    /// A queue holding up to `capacity` frames (at least one).
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            state: Mutex::default(),
+            ready: Condvar::new(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Queue `frame`, dropping the oldest frame if the queue is full.
+    /// Returns whether a frame was dropped.
+    pub fn push(&self, frame: Frame) -> bool {
+        let mut state = self.lock();
+        let full = state.frames.len() >= self.capacity;
+        if full {
+            state.frames.pop_front();
+            state.dropped += 1;
+        }
+        state.frames.push_back(frame);
+        self.ready.notify_one();
+        full
+    }
+
+    /// The oldest queued frame, waiting for one if the queue is empty.
+    /// `None` once the queue is closed and drained.
+    pub fn pop(&self) -> Option<Frame> {
+        let mut state = self.lock();
+        loop {
+            if let Some(frame) = state.frames.pop_front() {
+                return Some(frame);
+            }
+            if state.closed {
+                return None;
+            }
+            state = self
+                .ready
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// Wake every waiting [`pop`](Self::pop); frames still queued are
+    /// delivered first.
+    pub fn close(&self) {
+        self.lock().closed = true;
+        self.ready.notify_all();
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of frames dropped because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.lock().dropped
+    }
+}
+
+/// Capture and detection on dedicated threads, so heavy image matching
+/// never stalls the loop sending inputs.
+///
+/// The capture thread calls `capture` as fast as it returns frames and
+/// pushes them onto a [`FrameQueue`]; the detection thread runs `analyze` on
+/// the frames it pops and sends each result back. When analysis is slower
+/// than capture, stale frames are dropped instead of queueing up. The send
+/// loop polls [`latest`](Self::latest) without blocking:
+///
+/// ```no_run
+/// # use switchcontroller::{Frame, FramePipeline, Region, average_color};
+/// # fn grab() -> std::io::Result<Frame> { unimplemented!() }
+/// let pipeline = FramePipeline::spawn(1, grab, |frame| average_color(frame, &Region::FULL));
+/// loop {
+///     if let Some(color) = pipeline.latest() {
+///         // react to the screen
+///         # let _ = color;
+///     }
+///     // keep streaming inputs
+/// }
+/// ```
+///
+/// Both threads stop when the pipeline is stopped or dropped, or when
+/// `capture` fails.
+#[derive(Debug)]
+pub struct FramePipeline<R> {
+    stop: Arc<AtomicBool>,
+    queue: Arc<FrameQueue>,
+    results: Receiver<R>,
+    capture: Option<JoinHandle<io::Result<()>>>,
+    detect: Option<JoinHandle<()>>,
+}
+
+impl<R: Send + 'static> FramePipeline<R> {
+    /// Start capturing into a queue of `capacity` frames; 1 always analyzes
+    /// the newest frame.
+    pub fn spawn(
+        capacity: usize,
+        mut capture: impl FnMut() -> io::Result<Frame> + Send + 'static,
+        mut analyze: impl FnMut(&Frame) -> R + Send + 'static,
+    ) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let queue = Arc::new(FrameQueue::new(capacity));
+        let (tx, results) = mpsc::channel();
+
+        let (flag, frames) = (stop.clone(), queue.clone());
+        let capture = thread::spawn(move || {
+            let result = (|| {
+                while !flag.load(Ordering::Relaxed) {
+                    frames.push(capture()?);
+                }
+                Ok(())
+            })();
+            frames.close();
+            result
+        });
+        let frames = queue.clone();
+        let detect = thread::spawn(move || {
+            while let Some(frame) = frames.pop() {
+                if tx.send(analyze(&frame)).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            stop,
+            queue,
+            results,
+            capture: Some(capture),
+            detect: Some(detect),
+        }
+    }
+}
+
+impl<R> FramePipeline<R> {
+    /// The newest result since the last call, discarding older ones.
+    pub fn latest(&self) -> Option<R> {
+        self.results.try_iter().last()
+    }
+
+    /// Every result, in order, for callers that need all of them.
+    pub fn results(&self) -> &Receiver<R> {
+        &self.results
+    }
+
+    /// Number of captured frames dropped before they were analyzed.
+    pub fn dropped(&self) -> u64 {
+        self.queue.dropped()
+    }
+
+    /// Stop both threads, returning the capture error that ended the
+    /// pipeline early, if any.
+    pub fn stop(mut self) -> io::Result<()> {
+        self.shutdown()
+    }
+
+    fn shutdown(&mut self) -> io::Result<()> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.queue.close();
+        let detected = match self.detect.take().map(JoinHandle::join) {
+            Some(Err(_)) => Err(io::Error::other("detection thread panicked")),
+            _ => Ok(()),
+        };
+        match self.capture.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(io::Error::other("capture thread panicked")),
+            None => Ok(()),
+        }
+        .and(detected)
+    }
+}
+
+impl<R> Drop for FramePipeline<R> {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(value: u8) -> Frame {
+        Frame::filled(1, 1, [value; 3])
+    }
+
+    #[test]
+    fn newest_frame_wins() {
+        let queue = FrameQueue::new(2);
+        assert!(!queue.push(frame(1)));
+        assert!(!queue.push(frame(2)));
+        assert!(queue.push(frame(3)));
+        assert_eq!(queue.dropped(), 1);
+        queue.close();
+        assert_eq!(queue.pop(), Some(frame(2)));
+        assert_eq!(queue.pop(), Some(frame(3)));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn analyzes_on_its_own_thread() {
+        let mut n = 0u8;
+        let pipeline = FramePipeline::spawn(
+            1,
+            move || {
+                n = n.wrapping_add(1);
+                if n == 50 {
+                    return Err(io::Error::other("capture card unplugged"));
+                }
+                Ok(frame(n))
+            },
+            |frame| frame.pixel(0, 0)[0],
+        );
+        let results: Vec<u8> = pipeline.results().iter().collect();
+        assert!(!results.is_empty());
+        assert!(results.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(pipeline.latest(), None);
+        let err = pipeline.stop().unwrap_err();
+        assert_eq!(err.to_string(), "capture card unplugged");
+    }
+}
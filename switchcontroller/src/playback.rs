@@ -0,0 +1,284 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::{InputSequence, Step, SwitchController, Timeline};
+
+/// How often a cancellable playback checks its cancel flag during a wait.
+const CANCEL_POLL: Duration = Duration::from_millis(10);
+
+/// How long before a deadline [`sleep_until`] stops sleeping and starts spinning.
+const SPIN_THRESHOLD: Duration = Duration::from_millis(2);
+
+/// Block until `deadline`, sleeping for most of the wait and spinning for the
+/// last moment to avoid the scheduler's wake-up jitter.
+pub(crate) fn sleep_until(deadline: Instant) {
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    if remaining > SPIN_THRESHOLD {
+        thread::sleep(remaining - SPIN_THRESHOLD);
+    }
+    while Instant::now() < deadline {
+        std::hint::spin_loop();
+    }
+}
+
+/// A point within an [`InputSequence`] to start or resume playback from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct PlaybackPosition {
+    /// Index of the next step to execute.
+    pub step: usize,
+    /// Time already spent in the step when it is a [`Step::Wait`].
+    pub offset: Duration,
+}
+
+impl PlaybackPosition {
+    /// The position just before the step at `index`.
+    pub fn step(index: usize) -> Self {
+        Self {
+            step: index,
+            offset: Duration::ZERO,
+        }
+    }
+}
+
+impl SwitchController {
+    /// Set the playback speed multiplier for sequences.
+    ///
+    /// Waits are divided by `speed`, so `2.0` plays twice as fast and `0.25`
+    /// at quarter speed. The order of inputs is never changed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `speed` is not a positive, finite number.
+    pub fn set_playback_speed(&mut self, speed: f32) {
+        assert!(
+            speed.is_finite() && speed > 0.0,
+            "playback speed must be positive and finite, got {speed}"
+        );
+        self.speed = speed;
+    }
+
+    /// The current playback speed multiplier.
+    pub fn playback_speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Set the estimated transport latency between writing a command and the
+    /// console receiving it. Timed starts such as [`SwitchController::play_at`]
+    /// begin sending this much earlier.
+    pub fn set_latency(&mut self, latency: Duration) {
+        self.latency = latency;
+    }
+
+    /// The estimated transport latency.
+    pub fn latency(&self) -> Duration {
+        self.latency
+    }
+
+    /// Play back a sequence so that its first input reaches the console at `start`.
+    ///
+    /// Sending begins [`SwitchController::latency`] ahead of `start`. If that
+    /// moment has already passed, playback starts immediately.
+    #[doc(alias = "run_at")]
+    pub fn play_at(&mut self, start: Instant, sequence: &InputSequence) -> io::Result<()> {
+        self.flush()?;
+        sleep_until(start.checked_sub(self.latency).unwrap_or(start));
+        self.play(sequence)
+    }
+
+    /// Like [`SwitchController::play_at`], but with a wall-clock start time,
+    /// e.g. a raid opening or stream countdown.
+    pub fn play_at_time(&mut self, start: SystemTime, sequence: &InputSequence) -> io::Result<()> {
+        let now = Instant::now();
+        let start = match start.duration_since(SystemTime::now()) {
+            Ok(ahead) => now + ahead,
+            Err(_) => now,
+        };
+        self.play_at(start, sequence)
+    }
+
+    /// Play back a sequence, waiting on the host between steps.
+    ///
+    /// Waits are timed against monotonic deadlines from the start of
+    /// playback, with a precise final sleep, so the time taken to send
+    /// commands does not add up over long sequences.
+    pub fn play(&mut self, sequence: &InputSequence) -> io::Result<()> {
+        self.play_from(sequence, PlaybackPosition::default())
+    }
+
+    /// Play back a sequence starting at `from`.
+    ///
+    /// See [`SwitchController::play_cancellable`] for how the controller state is
+    /// re-established when starting mid-sequence.
+    pub fn play_from(
+        &mut self,
+        sequence: &InputSequence,
+        from: PlaybackPosition,
+    ) -> io::Result<()> {
+        let never = AtomicBool::new(false);
+        self.play_cancellable(sequence, from, &never).map(|_| ())
+    }
+
+    /// Play back a sequence starting at `from`, stopping early once `cancel` is set.
+    ///
+    /// When `from` is not the start of the sequence, a `STATE` command holding
+    /// everything the skipped steps would have left held is sent first, so the
+    /// remaining steps run against the same controller state as a full playback.
+    ///
+    /// Returns the position to resume from if playback was cancelled, or `None`
+    /// if it ran to the end. Positions are in sequence time, independent of the
+    /// playback speed.
+    pub fn play_cancellable(
+        &mut self,
+        sequence: &InputSequence,
+        from: PlaybackPosition,
+        cancel: &AtomicBool,
+    ) -> io::Result<Option<PlaybackPosition>> {
+        self.play_observed(sequence, from, cancel, &mut |_| Ok(()))
+    }
+
+    /// [`SwitchController::play_cancellable`], also reporting the current
+    /// position to `progress` before each step and every `CANCEL_POLL`
+    /// during waits. An error from `progress` aborts playback.
+    pub(crate) fn play_observed(
+        &mut self,
+        sequence: &InputSequence,
+        from: PlaybackPosition,
+        cancel: &AtomicBool,
+        progress: &mut dyn FnMut(PlaybackPosition) -> io::Result<()>,
+    ) -> io::Result<Option<PlaybackPosition>> {
+        if from != PlaybackPosition::default() {
+            let mut state = sequence.state_at(from.step);
+            state.left_stick.get_or_insert((0.0, 0.0));
+            state.right_stick.get_or_insert((0.0, 0.0));
+            self.state(&state)?;
+        }
+        // When the current step is due. Waits end at a fixed deadline measured
+        // from here rather than sleeping for their duration, so time spent
+        // sending commands is absorbed instead of accumulating as drift.
+        let mut deadline = Instant::now();
+        for (index, step) in sequence.steps().iter().enumerate().skip(from.step) {
+            match step {
+                Step::Wait(duration) => {
+                    let skipped = if index == from.step {
+                        from.offset.min(*duration)
+                    } else {
+                        Duration::ZERO
+                    };
+                    let end = deadline + (*duration - skipped).div_f32(self.speed);
+                    self.flush()?;
+                    loop {
+                        let now = Instant::now();
+                        if now >= end {
+                            break;
+                        }
+                        let position = PlaybackPosition {
+                            step: index,
+                            offset: duration.saturating_sub((end - now).mul_f32(self.speed)),
+                        };
+                        if cancel.load(Ordering::Relaxed) {
+                            return Ok(Some(position));
+                        }
+                        progress(position)?;
+                        self.release_timed(Some(now))?;
+                        if end - now > CANCEL_POLL {
+                            thread::sleep(CANCEL_POLL);
+                        } else {
+                            sleep_until(end);
+                        }
+                    }
+                    deadline = end;
+                }
+                _ => {
+                    if cancel.load(Ordering::Relaxed) {
+                        return Ok(Some(PlaybackPosition::step(index)));
+                    }
+                    progress(PlaybackPosition::step(index))?;
+                    self.run_step(step)?;
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Play the part of a sequence from marker `from` up to marker `to`, or
+    /// to the end with `None`.
+    ///
+    /// As with [`SwitchController::play_from`], whatever the skipped steps
+    /// would have left held is sent first. Inputs held at `to` stay held.
+    pub fn play_between(
+        &mut self,
+        sequence: &InputSequence,
+        from: &str,
+        to: Option<&str>,
+    ) -> io::Result<()> {
+        let find = |name| {
+            sequence.marker_position(name).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, format!("no marker `{name}`"))
+            })
+        };
+        let start = find(from)?;
+        let end = match to {
+            Some(to) => find(to)?.step,
+            None => sequence.len(),
+        };
+        if end < start.step {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("marker `{}` comes before `{from}`", to.unwrap()),
+            ));
+        }
+        let part = InputSequence::from(sequence.steps()[..end].to_vec());
+        self.play_from(&part, start)
+    }
+
+    /// Play back a timeline, flattening it into commands as it is sent.
+    pub fn play_timeline(&mut self, timeline: &Timeline) -> io::Result<()> {
+        self.play(&timeline.to_sequence())
+    }
+
+    /// Execute a single sequence step, as [`SwitchController::execute`]
+    /// would its command.
+    pub(crate) fn run_step(&mut self, step: &Step) -> io::Result<()> {
+        if let Step::Wait(duration) = step {
+            self.flush()?;
+            thread::sleep(duration.div_f32(self.speed));
+        }
+        match step.command() {
+            Some(command) => self.execute(&command),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Shared;
+    use crate::{Button, ControllerState, Socd};
+
+    #[test]
+    fn sleep_until_reaches_deadline() {
+        let deadline = Instant::now() + Duration::from_millis(5);
+        sleep_until(deadline);
+        assert!(Instant::now() >= deadline);
+        // A deadline in the past returns immediately.
+        sleep_until(deadline - Duration::from_millis(5));
+    }
+
+    #[test]
+    fn played_states_are_cleaned() {
+        let out = Shared::default();
+        let mut ctrl = SwitchController::from_writer(out.clone());
+        ctrl.set_socd(Some(Socd::Neutral));
+        let mut state = ControllerState::neutral();
+        state
+            .set_button(Button::DpadLeft, true)
+            .set_button(Button::DpadRight, true);
+        let mut seq = InputSequence::new();
+        seq.state(&state);
+        ctrl.play(&seq).unwrap();
+        assert_eq!(out.text(), "STATE 000000000000000000 0 0 0 0\n");
+    }
+}
@@ -0,0 +1,213 @@
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+
+use crate::{AutomationEvent, InputSequence};
+
+type Expand = dyn Fn(&[String]) -> Result<InputSequence, String> + Send + Sync;
+
+/// A command a [`Plugin`] adds to macro scripts: `name(args)` in a script
+/// expands to the sequence the command builds from its arguments, evaluated
+/// like macro arguments.
+#[derive(Clone)]
+pub struct PluginCommand {
+    name: String,
+    help: String,
+    expand: Arc<Expand>,
+}
+
+impl PluginCommand {
+    pub fn new(
+        name: impl Into<String>,
+        help: impl Into<String>,
+        expand: impl Fn(&[String]) -> Result<InputSequence, String> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            help: help.into(),
+            expand: Arc::new(expand),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// One line describing the command and its arguments.
+    pub fn help(&self) -> &str {
+        &self.help
+    }
+
+    /// Build the command's sequence, or an error message for bad arguments.
+    pub fn expand(&self, args: &[String]) -> Result<InputSequence, String> {
+        (self.expand)(args)
+    }
+}
+
+impl fmt::Debug for PluginCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PluginCommand")
+            .field("name", &self.name)
+            .field("help", &self.help)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A game-specific automation module, such as a Pokémon or Animal Crossing
+/// pack, that can live in its own crate.
+///
+/// A plugin crate exports a constructor returning `Box<dyn Plugin>`, and the
+/// program hosting the automation (e.g. a daemon) passes it to
+/// [`PluginRegistry::register`].
+pub trait Plugin: Send {
+    /// A unique name, such as `pokemon`.
+    fn name(&self) -> &str;
+
+    /// Called once when the plugin is registered, before its commands are
+    /// read. An error stops registration.
+    fn init(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Commands added to macro scripts.
+    fn commands(&self) -> Vec<PluginCommand> {
+        Vec::new()
+    }
+
+    /// Called for every event passed to [`PluginRegistry::dispatch`].
+    fn on_event(&mut self, _event: &AutomationEvent) {}
+}
+
+/// The registered [`Plugin`]s and the commands they contribute.
+///
+/// Give the commands to a script loader with
+/// [`ScriptLoader::commands`](crate::ScriptLoader::commands), and forward
+/// events with [`PluginRegistry::dispatch`], for example from an
+/// [`EventBus`](crate::EventBus) subscription.
+#[derive(Default)]
+pub struct PluginRegistry {
+    plugins: Vec<Box<dyn Plugin>>,
+    commands: Vec<PluginCommand>,
+}
+
+impl PluginRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Initialize `plugin` and add it with its commands. Fails with
+    /// [`io::ErrorKind::AlreadyExists`] if a plugin of the same name or a
+    /// command of the same name is already registered, leaving the registry
+    /// unchanged.
+    pub fn register(&mut self, mut plugin: Box<dyn Plugin>) -> io::Result<()> {
+        let exists = |what: String| io::Error::new(io::ErrorKind::AlreadyExists, what);
+        if self.plugin(plugin.name()).is_some() {
+            return Err(exists(format!(
+                "plugin `{}` is already registered",
+                plugin.name()
+            )));
+        }
+        plugin.init()?;
+        let commands = plugin.commands();
+        for (i, command) in commands.iter().enumerate() {
+            if self.command(command.name()).is_some()
+                || commands[..i].iter().any(|c| c.name() == command.name())
+            {
+                return Err(exists(format!(
+                    "command `{}` is already registered",
+                    command.name()
+                )));
+            }
+        }
+        self.commands.extend(commands);
+        self.plugins.push(plugin);
+        Ok(())
+    }
+
+    /// Names of the registered plugins, in registration order.
+    pub fn plugins(&self) -> impl Iterator<Item = &str> {
+        self.plugins.iter().map(|p| p.name())
+    }
+
+    pub fn plugin(&self, name: &str) -> Option<&dyn Plugin> {
+        self.plugins.iter().find(|p| p.name() == name).map(|p| &**p)
+    }
+
+    /// Every contributed command, in registration order.
+    pub fn commands(&self) -> &[PluginCommand] {
+        &self.commands
+    }
+
+    pub fn command(&self, name: &str) -> Option<&PluginCommand> {
+        self.commands.iter().find(|c| c.name() == name)
+    }
+
+    /// Pass `event` to every plugin, in registration order.
+    pub fn dispatch(&mut self, event: &AutomationEvent) {
+        for plugin in &mut self.plugins {
+            plugin.on_event(event);
+        }
+    }
+}
+
+impl fmt::Debug for PluginRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PluginRegistry")
+            .field("plugins", &self.plugins().collect::<Vec<_>>())
+            .field("commands", &self.commands)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Button, ScriptLoader};
+
+    struct Eggs;
+
+    impl Plugin for Eggs {
+        fn name(&self) -> &str {
+            "eggs"
+        }
+
+        fn commands(&self) -> Vec<PluginCommand> {
+            vec![PluginCommand::new(
+                "mash_a",
+                "mash_a(times): press A `times` times",
+                |args| {
+                    let [times] = args else {
+                        return Err("expected `mash_a(times)`".into());
+                    };
+                    let times: u32 = times.parse().map_err(|_| "invalid count")?;
+                    let mut seq = InputSequence::new();
+                    for _ in 0..times {
+                        seq.press(&[Button::A]);
+                    }
+                    Ok(seq)
+                },
+            )]
+        }
+    }
+
+    #[test]
+    fn plugins_add_script_commands() {
+        let mut plugins = PluginRegistry::new();
+        plugins.register(Box::new(Eggs)).unwrap();
+        let err = plugins.register(Box::new(Eggs)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(plugins.plugins().collect::<Vec<_>>(), ["eggs"]);
+
+        let loader = ScriptLoader::new().commands(plugins.commands());
+        let seq = loader
+            .parse("let n = 1\nmash_a($n + 1)\npress b\n")
+            .unwrap();
+        assert_eq!(seq.to_script(), "PRESS a\nPRESS a\nPRESS b\n");
+        let err = loader.parse("press b\nmash_a()\n").unwrap_err();
+        assert_eq!(
+            (err.line, err.message.as_str()),
+            (2, "mash_a: expected `mash_a(times)`")
+        );
+        assert!(ScriptLoader::new().parse("mash_a(2)").is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use serialport::{SerialPort, SerialPortType};
+
+/// USB vendor ID of Raspberry Pi devices, including the Pico W.
+const RASPBERRY_PI_VID: u16 = 0x2e8a;
+
+/// A serial port found by [`available_ports`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortInfo {
+    /// The path to pass to [`SwitchController::open`](crate::SwitchController::open).
+    pub path: String,
+    /// A human-readable name, if known: the USB product name, or on Windows
+    /// the device's friendly name from the registry (e.g. `USB Serial Device
+    /// (COM10)`).
+    pub name: Option<String>,
+    /// USB vendor and product IDs, for USB ports.
+    pub usb_id: Option<(u16, u16)>,
+}
+
+impl PortInfo {
+    /// Whether the port belongs to a Raspberry Pi device such as the Pico W.
+    pub fn is_pico(&self) -> bool {
+        self.usb_id.is_some_and(|(vid, _)| vid == RASPBERRY_PI_VID)
+    }
+}
+
+/// List the serial ports on this machine.
+pub fn available_ports() -> Result<Vec<PortInfo>, serialport::Error> {
+    Ok(serialport::available_ports()?
+        .into_iter()
+        .map(|port| {
+            let (name, usb_id) = match port.port_type {
+                SerialPortType::UsbPort(usb) => (usb.product, Some((usb.vid, usb.pid))),
+                _ => (None, None),
+            };
+            PortInfo {
+                path: port.port_name,
+                name,
+                usb_id,
+            }
+        })
+        .collect())
+}
+
+/// Rewrite the port spellings Windows users commonly type into a path
+/// `CreateFile` accepts: surrounding whitespace and a trailing `:` are
+/// dropped, MSYS/Cygwin `/dev/ttyS<n>` becomes `COM<n+1>`, and `COM<n>` gets
+/// the `\\.\` device prefix required for `COM10` and above.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn normalize_windows_path(path: &str) -> String {
+    let path = path.trim();
+    let path = path.strip_suffix(':').unwrap_or(path);
+    let number = if let Some(n) = path.strip_prefix("/dev/ttyS") {
+        n.parse::<u32>().ok().map(|n| n + 1)
+    } else if path.len() > 3 && path[..3].eq_ignore_ascii_case("com") {
+        path[3..].parse::<u32>().ok()
+    } else {
+        None
+    };
+    match number {
+        Some(n) => format!(r"\\.\COM{n}"),
+        None => path.to_string(),
+    }
+}
+
+pub(crate) fn open_port(
+    path: &str,
+    baud_rate: u32,
+) -> Result<Box<dyn SerialPort>, serialport::Error> {
+    #[cfg(windows)]
+    let path = &normalize_windows_path(path);
+    serialport::new(path, baud_rate)
+        .timeout(Duration::from_secs(1))
+        .open()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_paths() {
+        assert_eq!(normalize_windows_path("COM3"), r"\\.\COM3");
+        assert_eq!(normalize_windows_path(" com10: "), r"\\.\COM10");
+        assert_eq!(normalize_windows_path("/dev/ttyS9"), r"\\.\COM10");
+        assert_eq!(normalize_windows_path(r"\\.\COM12"), r"\\.\COM12");
+        assert_eq!(normalize_windows_path("COMX"), "COMX");
+    }
+}
@@ -0,0 +1,746 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::toml::{Document, Table, Value};
+use crate::{
+    AxisCalibration, Button, Calibration, ConfirmButton, ControllerState, Detection, Region, Remap,
+    Stick, StickCalibration, StickCurve, Timings,
+};
+
+/// An error parsing a [`Profile`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseProfileError {
+    /// 1-based line the error was found on, or 0 if it is not tied to a line.
+    pub line: usize,
+    pub message: String,
+}
+
+impl ParseProfileError {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            line: 0,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            0 => write!(f, "invalid profile: {}", self.message),
+            line => write!(f, "invalid profile: line {line}: {}", self.message),
+        }
+    }
+}
+
+impl Error for ParseProfileError {}
+
+/// A named bundle of input mappings, stick tuning and per-game settings.
+///
+/// Profiles are stored as TOML files named `<name>.toml` in a profiles
+/// directory (see [`Profile::default_dir`]):
+///
+/// ```toml
+/// name = "splatoon-aim"
+/// confirm = "a"
+///
+/// [remap]
+/// swap_sticks = false
+/// invert_horizontal = []
+/// invert_vertical = ["r_stick"]
+///
+/// [remap.buttons]
+/// zr = "r"
+///
+/// [curve.r_stick]
+/// exponent = 1.8
+/// scale = 1.0
+///
+/// [calibration.l_stick.horizontal]
+/// center = 0.02
+/// min = -0.95
+/// max = 0.97
+/// deadzone = 0.05
+///
+/// [timings]
+/// text_speed = 120
+///
+/// [regions]
+/// sparkle = [40, 10, 20, 15]
+///
+/// [detections.shiny]
+/// method = "color"
+/// region = "sparkle"
+/// color = [250, 220, 40]
+///
+/// [game]
+/// sensitivity = 3
+/// ```
+///
+/// Every table is optional. `confirm` is the button that confirms in
+/// menus, `"b"` for titles that swap A and B. `[timings]` overrides
+/// [`Timings`] constants, in milliseconds. `[regions]` names screen
+/// [`Region`]s for detections, as `[x, y, width, height]` in percent, and
+/// `[detections.<name>]` picks a [`Detection`] method for each named check.
+/// `[game]` holds free-form settings for the caller's own use.
+#[derive(Debug, Clone)]
+pub struct Profile {
+    pub name: String,
+    pub remap: Remap,
+    pub left_curve: StickCurve,
+    pub right_curve: StickCurve,
+    /// Calibration of the physical source gamepad, if captured.
+    pub calibration: Option<Calibration>,
+    /// Which button confirms in menus, for
+    /// [`SwitchController::set_confirm_button`](crate::SwitchController::set_confirm_button).
+    pub confirm: ConfirmButton,
+    /// Timing constants overriding the built-in defaults; see
+    /// [`Profile::timings`].
+    pub timing_overrides: Timings,
+    /// Named screen regions for detections.
+    pub regions: BTreeMap<String, Region>,
+    /// Named on-screen checks, each with its own detection method.
+    pub detections: BTreeMap<String, Detection>,
+    /// Free-form per-game settings, as their TOML source text (strings
+    /// unquoted).
+    pub game: BTreeMap<String, String>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            remap: Remap::default(),
+            left_curve: StickCurve::default(),
+            right_curve: StickCurve::default(),
+            calibration: None,
+            confirm: ConfirmButton::default(),
+            timing_overrides: Timings::empty(),
+            regions: BTreeMap::new(),
+            detections: BTreeMap::new(),
+            game: BTreeMap::new(),
+        }
+    }
+}
+
+impl Profile {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Self::default()
+        }
+    }
+
+    /// The built-in timing constants with this profile's overrides applied.
+    pub fn timings(&self) -> Timings {
+        let mut timings = Timings::new();
+        timings.extend(&self.timing_overrides);
+        timings
+    }
+
+    /// The profiles directory: `$SWITCHCONTROLLER_PROFILES` if set, otherwise
+    /// `profiles` in the working directory.
+    pub fn default_dir() -> PathBuf {
+        std::env::var_os("SWITCHCONTROLLER_PROFILES")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("profiles"))
+    }
+
+    /// Load the profile `<dir>/<name>.toml`.
+    pub fn load(dir: impl AsRef<Path>, name: &str) -> io::Result<Profile> {
+        let path = dir.as_ref().join(format!("{name}.toml"));
+        let text = fs::read_to_string(&path)?;
+        let mut profile: Profile = text
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if profile.name.is_empty() {
+            profile.name = name.to_string();
+        }
+        Ok(profile)
+    }
+
+    /// Write this profile to `<dir>/<name>.toml`, creating `dir` if needed.
+    pub fn save(&self, dir: impl AsRef<Path>) -> io::Result<()> {
+        if self.name.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "profile has no name",
+            ));
+        }
+        fs::create_dir_all(&dir)?;
+        fs::write(
+            dir.as_ref().join(format!("{}.toml", self.name)),
+            self.to_toml(),
+        )
+    }
+
+    /// Names of the profiles in `dir`, sorted.
+    pub fn list(dir: impl AsRef<Path>) -> io::Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "toml")
+                && let Some(stem) = path.file_stem().and_then(|s| s.to_str())
+            {
+                names.push(stem.to_string());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    pub fn curve(&self, stick: Stick) -> &StickCurve {
+        match stick {
+            Stick::Left => &self.left_curve,
+            Stick::Right => &self.right_curve,
+        }
+    }
+
+    /// Map a state from the physical source: calibration first, then the stick
+    /// curves, then the remap.
+    pub fn map_state(&self, state: &ControllerState) -> ControllerState {
+        let mut state = state.clone();
+        if let Some(calibration) = &self.calibration {
+            calibration.apply(&mut state);
+        }
+        if let Some(position) = &mut state.left_stick {
+            *position = self.left_curve.apply(*position);
+        }
+        if let Some(position) = &mut state.right_stick {
+            *position = self.right_curve.apply(*position);
+        }
+        self.remap.map_state(&state)
+    }
+
+    /// Serialize to the TOML format read by [`Profile::load`].
+    pub fn to_toml(&self) -> String {
+        self.to_document().to_string()
+    }
+
+    pub(crate) fn to_document(&self) -> Document {
+        let mut doc = Document::new();
+        doc.set("", "name", Value::String(self.name.clone()));
+        if self.confirm != ConfirmButton::default() {
+            doc.set("", "confirm", Value::String(self.confirm.as_str().into()));
+        }
+
+        let sticks = |flags: &[bool]| {
+            [Stick::Left, Stick::Right]
+                .into_iter()
+                .zip(flags.iter().step_by(2))
+                .filter(|&(_, &f)| f)
+                .map(|(s, _)| Value::String(s.to_string()))
+                .collect()
+        };
+        doc.set("remap", "swap_sticks", Value::Bool(self.remap.swap_sticks));
+        doc.set(
+            "remap",
+            "invert_horizontal",
+            Value::Array(sticks(&self.remap.invert)),
+        );
+        doc.set(
+            "remap",
+            "invert_vertical",
+            Value::Array(sticks(&self.remap.invert[1..])),
+        );
+        for from in Button::ALL {
+            if let Some(to) = self.remap.buttons.get(&from) {
+                doc.set(
+                    "remap.buttons",
+                    from.as_str(),
+                    Value::String(to.to_string()),
+                );
+            }
+        }
+
+        for stick in [Stick::Left, Stick::Right] {
+            let curve = self.curve(stick);
+            let table = format!("curve.{stick}");
+            doc.set(&table, "exponent", number(curve.exponent));
+            doc.set(&table, "scale", number(curve.scale));
+        }
+
+        if let Some(calibration) = &self.calibration {
+            for stick in [Stick::Left, Stick::Right] {
+                let c = calibration.stick(stick);
+                for (axis, a) in [("horizontal", c.horizontal), ("vertical", c.vertical)] {
+                    let table = format!("calibration.{stick}.{axis}");
+                    for (key, value) in [
+                        ("center", a.center),
+                        ("min", a.min),
+                        ("max", a.max),
+                        ("deadzone", a.deadzone),
+                    ] {
+                        doc.set(&table, key, number(value));
+                    }
+                }
+            }
+        }
+
+        for (name, duration) in self.timing_overrides.iter() {
+            doc.set(
+                "timings",
+                name,
+                Value::Number(duration.as_secs_f64() * 1000.0),
+            );
+        }
+
+        for (name, r) in &self.regions {
+            let bounds = [r.x, r.y, r.width, r.height].map(number);
+            doc.set("regions", name, Value::Array(bounds.to_vec()));
+        }
+
+        for (name, detection) in &self.detections {
+            let table = format!("detections.{name}");
+            let (method, score) = match detection {
+                Detection::Template { threshold, .. } => ("template", ("threshold", threshold)),
+                Detection::Color { tolerance, .. } => ("color", ("tolerance", tolerance)),
+                Detection::Histogram { similarity, .. } => {
+                    ("histogram", ("similarity", similarity))
+                }
+            };
+            doc.set(&table, "method", Value::String(method.into()));
+            if let Some(template) = detection.template() {
+                doc.set(&table, "template", Value::String(template.into()));
+            }
+            let r = detection.region();
+            let region = match self.regions.iter().find(|(_, region)| *region == r) {
+                Some((name, _)) => Value::String(name.clone()),
+                None => Value::Array([r.x, r.y, r.width, r.height].map(number).to_vec()),
+            };
+            doc.set(&table, "region", region);
+            if let Detection::Color { color, .. } = detection {
+                let channels = color.map(|c| Value::Number(c.into()));
+                doc.set(&table, "color", Value::Array(channels.to_vec()));
+            }
+            doc.set(&table, score.0, Value::Number(*score.1));
+        }
+
+        for (key, value) in &self.game {
+            let value = match value.parse::<Value>() {
+                Ok(v) if !matches!(v, Value::String(_)) => v,
+                _ => Value::String(value.clone()),
+            };
+            doc.set("game", key, value);
+        }
+        doc
+    }
+}
+
+/// A number with the shortest decimal form that reads back as the same `f32`.
+fn number(value: f32) -> Value {
+    Value::Number(value.to_string().parse().unwrap())
+}
+
+fn expected(key: &str, what: &str) -> ParseProfileError {
+    ParseProfileError::new(format!("`{key}` must be {what}"))
+}
+
+fn parse_stick(value: &Value, key: &str) -> Result<Stick, ParseProfileError> {
+    value
+        .as_str()
+        .and_then(Stick::from_name)
+        .ok_or_else(|| expected(key, "a list of stick names"))
+}
+
+fn parse_number(table: &Table, key: &str) -> Result<Option<f32>, ParseProfileError> {
+    match table.iter().find(|(k, _)| k == key) {
+        Some((_, v)) => v
+            .as_f64()
+            .map(|n| Some(n as f32))
+            .ok_or_else(|| expected(key, "a number")),
+        None => Ok(None),
+    }
+}
+
+const BOUNDS: &str = "[x, y, width, height] in percent of the screen";
+
+fn parse_bounds(value: &Value) -> Option<Region> {
+    match value.as_array()? {
+        [x, y, w, h] => Region::new(
+            x.as_f64()? as f32,
+            y.as_f64()? as f32,
+            w.as_f64()? as f32,
+            h.as_f64()? as f32,
+        ),
+        _ => None,
+    }
+}
+
+fn parse_detection(
+    table: &Table,
+    name: &str,
+    regions: &BTreeMap<String, Region>,
+) -> Result<Detection, ParseProfileError> {
+    let get = |key: &str| table.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+    let string = |key: &str| -> Result<Option<String>, ParseProfileError> {
+        get(key)
+            .map(|v| {
+                v.as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| expected(key, "a string"))
+            })
+            .transpose()
+    };
+    let fraction = |key: &str, default: f64| -> Result<f64, ParseProfileError> {
+        match get(key) {
+            Some(v) => v
+                .as_f64()
+                .filter(|n| (0.0..=1.0).contains(n))
+                .ok_or_else(|| expected(key, "a number from 0 to 1")),
+            None => Ok(default),
+        }
+    };
+    let region = match get("region") {
+        None => Region::FULL,
+        Some(Value::String(region)) => *regions
+            .get(region)
+            .ok_or_else(|| ParseProfileError::new(format!("unknown region `{region}`")))?,
+        Some(value) => parse_bounds(value)
+            .ok_or_else(|| expected("region", "a region name or [x, y, width, height]"))?,
+    };
+    let template = || string("template")?.ok_or_else(|| expected("template", "set"));
+    Ok(match string("method")?.as_deref() {
+        Some("template") => {
+            reject_unknown(table, name, &["method", "template", "region", "threshold"])?;
+            Detection::Template {
+                template: template()?,
+                region,
+                threshold: fraction("threshold", Detection::DEFAULT_THRESHOLD)?,
+            }
+        }
+        Some("color") => {
+            reject_unknown(table, name, &["method", "region", "color", "tolerance"])?;
+            let color = get("color")
+                .and_then(Value::as_array)
+                .and_then(|items| {
+                    let channel = |v: &Value| {
+                        v.as_f64()
+                            .filter(|c| c.fract() == 0.0 && (0.0..=255.0).contains(c))
+                            .map(|c| c as u8)
+                    };
+                    match items {
+                        [r, g, b] => Some([channel(r)?, channel(g)?, channel(b)?]),
+                        _ => None,
+                    }
+                })
+                .ok_or_else(|| expected("color", "[red, green, blue] from 0 to 255"))?;
+            Detection::Color {
+                region,
+                color,
+                tolerance: fraction("tolerance", Detection::DEFAULT_TOLERANCE)?,
+            }
+        }
+        Some("histogram") => {
+            reject_unknown(table, name, &["method", "template", "region", "similarity"])?;
+            Detection::Histogram {
+                template: template()?,
+                region,
+                similarity: fraction("similarity", Detection::DEFAULT_SIMILARITY)?,
+            }
+        }
+        _ => {
+            return Err(expected(
+                "method",
+                "\"template\", \"color\" or \"histogram\"",
+            ));
+        }
+    })
+}
+
+fn reject_unknown(table: &Table, name: &str, known: &[&str]) -> Result<(), ParseProfileError> {
+    match table.iter().find(|(k, _)| !known.contains(&k.as_str())) {
+        Some((key, _)) => Err(ParseProfileError::new(format!(
+            "unknown key `{key}` in [{name}]"
+        ))),
+        None => Ok(()),
+    }
+}
+
+impl FromStr for Profile {
+    type Err = ParseProfileError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Profile::from_document(&Document::parse(s)?)
+    }
+}
+
+impl Profile {
+    pub(crate) fn from_document(doc: &Document) -> Result<Self, ParseProfileError> {
+        let mut profile = Profile::default();
+        let mut calibration = Calibration::default();
+        let mut calibrated = false;
+
+        for (name, table) in doc.tables() {
+            match name.split('.').collect::<Vec<_>>()[..] {
+                [""] => {
+                    reject_unknown(table, "root", &["name", "confirm"])?;
+                    for (key, value) in table {
+                        if key == "confirm" {
+                            profile.confirm = value
+                                .as_str()
+                                .and_then(ConfirmButton::from_name)
+                                .ok_or_else(|| expected(key, "\"a\" or \"b\""))?;
+                            continue;
+                        }
+                        profile.name = value
+                            .as_str()
+                            .ok_or_else(|| expected(key, "a string"))?
+                            .to_string();
+                    }
+                }
+                ["remap"] => {
+                    reject_unknown(
+                        table,
+                        name,
+                        &["swap_sticks", "invert_horizontal", "invert_vertical"],
+                    )?;
+                    for (key, value) in table {
+                        if key == "swap_sticks" {
+                            if value.as_bool().ok_or_else(|| expected(key, "a boolean"))? {
+                                profile.remap.swap_sticks();
+                            }
+                            continue;
+                        }
+                        let items = value
+                            .as_array()
+                            .ok_or_else(|| expected(key, "a list of stick names"))?;
+                        for item in items {
+                            let stick = parse_stick(item, key)?;
+                            if key == "invert_horizontal" {
+                                profile.remap.invert_horizontal(stick);
+                            } else {
+                                profile.remap.invert_vertical(stick);
+                            }
+                        }
+                    }
+                }
+                ["remap", "buttons"] => {
+                    for (key, value) in table {
+                        let from = Button::from_name(key).ok_or_else(|| {
+                            ParseProfileError::new(format!("unknown button `{key}`"))
+                        })?;
+                        let to = value
+                            .as_str()
+                            .and_then(Button::from_name)
+                            .ok_or_else(|| expected(key, "a button name"))?;
+                        profile.remap.button(from, to);
+                    }
+                }
+                ["curve", stick] => {
+                    let stick = Stick::from_name(stick)
+                        .ok_or_else(|| ParseProfileError::new(format!("unknown table [{name}]")))?;
+                    reject_unknown(table, name, &["exponent", "scale"])?;
+                    let curve = match stick {
+                        Stick::Left => &mut profile.left_curve,
+                        Stick::Right => &mut profile.right_curve,
+                    };
+                    if let Some(exponent) = parse_number(table, "exponent")? {
+                        curve.exponent = exponent;
+                    }
+                    if let Some(scale) = parse_number(table, "scale")? {
+                        curve.scale = scale;
+                    }
+                }
+                ["calibration", stick, axis] => {
+                    let unknown = || ParseProfileError::new(format!("unknown table [{name}]"));
+                    let stick: &mut StickCalibration = match Stick::from_name(stick) {
+                        Some(Stick::Left) => &mut calibration.left,
+                        Some(Stick::Right) => &mut calibration.right,
+                        None => return Err(unknown()),
+                    };
+                    let axis: &mut AxisCalibration = match axis {
+                        "horizontal" => &mut stick.horizontal,
+                        "vertical" => &mut stick.vertical,
+                        _ => return Err(unknown()),
+                    };
+                    reject_unknown(table, name, &["center", "min", "max", "deadzone"])?;
+                    for (key, field) in [
+                        ("center", &mut axis.center),
+                        ("min", &mut axis.min),
+                        ("max", &mut axis.max),
+                        ("deadzone", &mut axis.deadzone),
+                    ] {
+                        if let Some(value) = parse_number(table, key)? {
+                            *field = value;
+                        }
+                    }
+                    calibrated = true;
+                }
+                ["timings"] => {
+                    for (key, value) in table {
+                        let at_line = |e: ParseProfileError| ParseProfileError {
+                            line: doc.line(name, key),
+                            ..e
+                        };
+                        let ms = value
+                            .as_f64()
+                            .filter(|ms| *ms >= 0.0)
+                            .ok_or_else(|| at_line(expected(key, "a number of milliseconds")))?;
+                        let duration = Duration::try_from_secs_f64(ms / 1000.0).map_err(|_| {
+                            at_line(ParseProfileError::new(format!("`{key}` is too long")))
+                        })?;
+                        profile.timing_overrides.set(key.clone(), duration);
+                    }
+                }
+                ["regions"] => {
+                    for (key, value) in table {
+                        let region = parse_bounds(value).ok_or_else(|| expected(key, BOUNDS))?;
+                        profile.regions.insert(key.clone(), region);
+                    }
+                }
+                // Read once every region is known
+                ["detections", _] => {}
+                ["game"] => {
+                    for (key, value) in table {
+                        let text = match value {
+                            Value::String(s) => s.clone(),
+                            other => other.to_string(),
+                        };
+                        profile.game.insert(key.clone(), text);
+                    }
+                }
+                _ => {
+                    return Err(ParseProfileError::new(format!("unknown table [{name}]")));
+                }
+            }
+        }
+        if calibrated {
+            profile.calibration = Some(calibration);
+        }
+        for (name, table) in doc.tables() {
+            if let Some(detection) = name.strip_prefix("detections.") {
+                let parsed = parse_detection(table, name, &profile.regions)?;
+                profile.detections.insert(detection.to_string(), parsed);
+            }
+        }
+        Ok(profile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_round_trips() {
+        let text = r#"
+name = "splatoon-aim"
+confirm = "b"
+
+[remap]
+invert_vertical = ["r_stick"]
+
+[remap.buttons]
+zr = "r"
+
+[curve.r_stick]
+exponent = 2
+
+[calibration.l_stick.horizontal]
+center = 0.1
+deadzone = 0.05
+
+[timings]
+text_speed = 120
+
+[regions]
+sparkle = [40, 10, 20, 15.5]
+
+[detections.battle]
+method = "color"
+region = [0, 85, 100, 15]
+color = [230, 60, 40]
+
+[detections.shiny]
+method = "template"
+template = "pokemon/shiny_sparkle"
+region = "sparkle"
+
+[game]
+sensitivity = 3
+mode = "turf war"
+"#;
+        let profile: Profile = text.parse().unwrap();
+        assert_eq!(profile.name, "splatoon-aim");
+        assert_eq!(profile.confirm, ConfirmButton::B);
+        assert_eq!(profile.remap.map_button(Button::ZR), Button::R);
+        assert_eq!(
+            profile.remap.map_stick(Stick::Right, 0.5, 0.5),
+            (Stick::Right, 0.5, -0.5)
+        );
+        assert_eq!(profile.right_curve.exponent, 2.0);
+        let calibration = profile.calibration.unwrap();
+        assert_eq!(calibration.left.horizontal.center, 0.1);
+        assert_eq!(calibration.left.horizontal.max, 1.0);
+        assert_eq!(
+            profile.timings()[Timings::TEXT_SPEED],
+            Duration::from_millis(120)
+        );
+        assert_eq!(
+            profile.timings()[Timings::LOAD_SCREEN],
+            Timings::new()[Timings::LOAD_SCREEN]
+        );
+        assert_eq!(
+            profile.regions["sparkle"],
+            Region::new(40.0, 10.0, 20.0, 15.5).unwrap()
+        );
+        assert_eq!(
+            profile.detections["battle"],
+            Detection::Color {
+                region: Region::new(0.0, 85.0, 100.0, 15.0).unwrap(),
+                color: [230, 60, 40],
+                tolerance: Detection::DEFAULT_TOLERANCE,
+            }
+        );
+        assert_eq!(
+            profile.detections["shiny"].region(),
+            &profile.regions["sparkle"]
+        );
+        assert_eq!(profile.game["sensitivity"], "3");
+        assert_eq!(profile.game["mode"], "turf war");
+
+        let again: Profile = profile.to_toml().parse().unwrap();
+        assert_eq!(again.to_toml(), profile.to_toml());
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        let err = "[remap]\nswap = true".parse::<Profile>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid profile: unknown key `swap` in [remap]"
+        );
+        assert!("[remap.buttons]\na = \"q\"".parse::<Profile>().is_err());
+        assert!("[curve.c_stick]".parse::<Profile>().is_err());
+        assert!("[regions]\nx = [90, 0, 20, 10]".parse::<Profile>().is_err());
+        assert!("[regions]\nx = [0, 0, 10]".parse::<Profile>().is_err());
+        let err = "[detections.x]\nmethod = \"color\"\nregion = \"nope\""
+            .parse::<Profile>()
+            .unwrap_err();
+        assert_eq!(err.to_string(), "invalid profile: unknown region `nope`");
+        assert!(
+            "[detections.x]\nmethod = \"sift\""
+                .parse::<Profile>()
+                .is_err()
+        );
+        assert!(
+            "[detections.x]\nmethod = \"histogram\""
+                .parse::<Profile>()
+                .is_err()
+        );
+        let err = "[timings]\nmenu = 100\nload_screen = 1e30"
+            .parse::<Profile>()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid profile: line 3: `load_screen` is too long"
+        );
+    }
+}
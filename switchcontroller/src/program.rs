@@ -0,0 +1,307 @@
+use std::io;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::dsl::Op;
+use crate::{
+    InputSequence, Issue, IssueKind, ParseScriptError, ScriptLoader, Step, SwitchController,
+    Validation,
+};
+
+/// One instruction of a [`Program`].
+#[derive(Debug, Clone)]
+pub enum Instruction {
+    Step(Step),
+    /// Continue at the instruction at this index.
+    Goto(usize),
+    /// Continue at `target` if the detector finds `template` on screen.
+    IfDetected {
+        template: String,
+        target: usize,
+    },
+}
+
+/// A macro script with control flow: `label`, `goto` and
+/// `if detected("template") goto label`, for closed-loop bots that react to
+/// what is on screen.
+///
+/// ```text
+/// label top
+/// press a
+/// wait 2s
+/// if detected("shiny.png") goto found
+/// goto top
+/// label found
+/// press capture
+/// ```
+///
+/// Load one with [`ScriptLoader::load_program`] or `parse`, and run it with
+/// [`SwitchController::run_program`]. Jumps go to the first `label` (or
+/// `mark`) with their name.
+#[derive(Debug, Clone, Default)]
+pub struct Program {
+    instructions: Vec<Instruction>,
+}
+
+impl Program {
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    /// The index of the label (or marker) called `name`.
+    pub fn label(&self, name: &str) -> Option<usize> {
+        self.instructions
+            .iter()
+            .position(|i| matches!(i, Instruction::Step(Step::Marker(n)) if n == name))
+    }
+
+    /// Whether the program contains any jumps.
+    pub fn has_jumps(&self) -> bool {
+        self.instructions
+            .iter()
+            .any(|i| !matches!(i, Instruction::Step(_)))
+    }
+
+    /// The program as a plain sequence, or `None` if it has jumps.
+    pub fn to_sequence(&self) -> Option<InputSequence> {
+        self.instructions
+            .iter()
+            .map(|i| match i {
+                Instruction::Step(step) => Some(step.clone()),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(InputSequence::from)
+    }
+
+    /// Check the program for problems: everything
+    /// [`InputSequence::validate`] finds in its steps, read top to bottom,
+    /// plus labels that can never be reached. Issue indices are instruction
+    /// indices, and the duration is that of one pass from top to bottom.
+    pub fn validate(&self) -> Validation {
+        let (indices, steps): (Vec<usize>, Vec<Step>) = self
+            .instructions
+            .iter()
+            .enumerate()
+            .filter_map(|(i, instruction)| match instruction {
+                Instruction::Step(step) => Some((i, step.clone())),
+                _ => None,
+            })
+            .unzip();
+        let mut validation = InputSequence::from(steps).validate();
+        for issue in &mut validation.issues {
+            issue.step = indices[issue.step];
+        }
+
+        let mut reachable = vec![false; self.instructions.len()];
+        let mut pending = vec![0];
+        while let Some(i) = pending.pop() {
+            if i >= reachable.len() || reachable[i] {
+                continue;
+            }
+            reachable[i] = true;
+            match &self.instructions[i] {
+                Instruction::Step(_) => pending.push(i + 1),
+                Instruction::Goto(target) => pending.push(*target),
+                Instruction::IfDetected { target, .. } => pending.extend([i + 1, *target]),
+            }
+        }
+        for (i, instruction) in self.instructions.iter().enumerate() {
+            if let Instruction::Step(Step::Marker(name)) = instruction
+                && !reachable[i]
+            {
+                validation.issues.push(Issue {
+                    step: i,
+                    kind: IssueKind::UnreachableLabel(name.clone()),
+                });
+            }
+        }
+        validation.issues.sort_by_key(|issue| issue.step);
+        validation
+    }
+
+    /// Resolve the labels jumped to by `ops`.
+    pub(crate) fn from_ops(ops: Vec<Op>) -> Result<Self, ParseScriptError> {
+        let labels: Vec<Option<&str>> = ops
+            .iter()
+            .map(|op| match op {
+                Op::Step(Step::Marker(name)) => Some(name.as_str()),
+                _ => None,
+            })
+            .collect();
+        let resolve = |label: &str, line: usize| {
+            labels
+                .iter()
+                .position(|l| *l == Some(label))
+                .ok_or_else(|| ParseScriptError {
+                    line,
+                    message: format!("unknown label `{label}`"),
+                })
+        };
+        let instructions = ops
+            .iter()
+            .map(|op| {
+                Ok(match op {
+                    Op::Step(step) => Instruction::Step(step.clone()),
+                    Op::Goto { label, line } => Instruction::Goto(resolve(label, *line)?),
+                    Op::IfDetected {
+                        template,
+                        label,
+                        line,
+                    } => Instruction::IfDetected {
+                        template: template.clone(),
+                        target: resolve(label, *line)?,
+                    },
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { instructions })
+    }
+}
+
+impl From<InputSequence> for Program {
+    fn from(sequence: InputSequence) -> Self {
+        Self {
+            instructions: sequence
+                .steps()
+                .iter()
+                .cloned()
+                .map(Instruction::Step)
+                .collect(),
+        }
+    }
+}
+
+impl FromStr for Program {
+    type Err = ParseScriptError;
+
+    /// Parse a script with jumps, resolving includes from the working
+    /// directory.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ScriptLoader::new().parse_program(s)
+    }
+}
+
+impl SwitchController {
+    /// Run `program` until it ends or `cancel` is set.
+    ///
+    /// `detect` is asked whether a template is on screen at each
+    /// `if detected(..)`, and is where a capture or image-matching library
+    /// plugs in. Programs that loop forever run until cancelled.
+    pub fn run_program(
+        &mut self,
+        program: &Program,
+        mut detect: impl FnMut(&str) -> bool,
+        cancel: &AtomicBool,
+    ) -> io::Result<()> {
+        let mut pc = Some(0);
+        while let Some(index) = pc {
+            if cancel.load(Ordering::Relaxed) {
+                break;
+            }
+            pc = self.step_program(program, index, &mut detect)?;
+        }
+        Ok(())
+    }
+
+    /// Execute the instruction at `index` of `program` and return the index
+    /// of the next one, or `None` once the program has ended. For driving a
+    /// program one instruction at a time, e.g. to pause between steps.
+    pub fn step_program(
+        &mut self,
+        program: &Program,
+        index: usize,
+        mut detect: impl FnMut(&str) -> bool,
+    ) -> io::Result<Option<usize>> {
+        let next = match program.instructions.get(index) {
+            None => return Ok(None),
+            Some(Instruction::Step(step)) => {
+                self.run_step(step)?;
+                index + 1
+            }
+            Some(Instruction::Goto(target)) => *target,
+            Some(Instruction::IfDetected { template, target }) => {
+                if detect(template) {
+                    *target
+                } else {
+                    index + 1
+                }
+            }
+        };
+        Ok((next < program.instructions.len()).then_some(next))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Shared;
+
+    #[test]
+    fn jumps_resolve_to_labels() {
+        let program: Program = "label top\npress a\nif detected(\"shiny.png\") goto found\n\
+             goto top\nlabel found\npress capture"
+            .parse()
+            .unwrap();
+        assert!(program.has_jumps());
+        assert!(program.to_sequence().is_none());
+        assert_eq!(program.label("found"), Some(4));
+        assert!(matches!(
+            &program.instructions()[2],
+            Instruction::IfDetected { template, target: 4 } if template == "shiny.png"
+        ));
+        assert!(matches!(program.instructions()[3], Instruction::Goto(0)));
+
+        let err = "press a\ngoto nowhere".parse::<Program>().unwrap_err();
+        assert_eq!(
+            (err.line, err.message.as_str()),
+            (2, "unknown label `nowhere`")
+        );
+        assert!("if detected(shiny) goto x".parse::<Program>().is_err());
+        let err = "goto top".parse::<InputSequence>().unwrap_err();
+        assert!(err.message.contains("load_program"));
+    }
+
+    #[test]
+    fn finds_unreachable_labels() {
+        let program: Program = "label top\nhold a\ngoto top\nlabel dead\nhold a\nlabel end"
+            .parse()
+            .unwrap();
+        let issues: Vec<_> = program
+            .validate()
+            .issues
+            .into_iter()
+            .map(|i| (i.step, i.kind))
+            .collect();
+        assert_eq!(
+            issues,
+            [
+                (3, IssueKind::UnreachableLabel("dead".into())),
+                (4, IssueKind::AlreadyHeld(crate::Button::A)),
+                (5, IssueKind::UnreachableLabel("end".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn runs_until_detected() {
+        let out = Shared::default();
+        let mut ctrl = SwitchController::from_writer(out.clone());
+        let program: Program = "label top\npress a\nif detected(\"done\") goto end\n\
+             goto top\nlabel end\npress b"
+            .parse()
+            .unwrap();
+        let mut calls = 0;
+        ctrl.run_program(
+            &program,
+            |template| {
+                assert_eq!(template, "done");
+                calls += 1;
+                calls == 3
+            },
+            &AtomicBool::new(false),
+        )
+        .unwrap();
+        assert_eq!(out.text(), "PRESS a\nPRESS a\nPRESS a\nPRESS b\n");
+    }
+}
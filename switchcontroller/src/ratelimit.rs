@@ -0,0 +1,178 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::SwitchController;
+use crate::playback::sleep_until;
+
+/// What happens to a command sent sooner than [`RateLimit::min_interval`]
+/// after the previous one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RateLimitPolicy {
+    /// Wait until the interval has passed, then send.
+    #[default]
+    Block,
+    /// Queue the command and return immediately. Queued commands go out, in
+    /// order and spaced by the interval, on [`SwitchController::pump`] (also
+    /// run by every later send) or [`SwitchController::flush`], which waits
+    /// for the whole queue. The queue is bounded by
+    /// [`RateLimit::queue_capacity`].
+    Queue,
+    /// Fail with [`io::ErrorKind::WouldBlock`] without sending.
+    Error,
+}
+
+/// What a [`RateLimitPolicy::Queue`] send does when the queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backpressure {
+    /// Wait for the oldest queued command to go out, making room.
+    #[default]
+    Wait,
+    /// Fail with an [`io::Error`] wrapping [`QueueFull`].
+    Error,
+}
+
+/// The error inside the [`io::Error`] returned when the outgoing queue is
+/// full and [`Backpressure::Error`] is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueFull {
+    pub capacity: usize,
+}
+
+impl fmt::Display for QueueFull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "outgoing command queue full ({} commands)",
+            self.capacity
+        )
+    }
+}
+
+impl Error for QueueFull {}
+
+/// A minimum gap enforced between commands, so bursty code can't overflow
+/// the device's input buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub min_interval: Duration,
+    pub policy: RateLimitPolicy,
+    /// Most commands [`RateLimitPolicy::Queue`] holds at once. A bounded
+    /// queue surfaces latency problems instead of hiding seconds of backlog.
+    pub queue_capacity: usize,
+    pub backpressure: Backpressure,
+}
+
+impl RateLimit {
+    pub fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            policy: RateLimitPolicy::default(),
+            queue_capacity: 64,
+            backpressure: Backpressure::default(),
+        }
+    }
+
+    pub fn policy(mut self, policy: RateLimitPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn queue_capacity(mut self, capacity: usize) -> Self {
+        self.queue_capacity = capacity;
+        self
+    }
+
+    pub fn backpressure(mut self, backpressure: Backpressure) -> Self {
+        self.backpressure = backpressure;
+        self
+    }
+}
+
+impl SwitchController {
+    /// Enforce a minimum interval between commands, or remove the limit with
+    /// `None`. There is no limit by default.
+    ///
+    /// Removing a limit leaves queued commands queued until the next
+    /// [`SwitchController::flush`].
+    pub fn set_rate_limit(&mut self, limit: Option<RateLimit>) -> &mut Self {
+        self.rate_limit = limit;
+        self
+    }
+
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.rate_limit
+    }
+
+    /// Number of commands waiting under [`RateLimitPolicy::Queue`].
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Send every queued command whose turn has come, and release
+    /// [`SwitchController::hold_timed`] buttons that are due, without waiting.
+    pub fn pump(&mut self) -> io::Result<()> {
+        self.release_timed(Some(Instant::now()))?;
+        while !self.queue.is_empty() && Instant::now() >= self.next_slot() {
+            let cmd = self.queue.pop_front().unwrap();
+            self.send_now(&cmd)?;
+        }
+        Ok(())
+    }
+
+    /// Send every queued command, waiting for each one's turn.
+    pub(crate) fn drain_queue(&mut self) -> io::Result<()> {
+        while let Some(cmd) = self.queue.pop_front() {
+            sleep_until(self.next_slot());
+            self.send_now(&cmd)?;
+        }
+        Ok(())
+    }
+
+    /// Earliest time the next command may go out.
+    fn next_slot(&self) -> Instant {
+        let gap = self.rate_limit.map_or(Duration::ZERO, |l| l.min_interval);
+        self.last_sent + gap
+    }
+
+    /// Send `cmd`, applying the rate limit.
+    pub(crate) fn send_limited(&mut self, cmd: &str) -> io::Result<()> {
+        let Some(limit) = self.rate_limit else {
+            return self.send_now(cmd);
+        };
+        if limit.policy == RateLimitPolicy::Queue {
+            self.pump()?;
+        }
+        if self.queue.is_empty() && Instant::now() >= self.next_slot() {
+            return self.send_now(cmd);
+        }
+        match limit.policy {
+            RateLimitPolicy::Block => {
+                sleep_until(self.next_slot());
+                self.send_now(cmd)
+            }
+            RateLimitPolicy::Queue => {
+                while self.queue.len() >= limit.queue_capacity.max(1) {
+                    if limit.backpressure == Backpressure::Error {
+                        return Err(io::Error::other(QueueFull {
+                            capacity: limit.queue_capacity,
+                        }));
+                    }
+                    let oldest = self.queue.pop_front().unwrap();
+                    sleep_until(self.next_slot());
+                    self.send_now(&oldest)?;
+                }
+                self.queue.push_back(cmd.to_string());
+                Ok(())
+            }
+            RateLimitPolicy::Error => Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                format!(
+                    "command sent less than {:?} after the previous one",
+                    limit.min_interval
+                ),
+            )),
+        }
+    }
+}
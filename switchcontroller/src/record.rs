@@ -0,0 +1,89 @@
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+
+use crate::script::parse_line;
+use crate::{InputSequence, SentCommand, SwitchController};
+
+/// Captures the commands a controller sends, with their timing, as an
+/// [`InputSequence`] for later replay or editing.
+pub struct Recorder {
+    commands: Receiver<SentCommand>,
+    /// Markers added with [`Recorder::mark`], as `MARK` lines.
+    marks: Vec<SentCommand>,
+}
+
+impl Recorder {
+    /// Start recording everything `ctrl` sends from now on.
+    pub fn start(ctrl: &mut SwitchController) -> Self {
+        Self {
+            commands: ctrl.subscribe_commands(),
+            marks: Vec::new(),
+        }
+    }
+
+    /// Insert a [`Step::Marker`](crate::Step::Marker) at the current point of
+    /// the recording.
+    pub fn mark(&mut self, name: &str) {
+        self.marks.push(SentCommand {
+            command: format!("MARK {name}"),
+            at: Instant::now(),
+        });
+    }
+
+    /// Stop recording and return the session as a sequence, with the gaps
+    /// between commands as waits. The idle time before the first command is
+    /// not included.
+    pub fn stop(self) -> InputSequence {
+        let mut commands: Vec<_> = self.commands.try_iter().collect();
+        commands.extend(self.marks);
+        commands.sort_by_key(|sent| sent.at);
+        to_sequence(&commands)
+    }
+}
+
+fn to_sequence(commands: &[SentCommand]) -> InputSequence {
+    let mut seq = InputSequence::new();
+    let mut last = commands.first().map(|sent| sent.at);
+    for sent in commands {
+        // Commands come from the controller itself and markers from `mark`,
+        // so they always parse.
+        let Ok(Some(step)) = parse_line(&sent.command) else {
+            continue;
+        };
+        let gap = last.map_or(Duration::ZERO, |last| {
+            sent.at.saturating_duration_since(last)
+        });
+        if gap > Duration::ZERO {
+            seq.wait(gap);
+        }
+        seq.push(step);
+        last = Some(sent.at);
+    }
+    seq
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    #[test]
+    fn recorded_timing() {
+        let start = Instant::now();
+        let sent = |ms, command: &str| SentCommand {
+            command: command.to_string(),
+            at: start + Duration::from_millis(ms),
+        };
+        let seq = to_sequence(&[
+            sent(100, "PRESS a"),
+            sent(400, "HOLD b"),
+            sent(400, "MARK held"),
+            sent(400, "RELEASE b"),
+        ]);
+        assert_eq!(
+            seq.to_script(),
+            "PRESS a\nWAIT 300ms\nHOLD b\nMARK held\nRELEASE b\n"
+        );
+    }
+}
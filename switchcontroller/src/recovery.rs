@@ -0,0 +1,163 @@
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::{ControllerState, SwitchController};
+
+/// What to do about a failed write, as decided by an [`OnWriteError::Callback`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorAction {
+    /// Return the error to the caller.
+    Fail,
+    /// Write the command once more on the same port.
+    Retry,
+    /// Reopen the port, then write the command again.
+    Reconnect,
+    /// Send a neutral state (best effort), then return the error.
+    Neutralize,
+}
+
+/// What the controller does when writing a command fails.
+///
+/// Set with [`SwitchController::set_error_policy`]. The policy applies to every
+/// command, including those sent during sequence playback, so callers don't
+/// need their own recovery around each call.
+#[derive(Clone, Default)]
+pub enum OnWriteError {
+    /// Return the error to the caller.
+    #[default]
+    Fail,
+    /// Reopen the port up to `attempts` times, `delay` apart, and resend the
+    /// failed command. Only possible for controllers created with
+    /// [`SwitchController::open`]; others fail as with [`OnWriteError::Fail`].
+    ReconnectAndResume { attempts: u32, delay: Duration },
+    /// Send a neutral state so nothing stays held (best effort), then return
+    /// the error.
+    NeutralizeAndAbort,
+    /// Let the application decide, per error.
+    Callback(Arc<dyn Fn(&io::Error) -> ErrorAction + Send + Sync>),
+}
+
+impl fmt::Debug for OnWriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OnWriteError::Fail => f.write_str("Fail"),
+            OnWriteError::ReconnectAndResume { attempts, delay } => f
+                .debug_struct("ReconnectAndResume")
+                .field("attempts", attempts)
+                .field("delay", delay)
+                .finish(),
+            OnWriteError::NeutralizeAndAbort => f.write_str("NeutralizeAndAbort"),
+            OnWriteError::Callback(_) => f.write_str("Callback(..)"),
+        }
+    }
+}
+
+impl SwitchController {
+    pub fn set_error_policy(&mut self, policy: OnWriteError) -> &mut Self {
+        self.on_write_error = policy;
+        self
+    }
+
+    pub fn error_policy(&self) -> &OnWriteError {
+        &self.on_write_error
+    }
+
+    /// Reopen the serial port this controller was opened with.
+    ///
+    /// Fails with [`io::ErrorKind::Unsupported`] for controllers created with
+    /// `from_port` or [`SwitchController::from_writer`].
+    pub fn reconnect(&mut self) -> io::Result<()> {
+        #[cfg(feature = "serial")]
+        if let Some((path, baud_rate)) = self.reopen.clone() {
+            self.port = Box::new(crate::port::open_port(&path, baud_rate)?);
+            self.last_state = None;
+            return Ok(());
+        }
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "controller was not opened by path",
+        ))
+    }
+
+    /// Apply the error policy to a failed write of `cmd`. An error still
+    /// returned carries the recent commands, as a
+    /// [`WriteError`](crate::WriteError).
+    pub(crate) fn recover(&mut self, cmd: &str, error: io::Error) -> io::Result<()> {
+        self.apply_policy(cmd, error)
+            .map_err(|e| self.with_history(e))
+    }
+
+    fn apply_policy(&mut self, cmd: &str, error: io::Error) -> io::Result<()> {
+        let action = match &self.on_write_error {
+            OnWriteError::Fail => ErrorAction::Fail,
+            OnWriteError::ReconnectAndResume { attempts, delay } => {
+                let (attempts, delay) = (*attempts, *delay);
+                for attempt in 0..attempts {
+                    if attempt > 0 {
+                        thread::sleep(delay);
+                    }
+                    if self.reconnect().is_ok() && self.write_now(cmd).is_ok() {
+                        return Ok(());
+                    }
+                }
+                return Err(error);
+            }
+            OnWriteError::NeutralizeAndAbort => ErrorAction::Neutralize,
+            OnWriteError::Callback(callback) => callback(&error),
+        };
+        match action {
+            ErrorAction::Fail => Err(error),
+            ErrorAction::Retry => self.write_now(cmd),
+            ErrorAction::Reconnect => {
+                self.reconnect()?;
+                self.write_now(cmd)
+            }
+            ErrorAction::Neutralize => {
+                let _ = self.write_now(&ControllerState::neutral().to_command());
+                // Whether or not it arrived, nothing should count as held
+                // any more, and the next state must not be skipped as a
+                // duplicate.
+                self.held = ControllerState::neutral();
+                self.last_state = None;
+                Err(error)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Shared;
+    use crate::{Button, Stick};
+
+    /// Fails every `STICK` command, passing the rest through.
+    struct NoSticks(Shared);
+
+    impl io::Write for NoSticks {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if buf.starts_with(b"STICK") {
+                return Err(io::ErrorKind::BrokenPipe.into());
+            }
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn neutralizing_resets_held_state() {
+        let out = Shared::default();
+        let mut ctrl = SwitchController::from_writer(NoSticks(out.clone()));
+        ctrl.set_error_policy(OnWriteError::NeutralizeAndAbort);
+        ctrl.hold(&[Button::A]).unwrap();
+        assert!(ctrl.stick(Stick::Left, 1.0, 0.0).is_err());
+        assert_eq!(ctrl.snapshot(), ControllerState::neutral());
+        assert!(out.text().ends_with("\nSTATE 000000000000000000 0 0 0 0\n"));
+    }
+}
@@ -0,0 +1,81 @@
+/// A rectangle on screen in percent of the capture's width and height, so
+/// detections keep working when the capture resolution changes.
+///
+/// Profiles name them in a `[regions]` table as `[x, y, width, height]`:
+///
+/// ```toml
+/// [regions]
+/// dialog = [5, 75, 90, 20]
+/// sparkle = [40, 10, 20, 15]
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Region {
+    /// The whole screen.
+    pub const FULL: Region = Region {
+        x: 0.0,
+        y: 0.0,
+        width: 100.0,
+        height: 100.0,
+    };
+
+    /// A region from percentages, or `None` if it is empty or does not fit
+    /// on screen.
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Option<Self> {
+        let fits = |start: f32, len: f32| {
+            start >= 0.0 && len > 0.0 && start + len <= 100.0 + f32::EPSILON * 100.0
+        };
+        (fits(x, width) && fits(y, height)).then_some(Self {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+
+    /// The region for a `width` x `height` pixel capture, as
+    /// `(x, y, width, height)` in pixels. Edges round to the nearest pixel,
+    /// and the result is at least one pixel in each direction.
+    pub fn to_pixels(&self, (width, height): (u32, u32)) -> (u32, u32, u32, u32) {
+        let edge = |percent: f32, size: u32| {
+            ((percent as f64 / 100.0 * size as f64).round() as u32).min(size)
+        };
+        let (left, top) = (edge(self.x, width), edge(self.y, height));
+        let right = edge(self.x + self.width, width).max(left + 1).min(width);
+        let bottom = edge(self.y + self.height, height).max(top + 1).min(height);
+        let (left, top) = (left.min(right - 1), top.min(bottom - 1));
+        (left, top, right - left, bottom - top)
+    }
+
+    /// Whether the point at `x`, `y` percent lies in the region.
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scales_to_any_resolution() {
+        let region = Region::new(40.0, 10.0, 20.0, 15.0).unwrap();
+        assert_eq!(region.to_pixels((1920, 1080)), (768, 108, 384, 162));
+        assert_eq!(region.to_pixels((1280, 720)), (512, 72, 256, 108));
+        assert_eq!(Region::FULL.to_pixels((640, 480)), (0, 0, 640, 480));
+        let tiny = Region::new(99.9, 99.9, 0.1, 0.1).unwrap();
+        assert_eq!(tiny.to_pixels((100, 100)), (99, 99, 1, 1));
+        assert!(region.contains(50.0, 20.0));
+        assert!(!region.contains(60.0, 20.0));
+
+        assert!(Region::new(90.0, 0.0, 20.0, 10.0).is_none());
+        assert!(Region::new(0.0, 0.0, 0.0, 10.0).is_none());
+        assert!(Region::new(-1.0, 0.0, 10.0, 10.0).is_none());
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::io;
+
+use crate::{Button, Command, ControllerState, InputSequence, Layer, Next, Step, Stick};
+
+/// A button and stick mapping applied with [`InputSequence::remap`], or to
+/// every command as a [`Layer`].
+///
+/// Buttons without an explicit mapping map to themselves.
+#[derive(Debug, Clone, Default)]
+pub struct Remap {
+    pub(crate) buttons: HashMap<Button, Button>,
+    pub(crate) swap_sticks: bool,
+    /// Axis inversion as `[left h, left v, right h, right v]`, applied after any swap.
+    pub(crate) invert: [bool; 4],
+}
+
+impl Remap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mirror left and right: swaps the D-pad left/right buttons and inverts
+    /// the horizontal axis of both sticks.
+    pub fn mirror() -> Self {
+        let mut remap = Self::new();
+        remap
+            .swap(Button::DpadLeft, Button::DpadRight)
+            .invert_horizontal(Stick::Left)
+            .invert_horizontal(Stick::Right);
+        remap
+    }
+
+    /// Swap A and B, for games that use B to confirm.
+    pub fn swap_confirm() -> Self {
+        let mut remap = Self::new();
+        remap.swap(Button::A, Button::B);
+        remap
+    }
+
+    /// Send `to` wherever `from` appears.
+    pub fn button(&mut self, from: Button, to: Button) -> &mut Self {
+        self.buttons.insert(from, to);
+        self
+    }
+
+    /// Exchange two buttons.
+    pub fn swap(&mut self, a: Button, b: Button) -> &mut Self {
+        self.button(a, b).button(b, a)
+    }
+
+    /// Exchange the left and right sticks (positions and clicks).
+    pub fn swap_sticks(&mut self) -> &mut Self {
+        self.swap_sticks = true;
+        self.swap(Button::LStick, Button::RStick)
+    }
+
+    /// Invert the horizontal axis of a stick.
+    pub fn invert_horizontal(&mut self, stick: Stick) -> &mut Self {
+        self.invert[Self::axis(stick)] ^= true;
+        self
+    }
+
+    /// Invert the vertical axis of a stick.
+    pub fn invert_vertical(&mut self, stick: Stick) -> &mut Self {
+        self.invert[Self::axis(stick) + 1] ^= true;
+        self
+    }
+
+    fn axis(stick: Stick) -> usize {
+        match stick {
+            Stick::Left => 0,
+            Stick::Right => 2,
+        }
+    }
+
+    /// The button `button` is mapped to.
+    pub fn map_button(&self, button: Button) -> Button {
+        self.buttons.get(&button).copied().unwrap_or(button)
+    }
+
+    /// The stick and position a stick input is mapped to.
+    pub fn map_stick(&self, stick: Stick, horizontal: f32, vertical: f32) -> (Stick, f32, f32) {
+        let stick = match (stick, self.swap_sticks) {
+            (Stick::Left, true) => Stick::Right,
+            (Stick::Right, true) => Stick::Left,
+            (stick, false) => stick,
+        };
+        let axis = Self::axis(stick);
+        let h = if self.invert[axis] {
+            -horizontal
+        } else {
+            horizontal
+        };
+        let v = if self.invert[axis + 1] {
+            -vertical
+        } else {
+            vertical
+        };
+        (stick, h, v)
+    }
+
+    fn map_buttons(&self, buttons: &[Button]) -> Vec<Button> {
+        buttons.iter().map(|&b| self.map_button(b)).collect()
+    }
+
+    /// Apply this mapping to a controller state.
+    pub fn map_state(&self, state: &ControllerState) -> ControllerState {
+        let mut mapped = ControllerState::new();
+        for &b in Button::ALL.iter().filter(|&&b| state.button(b)) {
+            mapped.set_button(self.map_button(b), true);
+        }
+        let sticks = [
+            (Stick::Left, state.left_stick),
+            (Stick::Right, state.right_stick),
+        ];
+        for (stick, position) in sticks {
+            if let Some((h, v)) = position {
+                match self.map_stick(stick, h, v) {
+                    (Stick::Left, h, v) => mapped.set_left_stick(h, v),
+                    (Stick::Right, h, v) => mapped.set_right_stick(h, v),
+                };
+            }
+        }
+        mapped
+    }
+
+    fn map_step(&self, step: &Step) -> Step {
+        match step {
+            Step::Press(buttons) => Step::Press(self.map_buttons(buttons)),
+            Step::Hold(buttons) => Step::Hold(self.map_buttons(buttons)),
+            Step::Release(buttons) => Step::Release(self.map_buttons(buttons)),
+            Step::Stick(stick, h, v) => {
+                let (stick, h, v) = self.map_stick(*stick, *h, *v);
+                Step::Stick(stick, h, v)
+            }
+            Step::State(state) => Step::State(self.map_state(state)),
+            Step::Wait(d) => Step::Wait(*d),
+            Step::Marker(name) => Step::Marker(name.clone()),
+        }
+    }
+
+    fn map_command(&self, command: Command) -> Command {
+        match command {
+            Command::Press(buttons) => Command::Press(self.map_buttons(&buttons)),
+            Command::Hold(buttons) => Command::Hold(self.map_buttons(&buttons)),
+            Command::Release(buttons) => Command::Release(self.map_buttons(&buttons)),
+            Command::Stick(stick, h, v) => {
+                let (stick, h, v) = self.map_stick(stick, h, v);
+                Command::Stick(stick, h, v)
+            }
+            Command::State(state) => Command::State(self.map_state(&state)),
+            command => command,
+        }
+    }
+}
+
+impl Layer for Remap {
+    fn handle(&mut self, command: Command, next: &mut Next<'_>) -> io::Result<()> {
+        next(self.map_command(command))
+    }
+}
+
+impl InputSequence {
+    /// This sequence with every button and stick input passed through `remap`.
+    pub fn remap(&self, remap: &Remap) -> InputSequence {
+        self.steps()
+            .iter()
+            .map(|step| remap.map_step(step))
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SwitchController;
+    use crate::tests::Shared;
+
+    #[test]
+    fn mirror_flips_horizontal_inputs() {
+        let mut seq = InputSequence::new();
+        seq.press(&[Button::DpadLeft, Button::A])
+            .stick(Stick::Left, 0.5, 1.0);
+
+        let mirrored = seq.remap(&Remap::mirror());
+        let commands: Vec<_> = mirrored
+            .steps()
+            .iter()
+            .filter_map(Step::to_command)
+            .collect();
+        assert_eq!(commands, ["PRESS dpad_right a", "STICK l_stick -0.5 1"]);
+
+        let out = Shared::default();
+        let mut ctrl = SwitchController::from_writer(out.clone());
+        ctrl.add_layer(Remap::mirror());
+        ctrl.play(&seq).unwrap();
+        assert_eq!(out.text(), "PRESS dpad_right a\nSTICK l_stick -0.5 1\n");
+    }
+
+    #[test]
+    fn swap_sticks_moves_state_sticks() {
+        let mut remap = Remap::swap_confirm();
+        remap.swap_sticks();
+
+        let mut state = ControllerState::new();
+        state
+            .set_button(Button::A, true)
+            .set_button(Button::LStick, true);
+        state.set_left_stick(1.0, 0.0);
+
+        let mapped = remap.map_state(&state);
+        assert!(mapped.button(Button::B));
+        assert!(mapped.button(Button::RStick));
+        assert!(!mapped.button(Button::A));
+        assert_eq!(mapped.left_stick, None);
+        assert_eq!(mapped.right_stick, Some((1.0, 0.0)));
+    }
+}
@@ -0,0 +1,427 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
+use std::time::{Duration, Instant};
+
+use crate::compress;
+
+/// Marks the start of a timestamp before a command line, and of the control
+/// lines a bridge understands.
+const STAMP: char = '@';
+
+/// Batches smaller than this are sent as they are, since compressing them
+/// saves little.
+const COMPRESS_THRESHOLD: usize = 1024;
+
+/// Largest batch a bridge accepts, after decompression.
+const MAX_FRAME: usize = 64 << 20;
+
+/// How batches sent to a bridge are compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    #[default]
+    None,
+    /// The LZ4 block format.
+    Lz4,
+}
+
+impl Compression {
+    /// Look up a compression by its handshake name, `"none"` or `"lz4"`.
+    pub fn from_name(name: &str) -> Option<Compression> {
+        match name {
+            "none" => Some(Compression::None),
+            "lz4" => Some(Compression::Lz4),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Compression::None => "none",
+            Compression::Lz4 => "lz4",
+        }
+    }
+}
+
+/// Offer `compression` to the bridge at the other end of `stream` and
+/// return what it agreed to: `compression` if the bridge supports it,
+/// otherwise [`Compression::None`]. Call before sending any command.
+///
+/// The client sends `@hello <compression>` and the bridge answers with
+/// `@hello <compression>` for the one it picked.
+pub fn handshake<S: io::Read + io::Write>(
+    stream: &mut S,
+    compression: Compression,
+) -> io::Result<Compression> {
+    writeln!(stream, "{STAMP}hello {}", compression.as_str())?;
+    stream.flush()?;
+    // Read byte by byte, so nothing after the reply is consumed.
+    let mut reply = Vec::new();
+    let mut byte = [0];
+    while reply.len() < 64 {
+        if stream.read(&mut byte)? == 0 || byte[0] == b'\n' {
+            break;
+        }
+        reply.push(byte[0]);
+    }
+    let reply = String::from_utf8_lossy(&reply);
+    reply
+        .trim_end()
+        .strip_prefix("@hello ")
+        .and_then(Compression::from_name)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unexpected handshake reply `{reply}`"),
+            )
+        })
+}
+
+/// Sends what is written between flushes as one batch, compressed if the
+/// bridge agreed to it in the [`handshake`] and the batch is large enough to
+/// benefit, such as a drawing job written with
+/// [`SwitchController::begin_batch`](crate::SwitchController::begin_batch).
+///
+/// A compressed batch is a line `@lz4 <compressed bytes> <bytes>` followed by
+/// the compressed data.
+#[derive(Debug)]
+pub struct CompressedWriter<W> {
+    inner: W,
+    compression: Compression,
+    batch: Vec<u8>,
+}
+
+impl<W: io::Write> CompressedWriter<W> {
+    pub fn new(inner: W, compression: Compression) -> Self {
+        Self {
+            inner,
+            compression,
+            batch: Vec::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: io::Write> io::Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.batch.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let batch = std::mem::take(&mut self.batch);
+        match self.compression {
+            Compression::Lz4 if batch.len() >= COMPRESS_THRESHOLD => {
+                let compressed = compress::compress(&batch);
+                writeln!(
+                    self.inner,
+                    "{STAMP}lz4 {} {}",
+                    compressed.len(),
+                    batch.len()
+                )?;
+                self.inner.write_all(&compressed)?;
+            }
+            _ => self.inner.write_all(&batch)?,
+        }
+        self.inner.flush()
+    }
+}
+
+/// Bridge-side reading of what a client sends: command lines, with
+/// compressed batches expanded back into lines and the [`handshake`]
+/// answered.
+#[derive(Debug)]
+pub struct FrameReader<R, W> {
+    inner: R,
+    /// Where handshake replies go, usually the other half of the stream.
+    reply: W,
+    /// Lines of a decompressed batch not yet returned.
+    pending: VecDeque<String>,
+}
+
+impl<R: BufRead, W: io::Write> FrameReader<R, W> {
+    pub fn new(inner: R, reply: W) -> Self {
+        Self {
+            inner,
+            reply,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// The next command line, without its newline, or `None` once the client
+    /// has disconnected.
+    ///
+    /// A malformed compressed batch fails with
+    /// [`io::ErrorKind::InvalidData`]; the stream can't be read further.
+    pub fn next_line(&mut self) -> io::Result<Option<String>> {
+        loop {
+            if let Some(line) = self.pending.pop_front() {
+                return Ok(Some(line));
+            }
+            let mut line = String::new();
+            if self.inner.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            let line = line.trim_end_matches(['\r', '\n']);
+            if let Some(offer) = line.strip_prefix("@hello ") {
+                let agreed = Compression::from_name(offer).unwrap_or_default();
+                writeln!(self.reply, "{STAMP}hello {}", agreed.as_str())?;
+                self.reply.flush()?;
+            } else if let Some(sizes) = line.strip_prefix("@lz4 ") {
+                self.read_batch(sizes)?;
+            } else {
+                return Ok(Some(line.to_string()));
+            }
+        }
+    }
+
+    /// Read and expand a compressed batch with the sizes given in its header.
+    fn read_batch(&mut self, sizes: &str) -> io::Result<()> {
+        let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, what.to_string());
+        let (compressed, len) = sizes
+            .split_once(' ')
+            .and_then(|(c, l)| Some((c.parse::<usize>().ok()?, l.parse::<usize>().ok()?)))
+            .filter(|&(c, l)| c <= MAX_FRAME && l <= MAX_FRAME)
+            .ok_or_else(|| invalid("invalid compressed batch header"))?;
+        let mut data = vec![0; compressed];
+        self.inner.read_exact(&mut data)?;
+        let batch =
+            compress::decompress(&data, len).ok_or_else(|| invalid("corrupt compressed batch"))?;
+        let text = String::from_utf8(batch).map_err(|_| invalid("compressed batch isn't text"))?;
+        self.pending.extend(text.lines().map(str::to_string));
+        Ok(())
+    }
+}
+
+/// Prefixes every command line written through it with when it was written,
+/// as `@<microseconds> <command>`, so a bridge running a [`JitterBuffer`] can
+/// play the commands back with the spacing they were sent with.
+///
+/// Wrap the network stream of a remote controller in one:
+/// `SwitchController::from_writer(TimestampedWriter::new(stream))`. The
+/// firmware itself doesn't accept timestamps; only write to a bridge.
+#[derive(Debug)]
+pub struct TimestampedWriter<W> {
+    inner: W,
+    start: Instant,
+    /// Whether the next byte starts a line.
+    line_start: bool,
+}
+
+impl<W: io::Write> TimestampedWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            start: Instant::now(),
+            line_start: true,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: io::Write> io::Write for TimestampedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let micros = self.start.elapsed().as_micros();
+        let mut out = Vec::with_capacity(buf.len() + 16);
+        for &byte in buf {
+            if self.line_start {
+                out.extend_from_slice(format!("{STAMP}{micros} ").as_bytes());
+            }
+            out.push(byte);
+            self.line_start = byte == b'\n';
+        }
+        self.inner.write_all(&out)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Bridge-side playback of timestamped commands from a
+/// [`TimestampedWriter`], smoothing out network jitter.
+///
+/// Each command is held back until `delay` after its send time, mapped onto
+/// the bridge's clock by the fastest arrival seen so far. Commands that
+/// arrive in a burst after a WiFi stall then still go out with the spacing
+/// they were sent with, as long as the stall was shorter than `delay`.
+/// Commands later than that go out at once, in order. Lines without a
+/// timestamp are due as soon as they arrive.
+#[derive(Debug, Clone)]
+pub struct JitterBuffer {
+    delay: Duration,
+    /// Arrival time and timestamp of the first timestamped command.
+    first: Option<(Instant, u64)>,
+    /// The smallest lateness seen, in microseconds, relative to the first
+    /// command.
+    skew: i64,
+    /// Commands waiting to go out, with when they are due.
+    frames: VecDeque<(Instant, String)>,
+}
+
+impl JitterBuffer {
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            first: None,
+            skew: 0,
+            frames: VecDeque::new(),
+        }
+    }
+
+    /// Queue a line as received from the network, without its newline.
+    pub fn push(&mut self, line: &str) -> io::Result<()> {
+        self.push_at(line, Instant::now())
+    }
+
+    /// Queue a line as if it arrived at `now`.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] for a line starting with `@`
+    /// but no valid timestamp.
+    pub fn push_at(&mut self, line: &str, now: Instant) -> io::Result<()> {
+        let Some(stamped) = line.strip_prefix(STAMP) else {
+            self.enqueue(now, line);
+            return Ok(());
+        };
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid timestamp in `{line}`"),
+            )
+        };
+        let (stamp, command) = stamped.split_once(' ').ok_or_else(invalid)?;
+        let stamp: u64 = stamp.parse().map_err(|_| invalid())?;
+        let &mut (arrival, start) = self.first.get_or_insert((now, stamp));
+
+        let elapsed = now.saturating_duration_since(arrival).as_micros() as i64;
+        let sent = stamp as i64 - start as i64;
+        self.skew = self.skew.min(elapsed - sent);
+        let due = sent + self.skew + self.delay.as_micros() as i64;
+        self.enqueue(arrival + Duration::from_micros(due.max(0) as u64), command);
+        Ok(())
+    }
+
+    /// Add a command due at `due`, or after the one before it if that is
+    /// later, so commands keep their order.
+    fn enqueue(&mut self, due: Instant, command: &str) {
+        let due = self.frames.back().map_or(due, |&(last, _)| due.max(last));
+        self.frames.push_back((due, command.to_string()));
+    }
+
+    /// The next command if it is due at `now`.
+    pub fn pop_due(&mut self, now: Instant) -> Option<String> {
+        match self.frames.front() {
+            Some(&(due, _)) if due <= now => self.frames.pop_front().map(|(_, c)| c),
+            _ => None,
+        }
+    }
+
+    /// When the next command is due.
+    pub fn next_due(&self) -> Option<Instant> {
+        self.frames.front().map(|&(due, _)| due)
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn compresses_large_batches() {
+        let mut writer = CompressedWriter::new(Vec::new(), Compression::Lz4);
+        let mut batch = String::new();
+        for i in 0..500 {
+            batch.push_str(&format!("STICK l_stick {} 1\n", i % 3));
+        }
+        writer.write_all(b"PRESS a\n").unwrap();
+        writer.flush().unwrap();
+        writer.write_all(batch.as_bytes()).unwrap();
+        writer.flush().unwrap();
+        let sent = writer.into_inner();
+        assert!(sent.starts_with(b"PRESS a\n@lz4 "));
+        assert!(sent.len() < batch.len() / 4);
+
+        let mut reply = Vec::new();
+        let mut input = b"@hello lz4\n".to_vec();
+        input.extend_from_slice(&sent);
+        let mut reader = FrameReader::new(&input[..], &mut reply);
+        let mut lines = Vec::new();
+        while let Some(line) = reader.next_line().unwrap() {
+            lines.push(line);
+        }
+        assert_eq!(lines.len(), 501);
+        assert_eq!(lines[1..].join("\n") + "\n", batch);
+        assert_eq!(reply, b"@hello lz4\n");
+
+        let reply = io::Cursor::new(b"@hello none\nPRESS a\n".to_vec());
+        let mut duplex = Duplex(reply, Vec::new());
+        assert_eq!(
+            handshake(&mut duplex, Compression::Lz4).unwrap(),
+            Compression::None
+        );
+        assert_eq!(duplex.1, b"@hello lz4\n");
+        assert_eq!(duplex.0.position(), 12);
+    }
+
+    /// Reads from one side and writes to the other.
+    struct Duplex(io::Cursor<Vec<u8>>, Vec<u8>);
+
+    impl io::Read for Duplex {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl io::Write for Duplex {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.1.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn replays_with_sent_spacing() {
+        let mut writer = TimestampedWriter::new(Vec::new());
+        writer.write_all(b"PRESS a\nSTICK l_stick 1 0\n").unwrap();
+        let text = String::from_utf8(writer.into_inner()).unwrap();
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with('@') && lines[0].ends_with(" PRESS a"));
+
+        let ms = Duration::from_millis;
+        let start = Instant::now();
+        let mut buffer = JitterBuffer::new(ms(50));
+        buffer.push_at("@0 PRESS a", start).unwrap();
+        // Sent 20 ms apart but stuck behind a 30 ms stall.
+        buffer.push_at("@20000 PRESS b", start + ms(50)).unwrap();
+        buffer.push_at("@40000 PRESS x", start + ms(50)).unwrap();
+        assert_eq!(buffer.next_due(), Some(start + ms(50)));
+        assert_eq!(buffer.pop_due(start + ms(50)).as_deref(), Some("PRESS a"));
+        assert_eq!(buffer.pop_due(start + ms(60)), None);
+        assert_eq!(buffer.pop_due(start + ms(70)).as_deref(), Some("PRESS b"));
+        assert_eq!(buffer.next_due(), Some(start + ms(90)));
+
+        buffer.push_at("STATE 000000000000000000", start).unwrap();
+        assert_eq!(buffer.len(), 2);
+        assert!(buffer.push_at("@soon PRESS a", start).is_err());
+    }
+}
@@ -0,0 +1,195 @@
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::{Button, PRESS_DURATION, Stick, StickTrack, Timeline};
+
+/// Characters for stick magnitude in text output, from centered to full tilt.
+const MAGNITUDE_CHARS: &[u8] = b".:-=+*#%@";
+
+/// Width reserved for row labels in SVG output, in pixels.
+const SVG_LABEL_WIDTH: f32 = 80.0;
+/// Height of each row in SVG output, in pixels.
+const SVG_ROW_HEIGHT: f32 = 20.0;
+
+fn magnitude((h, v): (f32, f32)) -> f32 {
+    (h * h + v * v).sqrt().min(1.0)
+}
+
+/// The largest stick magnitude reached within `[start, end)`.
+fn max_magnitude(track: &StickTrack, start: Duration, end: Duration) -> f32 {
+    track
+        .keyframes
+        .iter()
+        .filter(|(t, _, _)| *t >= start && *t < end)
+        .map(|&(_, h, v)| magnitude((h, v)))
+        .fold(magnitude(track.value_at(start)), f32::max)
+}
+
+impl Timeline {
+    /// Buttons with at least one hold or press, in [`Button::ALL`] order.
+    fn used_buttons(&self) -> impl Iterator<Item = Button> + '_ {
+        Button::ALL.into_iter().filter(|&b| {
+            let track = self.button(b);
+            !track.holds.is_empty() || !track.presses.is_empty()
+        })
+    }
+
+    /// Sticks with at least one keyframe.
+    fn used_sticks(&self) -> impl Iterator<Item = Stick> + '_ {
+        [Stick::Left, Stick::Right]
+            .into_iter()
+            .filter(|&s| !self.stick(s).keyframes.is_empty())
+    }
+
+    /// Render the timeline as a text grid with one column per `resolution`.
+    ///
+    /// Button rows show `#` while held, `*` for a momentary press and `.` when
+    /// idle. Stick rows show the largest tilt in each column, from `.`
+    /// (centered) to `@` (full tilt). Only buttons and sticks that are used get
+    /// a row.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `resolution` is zero.
+    pub fn render_text(&self, resolution: Duration) -> String {
+        assert!(!resolution.is_zero(), "resolution must be non-zero");
+        let columns = self.duration().div_duration_f64(resolution).ceil().max(1.0) as u32;
+        let cell = |i: u32| (resolution * i, resolution * (i + 1));
+        let mut out = String::new();
+
+        for b in self.used_buttons() {
+            let track = self.button(b);
+            let _ = write!(out, "{:<10}", b.to_string());
+            for i in 0..columns {
+                let (start, end) = cell(i);
+                let held = track
+                    .holds
+                    .iter()
+                    .any(|span| span.start < end && span.end.is_none_or(|e| e > start));
+                let pressed = track.presses.iter().any(|&p| p >= start && p < end);
+                out.push(match (held, pressed) {
+                    (true, _) => '#',
+                    (false, true) => '*',
+                    (false, false) => '.',
+                });
+            }
+            out.push('\n');
+        }
+
+        for stick in self.used_sticks() {
+            let track = self.stick(stick);
+            let _ = write!(out, "{:<10}", stick.to_string());
+            for i in 0..columns {
+                let (start, end) = cell(i);
+                let level = max_magnitude(track, start, end) * (MAGNITUDE_CHARS.len() - 1) as f32;
+                out.push(MAGNITUDE_CHARS[level.round() as usize] as char);
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Render the timeline as a standalone SVG document.
+    ///
+    /// Held periods and presses are drawn as bars on one row per button, and
+    /// stick rows are drawn as bars whose height follows the stick's tilt.
+    pub fn render_svg(&self, pixels_per_second: f32) -> String {
+        let x = |t: Duration| SVG_LABEL_WIDTH + t.as_secs_f32() * pixels_per_second;
+        let end = self.duration();
+        let rows = self.used_buttons().count() + self.used_sticks().count();
+        let width = x(end) + 10.0;
+        let height = rows as f32 * SVG_ROW_HEIGHT;
+
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" font-family="monospace" font-size="12">"#
+        );
+        let mut row = 0.0;
+        let mut label = |out: &mut String, name: String| {
+            let y = row * SVG_ROW_HEIGHT;
+            let _ = writeln!(
+                out,
+                r#"<text x="4" y="{}">{name}</text>"#,
+                y + SVG_ROW_HEIGHT - 6.0
+            );
+            row += 1.0;
+            y
+        };
+
+        for b in self.used_buttons() {
+            let y = label(&mut out, b.to_string()) + 3.0;
+            let h = SVG_ROW_HEIGHT - 6.0;
+            let track = self.button(b);
+            for span in &track.holds {
+                let (x0, x1) = (x(span.start), x(span.end.unwrap_or(end)));
+                let _ = writeln!(
+                    out,
+                    r##"<rect x="{x0}" y="{y}" width="{}" height="{h}" fill="#4a90d9"/>"##,
+                    x1 - x0
+                );
+            }
+            for &p in &track.presses {
+                let (x0, x1) = (x(p), x(p + PRESS_DURATION));
+                let _ = writeln!(
+                    out,
+                    r##"<rect x="{x0}" y="{y}" width="{}" height="{h}" fill="#e67e22"/>"##,
+                    x1 - x0
+                );
+            }
+        }
+
+        for stick in self.used_sticks() {
+            let bottom = label(&mut out, stick.to_string()) + SVG_ROW_HEIGHT - 3.0;
+            let keyframes = &self.stick(stick).keyframes;
+            for (i, &(t, h, v)) in keyframes.iter().enumerate() {
+                let next = keyframes.get(i + 1).map_or(end, |k| k.0);
+                let bar = magnitude((h, v)) * (SVG_ROW_HEIGHT - 6.0);
+                let _ = writeln!(
+                    out,
+                    r##"<rect x="{}" y="{}" width="{}" height="{bar}" fill="#27ae60"/>"##,
+                    x(t),
+                    bottom - bar,
+                    x(next) - x(t)
+                );
+            }
+        }
+        out.push_str("</svg>\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InputSequence;
+
+    #[test]
+    fn text_grid() {
+        let mut seq = InputSequence::new();
+        seq.hold(&[Button::ZR])
+            .wait(Duration::from_millis(200))
+            .release(&[Button::ZR])
+            .press(&[Button::A])
+            .stick(Stick::Left, 1.0, 0.0)
+            .wait(Duration::from_millis(100))
+            .stick(Stick::Left, 0.0, 0.0)
+            .wait(Duration::from_millis(100));
+
+        let text = Timeline::from(&seq).render_text(Duration::from_millis(100));
+        assert_eq!(text, "a         ..*.\nzr        ##..\nl_stick   ..@.\n");
+    }
+
+    #[test]
+    fn svg_has_a_bar_per_hold_and_press() {
+        let mut seq = InputSequence::new();
+        seq.hold(&[Button::B])
+            .press(&[Button::A])
+            .wait(Duration::from_secs(1));
+
+        let svg = Timeline::from(&seq).render_svg(100.0);
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches("<rect").count(), 2);
+        assert!(svg.contains(r#"<rect x="80" y="23" width="100""#));
+    }
+}
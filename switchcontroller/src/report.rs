@@ -0,0 +1,165 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::json;
+use crate::{AutomationEvent, InputSequence, PlaybackPosition, SwitchController};
+
+/// A summary of a sequence or loop run, for comparing macro tuning changes.
+///
+/// Produced by [`SwitchController::play_looped`], or built up from
+/// [`AutomationEvent`]s with [`RunReport::record_event`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RunReport {
+    /// Completed iterations.
+    pub iterations: u64,
+    /// Total wall-clock time of the run.
+    pub duration: Duration,
+    /// Time taken by each completed iteration.
+    pub loop_times: Vec<Duration>,
+    pub errors: Vec<String>,
+    pub detections: Vec<String>,
+    /// The last commands sent before a failed iteration, oldest first, from
+    /// [`SwitchController::recent_commands`].
+    pub recent_commands: Vec<String>,
+}
+
+fn millis(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+impl RunReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mean of [`RunReport::loop_times`], or `None` if nothing completed.
+    pub fn average_loop_time(&self) -> Option<Duration> {
+        let count = self.loop_times.len() as u32;
+        (count > 0).then(|| self.loop_times.iter().sum::<Duration>() / count)
+    }
+
+    pub fn record_iteration(&mut self, elapsed: Duration) -> &mut Self {
+        self.iterations += 1;
+        self.loop_times.push(elapsed);
+        self
+    }
+
+    /// Fold an automation event into the report: finished sequences count as
+    /// iterations, and errors and detections are collected.
+    pub fn record_event(&mut self, event: &AutomationEvent) -> &mut Self {
+        match event {
+            AutomationEvent::SequenceFinished { elapsed, .. } => {
+                self.record_iteration(*elapsed);
+            }
+            AutomationEvent::Error { message } => self.errors.push(message.clone()),
+            AutomationEvent::Detection { name } => self.detections.push(name.clone()),
+            _ => {}
+        }
+        self
+    }
+
+    /// The report as a single-line JSON object. Durations are in
+    /// milliseconds.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        let _ = write!(
+            out,
+            r#"{{"iterations":{},"duration_ms":{},"average_loop_ms":"#,
+            self.iterations,
+            millis(self.duration)
+        );
+        match self.average_loop_time() {
+            Some(average) => {
+                let _ = write!(out, "{}", millis(average));
+            }
+            None => out.push_str("null"),
+        }
+        out.push_str(r#","loop_times_ms":["#);
+        for (i, &t) in self.loop_times.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            let _ = write!(out, "{}", millis(t));
+        }
+        for (key, items) in [
+            ("errors", &self.errors),
+            ("detections", &self.detections),
+            ("recent_commands", &self.recent_commands),
+        ] {
+            let _ = write!(out, r#"],"{key}":["#);
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                let _ = json::write_string(&mut out, item);
+            }
+        }
+        out.push_str("]}");
+        out
+    }
+}
+
+impl SwitchController {
+    /// Play `sequence` `iterations` times, or until `stop` is set, and report
+    /// on the run.
+    ///
+    /// A failed iteration is recorded in [`RunReport::errors`] and ends the
+    /// run; the report is returned either way.
+    pub fn play_looped(
+        &mut self,
+        sequence: &InputSequence,
+        iterations: u64,
+        stop: &AtomicBool,
+    ) -> RunReport {
+        let mut report = RunReport::new();
+        let start = Instant::now();
+        for _ in 0..iterations {
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            let iteration_start = Instant::now();
+            match self.play_cancellable(sequence, PlaybackPosition::default(), stop) {
+                Ok(None) => {
+                    report.record_iteration(iteration_start.elapsed());
+                }
+                Ok(Some(_)) => break,
+                Err(e) => {
+                    report.errors.push(e.to_string());
+                    report.recent_commands = self.history_tail();
+                    break;
+                }
+            }
+        }
+        report.duration = start.elapsed();
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_output() {
+        let mut report = RunReport::new();
+        report
+            .record_iteration(Duration::from_millis(1500))
+            .record_iteration(Duration::from_millis(500))
+            .record_event(&AutomationEvent::Detection {
+                name: "shiny \"gold\"".into(),
+            });
+        report.duration = Duration::from_secs(2);
+
+        assert_eq!(report.average_loop_time(), Some(Duration::from_secs(1)));
+        assert_eq!(
+            report.to_json(),
+            r#"{"iterations":2,"duration_ms":2000,"average_loop_ms":1000,"loop_times_ms":[1500,500],"errors":[],"detections":["shiny \"gold\""],"recent_commands":[]}"#
+        );
+        assert!(
+            RunReport::new()
+                .to_json()
+                .contains(r#""average_loop_ms":null"#)
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+use crate::{InputSequence, SwitchController};
+
+/// How long to wait between attempts of [`SwitchController::retry_with`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backoff {
+    /// The same delay after every failed attempt.
+    Fixed(Duration),
+    /// A delay starting at `initial` and multiplied by `factor` after each
+    /// failed attempt, capped at `max`.
+    Exponential {
+        initial: Duration,
+        factor: f32,
+        max: Duration,
+    },
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff::Fixed(Duration::ZERO)
+    }
+}
+
+impl Backoff {
+    /// The delay after failed attempt number `attempt` (starting at 1).
+    pub fn delay(&self, attempt: u32) -> Duration {
+        match *self {
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential {
+                initial,
+                factor,
+                max,
+            } => {
+                let scale = factor.max(0.0).powi(attempt.saturating_sub(1) as i32);
+                Duration::try_from_secs_f32(initial.as_secs_f32() * scale)
+                    .map_or(max, |d| d.min(max))
+            }
+        }
+    }
+}
+
+/// Settings for [`SwitchController::retry_with`].
+#[derive(Debug, Clone)]
+pub struct Retry {
+    max_attempts: u32,
+    backoff: Backoff,
+    recovery: Option<InputSequence>,
+}
+
+impl Retry {
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            backoff: Backoff::default(),
+            recovery: None,
+        }
+    }
+
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Play `sequence` after every failed attempt, before the backoff delay,
+    /// to bring the game back to where the next attempt starts (a soft reset,
+    /// for example).
+    pub fn recovery(mut self, sequence: InputSequence) -> Self {
+        self.recovery = Some(sequence);
+        self
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+}
+
+/// The result of [`SwitchController::retry_until`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryOutcome {
+    /// The check passed on attempt number `attempts`.
+    Succeeded { attempts: u32 },
+    /// Every attempt ran and none passed the check.
+    Exhausted { attempts: u32 },
+}
+
+impl RetryOutcome {
+    pub fn is_success(&self) -> bool {
+        matches!(self, RetryOutcome::Succeeded { .. })
+    }
+
+    pub fn attempts(&self) -> u32 {
+        match *self {
+            RetryOutcome::Succeeded { attempts } | RetryOutcome::Exhausted { attempts } => attempts,
+        }
+    }
+}
+
+impl SwitchController {
+    /// Play `sequence` until `check` passes, at most `max_attempts` times, with
+    /// no delay or recovery between attempts.
+    ///
+    /// `check` receives the attempt number, starting at 1. See
+    /// [`SwitchController::retry_with`] for backoff and recovery.
+    pub fn retry_until(
+        &mut self,
+        max_attempts: u32,
+        sequence: &InputSequence,
+        check: impl FnMut(u32) -> bool,
+    ) -> io::Result<RetryOutcome> {
+        self.retry_with(&Retry::new(max_attempts), sequence, check)
+    }
+
+    /// Play `sequence` until `check` passes, following `retry` between failed
+    /// attempts: the recovery sequence plays first, then the backoff delay.
+    /// Nothing runs after the last attempt.
+    pub fn retry_with(
+        &mut self,
+        retry: &Retry,
+        sequence: &InputSequence,
+        mut check: impl FnMut(u32) -> bool,
+    ) -> io::Result<RetryOutcome> {
+        for attempt in 1..=retry.max_attempts {
+            self.play(sequence)?;
+            if check(attempt) {
+                return Ok(RetryOutcome::Succeeded { attempts: attempt });
+            }
+            if attempt < retry.max_attempts {
+                if let Some(recovery) = &retry.recovery {
+                    self.play(recovery)?;
+                }
+                thread::sleep(retry.backoff.delay(attempt));
+            }
+        }
+        Ok(RetryOutcome::Exhausted {
+            attempts: retry.max_attempts,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exponential_backoff_is_capped() {
+        let backoff = Backoff::Exponential {
+            initial: Duration::from_millis(100),
+            factor: 2.0,
+            max: Duration::from_millis(500),
+        };
+        let delays: Vec<_> = (1..=5).map(|a| backoff.delay(a).as_millis()).collect();
+        assert_eq!(delays, [100, 200, 400, 500, 500]);
+        assert_eq!(Backoff::default().delay(3), Duration::ZERO);
+    }
+}
@@ -0,0 +1,195 @@
+use std::io;
+use std::time::Duration;
+
+use crate::{Direction, InputSequence, Movement, SwitchController};
+
+/// One leg of a [`Route`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Waypoint {
+    /// Move towards a direction for a time.
+    Move(Direction, Duration),
+    /// Move by an offset, with up negative as on the stick, measured in seconds of movement
+    /// at full tilt: `(2, 0)` is two seconds right at full speed, or four at
+    /// half speed.
+    Offset(f32, f32),
+    /// Stop and ask the detector whether this template is on screen; the
+    /// route is abandoned if not.
+    Checkpoint(String),
+}
+
+/// A repeatable path through the overworld, followed with
+/// [`SwitchController::follow_route`].
+///
+/// ```
+/// # use std::time::Duration;
+/// # use switchcontroller::{Direction, Route};
+/// let mut route = Route::new();
+/// route
+///     .speed(0.8)
+///     .go(Direction::Up, Duration::from_secs(2))
+///     .offset(1.5, -0.5)
+///     .checkpoint("bridge.png");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    waypoints: Vec<Waypoint>,
+    speed: f32,
+    sprint: bool,
+}
+
+impl Default for Route {
+    fn default() -> Self {
+        Self {
+            waypoints: Vec::new(),
+            speed: 1.0,
+            sprint: false,
+        }
+    }
+}
+
+impl Route {
+    /// An empty route at full speed.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn waypoints(&self) -> &[Waypoint] {
+        &self.waypoints
+    }
+
+    /// Stick deflection while moving, from 0 to 1.
+    pub fn speed(&mut self, speed: f32) -> &mut Self {
+        self.speed = speed.clamp(f32::EPSILON, 1.0);
+        self
+    }
+
+    /// Hold the [`Movement::sprint`] button while moving.
+    pub fn sprint(&mut self, sprint: bool) -> &mut Self {
+        self.sprint = sprint;
+        self
+    }
+
+    pub fn push(&mut self, waypoint: Waypoint) -> &mut Self {
+        self.waypoints.push(waypoint);
+        self
+    }
+
+    /// Append a [`Waypoint::Move`].
+    pub fn go(&mut self, direction: Direction, duration: Duration) -> &mut Self {
+        self.push(Waypoint::Move(direction, duration))
+    }
+
+    /// Append a [`Waypoint::Offset`].
+    pub fn offset(&mut self, dx: f32, dy: f32) -> &mut Self {
+        self.push(Waypoint::Offset(dx, dy))
+    }
+
+    /// Append a [`Waypoint::Checkpoint`].
+    pub fn checkpoint(&mut self, template: &str) -> &mut Self {
+        self.push(Waypoint::Checkpoint(template.to_string()))
+    }
+
+    /// The inputs of one movement waypoint; `None` for checkpoints.
+    fn leg(&self, waypoint: &Waypoint, movement: &Movement) -> Option<InputSequence> {
+        match waypoint {
+            Waypoint::Move(direction, duration) => {
+                Some(movement.sequence(*direction, self.speed, self.sprint, *duration))
+            }
+            Waypoint::Offset(dx, dy) => {
+                let length = dx.hypot(*dy);
+                if length == 0.0 {
+                    return Some(InputSequence::new());
+                }
+                let (x, y) = (dx / length * self.speed, dy / length * self.speed);
+                let duration = Duration::from_secs_f32(length / self.speed);
+                Some(movement.sequence_to(x, y, self.sprint, duration))
+            }
+            Waypoint::Checkpoint(_) => None,
+        }
+    }
+
+    /// The whole route as a sequence, with a marker per checkpoint instead
+    /// of a check, for previews and dry runs.
+    pub fn to_sequence(&self, movement: &Movement) -> InputSequence {
+        let mut sequence = InputSequence::new();
+        for waypoint in &self.waypoints {
+            match (self.leg(waypoint, movement), waypoint) {
+                (Some(leg), _) => sequence.steps_mut().extend(leg.steps().iter().cloned()),
+                (None, Waypoint::Checkpoint(name)) => {
+                    sequence.marker(name);
+                }
+                (None, _) => {}
+            }
+        }
+        sequence
+    }
+}
+
+impl SwitchController {
+    /// Walk `route` with the configured [`Movement`], recentering the stick
+    /// after every leg.
+    ///
+    /// `detect` is asked about each checkpoint, as in
+    /// [`SwitchController::run_program`]. If a checkpoint is not on screen
+    /// the route stops there with an error of kind
+    /// [`io::ErrorKind::NotFound`], rather than wandering further off
+    /// course.
+    pub fn follow_route(
+        &mut self,
+        route: &Route,
+        mut detect: impl FnMut(&str) -> bool,
+    ) -> io::Result<()> {
+        for waypoint in route.waypoints() {
+            match route.leg(waypoint, &self.movement) {
+                Some(leg) => self.play(&leg)?,
+                None => {
+                    if let Waypoint::Checkpoint(template) = waypoint
+                        && !detect(template)
+                    {
+                        return Err(io::Error::new(
+                            io::ErrorKind::NotFound,
+                            format!("checkpoint `{template}` not reached"),
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Shared;
+
+    #[test]
+    fn follows_legs_and_checkpoints() {
+        let mut route = Route::new();
+        route
+            .speed(0.5)
+            .go(Direction::Right, Duration::from_millis(100))
+            .offset(0.0, 0.25)
+            .checkpoint("bridge.png")
+            .go(Direction::Up, Duration::ZERO);
+        assert_eq!(
+            route.to_sequence(&Movement::default()).to_script(),
+            "STICK l_stick 0.5 0\nWAIT 100ms\nSTICK l_stick 0 0\n\
+             STICK l_stick 0 0.5\nWAIT 500ms\nSTICK l_stick 0 0\n\
+             MARK bridge.png\n\
+             STICK l_stick 0 -0.5\nWAIT 0ms\nSTICK l_stick 0 0\n"
+        );
+
+        let out = Shared::default();
+        let mut ctrl = SwitchController::from_writer(out.clone());
+        ctrl.set_playback_speed(100.0);
+        let err = ctrl.follow_route(&route, |_| false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(!out.text().contains("0 -0.5"));
+        ctrl.follow_route(&route, |t| t == "bridge.png").unwrap();
+        assert!(
+            out.text()
+                .ends_with("STICK l_stick 0 -0.5\nSTICK l_stick 0 0\n")
+        );
+    }
+}
@@ -0,0 +1,464 @@
+//! A JSON-RPC 2.0 interface to a [`JobManager`], for GUIs and other tools
+//! that drive consoles without linking Rust. [`RpcServer::serve`] speaks it
+//! over TCP, one request (or batch) per line. RPC.md documents the methods.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use crate::json::{self, Json};
+use crate::{Button, InputSequence, JobId, JobInfo, JobManager, JobSpec, JobStatus, ScriptLoader};
+
+/// Version of the method set and result shapes, reported by `rpc.version`.
+/// Bumped whenever a method or result changes incompatibly.
+pub const RPC_SCHEMA_VERSION: u32 = 1;
+
+const METHODS: [&str; 8] = [
+    "rpc.version",
+    "status",
+    "press",
+    "run_macro",
+    "job.status",
+    "job.pause",
+    "job.resume",
+    "job.cancel",
+];
+
+// Error codes from the JSON-RPC 2.0 specification, plus one for failures of
+// the request itself.
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const REQUEST_FAILED: i64 = -32000;
+
+/// How often [`RpcServer::serve_until`] checks its stop flag.
+const STOP_POLL: Duration = Duration::from_millis(50);
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn params(message: impl Into<String>) -> Self {
+        Self::new(INVALID_PARAMS, message)
+    }
+}
+
+impl From<io::Error> for RpcError {
+    fn from(e: io::Error) -> Self {
+        Self::new(REQUEST_FAILED, e.to_string())
+    }
+}
+
+/// Serves JSON-RPC requests against the jobs and devices of a
+/// [`JobManager`].
+///
+#[cfg_attr(feature = "serial", doc = "```no_run")]
+#[cfg_attr(not(feature = "serial"), doc = "```ignore")]
+/// # use switchcontroller::{JobManager, RpcServer, SwitchController};
+/// let mut jobs = JobManager::new();
+/// jobs.add_device("left", SwitchController::open("/dev/ttyACM0", 115200)?)?;
+/// let server = RpcServer::new(jobs);
+/// server.serve(std::net::TcpListener::bind("127.0.0.1:7878")?)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct RpcServer {
+    jobs: JobManager,
+}
+
+impl RpcServer {
+    pub fn new(jobs: JobManager) -> Self {
+        Self { jobs }
+    }
+
+    pub fn jobs(&self) -> &JobManager {
+        &self.jobs
+    }
+
+    /// Take the manager back, cancelling nothing.
+    pub fn into_jobs(self) -> JobManager {
+        self.jobs
+    }
+
+    /// Answer one JSON-RPC message, a request or a batch. Returns `None`
+    /// when nothing should be sent back: for notifications (requests
+    /// without an `id`), and batches of only notifications.
+    pub fn handle(&self, message: &str) -> Option<String> {
+        let response = match json::parse(message) {
+            Ok(Json::Array(batch)) if !batch.is_empty() => {
+                let responses: Vec<Json> = batch.iter().filter_map(|r| self.call(r)).collect();
+                (!responses.is_empty()).then_some(Json::Array(responses))
+            }
+            Ok(request) => self.call(&request),
+            Err(e) => Some(error_response(
+                Json::Null,
+                RpcError::new(PARSE_ERROR, e.to_string()),
+            )),
+        };
+        response.map(|r| r.to_string())
+    }
+
+    /// Accept connections on `listener` and answer each line received with a
+    /// line holding the response. Connections are served on their own
+    /// threads; this only returns if accepting fails.
+    pub fn serve(&self, listener: TcpListener) -> io::Result<()> {
+        let never = AtomicBool::new(false);
+        self.serve_until(listener, &never)
+    }
+
+    /// [`RpcServer::serve`], but returning within a fraction of a second of
+    /// `stop` being set, closing any open connections.
+    pub fn serve_until(&self, listener: TcpListener, stop: &AtomicBool) -> io::Result<()> {
+        listener.set_nonblocking(true)?;
+        thread::scope(|scope| {
+            while !stop.load(Ordering::Relaxed) {
+                let stream = match listener.accept() {
+                    Ok((stream, _)) => stream,
+                    Err(e) if is_timeout(&e) || e.kind() == io::ErrorKind::Interrupted => {
+                        thread::sleep(STOP_POLL);
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+                stream.set_nonblocking(false)?;
+                stream.set_read_timeout(Some(STOP_POLL))?;
+                scope.spawn(move || {
+                    // A client going away mid-request is not the server's
+                    // problem.
+                    let _ = self.serve_connection(stream, stop);
+                });
+            }
+            Ok(())
+        })
+    }
+
+    fn serve_connection(&self, stream: TcpStream, stop: &AtomicBool) -> io::Result<()> {
+        let mut out = stream.try_clone()?;
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        while !stop.load(Ordering::Relaxed) {
+            // A timed-out read keeps what it read of the line so far.
+            match reader.read_line(&mut line) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) if is_timeout(&e) || e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+            if !line.trim().is_empty()
+                && let Some(response) = self.handle(&line)
+            {
+                writeln!(out, "{response}")?;
+            }
+            line.clear();
+        }
+        Ok(())
+    }
+
+    /// Answer a single request, or `None` for a notification.
+    fn call(&self, request: &Json) -> Option<Json> {
+        let id = request.get("id").cloned();
+        let result = match (
+            request.get("jsonrpc").and_then(Json::as_str),
+            request.get("method"),
+        ) {
+            (Some("2.0"), Some(Json::String(method))) => {
+                let params = request.get("params").unwrap_or(&Json::Null);
+                self.dispatch(method, params)
+            }
+            _ => Err(RpcError::new(INVALID_REQUEST, "not a JSON-RPC 2.0 request")),
+        };
+        // Invalid requests are answered even without an id, as the
+        // specification asks.
+        let id = match (id, &result) {
+            (Some(id), _) => id,
+            (None, Err(e)) if e.code == INVALID_REQUEST => Json::Null,
+            (None, _) => return None,
+        };
+        Some(match result {
+            Ok(value) => Json::Object(vec![
+                ("jsonrpc".into(), Json::String("2.0".into())),
+                ("result".into(), value),
+                ("id".into(), id),
+            ]),
+            Err(e) => error_response(id, e),
+        })
+    }
+
+    fn dispatch(&self, method: &str, params: &Json) -> Result<Json, RpcError> {
+        match method {
+            "rpc.version" => Ok(Json::Object(vec![
+                ("schema".into(), Json::Number(RPC_SCHEMA_VERSION.into())),
+                (
+                    "methods".into(),
+                    Json::Array(
+                        METHODS
+                            .iter()
+                            .map(|m| Json::String(m.to_string()))
+                            .collect(),
+                    ),
+                ),
+            ])),
+            "status" => Ok(Json::Object(vec![
+                ("schema".into(), Json::Number(RPC_SCHEMA_VERSION.into())),
+                (
+                    "devices".into(),
+                    Json::Array(
+                        self.jobs
+                            .devices()
+                            .map(|d| Json::String(d.to_string()))
+                            .collect(),
+                    ),
+                ),
+                (
+                    "jobs".into(),
+                    Json::Array(self.jobs.jobs().iter().map(job_json).collect()),
+                ),
+            ])),
+            "press" => {
+                let device = string_param(params, "device")?;
+                let buttons = params
+                    .get("buttons")
+                    .and_then(Json::as_array)
+                    .ok_or_else(|| RpcError::params("missing `buttons`"))?
+                    .iter()
+                    .map(|b| {
+                        b.as_str()
+                            .and_then(Button::from_name)
+                            .ok_or_else(|| RpcError::params(format!("unknown button {b}")))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if buttons.is_empty() {
+                    return Err(RpcError::params("`buttons` is empty"));
+                }
+                let mut seq = InputSequence::new();
+                seq.press(&buttons);
+                let id = self.jobs.submit(device, JobSpec::new("press", seq))?;
+                match self.jobs.wait(id) {
+                    Some(JobStatus::Failed(e)) => Err(RpcError::new(REQUEST_FAILED, e)),
+                    _ => Ok(Json::Bool(true)),
+                }
+            }
+            "run_macro" => {
+                let device = string_param(params, "device")?;
+                let script = string_param(params, "script")?;
+                let name = params.get("name").and_then(Json::as_str).unwrap_or("macro");
+                let iterations = match params.get("iterations") {
+                    None => 1,
+                    Some(n) => n
+                        .as_f64()
+                        .filter(|n| *n >= 0.0 && n.fract() == 0.0)
+                        .ok_or_else(|| RpcError::params("`iterations` must be a whole number"))?
+                        as u64,
+                };
+                let seq = ScriptLoader::from_env()
+                    .confine()
+                    .parse(script)
+                    .map_err(|e| RpcError::params(e.to_string()))?;
+                let id = self
+                    .jobs
+                    .submit(device, JobSpec::new(name, seq).iterations(iterations))?;
+                Ok(Json::Object(vec![(
+                    "job".into(),
+                    Json::Number(id.0 as f64),
+                )]))
+            }
+            "job.status" => {
+                let id = job_param(params)?;
+                let info = self.jobs.job(id).ok_or_else(|| no_job(id))?;
+                Ok(job_json(&info))
+            }
+            "job.pause" | "job.resume" | "job.cancel" => {
+                let id = job_param(params)?;
+                self.jobs.job(id).ok_or_else(|| no_job(id))?;
+                let changed = match method {
+                    "job.pause" => self.jobs.pause(id),
+                    "job.resume" => self.jobs.resume(id),
+                    _ => self.jobs.cancel(id),
+                };
+                Ok(Json::Bool(changed))
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("no method `{method}`"),
+            )),
+        }
+    }
+}
+
+/// Whether `error` is a non-blocking accept or a timed-out read finding
+/// nothing, which platforms report differently.
+fn is_timeout(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+fn error_response(id: Json, error: RpcError) -> Json {
+    Json::Object(vec![
+        ("jsonrpc".into(), Json::String("2.0".into())),
+        (
+            "error".into(),
+            Json::Object(vec![
+                ("code".into(), Json::Number(error.code as f64)),
+                ("message".into(), Json::String(error.message)),
+            ]),
+        ),
+        ("id".into(), id),
+    ])
+}
+
+fn string_param<'a>(params: &'a Json, key: &str) -> Result<&'a str, RpcError> {
+    params
+        .get(key)
+        .and_then(Json::as_str)
+        .ok_or_else(|| RpcError::params(format!("missing `{key}`")))
+}
+
+fn job_param(params: &Json) -> Result<JobId, RpcError> {
+    params
+        .get("job")
+        .and_then(Json::as_f64)
+        .filter(|n| *n >= 0.0 && n.fract() == 0.0)
+        .map(|n| JobId(n as u64))
+        .ok_or_else(|| RpcError::params("missing `job`"))
+}
+
+fn no_job(id: JobId) -> RpcError {
+    RpcError::new(REQUEST_FAILED, format!("no job {}", id.0))
+}
+
+fn job_json(info: &JobInfo) -> Json {
+    let (status, error) = match &info.status {
+        JobStatus::Queued => ("queued", None),
+        JobStatus::Running => ("running", None),
+        JobStatus::Paused => ("paused", None),
+        JobStatus::Finished => ("finished", None),
+        JobStatus::Cancelled => ("cancelled", None),
+        JobStatus::Failed(e) => ("failed", Some(e)),
+    };
+    let mut members = vec![
+        ("id".into(), Json::Number(info.id.0 as f64)),
+        ("name".into(), Json::String(info.name.clone())),
+        ("device".into(), Json::String(info.device.clone())),
+        ("status".into(), Json::String(status.into())),
+    ];
+    if let Some(error) = error {
+        members.push(("error".into(), Json::String(error.clone())));
+    }
+    members.extend([
+        ("completed".into(), Json::Number(info.completed as f64)),
+        ("iterations".into(), Json::Number(info.iterations as f64)),
+        (
+            "log".into(),
+            Json::Array(info.log.iter().map(|l| Json::String(l.clone())).collect()),
+        ),
+    ]);
+    Json::Object(members)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::SwitchController;
+    use crate::tests::Shared;
+
+    #[test]
+    fn answers_requests() {
+        let out = Shared::default();
+        let mut jobs = JobManager::new();
+        jobs.add_device("left", SwitchController::from_writer(out.clone()))
+            .unwrap();
+        let server = RpcServer::new(jobs);
+        let call = |message: &str| server.handle(message).unwrap();
+
+        assert_eq!(
+            call(
+                r#"{"jsonrpc":"2.0","method":"press","params":{"device":"left","buttons":["a","b"]},"id":1}"#
+            ),
+            r#"{"jsonrpc":"2.0","result":true,"id":1}"#
+        );
+        assert_eq!(out.text(), "PRESS a b\n");
+        assert_eq!(
+            call(
+                r#"{"jsonrpc":"2.0","method":"run_macro","params":{"device":"left","script":"press x\n"},"id":"m"}"#
+            ),
+            r#"{"jsonrpc":"2.0","result":{"job":2},"id":"m"}"#
+        );
+        server.jobs().wait(JobId(2));
+        assert!(
+            call(r#"{"jsonrpc":"2.0","method":"job.status","params":{"job":2},"id":3}"#)
+                .contains(r#""status":"finished","completed":1"#)
+        );
+
+        assert_eq!(
+            call(
+                r#"[{"jsonrpc":"2.0","method":"nope","id":4},{"jsonrpc":"2.0","method":"status"}]"#
+            ),
+            r#"[{"jsonrpc":"2.0","error":{"code":-32601,"message":"no method `nope`"},"id":4}]"#
+        );
+        assert!(call("{").contains(r#""code":-32700"#));
+        assert!(call(r#"{"method":"status"}"#).contains(r#""code":-32600"#));
+        assert!(
+            call(r#"{"jsonrpc":"2.0","method":"press","params":{"device":"right","buttons":["a"]},"id":5}"#)
+                .contains(r#""code":-32000,"message":"no device `right`""#)
+        );
+        assert!(
+            call(r#"{"jsonrpc":"2.0","method":"press","params":{"device":"left","buttons":[]},"id":6}"#)
+                .contains(r#""code":-32602,"message":"`buttons` is empty""#)
+        );
+        // Remote scripts cannot include files outside the macro path, nor
+        // see what they hold.
+        let secret =
+            std::env::temp_dir().join(format!("switchcontroller-rpc-{}", std::process::id()));
+        fs::write(&secret, "root:x:0:0\n").unwrap();
+        let response = call(&format!(
+            r#"{{"jsonrpc":"2.0","method":"run_macro","params":{{"device":"left","script":"include \"{}\""}},"id":7}}"#,
+            secret.display()
+        ));
+        fs::remove_file(&secret).unwrap();
+        assert!(response.contains(r#""code":-32602"#) && !response.contains("root:x"));
+        assert_eq!(out.text(), "PRESS a b\nPRESS x\n");
+        assert_eq!(
+            server.handle(r#"{"jsonrpc":"2.0","method":"job.cancel","params":{"job":2}}"#),
+            None
+        );
+    }
+
+    #[test]
+    fn serves_until_stopped() {
+        let server = RpcServer::new(JobManager::new());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let stop = AtomicBool::new(false);
+        thread::scope(|scope| {
+            let serving = scope.spawn(|| server.serve_until(listener, &stop));
+            let mut client = TcpStream::connect(address).unwrap();
+            client
+                .write_all(br#"{"jsonrpc":"2.0","method":"rpc"#)
+                .unwrap();
+            thread::sleep(STOP_POLL * 2);
+            client.write_all(b".version\",\"id\":1}\n").unwrap();
+            let mut response = String::new();
+            BufReader::new(&client).read_line(&mut response).unwrap();
+            assert!(response.contains(r#""schema":1"#));
+            // The open connection doesn't keep the server running.
+            stop.store(true, Ordering::Relaxed);
+            serving.join().unwrap().unwrap();
+        });
+    }
+}
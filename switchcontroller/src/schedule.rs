@@ -0,0 +1,598 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::duration::parse_duration;
+use crate::toml::Document;
+use crate::{InputSequence, JobManager, JobSpec, JobStatus, ScriptLoader, SwitchController};
+
+/// How often [`Scheduler::run`] checks its stop flag while idle.
+const STOP_POLL: Duration = Duration::from_millis(100);
+
+/// An error parsing a [`Cron`] expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseCronError(String);
+
+impl fmt::Display for ParseCronError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid cron expression: {}", self.0)
+    }
+}
+
+impl Error for ParseCronError {}
+
+/// A five-field cron expression: `minute hour day-of-month month day-of-week`.
+///
+/// Each field accepts `*`, single values, ranges (`1-5`), lists (`1,15`) and
+/// steps (`*/15`, `0-30/10`). Day-of-week runs from 0 (Sunday) to 6, with 7
+/// also meaning Sunday. As in standard cron, when both day fields are
+/// restricted a day matching either one fires.
+///
+/// Times are evaluated in UTC unless an offset is set with
+/// [`Cron::with_utc_offset`], since the standard library has no time zone
+/// database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+    utc_offset: i64,
+}
+
+/// Parse one cron field into a bitmask of allowed values in `min..=max`.
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, ParseCronError> {
+    let err = || ParseCronError(format!("bad field `{field}`"));
+    let mut mask = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| err())?),
+            None => (part, 1),
+        };
+        let (lo, hi) = if range == "*" {
+            (min, max)
+        } else if let Some((lo, hi)) = range.split_once('-') {
+            (
+                lo.parse().map_err(|_| err())?,
+                hi.parse().map_err(|_| err())?,
+            )
+        } else {
+            let value = range.parse().map_err(|_| err())?;
+            (value, if step > 1 { max } else { value })
+        };
+        if step == 0 || lo < min || hi > max || lo > hi {
+            return Err(err());
+        }
+        for value in (lo..=hi).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+/// Convert days since the Unix epoch to a `(year, month, day)` civil date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+impl Cron {
+    /// Evaluate the expression at a fixed offset from UTC, in seconds
+    /// (e.g. `9 * 3600` for UTC+9).
+    pub fn with_utc_offset(mut self, seconds: i64) -> Self {
+        self.utc_offset = seconds;
+        self
+    }
+
+    fn matches_day(&self, days: i64) -> bool {
+        let (_, month, day) = civil_from_days(days);
+        // 1970-01-01 was a Thursday.
+        let weekday = (days + 4).rem_euclid(7);
+        if self.months & (1 << month) == 0 {
+            return false;
+        }
+        let dom = self.days_of_month & (1 << day) != 0;
+        let dow = self.days_of_week & (1 << weekday) != 0;
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (false, false) => dom || dow,
+            _ => dom && dow,
+        }
+    }
+
+    /// The first matching minute strictly after `time`, or `None` if nothing
+    /// matches within the next eight years (e.g. `0 0 30 2 *`).
+    pub fn next_after(&self, time: SystemTime) -> Option<SystemTime> {
+        let secs = match time.duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_secs() as i64,
+            Err(e) => -(e.duration().as_secs() as i64),
+        };
+        let start = (secs + self.utc_offset).div_euclid(60) + 1;
+        let first_day = start.div_euclid(1440);
+        for days in first_day..first_day + 366 * 8 {
+            if !self.matches_day(days) {
+                continue;
+            }
+            let from = if days == first_day {
+                start.rem_euclid(1440)
+            } else {
+                0
+            };
+            for minute_of_day in from..1440 {
+                let (hour, minute) = (minute_of_day / 60, minute_of_day % 60);
+                if self.hours & (1 << hour) != 0 && self.minutes & (1 << minute) != 0 {
+                    let local = (days * 1440 + minute_of_day) * 60;
+                    let utc = local - self.utc_offset;
+                    return Some(if utc >= 0 {
+                        UNIX_EPOCH + Duration::from_secs(utc as u64)
+                    } else {
+                        UNIX_EPOCH - Duration::from_secs(utc.unsigned_abs())
+                    });
+                }
+            }
+        }
+        None
+    }
+}
+
+impl FromStr for Cron {
+    type Err = ParseCronError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let [minute, hour, dom, month, dow] = fields[..] else {
+            return Err(ParseCronError(format!(
+                "expected 5 fields, got {}",
+                fields.len()
+            )));
+        };
+        let mut days_of_week = parse_field(dow, 0, 7)?;
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week |= 1;
+        }
+        Ok(Cron {
+            minutes: parse_field(minute, 0, 59)?,
+            hours: parse_field(hour, 0, 23)?,
+            days_of_month: parse_field(dom, 1, 31)?,
+            months: parse_field(month, 1, 12)?,
+            days_of_week,
+            any_day_of_month: dom == "*",
+            any_day_of_week: dow == "*",
+            utc_offset: 0,
+        })
+    }
+}
+
+/// When a [`Job`] runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schedule {
+    /// Every interval, starting one interval after the scheduler is started.
+    Every(Duration),
+    /// At the times matched by a cron expression.
+    Cron(Cron),
+}
+
+impl Schedule {
+    /// The first run time after `previous`, or `None` if there is none the
+    /// system clock can represent.
+    fn next_after(&self, previous: SystemTime) -> Option<SystemTime> {
+        match self {
+            Schedule::Every(interval) => previous.checked_add(*interval),
+            Schedule::Cron(cron) => cron.next_after(previous),
+        }
+    }
+}
+
+/// What to do with a run that came due while another job was playing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Overlap {
+    /// Drop the missed run and wait for the next scheduled time.
+    #[default]
+    Skip,
+    /// Run as soon as the controller is free.
+    Delay,
+}
+
+/// A named sequence run on a [`Schedule`].
+#[derive(Debug, Clone)]
+pub struct Job {
+    name: String,
+    schedule: Schedule,
+    sequence: InputSequence,
+    overlap: Overlap,
+    /// The [`JobManager`] device to run on, for [`Scheduler::run_jobs`].
+    device: Option<String>,
+    next: Option<SystemTime>,
+}
+
+impl Job {
+    pub fn new(name: impl Into<String>, schedule: Schedule, sequence: InputSequence) -> Self {
+        Self {
+            name: name.into(),
+            schedule,
+            sequence,
+            overlap: Overlap::default(),
+            device: None,
+            next: None,
+        }
+    }
+
+    /// Run on the manager's device `name` with [`Scheduler::run_jobs`].
+    /// Without one, jobs run on the manager's only device.
+    pub fn on(mut self, device: impl Into<String>) -> Self {
+        self.device = Some(device.into());
+        self
+    }
+
+    /// Set the overlap policy (defaults to [`Overlap::Skip`]).
+    pub fn overlap(mut self, overlap: Overlap) -> Self {
+        self.overlap = overlap;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn device(&self) -> Option<&str> {
+        self.device.as_deref()
+    }
+
+    /// The next time this job will run, once the scheduler has started.
+    pub fn next_run(&self) -> Option<SystemTime> {
+        self.next
+    }
+}
+
+/// Something that happened while [`Scheduler::run`] was running.
+#[derive(Debug)]
+pub enum SchedulerEvent<'a> {
+    /// A job started playing.
+    Started { job: &'a str },
+    /// A job finished playing.
+    Finished { job: &'a str, elapsed: Duration },
+    /// A run was dropped because it came due while the controller was busy.
+    Skipped { job: &'a str, due: SystemTime },
+    /// A job failed to play; the scheduler stops after reporting this.
+    Failed { job: &'a str, error: &'a io::Error },
+}
+
+/// Runs named sequences on intervals or cron expressions.
+#[derive(Debug, Clone, Default)]
+pub struct Scheduler {
+    jobs: Vec<Job>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, job: Job) -> &mut Self {
+        self.jobs.push(job);
+        self
+    }
+
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    /// Read a schedule file: a `[jobs.<name>]` table per job, with the
+    /// `script` to play (relative to the file, with includes resolved as by
+    /// [`ScriptLoader::from_env`]) and either a `cron` expression, with an
+    /// optional `utc_offset` in hours, or `every` as a duration. `device`
+    /// and `overlap` (`"skip"` or `"delay"`) are optional.
+    ///
+    /// ```text
+    /// [jobs.login-bonus]
+    /// script = "login.macro"
+    /// cron = "0 6 * * *"
+    /// utc_offset = 9
+    ///
+    /// [jobs.feed]
+    /// device = "/dev/ttyACM1"
+    /// script = "feed.macro"
+    /// every = "1800s"
+    /// overlap = "delay"
+    /// ```
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let doc = Document::parse(&fs::read_to_string(path)?)
+            .map_err(|e| invalid(format!("invalid schedule: {e}")))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let loader = ScriptLoader::from_env();
+        let mut scheduler = Scheduler::new();
+        for (table, entries) in doc.tables() {
+            let bad = |key: &str, what: &str| {
+                let line = doc.line(table, key);
+                invalid(format!("invalid schedule: line {line}: {what}"))
+            };
+            let Some(name) = table.strip_prefix("jobs.") else {
+                match entries.first() {
+                    Some((key, _)) => return Err(bad(key, &format!("unknown key `{key}`"))),
+                    None => continue,
+                }
+            };
+            let get = |key: &str| entries.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+            let string = |key: &str| match get(key) {
+                None => Ok(None),
+                Some(value) => value
+                    .as_str()
+                    .map(Some)
+                    .ok_or_else(|| bad(key, &format!("`{key}` must be a string"))),
+            };
+            for (key, _) in entries {
+                if !["script", "cron", "utc_offset", "every", "device", "overlap"]
+                    .contains(&key.as_str())
+                {
+                    return Err(bad(key, &format!("unknown key `{key}`")));
+                }
+            }
+
+            let script = string("script")?.ok_or_else(|| {
+                invalid(format!("invalid schedule: job `{name}` has no `script`"))
+            })?;
+            let sequence = loader
+                .load(dir.join(script))
+                .map_err(|e| io::Error::new(e.kind(), format!("job `{name}`: {script}: {e}")))?;
+            let schedule = match (string("cron")?, string("every")?) {
+                (Some(cron), None) => {
+                    let cron: Cron = cron.parse().map_err(|e| bad("cron", &format!("{e}")))?;
+                    let offset = match get("utc_offset") {
+                        None => 0.0,
+                        Some(hours) => hours
+                            .as_f64()
+                            .filter(|h| h.abs() <= 24.0)
+                            .ok_or_else(|| bad("utc_offset", "`utc_offset` must be hours"))?,
+                    };
+                    Schedule::Cron(cron.with_utc_offset((offset * 3600.0).round() as i64))
+                }
+                (None, Some(every)) => Schedule::Every(
+                    parse_duration(every)
+                        .ok()
+                        .filter(|d| !d.is_zero())
+                        .ok_or_else(|| bad("every", &format!("invalid interval `{every}`")))?,
+                ),
+                _ => {
+                    return Err(invalid(format!(
+                        "invalid schedule: job `{name}` needs one of `cron` or `every`"
+                    )));
+                }
+            };
+            let mut job = Job::new(name, schedule, sequence);
+            job.overlap = match string("overlap")? {
+                None | Some("skip") => Overlap::Skip,
+                Some("delay") => Overlap::Delay,
+                Some(other) => return Err(bad("overlap", &format!("unknown overlap `{other}`"))),
+            };
+            job.device = string("device")?.map(str::to_string);
+            scheduler.add(job);
+        }
+        Ok(scheduler)
+    }
+
+    /// Compute the first run time of every job that has none yet.
+    fn arm(&mut self, now: SystemTime) {
+        for job in self.jobs.iter_mut().filter(|job| job.next.is_none()) {
+            job.next = job.schedule.next_after(now);
+        }
+    }
+
+    /// The job due soonest.
+    fn next_job(&self) -> Option<usize> {
+        (0..self.jobs.len())
+            .filter(|&i| self.jobs[i].next.is_some())
+            .min_by_key(|&i| self.jobs[i].next)
+    }
+
+    /// Run jobs as they come due until `stop` is set or no job has a future run.
+    ///
+    /// Jobs play one at a time. A run that comes due while another job is
+    /// playing is handled according to that job's [`Overlap`] policy. Every
+    /// start, finish, skip and failure is reported to `log`.
+    pub fn run(
+        &mut self,
+        ctrl: &mut SwitchController,
+        stop: &AtomicBool,
+        log: impl FnMut(SchedulerEvent<'_>),
+    ) -> io::Result<()> {
+        self.run_with(stop, log, |job| ctrl.play(&job.sequence))
+    }
+
+    /// [`Scheduler::run`], but submitting every run to its job's device on
+    /// `jobs` and waiting for it to finish, so scheduled runs queue with
+    /// the manager's other jobs. A run cancelled through the manager counts
+    /// as finished; one still playing when `stop` is set is cancelled.
+    pub fn run_jobs(
+        &mut self,
+        jobs: &JobManager,
+        stop: &AtomicBool,
+        log: impl FnMut(SchedulerEvent<'_>),
+    ) -> io::Result<()> {
+        self.run_with(stop, log, |job| {
+            let only = || {
+                let mut devices = jobs.devices();
+                devices.next().filter(|_| devices.next().is_none())
+            };
+            let device = job.device.as_deref().or_else(only).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("job `{}` has no device", job.name),
+                )
+            })?;
+            let id = jobs.submit(device, JobSpec::new(&job.name, job.sequence.clone()))?;
+            loop {
+                match jobs.job(id).map(|info| info.status) {
+                    Some(JobStatus::Failed(message)) => return Err(io::Error::other(message)),
+                    Some(status) if !status.is_done() => {}
+                    _ => return Ok(()),
+                }
+                if stop.load(Ordering::Relaxed) {
+                    jobs.cancel(id);
+                }
+                thread::sleep(STOP_POLL);
+            }
+        })
+    }
+
+    /// The loop of [`Scheduler::run`], playing each run with `play`.
+    fn run_with(
+        &mut self,
+        stop: &AtomicBool,
+        mut log: impl FnMut(SchedulerEvent<'_>),
+        mut play: impl FnMut(&Job) -> io::Result<()>,
+    ) -> io::Result<()> {
+        self.arm(SystemTime::now());
+        let mut busy_until = SystemTime::now();
+
+        while !stop.load(Ordering::Relaxed) {
+            let Some(index) = self.next_job() else {
+                return Ok(());
+            };
+            let job = &mut self.jobs[index];
+            let due = job.next.unwrap();
+
+            let now = SystemTime::now();
+            if let Ok(wait) = due.duration_since(now) {
+                thread::sleep(wait.min(STOP_POLL));
+                continue;
+            }
+
+            if due < busy_until && job.overlap == Overlap::Skip {
+                log(SchedulerEvent::Skipped {
+                    job: &job.name,
+                    due,
+                });
+                job.next = job.schedule.next_after(due);
+                continue;
+            }
+
+            log(SchedulerEvent::Started { job: &job.name });
+            let started = SystemTime::now();
+            if let Err(error) = play(job) {
+                log(SchedulerEvent::Failed {
+                    job: &job.name,
+                    error: &error,
+                });
+                return Err(error);
+            }
+            busy_until = SystemTime::now();
+            log(SchedulerEvent::Finished {
+                job: &job.name,
+                elapsed: busy_until.duration_since(started).unwrap_or_default(),
+            });
+
+            // For delayed runs, don't replay every missed slot back to back.
+            let mut next = job.schedule.next_after(due);
+            while let Some(t) = next.filter(|&t| t < started) {
+                next = job.schedule.next_after(t).filter(|&n| n > t);
+            }
+            job.next = next;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_783), (2024, 3, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    }
+
+    #[test]
+    fn daily_cron() {
+        let cron: Cron = "0 6 * * *".parse().unwrap();
+        // 2024-03-01 05:59:30 UTC
+        let t = at(1_709_272_770);
+        assert_eq!(cron.next_after(t), Some(at(1_709_272_800)));
+        // Just after 06:00 rolls to the next day.
+        assert_eq!(cron.next_after(at(1_709_272_800)), Some(at(1_709_359_200)));
+    }
+
+    #[test]
+    fn cron_with_offset_and_weekdays() {
+        // 06:00 at UTC+9 on Mondays.
+        let cron = "0 6 * * 1"
+            .parse::<Cron>()
+            .unwrap()
+            .with_utc_offset(9 * 3600);
+        // 2024-03-01 (a Friday) 00:00 UTC -> Monday 2024-03-04 06:00 +09:00.
+        assert_eq!(cron.next_after(at(1_709_251_200)), Some(at(1_709_499_600)));
+    }
+
+    #[test]
+    fn cron_fields() {
+        assert_eq!(
+            parse_field("*/15", 0, 59).unwrap(),
+            1 | 1 << 15 | 1 << 30 | 1 << 45
+        );
+        assert_eq!(parse_field("1-3,7", 0, 59).unwrap(), 0b1000_1110);
+        assert!(parse_field("60", 0, 59).is_err());
+        assert!("* * *".parse::<Cron>().is_err());
+        assert_eq!(
+            "0 0 30 2 *".parse::<Cron>().unwrap().next_after(at(0)),
+            None
+        );
+        assert_eq!(Schedule::Every(Duration::MAX).next_after(at(0)), None);
+    }
+
+    #[test]
+    fn runs_schedule_files_on_jobs() {
+        let dir =
+            std::env::temp_dir().join(format!("switchcontroller-schedule-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("tap.macro"), "press a\n").unwrap();
+        let write = |text: &str| {
+            fs::write(dir.join("schedule.toml"), text).unwrap();
+            Scheduler::load(dir.join("schedule.toml"))
+        };
+        let err = write("[jobs.tap]\nscript = \"tap.macro\"\ncron = \"6 * *\"\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid schedule: line 3: invalid cron expression: expected 5 fields, got 3"
+        );
+        let mut scheduler =
+            write("[jobs.tap]\nscript = \"tap.macro\"\nevery = \"10ms\"\n").unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let out = crate::tests::Shared::default();
+        let mut jobs = JobManager::new();
+        jobs.add_device("left", SwitchController::from_writer(out.clone()))
+            .unwrap();
+        let stop = AtomicBool::new(false);
+        scheduler
+            .run_jobs(&jobs, &stop, |event| {
+                if let SchedulerEvent::Finished { .. } = event {
+                    stop.store(true, Ordering::Relaxed);
+                }
+            })
+            .unwrap();
+        assert!(out.text().starts_with("PRESS a\n"));
+    }
+}
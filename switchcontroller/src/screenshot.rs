@@ -0,0 +1,154 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::framelog::timestamped_path;
+use crate::{Button, Frame, InputSequence, SwitchController};
+
+/// How long [`SwitchController::save_video_clip`] holds Capture. The console
+/// starts saving a clip after about a second; a shorter hold only takes a
+/// screenshot.
+pub const VIDEO_CLIP_HOLD: Duration = Duration::from_millis(1500);
+
+impl SwitchController {
+    /// Press Capture, saving a screenshot to the console's album.
+    pub fn take_screenshot(&mut self) -> io::Result<()> {
+        self.press(&[Button::Capture])
+    }
+
+    /// Hold Capture for [`VIDEO_CLIP_HOLD`], saving a clip of the last 30
+    /// seconds of gameplay to the console's album.
+    pub fn save_video_clip(&mut self) -> io::Result<()> {
+        self.save_video_clip_with(|| {})
+    }
+
+    /// [`save_video_clip`](Self::save_video_clip), calling `on_start` the
+    /// moment Capture goes down, to start a host-side recording (OBS, a
+    /// capture-card file) at the same time. Publishing
+    /// [`AutomationEvent::VideoClip`](crate::AutomationEvent::VideoClip)
+    /// from it reaches every [`EventBus`](crate::EventBus) subscriber.
+    pub fn save_video_clip_with(&mut self, on_start: impl FnOnce()) -> io::Result<()> {
+        self.hold(&[Button::Capture])?;
+        self.flush()?;
+        on_start();
+        self.play(
+            InputSequence::new()
+                .wait(VIDEO_CLIP_HOLD)
+                .release(&[Button::Capture]),
+        )
+    }
+}
+
+/// Keeps captured frames as evidence, in a directory of timestamped PPM
+/// images named `<unix-ms>-<prefix>.ppm`, optionally deleting the oldest
+/// beyond a limit so a week-long hunt does not fill the disk.
+#[derive(Debug, Clone)]
+pub struct FrameStore {
+    dir: PathBuf,
+    prefix: String,
+    keep: Option<usize>,
+}
+
+impl FrameStore {
+    /// A store in `dir`, created on first save, keeping every frame.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            prefix: "frame".to_string(),
+            keep: None,
+        }
+    }
+
+    /// Name frames `<unix-ms>-<prefix>.ppm`; `frame` by default. Stores
+    /// sharing a directory with different prefixes prune separately.
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Keep only the newest `count` frames.
+    pub fn keep(mut self, count: usize) -> Self {
+        self.keep = Some(count);
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Save `frame` and prune old frames. Returns the path written.
+    pub fn save_frame(&self, frame: &Frame) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.dir)?;
+        let path = timestamped_path(&self.dir, &self.prefix);
+        frame.save_ppm(&path, &self.prefix)?;
+        if let Some(keep) = self.keep {
+            let frames = self.frames()?;
+            for old in &frames[..frames.len().saturating_sub(keep)] {
+                fs::remove_file(old)?;
+            }
+        }
+        Ok(path)
+    }
+
+    /// The frames in the store, oldest first.
+    pub fn frames(&self) -> io::Result<Vec<PathBuf>> {
+        let mut frames = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            // <millis>-<prefix>[-<n>].ppm
+            let Some((millis, rest)) = name
+                .strip_suffix(".ppm")
+                .and_then(|stem| stem.split_once('-'))
+            else {
+                continue;
+            };
+            let Some(rest) = rest.strip_prefix(self.prefix.as_str()) else {
+                continue;
+            };
+            let n = match rest.strip_prefix('-') {
+                None if rest.is_empty() => Some(1),
+                Some(n) => n.parse::<u64>().ok(),
+                None => None,
+            };
+            if let (Ok(millis), Some(n)) = (millis.parse::<u128>(), n) {
+                frames.push(((millis, n), path));
+            }
+        }
+        frames.sort();
+        Ok(frames.into_iter().map(|(_, path)| path).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Shared;
+
+    #[test]
+    fn keeps_the_newest_frames() {
+        let out = Shared::default();
+        let mut ctrl = SwitchController::from_writer(out.clone());
+        ctrl.take_screenshot().unwrap();
+        ctrl.set_playback_speed(100.0);
+        let mut started = false;
+        ctrl.save_video_clip_with(|| started = true).unwrap();
+        assert!(started);
+        assert_eq!(out.text(), "PRESS capture\nHOLD capture\nRELEASE capture\n");
+
+        let dir =
+            std::env::temp_dir().join(format!("switchcontroller-frames-{}", std::process::id()));
+        let store = FrameStore::new(&dir).prefix("shiny").keep(2);
+        let other = FrameStore::new(&dir).prefix("shiny-egg");
+        let saved: Vec<PathBuf> = (0..4u8)
+            .map(|i| store.save_frame(&Frame::filled(1, 1, [i; 3])).unwrap())
+            .collect();
+        other.save_frame(&Frame::filled(1, 1, [9; 3])).unwrap();
+        assert_eq!(store.frames().unwrap(), saved[2..]);
+        assert_eq!(other.frames().unwrap().len(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,410 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::dsl;
+use crate::duration::{format_duration, parse_duration};
+use crate::toml::{Document, Value};
+use crate::{Button, ControllerState, InputSequence, PluginCommand, Program, Step, Stick};
+
+/// An error parsing a macro script. See [`InputSequence::to_script`] for the
+/// format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseScriptError {
+    /// 1-based line the error was found on, or 0 if it is not tied to a line.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            0 => write!(f, "invalid script: {}", self.message),
+            line => write!(f, "invalid script: line {line}: {}", self.message),
+        }
+    }
+}
+
+impl Error for ParseScriptError {}
+
+fn number(word: Option<&str>, what: &str) -> Result<f32, String> {
+    let word = word.ok_or_else(|| format!("missing {what}"))?;
+    word.parse().map_err(|_| format!("invalid {what} `{word}`"))
+}
+
+fn buttons<'a>(words: impl Iterator<Item = &'a str>) -> Result<Vec<Button>, String> {
+    let buttons = words
+        .map(|w| Button::from_name(w).ok_or_else(|| format!("unknown button `{w}`")))
+        .collect::<Result<Vec<_>, _>>()?;
+    if buttons.is_empty() {
+        return Err("missing button".into());
+    }
+    Ok(buttons)
+}
+
+fn state(words: &[&str]) -> Result<ControllerState, String> {
+    let bits = words.first().ok_or("missing button bits")?;
+    if bits.len() != Button::ALL.len() || !bits.bytes().all(|b| b == b'0' || b == b'1') {
+        return Err(format!("invalid button bits `{bits}`"));
+    }
+    let mut state = ControllerState::new();
+    for (button, bit) in Button::ALL.into_iter().zip(bits.bytes()) {
+        state.set_button(button, bit == b'1');
+    }
+    let axes = words[1..]
+        .iter()
+        .map(|w| number(Some(w), "stick value"))
+        .collect::<Result<Vec<_>, _>>()?;
+    match axes[..] {
+        [] => {}
+        [lh, lv] => {
+            state.set_left_stick(lh, lv);
+        }
+        [lh, lv, rh, rv] => {
+            state.set_left_stick(lh, lv).set_right_stick(rh, rv);
+        }
+        _ => return Err("expected 0, 2 or 4 stick values".into()),
+    }
+    Ok(state)
+}
+
+/// Parse one script line into a step, or `None` for blank and comment lines.
+pub(crate) fn parse_line(line: &str) -> Result<Option<Step>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let lower = line.to_ascii_lowercase();
+    let mut words = lower.split_whitespace();
+    let verb = words.next().unwrap();
+    let step = match verb {
+        "press" => Step::Press(buttons(words)?),
+        "hold" => Step::Hold(buttons(words)?),
+        "release" => Step::Release(buttons(words)?),
+        "stick" => {
+            let name = words.next().ok_or("missing stick")?;
+            let stick = Stick::from_name(name).ok_or_else(|| format!("unknown stick `{name}`"))?;
+            let h = number(words.next(), "horizontal value")?;
+            let v = number(words.next(), "vertical value")?;
+            if words.next().is_some() {
+                return Err("unexpected text after stick values".into());
+            }
+            Step::Stick(stick, h, v)
+        }
+        "state" => Step::State(state(&words.collect::<Vec<_>>())?),
+        // A device-side pause becomes a host-side wait of the same length.
+        "wait" | "sleep" => {
+            let duration = parse_duration(words.next().ok_or("missing duration")?)?;
+            if words.next().is_some() {
+                return Err("unexpected text after duration".into());
+            }
+            Step::Wait(duration)
+        }
+        "mark" => {
+            // Names keep their case, unlike commands.
+            let name = line
+                .split_whitespace()
+                .nth(1)
+                .ok_or("missing marker name")?;
+            if words.nth(1).is_some() {
+                return Err("marker names cannot contain spaces".into());
+            }
+            Step::Marker(name.to_string())
+        }
+        _ => return Err(format!("unknown command `{verb}`")),
+    };
+    Ok(Some(step))
+}
+
+/// One step as a script line, the inverse of [`parse_line`].
+pub(crate) fn script_line(step: &Step) -> String {
+    match step {
+        Step::Wait(duration) => format!("WAIT {}", format_duration(*duration)),
+        Step::Marker(name) => format!("MARK {name}"),
+        step => step.to_command().unwrap(),
+    }
+}
+
+impl InputSequence {
+    /// The sequence as a macro script: one device command per line, in the
+    /// wire format of the firmware's command reference, plus `WAIT <duration>`
+    /// for host-side waits and `MARK <name>` for markers. Durations are
+    /// written `750ms`, `2.5s`, `12f` (frames at 60 fps) or as plain seconds.
+    /// Commands are case-insensitive and lines starting with `#` are comments.
+    ///
+    /// ```text
+    /// # open the menu
+    /// PRESS x
+    /// WAIT 500ms
+    /// STICK l_stick 0 -1
+    /// ```
+    pub fn to_script(&self) -> String {
+        let mut out = String::new();
+        for step in self.steps() {
+            out.push_str(&script_line(step));
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Read a macro script from a file, resolving includes with
+    /// [`ScriptLoader::from_env`].
+    pub fn load_script(path: impl AsRef<Path>) -> io::Result<Self> {
+        ScriptLoader::from_env().load(path)
+    }
+
+    /// Write the sequence to a file as a macro script.
+    pub fn save_script(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_script())
+    }
+}
+
+impl InputSequence {
+    /// The sequence as TOML, for hand-edited macro files: a `steps` array of
+    /// script lines (see [`InputSequence::to_script`]).
+    ///
+    /// ```toml
+    /// steps = [
+    ///     "PRESS x",
+    ///     "WAIT 750ms",
+    ///     "STICK l_stick 0 -1",
+    /// ]
+    /// ```
+    pub fn to_toml(&self) -> String {
+        // One step per line, which the TOML writer doesn't do for arrays.
+        let mut out = String::from("steps = [\n");
+        for step in self.steps() {
+            out.push_str(&format!("    {},\n", Value::String(script_line(step))));
+        }
+        out.push_str("]\n");
+        out
+    }
+
+    /// Parse the format written by [`InputSequence::to_toml`]. Other keys
+    /// are ignored, so files can carry their own front matter.
+    pub fn from_toml(s: &str) -> Result<Self, ParseScriptError> {
+        let doc = Document::parse(s).map_err(|e| ParseScriptError {
+            line: e.line,
+            message: e.message,
+        })?;
+        let error = |message: String| ParseScriptError { line: 0, message };
+        let steps = doc
+            .table("")
+            .and_then(|t| t.iter().find(|(k, _)| k == "steps"))
+            .and_then(|(_, v)| v.as_array())
+            .ok_or_else(|| error("missing `steps` array".into()))?;
+        let mut seq = InputSequence::new();
+        for (i, line) in steps.iter().enumerate() {
+            let step = line
+                .as_str()
+                .ok_or_else(|| "must be a string".to_string())
+                .and_then(parse_line)
+                .map_err(|e| error(format!("step {}: {e}", i + 1)))?;
+            if let Some(step) = step {
+                seq.push(step);
+            }
+        }
+        Ok(seq)
+    }
+}
+
+/// Reads macro scripts, resolving `include "path"` lines.
+///
+/// An include is looked up relative to the file containing it, then in each
+/// search directory in the order added. Included files can define macros and
+/// variables for the rest of the including script.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptLoader {
+    search_path: Vec<PathBuf>,
+    commands: Vec<PluginCommand>,
+    confined: bool,
+}
+
+impl ScriptLoader {
+    /// A loader with an empty search path.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A loader searching the directories in `$SWITCHCONTROLLER_MACRO_PATH`,
+    /// separated like `PATH`.
+    pub fn from_env() -> Self {
+        let search_path = std::env::var_os("SWITCHCONTROLLER_MACRO_PATH")
+            .map(|paths| std::env::split_paths(&paths).collect())
+            .unwrap_or_default();
+        Self {
+            search_path,
+            commands: Vec::new(),
+            confined: false,
+        }
+    }
+
+    /// Add a directory to search for includes.
+    pub fn search_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.search_path.push(dir.into());
+        self
+    }
+
+    pub fn search_path(&self) -> &[PathBuf] {
+        &self.search_path
+    }
+
+    /// Only resolve includes inside the search directories, for scripts from
+    /// clients that shouldn't read other files, such as
+    /// [`RpcServer`](crate::RpcServer) requests. Absolute paths, `..` and
+    /// the working directory are refused, and errors inside included files
+    /// leave out what the failing line says.
+    pub fn confine(mut self) -> Self {
+        self.confined = true;
+        self
+    }
+
+    /// Allow calls to plugin `commands` in scripts, as with macros.
+    pub fn commands(mut self, commands: &[PluginCommand]) -> Self {
+        self.commands.extend_from_slice(commands);
+        self
+    }
+
+    /// Read the script at `path`.
+    pub fn load(&self, path: impl AsRef<Path>) -> io::Result<InputSequence> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)?;
+        dsl::expand(
+            &source,
+            Some(path),
+            &self.search_path,
+            &self.commands,
+            self.confined,
+        )
+        .map(InputSequence::from)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Parse a script, resolving includes from the working directory.
+    pub fn parse(&self, source: &str) -> Result<InputSequence, ParseScriptError> {
+        dsl::expand(
+            source,
+            None,
+            &self.search_path,
+            &self.commands,
+            self.confined,
+        )
+        .map(InputSequence::from)
+    }
+
+    /// Read the script at `path` as a [`Program`], allowing jumps.
+    pub fn load_program(&self, path: impl AsRef<Path>) -> io::Result<Program> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)?;
+        dsl::expand_ops(
+            &source,
+            Some(path),
+            &self.search_path,
+            &self.commands,
+            true,
+            self.confined,
+        )
+        .and_then(Program::from_ops)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Parse a script as a [`Program`], resolving includes from the working
+    /// directory.
+    pub fn parse_program(&self, source: &str) -> Result<Program, ParseScriptError> {
+        dsl::expand_ops(
+            source,
+            None,
+            &self.search_path,
+            &self.commands,
+            true,
+            self.confined,
+        )
+        .and_then(Program::from_ops)
+    }
+}
+
+impl FromStr for InputSequence {
+    type Err = ParseScriptError;
+
+    /// Parse a macro script. `SLEEP` lines are accepted and read as waits.
+    ///
+    /// Scripts may also use variables (`let delay = 250`, then `$delay` or
+    /// `${delay}ms`), arithmetic (`+ - * /`), `repeat <count> { .. }` blocks
+    /// and macros with parameters (`macro name(a, b) { .. }`, called as
+    /// `name(1, x)`). These are expanded on parsing, so the sequence holds
+    /// only plain steps. Includes are resolved from the working directory;
+    /// use a [`ScriptLoader`] to set a search path.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ScriptLoader::new().parse(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn script_round_trip() {
+        let mut state = ControllerState::new();
+        state.set_button(Button::ZR, true).set_left_stick(0.5, -1.0);
+        let mut seq = InputSequence::new();
+        seq.press(&[Button::A, Button::DpadUp])
+            .marker("Start")
+            .wait(Duration::from_millis(250))
+            .hold(&[Button::B])
+            .stick(Stick::Right, -1.0, 0.5)
+            .state(&state)
+            .release(&[Button::B]);
+        let script = seq.to_script();
+        assert_eq!(
+            script,
+            "PRESS a dpad_up\nMARK Start\nWAIT 250ms\nHOLD b\nSTICK r_stick -1 0.5\n\
+             STATE 000000010000000000 0.5 -1\nRELEASE b\n"
+        );
+        let parsed: InputSequence = script.parse().unwrap();
+        assert_eq!(parsed.to_script(), script);
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        let mut seq = InputSequence::new();
+        seq.press(&[Button::A])
+            .wait(Duration::from_millis(750))
+            .marker("loop")
+            .stick(Stick::Left, 0.0, -1.0)
+            .wait(Duration::from_millis(2500));
+        let toml = seq.to_toml();
+        assert_eq!(
+            toml,
+            "steps = [\n    \"PRESS a\",\n    \"WAIT 750ms\",\n    \"MARK loop\",\n    \
+             \"STICK l_stick 0 -1\",\n    \"WAIT 2.5s\",\n]\n"
+        );
+        let back = InputSequence::from_toml(&format!("author = \"me\"\n{toml}")).unwrap();
+        assert_eq!(back.to_script(), seq.to_script());
+        let err = InputSequence::from_toml("steps = [\"WAIT 1x\"]").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid script: step 1: invalid duration `1x`"
+        );
+    }
+
+    #[test]
+    fn script_errors() {
+        let parsed: InputSequence = "# comment\n\npress A\nSleep 2".parse().unwrap();
+        assert_eq!(parsed.to_script(), "PRESS a\nWAIT 2s\n");
+        let err = "PRESS a\nPRESS q".parse::<InputSequence>().unwrap_err();
+        assert_eq!((err.line, err.message.as_str()), (2, "unknown button `q`"));
+        assert!("STATE 0101".parse::<InputSequence>().is_err());
+        assert!("STICK l_stick 1".parse::<InputSequence>().is_err());
+        assert!("WAIT -1".parse::<InputSequence>().is_err());
+        assert!("WAIT soon".parse::<InputSequence>().is_err());
+        let frames: InputSequence = "WAIT 12f".parse().unwrap();
+        assert_eq!(frames.duration(), Duration::from_millis(200));
+        assert!("jump".parse::<InputSequence>().is_err());
+    }
+}
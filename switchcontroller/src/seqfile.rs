@@ -0,0 +1,334 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::json::{self, Json};
+use crate::script::{parse_line, script_line};
+use crate::toml::{Document, Value};
+use crate::{InputSequence, Profile};
+
+/// Identifies a JSON document as a sequence file.
+const FORMAT: &str = "switchcontroller-sequence";
+
+/// An error reading a [`SequenceFile`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseSequenceFileError {
+    pub message: String,
+}
+
+impl ParseSequenceFileError {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseSequenceFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid sequence file: {}", self.message)
+    }
+}
+
+impl Error for ParseSequenceFileError {}
+
+/// Descriptive fields stored with a [`SequenceFile`]. None of them affect
+/// playback.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SequenceMetadata {
+    pub name: Option<String>,
+    pub author: Option<String>,
+    /// The game the sequence was written for.
+    pub game: Option<String>,
+    /// The firmware version the sequence was tested against.
+    pub firmware: Option<String>,
+    pub description: Option<String>,
+}
+
+impl SequenceMetadata {
+    fn fields(&self) -> [(&'static str, &Option<String>); 5] {
+        [
+            ("name", &self.name),
+            ("author", &self.author),
+            ("game", &self.game),
+            ("firmware", &self.firmware),
+            ("description", &self.description),
+        ]
+    }
+
+    fn field_mut(&mut self, key: &str) -> Option<&mut Option<String>> {
+        match key {
+            "name" => Some(&mut self.name),
+            "author" => Some(&mut self.author),
+            "game" => Some(&mut self.game),
+            "firmware" => Some(&mut self.firmware),
+            "description" => Some(&mut self.description),
+            _ => None,
+        }
+    }
+}
+
+/// A sequence bundled with its metadata and the profiles it expects, for
+/// sharing as a single file.
+///
+/// Files are JSON, with steps written as macro script lines (see
+/// [`InputSequence::to_script`]) and profiles as objects mirroring their
+/// TOML tables:
+///
+/// ```json
+/// {
+///   "format": "switchcontroller-sequence",
+///   "version": 1,
+///   "metadata": {"name": "shiny-reset", "game": "Pokemon Violet"},
+///   "profiles": [{"name": "violet", "remap": {"swap_sticks": false}}],
+///   "steps": ["PRESS a", "WAIT 500ms", "MARK top"]
+/// }
+/// ```
+///
+/// Files with a `version` newer than [`SequenceFile::VERSION`] are rejected
+/// rather than misread; unknown keys are ignored, so fields can be added
+/// without a version bump.
+#[derive(Debug, Clone, Default)]
+pub struct SequenceFile {
+    pub metadata: SequenceMetadata,
+    pub profiles: Vec<Profile>,
+    pub sequence: InputSequence,
+}
+
+impl SequenceFile {
+    /// The format version written by [`SequenceFile::to_json`].
+    pub const VERSION: u32 = 1;
+
+    pub fn new(sequence: InputSequence) -> Self {
+        Self {
+            sequence,
+            ..Self::default()
+        }
+    }
+
+    /// The embedded profile called `name`.
+    pub fn profile(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.name == name)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        fs::read_to_string(path)?
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_json())
+    }
+
+    /// Serialize to the format read by [`SequenceFile::load`].
+    pub fn to_json(&self) -> String {
+        let metadata = self
+            .metadata
+            .fields()
+            .into_iter()
+            .filter_map(|(key, value)| Some((key.to_string(), Json::String(value.clone()?))))
+            .collect();
+        let profiles = self
+            .profiles
+            .iter()
+            .map(|p| document_to_json(&p.to_document()))
+            .collect();
+        let steps = self
+            .sequence
+            .steps()
+            .iter()
+            .map(|step| Json::String(script_line(step)))
+            .collect();
+        Json::Object(vec![
+            ("format".into(), Json::String(FORMAT.into())),
+            ("version".into(), Json::Number(Self::VERSION.into())),
+            ("metadata".into(), Json::Object(metadata)),
+            ("profiles".into(), Json::Array(profiles)),
+            ("steps".into(), Json::Array(steps)),
+        ])
+        .to_string()
+    }
+}
+
+impl FromStr for SequenceFile {
+    type Err = ParseSequenceFileError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let root = json::parse(s).map_err(|e| ParseSequenceFileError::new(e.to_string()))?;
+        if root.get("format").and_then(Json::as_str) != Some(FORMAT) {
+            return Err(ParseSequenceFileError::new("not a sequence file"));
+        }
+        let version = root
+            .get("version")
+            .and_then(Json::as_f64)
+            .ok_or_else(|| ParseSequenceFileError::new("missing version"))?;
+        if version > f64::from(Self::VERSION) {
+            return Err(ParseSequenceFileError::new(format!(
+                "unsupported format version {version}"
+            )));
+        }
+
+        let mut file = SequenceFile::default();
+        for (key, value) in root
+            .get("metadata")
+            .and_then(Json::as_object)
+            .unwrap_or_default()
+        {
+            if let Some(field) = file.metadata.field_mut(key) {
+                let value = value.as_str().ok_or_else(|| {
+                    ParseSequenceFileError::new(format!("metadata `{key}` must be a string"))
+                })?;
+                *field = Some(value.to_string());
+            }
+        }
+        for (i, profile) in root
+            .get("profiles")
+            .and_then(Json::as_array)
+            .unwrap_or_default()
+            .iter()
+            .enumerate()
+        {
+            let mut doc = Document::new();
+            let profile = json_to_document(profile, "", &mut doc)
+                .and_then(|()| Profile::from_document(&doc).map_err(|e| e.to_string()))
+                .map_err(|e| ParseSequenceFileError::new(format!("profile {}: {e}", i + 1)))?;
+            file.profiles.push(profile);
+        }
+        let steps = root
+            .get("steps")
+            .and_then(Json::as_array)
+            .ok_or_else(|| ParseSequenceFileError::new("missing steps"))?;
+        for (i, line) in steps.iter().enumerate() {
+            let step = line
+                .as_str()
+                .ok_or_else(|| "must be a string".to_string())
+                .and_then(parse_line)
+                .map_err(|e| ParseSequenceFileError::new(format!("step {}: {e}", i + 1)))?;
+            if let Some(step) = step {
+                file.sequence.push(step);
+            }
+        }
+        Ok(file)
+    }
+}
+
+/// Nest dotted table names as objects: `[remap.buttons]` becomes
+/// `"remap": {"buttons": {..}}`.
+fn document_to_json(doc: &Document) -> Json {
+    fn value(v: &Value) -> Json {
+        match v {
+            Value::String(s) => Json::String(s.clone()),
+            Value::Number(n) => Json::Number(*n),
+            Value::Bool(b) => Json::Bool(*b),
+            Value::Array(items) => Json::Array(items.iter().map(value).collect()),
+        }
+    }
+    fn table<'a>(root: &'a mut Vec<(String, Json)>, path: &str) -> &'a mut Vec<(String, Json)> {
+        let mut members = root;
+        for part in path.split('.').filter(|p| !p.is_empty()) {
+            let index = match members.iter().position(|(k, _)| k == part) {
+                Some(i) => i,
+                None => {
+                    members.push((part.to_string(), Json::Object(Vec::new())));
+                    members.len() - 1
+                }
+            };
+            let Json::Object(inner) = &mut members[index].1 else {
+                unreachable!("tables only nest inside tables");
+            };
+            members = inner;
+        }
+        members
+    }
+    let mut root = Vec::new();
+    for (name, entries) in doc.tables() {
+        let members = table(&mut root, name);
+        members.extend(entries.iter().map(|(k, v)| (k.clone(), value(v))));
+    }
+    Json::Object(root)
+}
+
+/// The inverse of [`document_to_json`], adding `json`'s members to the
+/// table `name` of `doc`.
+fn json_to_document(json: &Json, name: &str, doc: &mut Document) -> Result<(), String> {
+    fn value(json: &Json) -> Result<Value, String> {
+        match json {
+            Json::String(s) => Ok(Value::String(s.clone())),
+            Json::Number(n) => Ok(Value::Number(*n)),
+            Json::Bool(b) => Ok(Value::Bool(*b)),
+            Json::Array(items) => items
+                .iter()
+                .map(value)
+                .collect::<Result<_, _>>()
+                .map(Value::Array),
+            Json::Null | Json::Object(_) => Err("unsupported value".into()),
+        }
+    }
+    let members = json.as_object().ok_or("expected an object")?;
+    for (key, member) in members {
+        if let Json::Object(_) = member {
+            let inner = match name {
+                "" => key.clone(),
+                _ => format!("{name}.{key}"),
+            };
+            json_to_document(member, &inner, doc)?;
+        } else {
+            doc.set(
+                name,
+                key,
+                value(member).map_err(|e| format!("`{key}`: {e}"))?,
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{Button, Stick};
+
+    #[test]
+    fn round_trip() {
+        let mut profile = Profile::new("violet");
+        profile.remap.swap_sticks();
+        profile.remap.buttons.insert(Button::A, Button::B);
+        let mut seq = InputSequence::new();
+        seq.press(&[Button::A])
+            .marker("top")
+            .wait(Duration::from_millis(500))
+            .stick(Stick::Left, 0.0, -1.0);
+        let mut file = SequenceFile::new(seq);
+        file.metadata.author = Some("Yvonne \"Y\"".into());
+        file.metadata.firmware = Some("1.2".into());
+        file.profiles.push(profile.clone());
+
+        let json = file.to_json();
+        assert!(json.starts_with(r#"{"format":"switchcontroller-sequence","version":1,"#));
+        let back: SequenceFile = json.parse().unwrap();
+        assert_eq!(back.metadata, file.metadata);
+        assert_eq!(back.profile("violet").unwrap().to_toml(), profile.to_toml());
+        assert_eq!(back.sequence.to_script(), file.sequence.to_script());
+    }
+
+    #[test]
+    fn rejects_bad_files() {
+        let err = |s: &str| s.parse::<SequenceFile>().unwrap_err().message;
+        assert_eq!(err("{}"), "not a sequence file");
+        assert_eq!(
+            err(r#"{"format":"switchcontroller-sequence","version":2,"steps":[]}"#),
+            "unsupported format version 2"
+        );
+        assert_eq!(
+            err(r#"{"format":"switchcontroller-sequence","version":1,"steps":["PRESS q"]}"#),
+            "step 1: unknown button `q`"
+        );
+        assert!(err("{").starts_with("byte 1:"));
+    }
+}
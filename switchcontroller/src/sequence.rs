@@ -0,0 +1,333 @@
+use std::time::Duration;
+
+use crate::{Button, Command, ControllerState, PRESS_DURATION, PlaybackPosition, Stick};
+
+/// A single step of an [`InputSequence`].
+#[derive(Debug, Clone)]
+pub enum Step {
+    /// Press and immediately release buttons (`PRESS`).
+    Press(Vec<Button>),
+    /// Hold buttons down until released (`HOLD`).
+    Hold(Vec<Button>),
+    /// Release held buttons (`RELEASE`).
+    Release(Vec<Button>),
+    /// Set a stick position (`STICK`).
+    Stick(Stick, f32, f32),
+    /// Set the entire controller state (`STATE`).
+    State(ControllerState),
+    /// Wait on the host before sending the next step.
+    Wait(Duration),
+    /// A named point in the sequence, for playing part of it with
+    /// [`SwitchController::play_between`]. Sends nothing and takes no time.
+    ///
+    /// [`SwitchController::play_between`]: crate::SwitchController::play_between
+    Marker(String),
+}
+
+impl Step {
+    /// The command sent for this step, or `None` for host-side steps like [`Step::Wait`].
+    pub fn command(&self) -> Option<Command> {
+        match self {
+            Step::Press(buttons) => Some(Command::Press(buttons.clone())),
+            Step::Hold(buttons) => Some(Command::Hold(buttons.clone())),
+            Step::Release(buttons) => Some(Command::Release(buttons.clone())),
+            Step::Stick(stick, h, v) => Some(Command::Stick(*stick, *h, *v)),
+            Step::State(state) => Some(Command::State(state.clone())),
+            Step::Wait(_) | Step::Marker(_) => None,
+        }
+    }
+
+    /// The command line sent for this step, as with [`Step::command`].
+    pub fn to_command(&self) -> Option<String> {
+        self.command().map(|command| command.to_string())
+    }
+
+    /// Update `state` to reflect what the device holds after this step, as
+    /// [`ControllerState::apply`] does for its command.
+    ///
+    /// `PRESS` only lasts a frame, so it leaves the held state untouched, as
+    /// do host-side steps.
+    pub(crate) fn apply(&self, state: &mut ControllerState) {
+        if let Some(command) = self.command() {
+            state.apply(&command);
+        }
+    }
+}
+
+/// An ordered list of steps that can be played back with [`SwitchController::play`].
+///
+/// [`SwitchController::play`]: crate::SwitchController::play
+#[derive(Debug, Clone, Default)]
+pub struct InputSequence {
+    steps: Vec<Step>,
+}
+
+impl InputSequence {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The steps of this sequence in playback order.
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Mutable access to the steps of this sequence.
+    pub fn steps_mut(&mut self) -> &mut Vec<Step> {
+        &mut self.steps
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Total time spent in [`Step::Wait`] steps.
+    pub fn duration(&self) -> Duration {
+        self.steps
+            .iter()
+            .map(|step| match step {
+                Step::Wait(d) => *d,
+                _ => Duration::ZERO,
+            })
+            .sum()
+    }
+
+    /// The playback position `time` into the sequence.
+    ///
+    /// Times past the end of the sequence map to the position after the last step.
+    pub fn position_at(&self, time: Duration) -> PlaybackPosition {
+        let mut elapsed = Duration::ZERO;
+        for (index, step) in self.steps.iter().enumerate() {
+            match step {
+                Step::Wait(d) if elapsed + *d > time => {
+                    return PlaybackPosition {
+                        step: index,
+                        offset: time.saturating_sub(elapsed),
+                    };
+                }
+                Step::Wait(d) => elapsed += *d,
+                _ if elapsed >= time => return PlaybackPosition::step(index),
+                _ => {}
+            }
+        }
+        PlaybackPosition::step(self.steps.len())
+    }
+
+    /// The controller state held after executing the first `index` steps.
+    pub fn state_at(&self, index: usize) -> ControllerState {
+        let mut state = ControllerState::new();
+        for step in self.steps.iter().take(index) {
+            step.apply(&mut state);
+        }
+        state
+    }
+
+    /// Append a step.
+    pub fn push(&mut self, step: Step) -> &mut Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Append a `PRESS` step.
+    pub fn press(&mut self, buttons: &[Button]) -> &mut Self {
+        self.push(Step::Press(buttons.to_vec()))
+    }
+
+    /// Append a `HOLD` step.
+    pub fn hold(&mut self, buttons: &[Button]) -> &mut Self {
+        self.push(Step::Hold(buttons.to_vec()))
+    }
+
+    /// Append a `RELEASE` step.
+    pub fn release(&mut self, buttons: &[Button]) -> &mut Self {
+        self.push(Step::Release(buttons.to_vec()))
+    }
+
+    /// Append a `STICK` step.
+    pub fn stick(&mut self, stick: Stick, horizontal: f32, vertical: f32) -> &mut Self {
+        self.push(Step::Stick(stick, horizontal, vertical))
+    }
+
+    /// Append a `STATE` step.
+    pub fn state(&mut self, state: &ControllerState) -> &mut Self {
+        self.push(Step::State(state.clone()))
+    }
+
+    /// Append a host-side wait.
+    pub fn wait(&mut self, duration: Duration) -> &mut Self {
+        self.push(Step::Wait(duration))
+    }
+
+    /// Append a named [`Step::Marker`].
+    pub fn marker(&mut self, name: &str) -> &mut Self {
+        self.push(Step::Marker(name.to_string()))
+    }
+
+    /// The position of the first marker called `name`.
+    pub fn marker_position(&self, name: &str) -> Option<PlaybackPosition> {
+        self.steps
+            .iter()
+            .position(|s| matches!(s, Step::Marker(n) if n == name))
+            .map(PlaybackPosition::step)
+    }
+
+    /// The names of all markers, with the time each is reached.
+    pub fn markers(&self) -> Vec<(&str, Duration)> {
+        let mut now = Duration::ZERO;
+        let mut markers = Vec::new();
+        for step in &self.steps {
+            match step {
+                Step::Wait(d) => now += *d,
+                Step::Marker(name) => markers.push((name.as_str(), now)),
+                _ => {}
+            }
+        }
+        markers
+    }
+
+    /// Append a chord whose buttons go down at the given offsets from its
+    /// start, in offset order, and are all released together
+    /// [`PRESS_DURATION`] after the last one. For shortcuts that need holds
+    /// to begin in a specific order.
+    pub fn press_staggered(&mut self, buttons: &[(Button, Duration)]) -> &mut Self {
+        if buttons.is_empty() {
+            return self;
+        }
+        let mut buttons = buttons.to_vec();
+        buttons.sort_by_key(|&(_, offset)| offset);
+        let mut elapsed = Duration::ZERO;
+        for group in buttons.chunk_by(|a, b| a.1 == b.1) {
+            let offset = group[0].1;
+            if offset > elapsed {
+                self.wait(offset - elapsed);
+                elapsed = offset;
+            }
+            let held: Vec<Button> = group.iter().map(|&(b, _)| b).collect();
+            self.hold(&held);
+        }
+        let all: Vec<Button> = buttons.iter().map(|&(b, _)| b).collect();
+        self.wait(PRESS_DURATION).release(&all)
+    }
+}
+
+impl From<Vec<Step>> for InputSequence {
+    fn from(steps: Vec<Step>) -> Self {
+        Self { steps }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn step_commands() {
+        assert_eq!(
+            Step::Press(vec![Button::A, Button::B])
+                .to_command()
+                .as_deref(),
+            Some("PRESS a b")
+        );
+        assert_eq!(
+            Step::Stick(Stick::Right, -1.0, 0.5).to_command().as_deref(),
+            Some("STICK r_stick -1 0.5")
+        );
+        assert_eq!(Step::Wait(Duration::from_millis(10)).to_command(), None);
+    }
+
+    #[test]
+    fn apply_tracks_held_state() {
+        let mut seq = InputSequence::new();
+        seq.hold(&[Button::ZR, Button::A])
+            .press(&[Button::B])
+            .release(&[Button::A])
+            .stick(Stick::Left, 1.0, 0.0);
+
+        let state = seq.state_at(seq.len());
+        assert!(state.button(Button::ZR));
+        assert!(!state.button(Button::A));
+        assert!(!state.button(Button::B));
+        assert_eq!(state.left_stick, Some((1.0, 0.0)));
+        assert_eq!(state.right_stick, None);
+    }
+
+    #[test]
+    fn position_at_time() {
+        let mut seq = InputSequence::new();
+        seq.press(&[Button::A])
+            .wait(Duration::from_millis(100))
+            .press(&[Button::B])
+            .wait(Duration::from_millis(200));
+
+        assert_eq!(seq.duration(), Duration::from_millis(300));
+        assert_eq!(seq.position_at(Duration::ZERO), PlaybackPosition::step(0));
+        assert_eq!(
+            seq.position_at(Duration::from_millis(40)),
+            PlaybackPosition {
+                step: 1,
+                offset: Duration::from_millis(40)
+            }
+        );
+        assert_eq!(
+            seq.position_at(Duration::from_millis(100)),
+            PlaybackPosition::step(2)
+        );
+        assert_eq!(
+            seq.position_at(Duration::from_millis(250)),
+            PlaybackPosition {
+                step: 3,
+                offset: Duration::from_millis(150)
+            }
+        );
+        assert_eq!(
+            seq.position_at(Duration::from_secs(5)),
+            PlaybackPosition::step(4)
+        );
+    }
+
+    #[test]
+    fn markers() {
+        let mut seq = InputSequence::new();
+        seq.press(&[Button::A])
+            .wait(Duration::from_secs(1))
+            .marker("boss")
+            .wait(Duration::from_secs(2))
+            .marker("end");
+        assert_eq!(
+            seq.markers(),
+            [
+                ("boss", Duration::from_secs(1)),
+                ("end", Duration::from_secs(3))
+            ]
+        );
+        assert_eq!(seq.marker_position("boss"), Some(PlaybackPosition::step(2)));
+        assert_eq!(seq.marker_position("missing"), None);
+        assert_eq!(seq.duration(), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn staggered_press() {
+        let mut seq = InputSequence::new();
+        seq.press_staggered(&[
+            (Button::Capture, Duration::from_millis(50)),
+            (Button::L, Duration::ZERO),
+            (Button::R, Duration::ZERO),
+        ]);
+        let commands: Vec<_> = seq.steps().iter().map(Step::to_command).collect();
+        assert_eq!(
+            commands,
+            [
+                Some("HOLD l r".to_string()),
+                None,
+                Some("HOLD capture".to_string()),
+                None,
+                Some("RELEASE l r capture".to_string()),
+            ]
+        );
+        assert_eq!(seq.duration(), Duration::from_millis(150));
+    }
+}
@@ -0,0 +1,43 @@
+use std::io;
+
+use crate::{InputSequence, SwitchController};
+
+impl SwitchController {
+    /// Set a sequence to leave the console in a safe state (release
+    /// everything, go Home, close the software, ...) when the controller shuts
+    /// down.
+    ///
+    /// It plays, best effort, when the controller is dropped, including when a
+    /// fatal error unwinds past it, or when [`SwitchController::shutdown`] is
+    /// called explicitly (for example from a Ctrl-C handler). It plays at most
+    /// once.
+    pub fn set_shutdown_sequence(&mut self, sequence: InputSequence) -> &mut Self {
+        self.shutdown = Some(sequence);
+        self
+    }
+
+    pub fn clear_shutdown_sequence(&mut self) -> &mut Self {
+        self.shutdown = None;
+        self
+    }
+
+    pub fn shutdown_sequence(&self) -> Option<&InputSequence> {
+        self.shutdown.as_ref()
+    }
+
+    /// Play the shutdown sequence now, if one is set and has not played yet.
+    pub fn shutdown(&mut self) -> io::Result<()> {
+        match self.shutdown.take() {
+            Some(sequence) => self.play(&sequence),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for SwitchController {
+    fn drop(&mut self) {
+        let _ = self.release_timed(None);
+        let _ = self.shutdown();
+        let _ = self.flush();
+    }
+}
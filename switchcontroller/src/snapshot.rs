@@ -0,0 +1,77 @@
+use std::io;
+
+use crate::{Command, ControllerState, SwitchController, parse_line};
+
+impl SwitchController {
+    /// The buttons and sticks currently held, as set by every command sent
+    /// so far. Sticks that were never moved are `None`.
+    pub fn snapshot(&self) -> ControllerState {
+        self.held.clone()
+    }
+
+    /// Put the controller back into a state taken with
+    /// [`SwitchController::snapshot`], for example after an emergency pause
+    /// or a reconnect.
+    ///
+    /// The state goes out as a single `STATE` command with both sticks set,
+    /// centering any the snapshot never moved. It skips the layers, D-pad
+    /// cleaning and duplicate suppression, so it is sent exactly as taken.
+    pub fn restore(&mut self, snapshot: &ControllerState) -> io::Result<()> {
+        let mut state = ControllerState::new();
+        state.set_button_mask(snapshot.button_mask());
+        let (lh, lv) = snapshot.left_stick.unwrap_or((0.0, 0.0));
+        let (rh, rv) = snapshot.right_stick.unwrap_or((0.0, 0.0));
+        state.set_left_stick(lh, lv).set_right_stick(rh, rv);
+        self.send(&Command::State(state))
+    }
+
+    /// Send a command through the rate limit and, once it is sent or
+    /// queued, update the held state from the command itself rather than
+    /// its line, so values the parser would reject still count. Raw lines
+    /// count when they parse; presses leave it unchanged, as they release
+    /// on their own.
+    pub(crate) fn send_tracked(&mut self, command: &Command) -> io::Result<()> {
+        self.send_limited(&command.to_string())?;
+        match command {
+            Command::Raw(line) => {
+                if let Ok(Some(command)) = parse_line(line) {
+                    self.held.apply(&command);
+                }
+            }
+            command => {
+                self.held.apply(command);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Shared;
+    use crate::{Button, Stick};
+
+    #[test]
+    fn restores_held_state() {
+        let out = Shared::default();
+        let mut ctrl = SwitchController::from_writer(out.clone());
+        ctrl.hold(&[Button::ZR, Button::A]).unwrap();
+        ctrl.press(&[Button::B]).unwrap();
+        ctrl.stick(Stick::Left, 1.0, 0.0).unwrap();
+        let snapshot = ctrl.snapshot();
+        assert!(snapshot.button(Button::ZR) && !snapshot.button(Button::B));
+        assert_eq!(snapshot.right_stick, None);
+
+        ctrl.state(&ControllerState::neutral()).unwrap();
+        assert_eq!(ctrl.snapshot(), ControllerState::neutral());
+        ctrl.restore(&snapshot).unwrap();
+        assert!(out.text().ends_with("\nSTATE 100000010000000000 1 0 0 0\n"));
+        assert!(ctrl.snapshot().button(Button::A));
+
+        // The parser rejects sticks past full tilt, but the controller
+        // still sends them, so the snapshot has to keep them too.
+        ctrl.stick(Stick::Right, 2.0, 0.0).unwrap();
+        assert_eq!(ctrl.snapshot().right_stick, Some((2.0, 0.0)));
+    }
+}
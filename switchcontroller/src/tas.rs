@@ -0,0 +1,237 @@
+use std::io;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::duration::{FRAME, FRAME_RATE};
+use crate::{Button, ControllerState, InputSequence, Step, SwitchController};
+
+/// What a TAS replay lines up with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncPoint {
+    /// Press a button, typically A on the title screen; the press is the
+    /// reference point.
+    Press(Button),
+    /// Wait until the detector reports a template on screen; the moment it
+    /// does is the reference point.
+    Detection(String),
+}
+
+/// How [`SwitchController::play_synced`] starts a replay: at `offset` after
+/// a [`SyncPoint`], so replays line up the same way on every attempt.
+///
+/// ```
+/// # use switchcontroller::{Button, TasSync};
+/// // Start 2.5 frames after pressing A on the title screen
+/// let sync = TasSync::press(Button::A).offset_frames(2.5);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TasSync {
+    pub point: SyncPoint,
+    /// Delay from the sync point to the replay's first input.
+    pub offset: Duration,
+    /// How long to wait for a [`SyncPoint::Detection`] before giving up.
+    pub timeout: Duration,
+    /// How often the detector is asked.
+    pub poll_interval: Duration,
+}
+
+impl TasSync {
+    fn new(point: SyncPoint) -> Self {
+        Self {
+            point,
+            offset: Duration::ZERO,
+            timeout: Duration::from_secs(60),
+            poll_interval: Duration::from_millis(5),
+        }
+    }
+
+    /// Sync to pressing `button`.
+    pub fn press(button: Button) -> Self {
+        Self::new(SyncPoint::Press(button))
+    }
+
+    /// Sync to `template` appearing on screen.
+    pub fn detection(template: &str) -> Self {
+        Self::new(SyncPoint::Detection(template.to_string()))
+    }
+
+    pub fn offset(mut self, offset: Duration) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Set the offset in frames at 60 fps; fractions of a frame are kept,
+    /// for nudging a replay that lands a frame early or late.
+    pub fn offset_frames(self, frames: f64) -> Self {
+        self.offset(Duration::from_secs_f64(frames.max(0.0) / FRAME_RATE as f64))
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl SwitchController {
+    /// Play `sequence` lined up with `sync`'s sync point, as with
+    /// [`SwitchController::play_at`], so the configured latency applies.
+    ///
+    /// `detect` is only asked for a [`SyncPoint::Detection`]; if it never
+    /// sees the template within the timeout, nothing is played and an error
+    /// of kind [`io::ErrorKind::TimedOut`] is returned.
+    pub fn play_synced(
+        &mut self,
+        sequence: &InputSequence,
+        sync: &TasSync,
+        mut detect: impl FnMut(&str) -> bool,
+    ) -> io::Result<()> {
+        let reference = match &sync.point {
+            SyncPoint::Press(button) => {
+                self.press(&[*button])?;
+                self.flush()?;
+                Instant::now()
+            }
+            SyncPoint::Detection(template) => {
+                let deadline = Instant::now() + sync.timeout;
+                loop {
+                    if detect(template) {
+                        break Instant::now();
+                    }
+                    if Instant::now() >= deadline {
+                        return Err(io::Error::new(
+                            io::ErrorKind::TimedOut,
+                            format!("sync point `{template}` not detected"),
+                        ));
+                    }
+                    thread::sleep(sync.poll_interval);
+                }
+            }
+        };
+        self.play_at(reference + sync.offset, sequence)
+    }
+}
+
+impl SwitchController {
+    /// Play `sequence` one frame at a time, for checking a TAS script
+    /// against the real console frame by frame.
+    ///
+    /// Each frame's input is sent as a `STATE`, held for one frame and then
+    /// released, after which `advance` is called with the frame number
+    /// (from 1) and the state that was sent. It should block until the user
+    /// or an external trigger is ready for the next frame, and return
+    /// `false` to stop. The console keeps running while `advance` blocks, so
+    /// held inputs are re-sent at the start of every frame they span.
+    ///
+    /// A `PRESS` counts as input for the next frame only, and waits are
+    /// rounded to whole frames at 60 fps; inputs separated by less than half
+    /// a frame share one. Returns the number of frames played.
+    pub fn play_frame_advance(
+        &mut self,
+        sequence: &InputSequence,
+        mut advance: impl FnMut(u64, &ControllerState) -> bool,
+    ) -> io::Result<u64> {
+        let mut held = ControllerState::neutral();
+        let mut frame = held.clone();
+        let mut pending = false;
+        let mut played = 0;
+        let steps = sequence.steps().iter().map(Some).chain([None]);
+        for step in steps {
+            let count = match step {
+                Some(Step::Wait(d)) => (d.as_secs_f64() * FRAME_RATE as f64).round() as u64,
+                // The inputs after the last wait get a frame of their own.
+                None if pending => 1,
+                None => 0,
+                Some(Step::Press(buttons)) => {
+                    for &b in buttons {
+                        frame.set_button(b, true);
+                    }
+                    pending = true;
+                    continue;
+                }
+                Some(step) => {
+                    step.apply(&mut held);
+                    step.apply(&mut frame);
+                    pending = true;
+                    continue;
+                }
+            };
+            for _ in 0..count {
+                self.state(&frame)?;
+                self.flush()?;
+                thread::sleep(FRAME.div_f32(self.speed));
+                self.state(&ControllerState::neutral())?;
+                self.flush()?;
+                played += 1;
+                if !advance(played, &frame) {
+                    return Ok(played);
+                }
+                frame = held.clone();
+                pending = false;
+            }
+        }
+        Ok(played)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Shared;
+
+    #[test]
+    fn starts_after_the_sync_point() {
+        let sync = TasSync::press(Button::A).offset_frames(1.5);
+        assert_eq!(sync.offset, Duration::from_millis(25));
+
+        let out = Shared::default();
+        let mut ctrl = SwitchController::from_writer(out.clone());
+        let mut seq = InputSequence::new();
+        seq.press(&[Button::B]);
+        let start = Instant::now();
+        ctrl.play_synced(&seq, &sync, |_| unreachable!()).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(25));
+        assert_eq!(out.text(), "PRESS a\nPRESS b\n");
+
+        let mut polls = 0;
+        let sync = TasSync::detection("title.png");
+        ctrl.play_synced(&seq, &sync, |_| {
+            polls += 1;
+            polls == 3
+        })
+        .unwrap();
+        assert_eq!(polls, 3);
+        let never = sync.timeout(Duration::ZERO);
+        let err = ctrl.play_synced(&seq, &never, |_| false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(out.text(), "PRESS a\nPRESS b\nPRESS b\n");
+    }
+
+    #[test]
+    fn advances_one_frame_at_a_time() {
+        let out = Shared::default();
+        let mut ctrl = SwitchController::from_writer(out.clone());
+        ctrl.set_playback_speed(100.0);
+        let mut seq = InputSequence::new();
+        seq.hold(&[Button::A])
+            .press(&[Button::B])
+            .wait(FRAME * 2)
+            .release(&[Button::A]);
+        let mut seen = Vec::new();
+        let played = ctrl
+            .play_frame_advance(&seq, |n, state| {
+                seen.push((n, state.button(Button::A), state.button(Button::B)));
+                true
+            })
+            .unwrap();
+        assert_eq!(played, 3);
+        assert_eq!(seen, [(1, true, true), (2, true, false), (3, false, false)]);
+        assert_eq!(
+            out.text(),
+            "STATE 110000000000000000 0 0 0 0\nSTATE 000000000000000000 0 0 0 0\n\
+             STATE 100000000000000000 0 0 0 0\nSTATE 000000000000000000 0 0 0 0\n"
+        );
+
+        let played = ctrl.play_frame_advance(&seq, |_, _| false).unwrap();
+        assert_eq!(played, 1);
+    }
+}
@@ -0,0 +1,210 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A detection template resolved by a [`TemplateStore`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    pub path: PathBuf,
+    /// The capture resolution the template was cut from.
+    pub resolution: (u32, u32),
+    /// Horizontal and vertical factors to scale the template by before
+    /// matching it against a capture at the requested resolution.
+    pub scale: (f64, f64),
+}
+
+/// Detection templates namespaced by game and capture resolution, so
+/// scripts say `pokemon/shiny_sparkle` instead of an absolute path.
+///
+/// ```text
+/// templates/
+///   pokemon/1920x1080/shiny_sparkle.png
+///   pokemon/1280x720/shiny_sparkle.png
+///   splatoon/1920x1080/lobby.png
+/// ```
+///
+/// Resolving picks the template cut at the capture's resolution, or else
+/// the closest one with the same aspect ratio (then any aspect ratio), and
+/// reports how to scale it. The store does not decode images; the scaling is
+/// left to the matcher plugged into [`SwitchController::run_program`].
+///
+/// [`SwitchController::run_program`]: crate::SwitchController::run_program
+#[derive(Debug, Clone)]
+pub struct TemplateStore {
+    root: PathBuf,
+}
+
+impl TemplateStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// The store at `$SWITCHCONTROLLER_TEMPLATES` if set, otherwise
+    /// `templates` in the working directory.
+    pub fn from_env() -> Self {
+        Self::new(
+            std::env::var_os("SWITCHCONTROLLER_TEMPLATES")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| PathBuf::from("templates")),
+        )
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The resolutions templates exist at for `game`, sorted.
+    pub fn resolutions(&self, game: &str) -> io::Result<Vec<(u32, u32)>> {
+        let mut resolutions = Vec::new();
+        for entry in fs::read_dir(self.root.join(game))? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir()
+                && let Some(resolution) = entry.file_name().to_str().and_then(parse_resolution)
+            {
+                resolutions.push(resolution);
+            }
+        }
+        resolutions.sort();
+        Ok(resolutions)
+    }
+
+    /// Find template `id` (`game/name`, with any file extension) for a
+    /// capture at `capture` resolution.
+    pub fn resolve(&self, id: &str, capture: (u32, u32)) -> io::Result<Template> {
+        let not_found = || {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no template `{id}` in {}", self.root.display()),
+            )
+        };
+        let (game, name) = id
+            .split_once('/')
+            .filter(|(game, name)| {
+                !game.is_empty()
+                    && !name.is_empty()
+                    && !id.split('/').any(|part| part.is_empty() || part == "..")
+            })
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("template `{id}` is not of the form game/name"),
+                )
+            })?;
+        let mut candidates = Vec::new();
+        for resolution in self.resolutions(game).map_err(|_| not_found())? {
+            let dir = self.root.join(game).join(format_resolution(resolution));
+            if let Some(path) = find_stem(&dir, name)? {
+                candidates.push((resolution, path));
+            }
+        }
+        let (resolution, path) = candidates
+            .into_iter()
+            .min_by_key(|(resolution, _)| distance(*resolution, capture))
+            .ok_or_else(not_found)?;
+        Ok(Template {
+            path,
+            resolution,
+            scale: (
+                capture.0 as f64 / resolution.0 as f64,
+                capture.1 as f64 / resolution.1 as f64,
+            ),
+        })
+    }
+}
+
+/// How far a template cut at `resolution` is from `capture`: a different
+/// aspect ratio always ranks after the same one, then by width difference.
+fn distance(resolution: (u32, u32), capture: (u32, u32)) -> (bool, u32) {
+    let same_aspect =
+        resolution.0 as u64 * capture.1 as u64 == capture.0 as u64 * resolution.1 as u64;
+    (!same_aspect, resolution.0.abs_diff(capture.0))
+}
+
+fn parse_resolution(s: &str) -> Option<(u32, u32)> {
+    let (w, h) = s.split_once('x')?;
+    let resolution = (w.parse().ok()?, h.parse().ok()?);
+    (resolution.0 > 0 && resolution.1 > 0).then_some(resolution)
+}
+
+fn format_resolution((w, h): (u32, u32)) -> String {
+    format!("{w}x{h}")
+}
+
+/// The file in `dir` named `name` with any extension. `name` may contain
+/// subdirectories.
+fn find_stem(dir: &Path, name: &str) -> io::Result<Option<PathBuf>> {
+    let path = dir.join(name);
+    let (Some(parent), Some(stem)) = (path.parent(), path.file_name()) else {
+        return Ok(None);
+    };
+    let Ok(entries) = fs::read_dir(parent) else {
+        return Ok(None);
+    };
+    let mut found: Option<PathBuf> = None;
+    for entry in entries {
+        let candidate = entry?.path();
+        if candidate.is_file() && candidate.file_stem() == Some(stem) {
+            // Pick deterministically if several extensions exist
+            if found.as_ref().is_none_or(|f| candidate < *f) {
+                found = Some(candidate);
+            }
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_closest_resolution() {
+        let root =
+            std::env::temp_dir().join(format!("switchcontroller-templates-{}", std::process::id()));
+        for (dir, file) in [
+            ("pokemon/1920x1080", "shiny_sparkle.png"),
+            ("pokemon/1280x720", "shiny_sparkle.png"),
+            ("pokemon/1280x720/battle", "menu.png"),
+            ("pokemon/640x480", "shiny_sparkle.png"),
+        ] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+            fs::write(root.join(dir).join(file), [0u8; 4]).unwrap();
+        }
+        let store = TemplateStore::new(&root);
+        assert_eq!(
+            store.resolutions("pokemon").unwrap(),
+            [(640, 480), (1280, 720), (1920, 1080)]
+        );
+
+        let exact = store.resolve("pokemon/shiny_sparkle", (1280, 720)).unwrap();
+        assert_eq!(exact.path, root.join("pokemon/1280x720/shiny_sparkle.png"));
+        assert_eq!(exact.scale, (1.0, 1.0));
+
+        let scaled = store
+            .resolve("pokemon/shiny_sparkle", (2560, 1440))
+            .unwrap();
+        assert_eq!(scaled.resolution, (1920, 1080));
+        assert_eq!(scaled.scale, (4.0 / 3.0, 4.0 / 3.0));
+
+        let nested = store.resolve("pokemon/battle/menu", (1920, 1080)).unwrap();
+        assert_eq!(nested.resolution, (1280, 720));
+        assert_eq!(nested.scale, (1.5, 1.5));
+
+        assert_eq!(
+            store
+                .resolve("pokemon/missing", (1280, 720))
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::NotFound
+        );
+        assert_eq!(
+            store
+                .resolve("pokemon/../x", (1280, 720))
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidInput
+        );
+        assert!(store.resolve("splatoon/lobby", (1280, 720)).is_err());
+        fs::remove_dir_all(root).unwrap();
+    }
+}
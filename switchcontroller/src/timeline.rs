@@ -0,0 +1,291 @@
+use std::time::Duration;
+
+use crate::{Button, ControllerState, InputSequence, Step, Stick};
+
+/// A period during which a button is held.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: Duration,
+    /// When the button is released, or `None` if it is still held at the end.
+    pub end: Option<Duration>,
+}
+
+impl Span {
+    /// Whether the button is held at `time`.
+    pub fn contains(&self, time: Duration) -> bool {
+        time >= self.start && self.end.is_none_or(|end| time < end)
+    }
+}
+
+/// Everything a single button does over a [`Timeline`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ButtonTrack {
+    /// Held periods from `HOLD`/`RELEASE`/`STATE`, in time order.
+    pub holds: Vec<Span>,
+    /// Times of momentary `PRESS` inputs, in time order.
+    pub presses: Vec<Duration>,
+}
+
+impl ButtonTrack {
+    /// Whether the button is held at `time`. Momentary presses are not included.
+    pub fn is_held_at(&self, time: Duration) -> bool {
+        self.holds.iter().any(|span| span.contains(time))
+    }
+}
+
+/// The positions of a single stick over a [`Timeline`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StickTrack {
+    /// `(time, horizontal, vertical)` keyframes in time order. The stick stays at
+    /// each position until the next keyframe.
+    pub keyframes: Vec<(Duration, f32, f32)>,
+}
+
+impl StickTrack {
+    /// The stick position at `time`, centered before the first keyframe.
+    pub fn value_at(&self, time: Duration) -> (f32, f32) {
+        self.keyframes
+            .iter()
+            .take_while(|(t, _, _)| *t <= time)
+            .last()
+            .map_or((0.0, 0.0), |&(_, h, v)| (h, v))
+    }
+}
+
+/// A multi-track view of an [`InputSequence`]: one on/off track per button and
+/// one position track per stick, on a shared time axis.
+///
+/// Convert with `Timeline::from(&sequence)` and back with
+/// [`Timeline::to_sequence`], or send directly with
+/// [`SwitchController::play_timeline`].
+///
+/// [`SwitchController::play_timeline`]: crate::SwitchController::play_timeline
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Timeline {
+    /// Button tracks in [`Button::ALL`] order.
+    buttons: [ButtonTrack; 18],
+    left_stick: StickTrack,
+    right_stick: StickTrack,
+    duration: Duration,
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Total length of the timeline.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Set the total length of the timeline.
+    pub fn set_duration(&mut self, duration: Duration) -> &mut Self {
+        self.duration = duration;
+        self
+    }
+
+    pub fn button(&self, button: Button) -> &ButtonTrack {
+        &self.buttons[button.index()]
+    }
+
+    pub fn button_mut(&mut self, button: Button) -> &mut ButtonTrack {
+        &mut self.buttons[button.index()]
+    }
+
+    pub fn stick(&self, stick: Stick) -> &StickTrack {
+        match stick {
+            Stick::Left => &self.left_stick,
+            Stick::Right => &self.right_stick,
+        }
+    }
+
+    pub fn stick_mut(&mut self, stick: Stick) -> &mut StickTrack {
+        match stick {
+            Stick::Left => &mut self.left_stick,
+            Stick::Right => &mut self.right_stick,
+        }
+    }
+
+    /// The held buttons and stick positions at `time`.
+    pub fn state_at(&self, time: Duration) -> ControllerState {
+        let mut state = ControllerState::new();
+        for b in Button::ALL {
+            state.set_button(b, self.button(b).is_held_at(time));
+        }
+        let (lh, lv) = self.left_stick.value_at(time);
+        let (rh, rv) = self.right_stick.value_at(time);
+        state.set_left_stick(lh, lv).set_right_stick(rh, rv);
+        state
+    }
+
+    /// Open or close a hold span on `button` at `time`.
+    ///
+    /// A hold released at the same instant it started has no lasting effect
+    /// and is dropped.
+    fn set_held(&mut self, button: Button, held: bool, time: Duration) {
+        let holds = &mut self.button_mut(button).holds;
+        match holds.last_mut().filter(|span| span.end.is_none()) {
+            None if held => holds.push(Span {
+                start: time,
+                end: None,
+            }),
+            Some(span) if !held && span.start == time => {
+                holds.pop();
+            }
+            Some(span) if !held => span.end = Some(time),
+            _ => {}
+        }
+    }
+
+    /// Flatten the tracks back into a sequence of commands and waits.
+    ///
+    /// Steps at the same instant are ordered releases, holds, presses, then
+    /// sticks, and buttons changing together are grouped into one command.
+    pub fn to_sequence(&self) -> InputSequence {
+        // (time, order within the instant, step)
+        let mut events: Vec<(Duration, u8, Step)> = Vec::new();
+        for b in Button::ALL {
+            let track = self.button(b);
+            for span in &track.holds {
+                events.push((span.start, 1, Step::Hold(vec![b])));
+                if let Some(end) = span.end {
+                    events.push((end, 0, Step::Release(vec![b])));
+                }
+            }
+            for &time in &track.presses {
+                events.push((time, 2, Step::Press(vec![b])));
+            }
+        }
+        for stick in [Stick::Left, Stick::Right] {
+            for &(time, h, v) in &self.stick(stick).keyframes {
+                events.push((time, 3, Step::Stick(stick, h, v)));
+            }
+        }
+        events.sort_by_key(|(time, order, _)| (*time, *order));
+
+        let mut merged: Vec<(Duration, u8, Step)> = Vec::new();
+        for (time, order, step) in events {
+            if let Some((last_time, last_order, last_step)) = merged.last_mut()
+                && *last_time == time
+                && *last_order == order
+            {
+                match (last_step, &step) {
+                    (Step::Hold(a), Step::Hold(b))
+                    | (Step::Release(a), Step::Release(b))
+                    | (Step::Press(a), Step::Press(b)) => {
+                        a.extend_from_slice(b);
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+            merged.push((time, order, step));
+        }
+
+        let events = merged
+            .into_iter()
+            .map(|(time, _, step)| (time, step))
+            .collect();
+        InputSequence::from_timed(events, self.duration)
+    }
+}
+
+impl From<&InputSequence> for Timeline {
+    fn from(sequence: &InputSequence) -> Self {
+        let mut timeline = Timeline::new();
+        timeline.set_duration(sequence.duration());
+        let mut now = Duration::ZERO;
+        for step in sequence.steps() {
+            match step {
+                Step::Wait(d) => now += *d,
+                // Timelines only track inputs; markers are dropped.
+                Step::Marker(_) => {}
+                Step::Press(buttons) => {
+                    for &b in buttons {
+                        timeline.button_mut(b).presses.push(now);
+                    }
+                }
+                Step::Hold(buttons) => {
+                    for &b in buttons {
+                        timeline.set_held(b, true, now);
+                    }
+                }
+                Step::Release(buttons) => {
+                    for &b in buttons {
+                        timeline.set_held(b, false, now);
+                    }
+                }
+                Step::Stick(stick, h, v) => {
+                    timeline.stick_mut(*stick).keyframes.push((now, *h, *v));
+                }
+                Step::State(state) => {
+                    for b in Button::ALL {
+                        timeline.set_held(b, state.button(b), now);
+                    }
+                    if let Some((h, v)) = state.left_stick {
+                        timeline.left_stick.keyframes.push((now, h, v));
+                    }
+                    if let Some((h, v)) = state.right_stick {
+                        timeline.right_stick.keyframes.push((now, h, v));
+                    }
+                }
+            }
+        }
+        timeline
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands(seq: &InputSequence) -> Vec<String> {
+        seq.steps()
+            .iter()
+            .map(|step| match step {
+                Step::Wait(d) => format!("WAIT {}", d.as_millis()),
+                step => step.to_command().unwrap(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trips_through_sequence() {
+        let mut seq = InputSequence::new();
+        seq.hold(&[Button::A, Button::ZR])
+            .wait(Duration::from_millis(100))
+            .press(&[Button::B])
+            .stick(Stick::Left, 1.0, 0.0)
+            .wait(Duration::from_millis(50))
+            .release(&[Button::A, Button::ZR])
+            .wait(Duration::from_millis(10));
+
+        let timeline = Timeline::from(&seq);
+        assert_eq!(commands(&timeline.to_sequence()), commands(&seq));
+    }
+
+    #[test]
+    fn tracks_state_steps() {
+        let mut state = ControllerState::new();
+        state.set_button(Button::X, true).set_left_stick(0.5, 0.5);
+        let mut seq = InputSequence::new();
+        seq.state(&state)
+            .wait(Duration::from_millis(100))
+            .state(&ControllerState::new())
+            .wait(Duration::from_millis(100));
+
+        let timeline = Timeline::from(&seq);
+        assert_eq!(
+            timeline.button(Button::X).holds,
+            [Span {
+                start: Duration::ZERO,
+                end: Some(Duration::from_millis(100)),
+            }]
+        );
+        let at = timeline.state_at(Duration::from_millis(150));
+        assert!(!at.button(Button::X));
+        assert_eq!(at.left_stick, Some((0.5, 0.5)));
+        assert_eq!(at.right_stick, Some((0.0, 0.0)));
+    }
+}
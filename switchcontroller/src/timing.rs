@@ -0,0 +1,135 @@
+use std::collections::BTreeMap;
+use std::ops::Index;
+use std::time::Duration;
+
+/// A registry of named timing constants, so sequences can wait for "a menu
+/// transition" instead of a hard-coded number of milliseconds.
+///
+/// [`Timings::new`] starts with built-in defaults, which a [`Profile`] can
+/// override per game or console:
+///
+/// ```
+/// # use switchcontroller::{InputSequence, Timings, Button};
+/// let timings = Timings::new();
+/// let mut seq = InputSequence::new();
+/// seq.press(&[Button::A]).wait(timings[Timings::MENU_TRANSITION]);
+/// ```
+///
+/// [`Profile`]: crate::Profile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timings {
+    values: BTreeMap<String, Duration>,
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Timings {
+    /// Time for a menu or screen transition to finish.
+    pub const MENU_TRANSITION: &str = "menu_transition";
+    /// Time for one text box to finish printing.
+    pub const TEXT_SPEED: &str = "text_speed";
+    /// Upper bound on a loading screen.
+    pub const LOAD_SCREEN: &str = "load_screen";
+    /// Time for the console to close the running software.
+    pub const CLOSE_SOFTWARE: &str = "close_software";
+    /// Time from launching a game to its title screen.
+    pub const GAME_LAUNCH: &str = "game_launch";
+    /// Time for a sleeping console to show its lock screen.
+    pub const WAKE_UP: &str = "wake_up";
+
+    /// A registry holding the built-in defaults.
+    pub fn new() -> Self {
+        let mut timings = Self::empty();
+        for &(name, duration) in &DEFAULTS {
+            timings.set(name, duration);
+        }
+        timings
+    }
+
+    /// A registry with no constants at all.
+    pub fn empty() -> Self {
+        Self {
+            values: BTreeMap::new(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Duration> {
+        self.values.get(name).copied()
+    }
+
+    /// Define or override a constant.
+    pub fn set(&mut self, name: impl Into<String>, duration: Duration) -> &mut Self {
+        self.values.insert(name.into(), duration);
+        self
+    }
+
+    /// Apply every constant from `overrides` on top of this registry.
+    pub fn extend(&mut self, overrides: &Timings) -> &mut Self {
+        for (name, &duration) in &overrides.values {
+            self.values.insert(name.clone(), duration);
+        }
+        self
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Duration)> {
+        self.values.iter().map(|(name, &d)| (name.as_str(), d))
+    }
+}
+
+/// The built-in constants and their defaults.
+static DEFAULTS: [(&str, Duration); 6] = [
+    (Timings::MENU_TRANSITION, Duration::from_millis(500)),
+    (Timings::TEXT_SPEED, Duration::from_millis(300)),
+    (Timings::LOAD_SCREEN, Duration::from_secs(10)),
+    (Timings::CLOSE_SOFTWARE, Duration::from_secs(3)),
+    (Timings::GAME_LAUNCH, Duration::from_secs(20)),
+    (Timings::WAKE_UP, Duration::from_secs(3)),
+];
+
+impl Index<&str> for Timings {
+    type Output = Duration;
+
+    /// A built-in constant missing from the registry, as in one built from
+    /// [`Timings::empty`], reads as its default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` is neither defined nor built in.
+    fn index(&self, name: &str) -> &Duration {
+        self.values
+            .get(name)
+            .or_else(|| DEFAULTS.iter().find(|(n, _)| *n == name).map(|(_, d)| d))
+            .unwrap_or_else(|| panic!("no timing constant named `{name}`"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_replace_defaults() {
+        let mut overrides = Timings::empty();
+        overrides
+            .set(Timings::TEXT_SPEED, Duration::from_millis(50))
+            .set("egg_hatch", Duration::from_secs(18));
+
+        let mut timings = Timings::new();
+        timings.extend(&overrides);
+        assert_eq!(timings[Timings::TEXT_SPEED], Duration::from_millis(50));
+        assert_eq!(
+            timings[Timings::MENU_TRANSITION],
+            Duration::from_millis(500)
+        );
+        assert_eq!(timings.get("egg_hatch"), Some(Duration::from_secs(18)));
+        assert_eq!(timings.get("missing"), None);
+        assert_eq!(
+            Timings::empty()[Timings::LOAD_SCREEN],
+            Duration::from_secs(10)
+        );
+    }
+}
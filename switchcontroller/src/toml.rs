@@ -0,0 +1,432 @@
+//! The small subset of TOML used for profiles, checkpoints, counters and
+//! schedules: `[table]` headers (dotted names allowed), `key = value` pairs
+//! with bare or quoted keys, and values that are strings, numbers, booleans
+//! or arrays of those. Arrays may span lines. Comments start with `#`.
+
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+use crate::ParseProfileError;
+
+/// Arrays nested deeper than this are rejected rather than risking the stack.
+const MAX_DEPTH: usize = 128;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    String(String),
+    Number(f64),
+    Bool(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::String(s) => {
+                f.write_char('"')?;
+                for c in s.chars() {
+                    match c {
+                        '"' => f.write_str("\\\"")?,
+                        '\\' => f.write_str("\\\\")?,
+                        '\n' => f.write_str("\\n")?,
+                        '\t' => f.write_str("\\t")?,
+                        c => f.write_char(c)?,
+                    }
+                }
+                f.write_char('"')
+            }
+            Value::Number(n) => write!(f, "{n}"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Array(items) => {
+                f.write_char('[')?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                f.write_char(']')
+            }
+        }
+    }
+}
+
+impl FromStr for Value {
+    type Err = ParseProfileError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut cursor = Cursor(s);
+        let value = cursor.value(0);
+        match value {
+            Ok(value) if cursor.at_end() => Ok(value),
+            _ => Err(ParseProfileError {
+                line: 0,
+                message: format!("invalid value `{s}`"),
+            }),
+        }
+    }
+}
+
+/// Key/value pairs of one table, in file order.
+pub(crate) type Table = Vec<(String, Value)>;
+
+/// A parsed document: the root table (named `""`) followed by every
+/// `[table]`, in file order.
+#[derive(Debug, Clone)]
+pub(crate) struct Document {
+    tables: Vec<(String, Table)>,
+    /// Per table, the 1-based line of each key, or 0 for keys added with
+    /// [`Document::set`].
+    lines: Vec<Vec<usize>>,
+}
+
+/// Documents are equal when their tables are, wherever the keys were.
+impl PartialEq for Document {
+    fn eq(&self, other: &Self) -> bool {
+        self.tables == other.tables
+    }
+}
+
+impl Document {
+    pub(crate) fn new() -> Self {
+        Self {
+            tables: vec![(String::new(), Vec::new())],
+            lines: vec![Vec::new()],
+        }
+    }
+
+    pub(crate) fn tables(&self) -> impl Iterator<Item = (&str, &Table)> {
+        self.tables
+            .iter()
+            .map(|(name, table)| (name.as_str(), table))
+    }
+
+    pub(crate) fn table(&self, name: &str) -> Option<&Table> {
+        self.tables.iter().find(|(n, _)| n == name).map(|(_, t)| t)
+    }
+
+    /// The line `key` of `table` was read from, or 0 if it was not parsed.
+    pub(crate) fn line(&self, table: &str, key: &str) -> usize {
+        self.tables
+            .iter()
+            .position(|(n, _)| n == table)
+            .and_then(|t| {
+                let i = self.tables[t].1.iter().position(|(k, _)| k == key)?;
+                self.lines[t].get(i).copied()
+            })
+            .unwrap_or(0)
+    }
+
+    /// Append `key = value` to a table, creating the table if needed.
+    pub(crate) fn set(&mut self, table: &str, key: &str, value: Value) {
+        let index = match self.tables.iter().position(|(n, _)| n == table) {
+            Some(i) => i,
+            None => {
+                self.tables.push((table.to_string(), Vec::new()));
+                self.lines.push(Vec::new());
+                self.tables.len() - 1
+            }
+        };
+        self.tables[index].1.push((key.to_string(), value));
+        self.lines[index].push(0);
+    }
+
+    pub(crate) fn parse(input: &str) -> Result<Self, ParseProfileError> {
+        let mut doc = Self::new();
+        let mut current = 0;
+        let mut lines = input.lines().enumerate();
+        while let Some((i, line)) = lines.next() {
+            let err = |message: String| ParseProfileError {
+                line: i + 1,
+                message,
+            };
+            // Join the rest of an array that continues on later lines.
+            let mut line = line.to_string();
+            if !line.trim_start().starts_with('[') {
+                while scan(&line).0 > 0 {
+                    let Some((_, next)) = lines.next() else {
+                        break;
+                    };
+                    line.truncate(scan(&line).1);
+                    line.push(' ');
+                    line.push_str(next);
+                }
+            }
+            let mut cursor = Cursor(line.trim());
+            if cursor.at_end() {
+                continue;
+            }
+            if cursor.eat('[') {
+                let name = cursor.take_while(|c| c != ']').trim().to_string();
+                if !cursor.eat(']') || name.is_empty() {
+                    return Err(err("malformed table header".into()));
+                }
+                if !cursor.at_end() {
+                    return Err(err("unexpected text after table header".into()));
+                }
+                if doc.table(&name).is_some() {
+                    return Err(err(format!("duplicate table `{name}`")));
+                }
+                doc.tables.push((name, Vec::new()));
+                doc.lines.push(Vec::new());
+                current = doc.tables.len() - 1;
+                continue;
+            }
+            let key = cursor.key().map_err(err)?;
+            if !cursor.eat('=') {
+                return Err(err(format!("expected `=` after `{key}`")));
+            }
+            let value = cursor.value(0).map_err(err)?;
+            if !cursor.at_end() {
+                return Err(err("unexpected text after value".into()));
+            }
+            let table = &mut doc.tables[current].1;
+            if table.iter().any(|(k, _)| *k == key) {
+                return Err(err(format!("duplicate key `{key}`")));
+            }
+            table.push((key, value));
+            doc.lines[current].push(i + 1);
+        }
+        Ok(doc)
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        for (name, table) in &self.tables {
+            if !name.is_empty() {
+                if table.is_empty() {
+                    continue;
+                }
+                if !first {
+                    f.write_char('\n')?;
+                }
+                writeln!(f, "[{name}]")?;
+            }
+            for (key, value) in table {
+                if key.chars().all(is_bare_key_char) && !key.is_empty() {
+                    writeln!(f, "{key} = {value}")?;
+                } else {
+                    writeln!(f, "{} = {value}", Value::String(key.clone()))?;
+                }
+            }
+            first = false;
+        }
+        Ok(())
+    }
+}
+
+/// How many more `[` than `]` `line` opens outside strings and comments, and
+/// the byte offset its comment starts at (its length if it has none).
+fn scan(line: &str) -> (i32, usize) {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '#' if !in_string => return (depth, i),
+            '[' if !in_string => depth += 1,
+            ']' if !in_string => depth -= 1,
+            _ => {}
+        }
+    }
+    (depth, line.len())
+}
+
+fn is_bare_key_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+/// The unparsed rest of a line.
+struct Cursor<'a>(&'a str);
+
+impl Cursor<'_> {
+    fn skip_space(&mut self) {
+        self.0 = self.0.trim_start();
+    }
+
+    /// Whether only whitespace or a comment remains.
+    fn at_end(&mut self) -> bool {
+        self.skip_space();
+        self.0.is_empty() || self.0.starts_with('#')
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_space();
+        match self.0.strip_prefix(c) {
+            Some(rest) => {
+                self.0 = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &str {
+        let end = self.0.find(|c| !f(c)).unwrap_or(self.0.len());
+        let (taken, rest) = self.0.split_at(end);
+        self.0 = rest;
+        taken
+    }
+
+    fn key(&mut self) -> Result<String, String> {
+        self.skip_space();
+        if self.0.starts_with('"') {
+            return self.string();
+        }
+        let key = self.take_while(is_bare_key_char);
+        if key.is_empty() {
+            return Err("expected a key".into());
+        }
+        Ok(key.to_string())
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.eat('"');
+        let mut out = String::new();
+        let mut chars = self.0.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.0 = &self.0[i + 1..];
+                    return Ok(out);
+                }
+                '\\' => match chars.next() {
+                    Some((_, '"')) => out.push('"'),
+                    Some((_, '\\')) => out.push('\\'),
+                    Some((_, 'n')) => out.push('\n'),
+                    Some((_, 't')) => out.push('\t'),
+                    _ => return Err("unsupported escape in string".into()),
+                },
+                c => out.push(c),
+            }
+        }
+        Err("unterminated string".into())
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, String> {
+        if depth > MAX_DEPTH {
+            return Err("arrays nested too deeply".into());
+        }
+        self.skip_space();
+        if self.0.starts_with('"') {
+            return self.string().map(Value::String);
+        }
+        if self.eat('[') {
+            let mut items = Vec::new();
+            while !self.eat(']') {
+                items.push(self.value(depth + 1)?);
+                if !self.eat(',') {
+                    if !self.eat(']') {
+                        return Err("expected `,` or `]` in array".into());
+                    }
+                    break;
+                }
+            }
+            return Ok(Value::Array(items));
+        }
+        let token = self.take_while(|c| !c.is_whitespace() && !matches!(c, ',' | ']' | '#'));
+        match token {
+            "true" => Ok(Value::Bool(true)),
+            "false" => Ok(Value::Bool(false)),
+            _ => token
+                .replace('_', "")
+                .parse()
+                .map(Value::Number)
+                .map_err(|_| format!("invalid value `{token}`")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        let input = r#"name = "aim \"fast\"" # comment
+scale = 1.5
+
+[remap.buttons]
+a = "b"
+"odd key" = [1, true, "x",]
+"#;
+        let doc = Document::parse(input).unwrap();
+        let table = doc.table("remap.buttons").unwrap();
+        assert_eq!(table[0], ("a".into(), Value::String("b".into())));
+        assert_eq!(
+            table[1].1,
+            Value::Array(vec![
+                Value::Number(1.0),
+                Value::Bool(true),
+                Value::String("x".into())
+            ])
+        );
+        assert_eq!(Document::parse(&doc.to_string()).unwrap(), doc);
+    }
+
+    #[test]
+    fn multi_line_arrays() {
+        let input = "steps = [\n    \"PRESS a\", # first\n    \"# not a comment\",\n]\nafter = 1\n";
+        let doc = Document::parse(input).unwrap();
+        let root = doc.table("").unwrap();
+        assert_eq!(
+            root[0].1,
+            Value::Array(vec![
+                Value::String("PRESS a".into()),
+                Value::String("# not a comment".into())
+            ])
+        );
+        assert_eq!(root[1].0, "after");
+        assert!(Document::parse("a = [1,\n2").is_err());
+    }
+
+    #[test]
+    fn reports_line_numbers() {
+        let err = Document::parse("a = 1\n\nb = nope\n").unwrap_err();
+        assert_eq!(err.line, 3);
+        assert!(Document::parse("a = 1\na = 2").is_err());
+        assert!(Document::parse("a = \"open").is_err());
+
+        let deep = format!("a = 1\nx = {}", "[".repeat(100_000));
+        let err = Document::parse(&deep).unwrap_err();
+        assert_eq!(
+            (err.line, err.message.as_str()),
+            (2, "arrays nested too deeply")
+        );
+    }
+}
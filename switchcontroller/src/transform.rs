@@ -0,0 +1,249 @@
+use std::ops::Range;
+use std::time::Duration;
+
+use crate::{Button, ControllerState, InputSequence, Step, Stick};
+
+impl InputSequence {
+    /// The non-wait steps of this sequence with the time each one is sent at.
+    pub(crate) fn timed(&self) -> Vec<(Duration, Step)> {
+        let mut now = Duration::ZERO;
+        let mut events = Vec::new();
+        for step in self.steps() {
+            match step {
+                Step::Wait(d) => now += *d,
+                step => events.push((now, step.clone())),
+            }
+        }
+        events
+    }
+
+    /// Build a sequence from time-sorted events, padding with a final wait up to `end`.
+    pub(crate) fn from_timed(events: Vec<(Duration, Step)>, end: Duration) -> Self {
+        let mut seq = InputSequence::new();
+        let mut now = Duration::ZERO;
+        for (time, step) in events {
+            if time > now {
+                seq.wait(time - now);
+                now = time;
+            }
+            seq.push(step);
+        }
+        if end > now {
+            seq.wait(end - now);
+        }
+        seq
+    }
+
+    /// This sequence followed by `other`.
+    pub fn concat(&self, other: &InputSequence) -> InputSequence {
+        let mut steps = self.steps().to_vec();
+        steps.extend_from_slice(other.steps());
+        steps.into()
+    }
+
+    /// This sequence and `other` played in parallel, both starting at time zero.
+    ///
+    /// Steps sent at the same instant keep their order, with this sequence's
+    /// steps first. The result lasts as long as the longer of the two.
+    pub fn overlay(&self, other: &InputSequence) -> InputSequence {
+        let mut events = self.timed();
+        events.extend(other.timed());
+        events.sort_by_key(|(time, _)| *time);
+        let end = self.duration().max(other.duration());
+        InputSequence::from_timed(events, end)
+    }
+
+    /// This sequence delayed by `offset`.
+    pub fn shifted(&self, offset: Duration) -> InputSequence {
+        let mut seq = InputSequence::new();
+        if !offset.is_zero() {
+            seq.wait(offset);
+        }
+        seq.concat(self)
+    }
+
+    /// This sequence with every wait multiplied by `factor`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `factor` is negative or not finite.
+    pub fn stretched(&self, factor: f32) -> InputSequence {
+        assert!(
+            factor.is_finite() && factor >= 0.0,
+            "stretch factor must be non-negative and finite, got {factor}"
+        );
+        self.steps()
+            .iter()
+            .map(|step| match step {
+                Step::Wait(d) => Step::Wait(d.mul_f32(factor)),
+                step => step.clone(),
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    /// The steps sent within `range`, rebased so the range start is time zero.
+    ///
+    /// Only steps inside the range are kept; buttons held before `range.start`
+    /// are not re-held. Use [`InputSequence::state_at`] to recover them if needed.
+    pub fn slice(&self, range: Range<Duration>) -> InputSequence {
+        let events = self
+            .timed()
+            .into_iter()
+            .filter(|(time, _)| range.contains(time))
+            .map(|(time, step)| (time - range.start, step))
+            .collect();
+        let end = range.end.min(self.duration()).saturating_sub(range.start);
+        InputSequence::from_timed(events, end)
+    }
+
+    /// This sequence played backwards in time.
+    ///
+    /// Each step is replaced by the step that undoes it: holds become releases
+    /// and vice versa, and stick and `STATE` steps restore the position held
+    /// before them. Anything still held at the end of this sequence is held at
+    /// the start of the reversed one.
+    pub fn reversed(&self) -> InputSequence {
+        let end = self.duration();
+        let mut events = Vec::new();
+
+        // The held state before each step, plus the final state.
+        let mut before = Vec::with_capacity(self.len() + 1);
+        let mut state = ControllerState::new();
+        for step in self.steps() {
+            before.push(state.clone());
+            step.apply(&mut state);
+        }
+        let mut last = state;
+        let centered = |stick: Option<(f32, f32)>| stick.is_none_or(|s| s == (0.0, 0.0));
+        if Button::ALL.iter().any(|&b| last.button(b))
+            || !centered(last.left_stick)
+            || !centered(last.right_stick)
+        {
+            last.left_stick.get_or_insert((0.0, 0.0));
+            last.right_stick.get_or_insert((0.0, 0.0));
+            events.push((Duration::ZERO, Step::State(last)));
+        }
+
+        let mut index = self.len();
+        let mut time = end;
+        for step in self.steps().iter().rev() {
+            index -= 1;
+            let inverse = match step {
+                Step::Wait(d) => {
+                    time -= *d;
+                    continue;
+                }
+                Step::Marker(name) => Step::Marker(name.clone()),
+                Step::Press(buttons) => Step::Press(buttons.clone()),
+                Step::Hold(buttons) => Step::Release(buttons.clone()),
+                Step::Release(buttons) => Step::Hold(buttons.clone()),
+                Step::Stick(stick, _, _) => {
+                    let position = match stick {
+                        Stick::Left => before[index].left_stick,
+                        Stick::Right => before[index].right_stick,
+                    };
+                    let (h, v) = position.unwrap_or((0.0, 0.0));
+                    Step::Stick(*stick, h, v)
+                }
+                Step::State(_) => {
+                    let mut restore = before[index].clone();
+                    restore.left_stick.get_or_insert((0.0, 0.0));
+                    restore.right_stick.get_or_insert((0.0, 0.0));
+                    Step::State(restore)
+                }
+            };
+            events.push((end - time, inverse));
+        }
+        InputSequence::from_timed(events, end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands(seq: &InputSequence) -> Vec<String> {
+        seq.steps()
+            .iter()
+            .map(|step| match step {
+                Step::Wait(d) => format!("WAIT {}", d.as_millis()),
+                step => step.to_command().unwrap(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn overlay_interleaves_by_time() {
+        let mut a = InputSequence::new();
+        a.press(&[Button::A])
+            .wait(Duration::from_millis(100))
+            .press(&[Button::A]);
+        let mut b = InputSequence::new();
+        b.wait(Duration::from_millis(50))
+            .stick(Stick::Left, 1.0, 0.0)
+            .wait(Duration::from_millis(100));
+
+        assert_eq!(
+            commands(&a.overlay(&b)),
+            [
+                "PRESS a",
+                "WAIT 50",
+                "STICK l_stick 1 0",
+                "WAIT 50",
+                "PRESS a",
+                "WAIT 50"
+            ]
+        );
+    }
+
+    #[test]
+    fn shift_stretch_and_concat() {
+        let mut a = InputSequence::new();
+        a.press(&[Button::A]).wait(Duration::from_millis(100));
+        let b = a.shifted(Duration::from_millis(10)).stretched(2.0);
+
+        assert_eq!(
+            commands(&a.concat(&b)),
+            ["PRESS a", "WAIT 100", "WAIT 20", "PRESS a", "WAIT 200"]
+        );
+    }
+
+    #[test]
+    fn slice_rebases_times() {
+        let mut seq = InputSequence::new();
+        seq.press(&[Button::A])
+            .wait(Duration::from_millis(100))
+            .press(&[Button::B])
+            .wait(Duration::from_millis(100))
+            .press(&[Button::X])
+            .wait(Duration::from_millis(100));
+
+        let slice = seq.slice(Duration::from_millis(50)..Duration::from_millis(200));
+        assert_eq!(commands(&slice), ["WAIT 50", "PRESS b", "WAIT 100"]);
+    }
+
+    #[test]
+    fn reversed_undoes_each_step() {
+        let mut seq = InputSequence::new();
+        seq.hold(&[Button::ZR])
+            .wait(Duration::from_millis(100))
+            .stick(Stick::Left, 1.0, 0.0)
+            .wait(Duration::from_millis(50))
+            .release(&[Button::ZR])
+            .wait(Duration::from_millis(10));
+
+        assert_eq!(
+            commands(&seq.reversed()),
+            [
+                "STATE 000000000000000000 1 0 0 0",
+                "WAIT 10",
+                "HOLD zr",
+                "WAIT 50",
+                "STICK l_stick 0 0",
+                "WAIT 100",
+                "RELEASE zr"
+            ]
+        );
+    }
+}
@@ -0,0 +1,214 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::{Button, ControllerState, InputSequence, Step, Stick};
+
+/// D-pad directions that cannot be held at the same time.
+const DPAD_CONFLICTS: [(Button, Button); 2] = [
+    (Button::DpadLeft, Button::DpadRight),
+    (Button::DpadUp, Button::DpadDown),
+];
+
+/// A problem found by [`InputSequence::validate`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum IssueKind {
+    /// A button is released without having been held.
+    ReleaseNotHeld(Button),
+    /// A button is held again while already held, so one of the holds
+    /// overlaps the other.
+    AlreadyHeld(Button),
+    /// Opposite D-pad directions are active at the same time.
+    ConflictingDpad(Button, Button),
+    /// A stick position is outside [-1.0, 1.0].
+    StickOutOfRange(Stick, f32, f32),
+    /// A wait of zero length, which sends the surrounding steps back-to-back.
+    ZeroWait,
+    /// A marker name used more than once, so playing from it is ambiguous.
+    DuplicateMarker(String),
+    /// A [`Program`](crate::Program) label that no path of execution reaches.
+    UnreachableLabel(String),
+}
+
+/// An [`IssueKind`] together with the index of the step it was found at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    pub step: usize,
+    pub kind: IssueKind,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "step {}: ", self.step)?;
+        match &self.kind {
+            IssueKind::ReleaseNotHeld(b) => write!(f, "{b} released but never held"),
+            IssueKind::AlreadyHeld(b) => write!(f, "{b} held while already held"),
+            IssueKind::ConflictingDpad(a, b) => write!(f, "{a} and {b} active at the same time"),
+            IssueKind::StickOutOfRange(stick, h, v) => {
+                write!(f, "{stick} position ({h}, {v}) out of range")
+            }
+            IssueKind::ZeroWait => f.write_str("zero-length wait"),
+            IssueKind::DuplicateMarker(name) => write!(f, "marker `{name}` already defined"),
+            IssueKind::UnreachableLabel(name) => write!(f, "label `{name}` is unreachable"),
+        }
+    }
+}
+
+/// The result of [`InputSequence::validate`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Validation {
+    /// Problems found, in step order.
+    pub issues: Vec<Issue>,
+    /// Estimated playback time (the sum of all waits).
+    pub duration: Duration,
+}
+
+impl Validation {
+    /// Whether no issues were found.
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+fn in_range(value: f32) -> bool {
+    (-1.0..=1.0).contains(&value)
+}
+
+impl InputSequence {
+    /// Check the sequence for problems before playing it back.
+    pub fn validate(&self) -> Validation {
+        let mut issues = Vec::new();
+        let mut held = ControllerState::new();
+        // Per pair of `DPAD_CONFLICTS`, whether it was active after the
+        // previous step, so a conflict is reported once, where it starts.
+        let mut conflicting = [false; DPAD_CONFLICTS.len()];
+
+        for (index, step) in self.steps().iter().enumerate() {
+            let mut push = |kind| issues.push(Issue { step: index, kind });
+            match step {
+                Step::Release(buttons) => {
+                    for &b in buttons.iter().filter(|&&b| !held.button(b)) {
+                        push(IssueKind::ReleaseNotHeld(b));
+                    }
+                }
+                Step::Stick(stick, h, v) => {
+                    if !in_range(*h) || !in_range(*v) {
+                        push(IssueKind::StickOutOfRange(*stick, *h, *v));
+                    }
+                }
+                Step::State(state) => {
+                    let sticks = [
+                        (Stick::Left, state.left_stick),
+                        (Stick::Right, state.right_stick),
+                    ];
+                    for (stick, position) in sticks {
+                        if let Some((h, v)) =
+                            position.filter(|&(h, v)| !in_range(h) || !in_range(v))
+                        {
+                            push(IssueKind::StickOutOfRange(stick, h, v));
+                        }
+                    }
+                }
+                Step::Wait(d) if d.is_zero() => push(IssueKind::ZeroWait),
+                Step::Marker(name) => {
+                    if self.steps()[..index]
+                        .iter()
+                        .any(|s| matches!(s, Step::Marker(n) if n == name))
+                    {
+                        push(IssueKind::DuplicateMarker(name.clone()));
+                    }
+                }
+                Step::Hold(buttons) => {
+                    for &b in buttons.iter().filter(|&&b| held.button(b)) {
+                        push(IssueKind::AlreadyHeld(b));
+                    }
+                }
+                Step::Press(_) | Step::Wait(_) => {}
+            }
+
+            step.apply(&mut held);
+            let mut active = held.clone();
+            if let Step::Press(buttons) = step {
+                for &b in buttons {
+                    active.set_button(b, true);
+                }
+            }
+            for ((a, b), was) in DPAD_CONFLICTS.into_iter().zip(&mut conflicting) {
+                let now = active.button(a) && active.button(b);
+                if now && !*was {
+                    push(IssueKind::ConflictingDpad(a, b));
+                }
+                *was = now;
+            }
+        }
+
+        Validation {
+            issues,
+            duration: self.duration(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_issues() {
+        let mut seq = InputSequence::new();
+        seq.release(&[Button::A])
+            .hold(&[Button::DpadLeft])
+            .press(&[Button::DpadRight])
+            .release(&[Button::DpadLeft])
+            .stick(Stick::Right, 1.5, 0.0)
+            .wait(Duration::ZERO)
+            .wait(Duration::from_millis(250))
+            .hold(&[Button::B])
+            .hold(&[Button::B, Button::A]);
+
+        let validation = seq.validate();
+        let kinds: Vec<_> = validation
+            .issues
+            .iter()
+            .map(|i| (i.step, i.kind.clone()))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                (0, IssueKind::ReleaseNotHeld(Button::A)),
+                (
+                    2,
+                    IssueKind::ConflictingDpad(Button::DpadLeft, Button::DpadRight)
+                ),
+                (4, IssueKind::StickOutOfRange(Stick::Right, 1.5, 0.0)),
+                (5, IssueKind::ZeroWait),
+                (8, IssueKind::AlreadyHeld(Button::B)),
+            ]
+        );
+        assert_eq!(validation.duration, Duration::from_millis(250));
+        assert_eq!(
+            validation.issues[0].to_string(),
+            "step 0: a released but never held"
+        );
+    }
+
+    #[test]
+    fn reports_held_conflicts_once() {
+        let mut seq = InputSequence::new();
+        seq.hold(&[Button::DpadUp, Button::DpadDown]);
+        for _ in 0..10 {
+            seq.press(&[Button::A]).wait(Duration::from_millis(100));
+        }
+        seq.release(&[Button::DpadDown]).hold(&[Button::DpadDown]);
+        let steps: Vec<usize> = seq.validate().issues.iter().map(|i| i.step).collect();
+        assert_eq!(steps, [0, 22]);
+    }
+
+    #[test]
+    fn clean_sequence_is_ok() {
+        let mut seq = InputSequence::new();
+        seq.hold(&[Button::ZR])
+            .wait(Duration::from_millis(100))
+            .release(&[Button::ZR]);
+        assert!(seq.validate().is_ok());
+    }
+}
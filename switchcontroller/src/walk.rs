@@ -0,0 +1,124 @@
+use std::io;
+use std::time::Duration;
+
+use crate::{Button, Direction, InputSequence, Stick, SwitchController};
+
+/// Settings for [`SwitchController::walk`] and [`SwitchController::run`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Movement {
+    /// The stick that moves the character.
+    pub stick: Stick,
+    /// Stick deflection when walking, from 0 to 1.
+    pub walk_speed: f32,
+    /// Stick deflection when running, from 0 to 1.
+    pub run_speed: f32,
+    /// A button held while running, for games where holding B sprints.
+    pub sprint: Option<Button>,
+}
+
+impl Default for Movement {
+    /// The left stick, half tilted to walk and fully tilted to run, without a
+    /// sprint button.
+    fn default() -> Self {
+        Self {
+            stick: Stick::Left,
+            walk_speed: 0.5,
+            run_speed: 1.0,
+            sprint: None,
+        }
+    }
+}
+
+impl SwitchController {
+    /// Configure how [`SwitchController::walk`] and
+    /// [`SwitchController::run`] move.
+    pub fn set_movement(&mut self, movement: Movement) -> &mut Self {
+        self.movement = movement;
+        self
+    }
+
+    pub fn movement(&self) -> &Movement {
+        &self.movement
+    }
+
+    /// Tilt the movement stick towards `direction` at walking speed for
+    /// `duration`, then recenter it.
+    pub fn walk(&mut self, direction: Direction, duration: Duration) -> io::Result<()> {
+        let speed = self.movement.walk_speed;
+        self.play(&self.movement.sequence(direction, speed, false, duration))
+    }
+
+    /// Like [`SwitchController::walk`] at running speed, holding the sprint
+    /// button throughout if one is configured.
+    pub fn run(&mut self, direction: Direction, duration: Duration) -> io::Result<()> {
+        let speed = self.movement.run_speed;
+        self.play(&self.movement.sequence(direction, speed, true, duration))
+    }
+}
+
+impl Movement {
+    /// Hold `direction` at `speed` for `duration`, sprinting if asked to.
+    pub(crate) fn sequence(
+        &self,
+        direction: Direction,
+        speed: f32,
+        sprint: bool,
+        duration: Duration,
+    ) -> InputSequence {
+        let (x, y) = direction.vector();
+        self.sequence_to(x * speed, y * speed, sprint, duration)
+    }
+
+    /// Hold the stick at `(x, y)` for `duration`, then recenter it.
+    pub(crate) fn sequence_to(
+        &self,
+        x: f32,
+        y: f32,
+        sprint: bool,
+        duration: Duration,
+    ) -> InputSequence {
+        let sprint = self.sprint.filter(|_| sprint);
+        let mut sequence = InputSequence::new();
+        if let Some(button) = sprint {
+            sequence.hold(&[button]);
+        }
+        sequence
+            .stick(self.stick, x, y)
+            .wait(duration)
+            .stick(self.stick, 0.0, 0.0);
+        if let Some(button) = sprint {
+            sequence.release(&[button]);
+        }
+        sequence
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn holds_and_recenters() {
+        let second = Duration::from_secs(1);
+        let movement = Movement::default();
+        assert_eq!(
+            movement
+                .sequence(Direction::Up, movement.walk_speed, false, second)
+                .to_script(),
+            "STICK l_stick 0 -0.5\nWAIT 1s\nSTICK l_stick 0 0\n"
+        );
+
+        let sprinting = Movement {
+            sprint: Some(Button::B),
+            ..movement
+        };
+        assert_eq!(
+            sprinting
+                .sequence(Direction::Left, sprinting.run_speed, true, second)
+                .to_script(),
+            "HOLD b\nSTICK l_stick -1 0\nWAIT 1s\nSTICK l_stick 0 0\nRELEASE b\n"
+        );
+        let walking = sprinting.sequence(Direction::Left, 0.5, false, second);
+        assert!(!walking.to_script().contains("HOLD"));
+    }
+}